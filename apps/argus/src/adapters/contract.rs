@@ -1,12 +1,21 @@
-use super::ethereum::PythPulse;
+use super::ethereum::{PulseFees, PythFees, PythPulse};
 use super::types::*;
 use crate::adapters::ethereum::SubscriptionParams;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use ethers::contract::ContractError;
 use ethers::providers::Middleware;
-use ethers::types::H256;
+use ethers::types::{Address, Bytes, H256, U256, U64};
+use fortuna::eth_utils::nonce_manager::NonceManaged;
 use pyth_sdk::Price;
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// The maximum number of subscriptions fetched by a single `getActiveSubscriptions` call.
+const ACTIVE_SUBSCRIPTIONS_PAGE_SIZE: u64 = 100;
+
+/// How long to wait for a price update transaction to be confirmed before considering it stuck.
+const TX_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[async_trait]
 pub trait GetChainPrices {
@@ -15,44 +24,154 @@ pub trait GetChainPrices {
         subscription_id: SubscriptionId,
         feed_id: &PriceId,
     ) -> Result<Option<Price>>;
+
+    /// The publish time of the last update of the subscription, or 0 if it was never updated.
+    async fn get_last_published_time(&self, subscription_id: SubscriptionId) -> Result<i64>;
 }
 
 #[async_trait]
 impl<M: Middleware + 'static> GetChainPrices for PythPulse<M> {
     async fn get_price_unsafe(
         &self,
-        _subscription_id: SubscriptionId,
-        _feed_id: &PriceId,
+        subscription_id: SubscriptionId,
+        feed_id: &PriceId,
     ) -> Result<Option<Price>> {
-        todo!()
+        let prices = match self
+            .get_prices_unsafe(subscription_id, vec![feed_id.to_bytes()])
+            .call()
+            .await
+        {
+            Ok(prices) => prices,
+            // The contract reverts if the feed was never updated for this subscription, if the
+            // subscription is inactive, or if the keeper is not on its reader whitelist.
+            Err(ContractError::Revert(_)) => return Ok(None),
+            Err(e) => return Err(anyhow!("Error reading on-chain price: {:?}", e)),
+        };
+
+        prices
+            .into_iter()
+            .next()
+            .map(|price| {
+                Ok(Price {
+                    price: price.price,
+                    conf: price.conf,
+                    expo: price.expo,
+                    publish_time: i64::try_from(price.publish_time)
+                        .map_err(|_| anyhow!("Invalid publish time {}", price.publish_time))?,
+                })
+            })
+            .transpose()
+    }
+
+    async fn get_last_published_time(&self, subscription_id: SubscriptionId) -> Result<i64> {
+        let (_, status) = self
+            .get_subscription(subscription_id)
+            .call()
+            .await
+            .map_err(|e| anyhow!("Error reading subscription: {:?}", e))?;
+        i64::try_from(status.price_last_updated_at)
+            .map_err(|_| anyhow!("Invalid publish time {}", status.price_last_updated_at))
     }
 }
+
 #[async_trait]
 pub trait UpdateChainPrices {
+    /// Submit a single price update transaction and wait for its confirmation.
+    /// The gas estimate and the gas price are padded by the given multipliers
+    /// (100 submits the transaction with the estimates as is).
     async fn update_price_feeds(
         &self,
         subscription_id: SubscriptionId,
         price_ids: &[PriceId],
         update_data: &[Vec<u8>],
+        gas_multiplier_pct: u64,
+        fee_multiplier_pct: u64,
     ) -> Result<H256>;
 }
+
 #[async_trait]
-impl<M: Middleware + 'static> UpdateChainPrices for PythPulse<M> {
+impl<M: Middleware + NonceManaged + 'static> UpdateChainPrices for PythPulse<M> {
     async fn update_price_feeds(
         &self,
         subscription_id: SubscriptionId,
         price_ids: &[PriceId],
         update_data: &[Vec<u8>],
+        gas_multiplier_pct: u64,
+        fee_multiplier_pct: u64,
     ) -> Result<H256> {
         tracing::debug!(
             subscription_id = subscription_id.to_string(),
             price_ids_count = price_ids.len(),
             update_data_count = update_data.len(),
+            gas_multiplier_pct,
+            fee_multiplier_pct,
             "Updating price feeds on-chain via PythPulse"
         );
-        todo!()
+
+        // The Pyth update fee is paid by the contract out of the subscription balance,
+        // so the transaction itself carries no value.
+        let call = PythPulse::update_price_feeds(
+            self,
+            subscription_id,
+            update_data.iter().cloned().map(Bytes::from).collect(),
+        );
+        let client = self.client();
+
+        // Estimate the gas *before* filling the transaction, as filling the transaction increments the nonce.
+        // The estimate also fails if the contract would revert, e.g. because the update criteria are not met.
+        let gas = call
+            .estimate_gas()
+            .await
+            .map_err(|e| anyhow!("Error estimating gas for price update: {:?}", e))?;
+
+        let mut transaction = call.tx.clone();
+        transaction.set_gas(gas.saturating_mul(gas_multiplier_pct.into()) / 100);
+        client
+            .fill_transaction(&mut transaction, None)
+            .await
+            .map_err(|e| anyhow!("Error filling price update transaction: {:?}", e))?;
+        transaction.set_gas_price(
+            transaction
+                .gas_price()
+                .unwrap_or_default()
+                .saturating_mul(fee_multiplier_pct.into())
+                / 100,
+        );
+
+        let pending_tx = client
+            .send_transaction(transaction, None)
+            .await
+            .map_err(|e| anyhow!("Error submitting price update transaction: {:?}", e))?;
+        let tx_hash = pending_tx.tx_hash();
+
+        let receipt = match tokio::time::timeout(TX_CONFIRMATION_TIMEOUT, pending_tx).await {
+            Ok(receipt) => receipt.map_err(|e| {
+                anyhow!(
+                    "Error waiting for price update transaction {:?}: {:?}",
+                    tx_hash,
+                    e
+                )
+            })?,
+            Err(_) => None,
+        };
+        let Some(receipt) = receipt else {
+            // The transaction is stuck in the mempool or was dropped, likely because of a nonce gap.
+            // Resynchronize the nonce with the chain before the next attempt.
+            client.reset();
+            return Err(anyhow!(
+                "Price update transaction {:?} was not confirmed",
+                tx_hash
+            ));
+        };
+
+        if receipt.status == Some(U64::zero()) {
+            return Err(anyhow!("Price update transaction {:?} reverted", tx_hash));
+        }
+
+        Ok(tx_hash)
     }
 }
+
 #[async_trait]
 pub trait ReadChainSubscriptions {
     async fn get_active_subscriptions(&self)
@@ -65,6 +184,383 @@ impl<M: Middleware + 'static> ReadChainSubscriptions for PythPulse<M> {
         &self,
     ) -> Result<HashMap<SubscriptionId, SubscriptionParams>> {
         tracing::debug!("Getting active subscriptions via PythPulse");
-        Ok(HashMap::new())
+        read_active_subscriptions(self, ACTIVE_SUBSCRIPTIONS_PAGE_SIZE).await
+    }
+}
+
+/// The fees that make up the cost of a price update.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateFees {
+    /// The current gas price, in wei.
+    pub gas_price: U256,
+    /// The fee paid by a subscription to the keeper for each updated feed, on top of the gas, in wei.
+    pub keeper_fee_per_feed: U256,
+    /// The fee of the Pyth contract for each updated feed, in wei.
+    pub pyth_fee_per_feed: U256,
+}
+
+#[async_trait]
+pub trait ReadSubscriptionBalances {
+    /// The current balance of the subscription, in wei.
+    async fn get_subscription_balance(&self, subscription_id: SubscriptionId) -> Result<U256>;
+
+    /// The minimum balance that a subscription of `num_price_feeds` feeds must hold, in wei.
+    async fn get_required_balance(&self, num_price_feeds: usize) -> Result<U256>;
+
+    /// The current fees of an update. The Pyth fee is read from `pyth_contract` if provided,
+    /// and is assumed to be zero otherwise.
+    async fn get_update_fees(&self, pyth_contract: Option<Address>) -> Result<UpdateFees>;
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> ReadSubscriptionBalances for PythPulse<M> {
+    async fn get_subscription_balance(&self, subscription_id: SubscriptionId) -> Result<U256> {
+        let (_, status) = self
+            .get_subscription(subscription_id)
+            .call()
+            .await
+            .map_err(|e| anyhow!("Error reading subscription: {:?}", e))?;
+        Ok(status.balance_in_wei)
+    }
+
+    async fn get_required_balance(&self, num_price_feeds: usize) -> Result<U256> {
+        self.get_minimum_balance(u8::try_from(num_price_feeds)?)
+            .call()
+            .await
+            .map_err(|e| anyhow!("Error reading minimum balance: {:?}", e))
+    }
+
+    async fn get_update_fees(&self, pyth_contract: Option<Address>) -> Result<UpdateFees> {
+        let client = self.client();
+        let gas_price = client
+            .get_gas_price()
+            .await
+            .map_err(|e| anyhow!("Error reading gas price: {:?}", e))?;
+        let keeper_fee_per_feed = PulseFees::new(self.address(), client.clone())
+            .get_single_update_keeper_fee_in_wei()
+            .call()
+            .await
+            .map_err(|e| anyhow!("Error reading keeper fee: {:?}", e))?;
+        let pyth_fee_per_feed = match pyth_contract {
+            Some(address) => PythFees::new(address, client)
+                .single_update_fee_in_wei()
+                .call()
+                .await
+                .map_err(|e| anyhow!("Error reading Pyth update fee: {:?}", e))?,
+            None => U256::zero(),
+        };
+
+        Ok(UpdateFees {
+            gas_price,
+            keeper_fee_per_feed: keeper_fee_per_feed.into(),
+            pyth_fee_per_feed,
+        })
+    }
+}
+
+/// Read all the active subscriptions of the contract, `page_size` subscriptions per call.
+/// Subscriptions deactivated between two pages may shift the remaining ones, so a subscription
+/// can occasionally be missed; it is picked up again on the next refresh.
+async fn read_active_subscriptions<M: Middleware + 'static>(
+    contract: &PythPulse<M>,
+    page_size: u64,
+) -> Result<HashMap<SubscriptionId, SubscriptionParams>> {
+    let mut subscriptions = HashMap::new();
+    let mut start_index = U256::zero();
+    loop {
+        let (subscription_ids, subscription_params, total_count) = contract
+            .get_active_subscriptions(start_index, page_size.into())
+            .call()
+            .await
+            .map_err(|e| anyhow!("Error reading active subscriptions: {:?}", e))?;
+
+        if subscription_ids.is_empty() {
+            break;
+        }
+
+        start_index += subscription_ids.len().into();
+        subscriptions.extend(subscription_ids.into_iter().zip(subscription_params));
+
+        if start_index >= total_count {
+            break;
+        }
+    }
+
+    Ok(subscriptions)
+}
+
+#[cfg(test)]
+mod test {
+    //! These tests only run if `ARGUS_TEST_E2E` is set, against anvil and the contracts deployed
+    //! with `fortuna::test_utils`: `ARGUS_TEST_E2E=1 cargo test adapters::contract`.
+
+    use {
+        super::*,
+        crate::{
+            adapters::ethereum::{SignablePythContract, UpdateCriteria},
+            config::EthereumConfig,
+        },
+        ethers::{
+            abi::Token,
+            types::{Address, BlockNumber},
+            utils::{Anvil, AnvilInstance},
+        },
+        fortuna::test_utils::{anvil_keys, deploy, deployer},
+    };
+
+    /// The fee charged by the mock Pyth contract for each price update.
+    const PYTH_UPDATE_FEE: u64 = 1_000;
+
+    /// A local anvil chain with the Pulse scheduler behind a proxy, backed by a mock Pyth contract.
+    struct TestChain {
+        _anvil: AnvilInstance,
+        /// The scheduler, signing with the account that creates the subscriptions.
+        subscriber: SignablePythContract,
+        /// The scheduler, signing with the keeper account.
+        keeper: SignablePythContract,
+    }
+
+    impl TestChain {
+        async fn start() -> Result<Self> {
+            let anvil = Anvil::new().spawn();
+            let keys = anvil_keys(&anvil);
+            let client = deployer(&anvil)?;
+            let deployer = client.address();
+
+            let pyth = deploy(
+                client.clone(),
+                "MockPyth",
+                (U256::from(60), U256::from(PYTH_UPDATE_FEE)),
+            )
+            .await?;
+            let implementation = deploy(client.clone(), "SchedulerUpgradeable", ()).await?;
+            let initialize = implementation
+                .abi()
+                .function("initialize")?
+                .encode_input(&[
+                    Token::Address(deployer),
+                    Token::Address(deployer),
+                    Token::Address(pyth.address()),
+                    // minimumBalancePerFeed
+                    Token::Uint(U256::from(10u64).pow(15.into())),
+                    // singleUpdateKeeperFeeInWei
+                    Token::Uint(U256::from(10u64).pow(12.into())),
+                ])?;
+            let proxy = deploy(
+                client.clone(),
+                "ERC1967Proxy",
+                (implementation.address(), Bytes::from(initialize)),
+            )
+            .await?;
+
+            let config = |address: Address| EthereumConfig {
+                geth_rpc_addr: anvil.endpoint(),
+                geth_rpc_wss: None,
+                contract_addr: address,
+                pyth_contract_addr: None,
+                confirmed_block_status: Default::default(),
+                legacy_tx: false,
+                priority_fee_multiplier_pct: 100,
+                escalation_policy: Default::default(),
+            };
+            let subscriber =
+                SignablePythContract::from_config(&config(proxy.address()), &keys[1]).await?;
+            let keeper =
+                SignablePythContract::from_config(&config(proxy.address()), &keys[2]).await?;
+
+            Ok(Self {
+                _anvil: anvil,
+                subscriber,
+                keeper,
+            })
+        }
+
+        /// Create a funded subscription to `price_ids` with a heartbeat criterion.
+        async fn create_subscription(&self, price_ids: &[PriceId]) -> Result<SubscriptionId> {
+            let params = SubscriptionParams {
+                price_ids: price_ids.iter().map(|id| id.to_bytes()).collect(),
+                reader_whitelist: vec![],
+                whitelist_enabled: false,
+                is_active: true,
+                is_permanent: false,
+                update_criteria: UpdateCriteria {
+                    update_on_heartbeat: true,
+                    heartbeat_seconds: 60,
+                    update_on_deviation: false,
+                    deviation_threshold_bps: 0,
+                },
+            };
+            let minimum_balance = self
+                .subscriber
+                .get_minimum_balance(u8::try_from(price_ids.len())?)
+                .call()
+                .await?;
+            let call = self
+                .subscriber
+                .create_subscription(params)
+                .value(minimum_balance * 10);
+            let subscription_id = call.call().await?;
+            call.send().await?.await?;
+            Ok(subscription_id)
+        }
+
+        /// The timestamp of the latest block, which the price updates must be close to.
+        async fn now(&self) -> Result<u64> {
+            Ok(self
+                .keeper
+                .client()
+                .get_block(BlockNumber::Latest)
+                .await?
+                .ok_or(anyhow!("No latest block"))?
+                .timestamp
+                .as_u64())
+        }
+    }
+
+    /// A price update in the format accepted by the mock Pyth contract.
+    fn mock_update_data(feed_id: &PriceId, price: i64, publish_time: u64) -> Vec<u8> {
+        let price = Token::Tuple(vec![
+            Token::Int(U256::from(price)),
+            Token::Uint(U256::from(10)),
+            Token::Int(U256::MAX - 7), // expo = -8
+            Token::Uint(U256::from(publish_time)),
+        ]);
+        ethers::abi::encode(&[
+            Token::Tuple(vec![
+                Token::FixedBytes(feed_id.to_bytes().to_vec()),
+                price.clone(),
+                price,
+            ]),
+            Token::Uint(U256::zero()),
+        ])
+    }
+
+    fn e2e_enabled() -> bool {
+        std::env::var("ARGUS_TEST_E2E").is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_read_active_subscriptions() {
+        if !e2e_enabled() {
+            return;
+        }
+        let chain = TestChain::start().await.unwrap();
+
+        assert!(read_active_subscriptions(&chain.keeper, 2)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut expected = HashMap::new();
+        for i in 0..5u8 {
+            let price_ids = vec![PriceId::new([i + 1; 32]), PriceId::new([i + 100; 32])];
+            let subscription_id = chain.create_subscription(&price_ids).await.unwrap();
+            expected.insert(subscription_id, price_ids);
+        }
+
+        // Exercise the pagination with pages that do not divide the number of subscriptions.
+        for page_size in [1, 2, 5, 100] {
+            let subscriptions = read_active_subscriptions(&chain.keeper, page_size)
+                .await
+                .unwrap();
+            assert_eq!(subscriptions.len(), expected.len());
+            for (subscription_id, price_ids) in &expected {
+                let params = &subscriptions[subscription_id];
+                assert!(params.is_active);
+                assert_eq!(
+                    params.price_ids,
+                    price_ids.iter().map(|id| id.to_bytes()).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_and_get_prices() {
+        if !e2e_enabled() {
+            return;
+        }
+        let chain = TestChain::start().await.unwrap();
+        let feed_id = PriceId::new([1; 32]);
+        let subscription_id = chain.create_subscription(&[feed_id]).await.unwrap();
+
+        // Nothing was pushed yet.
+        assert_eq!(
+            chain
+                .keeper
+                .get_price_unsafe(subscription_id, &feed_id)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let publish_time = chain.now().await.unwrap();
+        let update_data = mock_update_data(&feed_id, 12_345, publish_time);
+        let tx_hash = UpdateChainPrices::update_price_feeds(
+            &chain.keeper,
+            subscription_id,
+            &[feed_id],
+            &[update_data],
+            150,
+            200,
+        )
+        .await
+        .unwrap();
+
+        let price = chain
+            .keeper
+            .get_price_unsafe(subscription_id, &feed_id)
+            .await
+            .unwrap()
+            .expect("No on-chain price after the update");
+        assert_eq!(price.price, 12_345);
+        assert_eq!(price.conf, 10);
+        assert_eq!(price.expo, -8);
+        assert_eq!(price.publish_time, i64::try_from(publish_time).unwrap());
+        assert_eq!(
+            chain
+                .keeper
+                .get_last_published_time(subscription_id)
+                .await
+                .unwrap(),
+            price.publish_time
+        );
+
+        // The gas limit was padded over the gas actually used.
+        let client = chain.keeper.client();
+        let transaction = client
+            .get_transaction(tx_hash)
+            .await
+            .unwrap()
+            .expect("Transaction not found");
+        let receipt = client
+            .get_transaction_receipt(tx_hash)
+            .await
+            .unwrap()
+            .expect("Receipt not found");
+        assert!(transaction.gas > receipt.gas_used.unwrap_or_default() * 140 / 100);
+
+        // The Pyth fee was paid out of the subscription balance.
+        let (_, status) = chain
+            .keeper
+            .get_subscription(subscription_id)
+            .call()
+            .await
+            .unwrap();
+        assert_eq!(status.total_updates, U256::one());
+        assert!(status.total_spent >= U256::from(PYTH_UPDATE_FEE));
+
+        // An update that is not newer than the on-chain price is rejected.
+        let stale_update_data = mock_update_data(&feed_id, 1, publish_time);
+        assert!(UpdateChainPrices::update_price_feeds(
+            &chain.keeper,
+            subscription_id,
+            &[feed_id],
+            &[stale_update_data],
+            100,
+            100,
+        )
+        .await
+        .is_err());
     }
 }
@@ -25,6 +25,19 @@ use {
 // get the ABI from the SDK package.
 abigen!(PythPulse, "abi/IScheduler.abi.json");
 
+// Fee getters of the Pulse and Pyth contracts that are not part of the scheduler interface.
+abigen!(
+    PulseFees,
+    r#"[
+        function getSingleUpdateKeeperFeeInWei() external view returns (uint128)
+    ]"#;
+
+    PythFees,
+    r#"[
+        function singleUpdateFeeInWei() external view returns (uint256)
+    ]"#;
+);
+
 pub type MiddlewaresWrapper<T> = LegacyTxMiddleware<
     GasOracleMiddleware<
         NonceManagerMiddleware<SignerMiddleware<Provider<T>, LocalWallet>>,
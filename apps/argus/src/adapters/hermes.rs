@@ -1,21 +1,304 @@
 use super::types::*;
-use anyhow::Result;
+use crate::config::HermesConfig;
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use pyth_hermes_client_rust::{
+    client::{HermesClient as HermesWsClient, HermesClientBuilder},
+    ws_connection::{
+        HermesClientMessageSubscribe, HermesClientMessageUnsubscribe, HermesPriceFeed,
+        HermesServerMessage, HermesServerResponseMessage,
+    },
+};
+use pyth_sdk::Price;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::ReceiverStream;
+use url::Url;
 
-pub struct HermesClient;
+/// Timeout of the requests to the Hermes HTTP API.
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A stream of the latest Pyth prices of the subscribed feeds.
+pub type PriceUpdateStream = BoxStream<'static, (PriceId, Price)>;
 
 #[async_trait]
 pub trait ReadPythPrices {
+    /// Fetch the binary update data of the latest prices of `feed_ids`, ready to be pushed on-chain.
     async fn get_latest_prices(&self, feed_ids: &[PriceId]) -> Result<Vec<Vec<u8>>>;
-    async fn subscribe_to_price_updates(&self, feed_ids: &[PriceId]) -> Result<()>; // TODO: return a stream
+
+    /// Start streaming the prices of `feed_ids`. This can only be called once per client;
+    /// use `update_price_subscription` to change the streamed feeds afterwards.
+    async fn subscribe_to_price_updates(&self, feed_ids: &[PriceId]) -> Result<PriceUpdateStream>;
+
+    /// Change the set of streamed feeds to `feed_ids`. Only the feeds that were added or
+    /// removed are sent to Hermes, over the already established connections.
+    async fn update_price_subscription(&self, feed_ids: &HashSet<PriceId>) -> Result<()>;
+}
+
+/// A client of the Hermes HTTP API, for the update data, and of its WebSocket API, for the
+/// streamed prices.
+pub struct HermesClient {
+    http_client: reqwest::Client,
+    http_endpoint: Url,
+    stream: Mutex<PriceStreamState>,
+}
+
+struct PriceStreamState {
+    ws_client: HermesWsClient,
+    started: bool,
+    feed_ids: HashSet<PriceId>,
+}
+
+impl HermesClient {
+    pub fn new(config: &HermesConfig) -> Result<Self> {
+        let ws_endpoints = config
+            .ws_endpoints
+            .iter()
+            .map(|endpoint| Url::parse(endpoint))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ws_client = HermesClientBuilder::default()
+            .with_endpoints(ws_endpoints)
+            .with_num_connections(config.num_connections)
+            .build()?;
+
+        Ok(Self {
+            http_client: reqwest::Client::builder()
+                .timeout(HTTP_REQUEST_TIMEOUT)
+                .build()?,
+            http_endpoint: Url::parse(&config.http_endpoint)?,
+            stream: Mutex::new(PriceStreamState {
+                ws_client,
+                started: false,
+                feed_ids: HashSet::new(),
+            }),
+        })
+    }
 }
+
+#[derive(serde::Deserialize)]
+struct LatestPriceUpdates {
+    binary: BinaryUpdateData,
+}
+
+#[derive(serde::Deserialize)]
+struct BinaryUpdateData {
+    data: Vec<String>,
+}
+
 #[async_trait]
 impl ReadPythPrices for HermesClient {
-    async fn get_latest_prices(&self, _feed_ids: &[PriceId]) -> Result<Vec<Vec<u8>>> {
-        todo!()
+    async fn get_latest_prices(&self, feed_ids: &[PriceId]) -> Result<Vec<Vec<u8>>> {
+        let mut url = self.http_endpoint.join("v2/updates/price/latest")?;
+        url.query_pairs_mut()
+            .extend_pairs(feed_ids.iter().map(|id| ("ids[]", id.to_hex())))
+            .append_pair("encoding", "hex")
+            .append_pair("parsed", "false");
+
+        let response = self.http_client.get(url).send().await?;
+        if !response.status().is_success() {
+            bail!(
+                "Hermes returned status {} for the latest price updates: {}",
+                response.status(),
+                response.text().await.unwrap_or_default()
+            );
+        }
+        let updates: LatestPriceUpdates = response.json().await?;
+
+        updates
+            .binary
+            .data
+            .iter()
+            .map(|data| hex::decode(data).map_err(|e| anyhow!("Invalid update data: {}", e)))
+            .collect()
+    }
+
+    async fn subscribe_to_price_updates(&self, feed_ids: &[PriceId]) -> Result<PriceUpdateStream> {
+        let mut state = self.stream.lock().await;
+        if state.started {
+            bail!("The price stream was already started");
+        }
+        let receiver = state.ws_client.start().await?;
+        state.started = true;
+        update_subscription(&mut state, &feed_ids.iter().cloned().collect()).await?;
+
+        Ok(ReceiverStream::new(receiver)
+            .filter_map(|message| async move {
+                match message {
+                    HermesServerMessage::PriceUpdate { price_feed } => {
+                        match parse_price_feed(&price_feed) {
+                            Ok(update) => Some(update),
+                            Err(e) => {
+                                tracing::warn!(feed_id = price_feed.id, error = %e, "Invalid price update");
+                                None
+                            }
+                        }
+                    }
+                    HermesServerMessage::Response(HermesServerResponseMessage::Err { error }) => {
+                        tracing::error!(error, "Hermes rejected a subscription request");
+                        None
+                    }
+                    HermesServerMessage::Response(HermesServerResponseMessage::Success) => None,
+                }
+            })
+            .boxed())
+    }
+
+    async fn update_price_subscription(&self, feed_ids: &HashSet<PriceId>) -> Result<()> {
+        let mut state = self.stream.lock().await;
+        if !state.started {
+            bail!("The price stream was not started");
+        }
+        update_subscription(&mut state, feed_ids).await
+    }
+}
+
+/// Subscribe to the feeds of `feed_ids` that are not streamed yet, and unsubscribe from the
+/// streamed feeds that are not in `feed_ids`.
+async fn update_subscription(
+    state: &mut PriceStreamState,
+    feed_ids: &HashSet<PriceId>,
+) -> Result<()> {
+    let (added, removed) = subscription_changes(&state.feed_ids, feed_ids);
+
+    if !removed.is_empty() {
+        tracing::info!(feed_count = removed.len(), "Unsubscribing from price feeds");
+        state
+            .ws_client
+            .unsubscribe(HermesClientMessageUnsubscribe {
+                ids: removed.iter().map(PriceId::to_hex).collect(),
+            })
+            .await?;
+    }
+
+    if !added.is_empty() {
+        tracing::info!(feed_count = added.len(), "Subscribing to price feeds");
+        // Subscriptions are additive on the server, but each connection only replays its last
+        // subscribe message when it reconnects, so the message carries the whole set of feeds.
+        state
+            .ws_client
+            .subscribe(HermesClientMessageSubscribe {
+                ids: feed_ids.iter().map(PriceId::to_hex).collect(),
+                verbose: false,
+                binary: false,
+                allow_out_of_order: false,
+                // Don't let a single unknown feed fail the subscription of all the others.
+                ignore_invalid_price_ids: true,
+            })
+            .await?;
+    }
+
+    state.feed_ids = feed_ids.clone();
+    Ok(())
+}
+
+/// The feeds to subscribe to and to unsubscribe from to go from the `current` set of feeds to `target`.
+fn subscription_changes(
+    current: &HashSet<PriceId>,
+    target: &HashSet<PriceId>,
+) -> (Vec<PriceId>, Vec<PriceId>) {
+    (
+        target.difference(current).cloned().collect(),
+        current.difference(target).cloned().collect(),
+    )
+}
+
+fn parse_price_feed(price_feed: &HermesPriceFeed) -> Result<(PriceId, Price)> {
+    let feed_id = PriceId::from_hex(&price_feed.id)?;
+    Ok((
+        feed_id,
+        Price {
+            price: price_feed.price.price,
+            conf: price_feed.price.conf,
+            expo: price_feed.price.expo,
+            publish_time: price_feed.price.publish_time,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, pyth_hermes_client_rust::ws_connection::HermesPrice};
+
+    #[test]
+    fn test_subscription_changes() {
+        let feed = |i: u8| PriceId::new([i; 32]);
+        let current: HashSet<_> = [feed(1), feed(2), feed(3)].into_iter().collect();
+
+        let (added, removed) = subscription_changes(&current, &current);
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+
+        let target = [feed(2), feed(3), feed(4)].into_iter().collect();
+        let (added, removed) = subscription_changes(&current, &target);
+        assert_eq!(added, vec![feed(4)]);
+        assert_eq!(removed, vec![feed(1)]);
+
+        let (added, removed) = subscription_changes(&HashSet::new(), &current);
+        assert_eq!(added.len(), 3);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn test_parse_price_feed() {
+        let message: HermesServerMessage = serde_json::from_str(
+            r#"{
+                "type": "price_update",
+                "price_feed": {
+                    "id": "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43",
+                    "price": {"price": "6432100000000", "conf": "2500000000", "expo": -8, "publish_time": 1717632000},
+                    "ema_price": {"price": "6430000000000", "conf": "2600000000", "expo": -8, "publish_time": 1717632000}
+                }
+            }"#,
+        )
+        .unwrap();
+        let HermesServerMessage::PriceUpdate { price_feed } = message else {
+            panic!("Expected a price update");
+        };
+
+        let (feed_id, price) = parse_price_feed(&price_feed).unwrap();
+        assert_eq!(
+            feed_id,
+            PriceId::from_hex("e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43")
+                .unwrap()
+        );
+        assert_eq!(
+            price,
+            Price {
+                price: 6_432_100_000_000,
+                conf: 2_500_000_000,
+                expo: -8,
+                publish_time: 1_717_632_000,
+            }
+        );
+
+        let invalid = HermesPriceFeed {
+            id: "not a feed id".to_string(),
+            price: HermesPrice {
+                price: 1,
+                conf: 1,
+                expo: 0,
+                publish_time: 0,
+            },
+            ema_price: HermesPrice {
+                price: 1,
+                conf: 1,
+                expo: 0,
+                publish_time: 0,
+            },
+            metadata: None,
+            vaa: None,
+        };
+        assert!(parse_price_feed(&invalid).is_err());
     }
 
-    async fn subscribe_to_price_updates(&self, _feed_ids: &[PriceId]) -> Result<()> {
-        todo!()
+    #[test]
+    fn test_parse_latest_price_updates() {
+        let updates: LatestPriceUpdates = serde_json::from_str(
+            r#"{"binary": {"encoding": "hex", "data": ["504e4155", "0102"]}, "parsed": null}"#,
+        )
+        .unwrap();
+        assert_eq!(updates.binary.data, vec!["504e4155", "0102"]);
     }
 }
@@ -1,6 +1,7 @@
 //! API server for Prometheus metrics and health checks
 
 use {
+    crate::state::{ArgusState, ChainName},
     anyhow::{anyhow, Result},
     axum::{body::Body, routing::get, Router},
     index::index,
@@ -8,7 +9,8 @@ use {
     metrics::metrics,
     prometheus_client::registry::Registry,
     ready::ready,
-    std::{net::SocketAddr, sync::Arc},
+    std::{collections::HashMap, net::SocketAddr, sync::Arc},
+    subscriptions::subscriptions,
     tokio::sync::{watch, RwLock},
     tower_http::cors::CorsLayer,
 };
@@ -16,9 +18,12 @@ mod index;
 mod live;
 mod metrics;
 mod ready;
+mod subscriptions;
 #[derive(Clone)]
 pub struct ApiState {
     pub metrics_registry: Arc<RwLock<Registry>>,
+    /// The state of the keeper of each chain.
+    pub chains: Arc<HashMap<ChainName, Arc<ArgusState>>>,
 }
 
 pub fn routes(api_state: ApiState) -> Router<(), Body> {
@@ -27,16 +32,19 @@ pub fn routes(api_state: ApiState) -> Router<(), Body> {
         .route("/live", get(live))
         .route("/ready", get(ready))
         .route("/metrics", get(metrics))
+        .route("/v1/chains/:chain_name/subscriptions", get(subscriptions))
         .with_state(api_state)
 }
 
 pub async fn run_api_server(
     socket_addr: SocketAddr,
     metrics_registry: Arc<RwLock<Registry>>,
+    chains: HashMap<ChainName, Arc<ArgusState>>,
     mut exit_rx: watch::Receiver<bool>,
 ) -> Result<()> {
     let api_state = ApiState {
         metrics_registry: metrics_registry.clone(),
+        chains: Arc::new(chains),
     };
 
     let app = Router::new();
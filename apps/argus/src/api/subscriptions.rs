@@ -0,0 +1,70 @@
+//! The funding status of the active subscriptions of a chain.
+
+use {
+    crate::{
+        adapters::types::PriceId,
+        api::ApiState,
+        state::{ChainName, FundingStatus},
+    },
+    axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Json,
+    },
+};
+
+#[derive(Debug, serde::Serialize)]
+pub struct SubscriptionStatus {
+    pub subscription_id: String,
+    pub price_ids: Vec<String>,
+    /// The balance of the subscription, in wei. Amounts are strings, as they may not fit in a JSON number.
+    /// The balance fields are null until the balance of the subscription is first read.
+    pub balance: Option<String>,
+    pub minimum_balance: Option<String>,
+    pub next_update_cost: Option<String>,
+    pub funding_status: Option<FundingStatus>,
+    /// The latest publish time of the on-chain prices of the subscription, if any.
+    pub last_published_time: Option<i64>,
+}
+
+pub async fn subscriptions(
+    State(state): State<ApiState>,
+    Path(chain_name): Path<ChainName>,
+) -> Response {
+    let Some(chain_state) = state.chains.get(&chain_name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Unknown chain: {chain_name}"),
+        )
+            .into_response();
+    };
+
+    let mut statuses: Vec<_> = chain_state
+        .subscription_state
+        .get_subscriptions()
+        .into_iter()
+        .map(|(subscription_id, params)| {
+            let balance = chain_state.subscription_state.get_balance(&subscription_id);
+            SubscriptionStatus {
+                subscription_id: subscription_id.to_string(),
+                price_ids: params
+                    .price_ids
+                    .iter()
+                    .map(|id| PriceId::new(*id).to_hex())
+                    .collect(),
+                balance: balance.map(|b| b.balance.to_string()),
+                minimum_balance: balance.map(|b| b.minimum_balance.to_string()),
+                next_update_cost: balance.map(|b| b.next_update_cost.to_string()),
+                funding_status: balance.map(|b| b.funding_status()),
+                last_published_time: chain_state
+                    .chain_price_state
+                    .get_subscription_prices(&subscription_id)
+                    .and_then(|prices| prices.values().map(|p| p.publish_time).max()),
+            }
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.subscription_id.cmp(&b.subscription_id));
+
+    Json(statuses).into_response()
+}
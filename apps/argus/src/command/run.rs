@@ -7,8 +7,8 @@ use {
         config::{Config, EthereumConfig, RunOptions},
         metrics::KeeperMetrics,
         services::{
-            ChainPriceService, ControllerService, PricePusherService, PythPriceService, Service,
-            SubscriptionService,
+            BalanceService, ChainPriceService, ControllerService, PricePusherService,
+            PythPriceService, Service, SubscriptionService,
         },
         state::ArgusState,
     },
@@ -17,7 +17,7 @@ use {
     ethers::signers::Signer,
     fortuna::eth_utils::traced_client::RpcMetrics,
     prometheus_client::registry::Registry,
-    std::sync::Arc,
+    std::{collections::HashMap, sync::Arc},
     tokio::{
         spawn,
         sync::{watch, RwLock},
@@ -61,7 +61,10 @@ pub async fn run(opts: &RunOptions) -> Result<()> {
     });
 
     // Run keeper services for all chains
+    let mut chain_states = HashMap::new();
     for (chain_name, chain_config) in &config.chains {
+        let state = Arc::new(ArgusState::new());
+        chain_states.insert(chain_name.clone(), state.clone());
         spawn(run_keeper_for_chain(
             keeper_private_key.clone(),
             chain_config.clone(),
@@ -70,25 +73,31 @@ pub async fn run(opts: &RunOptions) -> Result<()> {
             rpc_metrics.clone(),
             exit_rx.clone(),
             config.clone(),
+            state,
         ));
     }
 
     // Run API server for metrics and health checks
-    api::run_api_server(opts.addr, metrics_registry, exit_rx).await?;
+    api::run_api_server(opts.addr, metrics_registry, chain_states, exit_rx).await?;
 
     Ok(())
 }
 
 /// Run keeper services for the given chain
 #[tracing::instrument(skip_all, fields(chain_name))]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the chain state is shared with the API server"
+)]
 pub async fn run_keeper_for_chain(
     private_key: String,
     chain_eth_config: EthereumConfig,
     chain_name: String,
-    _metrics: Arc<KeeperMetrics>, // TODO: add metrics
+    metrics: Arc<KeeperMetrics>,
     rpc_metrics: Arc<RpcMetrics>,
     exit_rx: watch::Receiver<bool>,
     config: Config,
+    state: Arc<ArgusState>,
 ) -> Result<()> {
     tracing::info!("Starting keeper for chain {}", chain_name);
 
@@ -114,9 +123,7 @@ pub async fn run_keeper_for_chain(
         "Keeper address"
     );
 
-    let state = Arc::new(ArgusState::new());
-
-    let hermes_client = Arc::new(HermesClient);
+    let hermes_client = Arc::new(HermesClient::new(&config.hermes)?);
     let backoff_policy = ExponentialBackoff {
         initial_interval: config.keeper.backoff_initial_interval,
         max_interval: config.keeper.backoff_max_interval,
@@ -145,28 +152,45 @@ pub async fn run_keeper_for_chain(
         chain_name.clone(),
         contract.clone(),
         config.keeper.chain_price_poll_interval,
+        state.subscription_state.clone(),
         state.chain_price_state.clone(),
     );
 
+    let balance_service = BalanceService::new(
+        chain_name.clone(),
+        contract.clone(),
+        config.keeper.balance_poll_interval,
+        chain_eth_config.pyth_contract_addr,
+        state.subscription_state.clone(),
+        metrics.clone(),
+    );
+
     let price_pusher_service = PricePusherService::new(
         chain_name.clone(),
         contract.clone(),
+        contract.clone(),
         hermes_client.clone(),
         backoff_policy,
+        chain_eth_config.escalation_policy.clone(),
     );
 
     let controller_service = ControllerService::new(
         chain_name.clone(),
         config.keeper.controller_update_interval,
+        // The pusher gives up on a request after retrying it for this long.
+        config.keeper.backoff_max_elapsed_time,
         state.subscription_state.clone(),
         state.pyth_price_state.clone(),
         state.chain_price_state.clone(),
+        price_pusher_service.request_sender(),
+        config.keeper.replica_config.clone(),
     );
 
     let services: Vec<Arc<dyn Service>> = vec![
         Arc::new(subscription_service),
         Arc::new(pyth_price_service),
         Arc::new(chain_price_service),
+        Arc::new(balance_service),
         Arc::new(price_pusher_service),
         Arc::new(controller_service),
     ];
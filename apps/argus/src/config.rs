@@ -2,10 +2,13 @@
 
 pub use run::RunOptions;
 use {
-    crate::{adapters::ethereum::BlockStatus, state::ChainName},
+    crate::{
+        adapters::{ethereum::BlockStatus, types::SubscriptionId},
+        state::ChainName,
+    },
     anyhow::{anyhow, Result},
     clap::{crate_authors, crate_description, crate_name, crate_version, Args, Parser},
-    ethers::types::Address,
+    ethers::types::{Address, U256},
     fortuna::eth_utils::utils::EscalationPolicy,
     serde::{Deserialize, Serialize},
     std::{collections::HashMap, fs, time::Duration},
@@ -40,6 +43,8 @@ pub struct ConfigOptions {
 pub struct Config {
     pub chains: HashMap<ChainName, EthereumConfig>,
     pub keeper: KeeperConfig,
+    #[serde(default)]
+    pub hermes: HermesConfig,
 }
 
 impl Config {
@@ -51,6 +56,16 @@ impl Config {
         let config: Config = serde_yaml::from_str(&yaml_content)
             .map_err(|e| anyhow!("Failed to parse config file '{}': {}", path, e))?;
 
+        if let Some(replica_config) = &config.keeper.replica_config {
+            if replica_config.replica_id >= replica_config.total_replicas {
+                return Err(anyhow!(
+                    "Invalid replica config: replica_id {} must be less than total_replicas {}",
+                    replica_config.replica_id,
+                    replica_config.total_replicas
+                ));
+            }
+        }
+
         Ok(config)
     }
 
@@ -74,6 +89,12 @@ pub struct EthereumConfig {
     /// Address of a Pyth Pulse contract to interact with.
     pub contract_addr: Address,
 
+    /// Address of the Pyth contract that the Pulse contract verifies the updates with.
+    /// Used to include the Pyth fee in the estimated cost of the updates, which otherwise
+    /// only covers the gas and the keeper fee.
+    #[serde(default)]
+    pub pyth_contract_addr: Option<Address>,
+
     /// The BlockStatus of the block that is considered confirmed.
     /// For example, Finalized, Safe, Latest
     #[serde(default)]
@@ -163,6 +184,61 @@ impl EscalationPolicyConfig {
             fee_multiplier_cap_pct: self.fee_multiplier_cap_pct,
         }
     }
+
+    /// The multiplier to apply to the gas estimate of the tx on the given retry.
+    pub fn get_gas_multiplier_pct(&self, num_retries: u64) -> u64 {
+        let mut current = self.initial_gas_multiplier_pct;
+        let mut i = 0;
+        while i < num_retries && current < self.gas_multiplier_cap_pct {
+            current = current.saturating_mul(self.gas_multiplier_pct) / 100;
+            i += 1;
+        }
+
+        current.min(self.gas_multiplier_cap_pct)
+    }
+
+    /// The multiplier to apply to the fee estimate of the tx on the given retry.
+    pub fn get_fee_multiplier_pct(&self, num_retries: u64) -> u64 {
+        self.to_policy().get_fee_multiplier_pct(num_retries)
+    }
+}
+
+/// The Hermes endpoints that Argus reads the Pyth prices from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HermesConfig {
+    /// URL of the Hermes HTTP API, used to fetch the update data pushed on-chain.
+    #[serde(default = "default_hermes_http_endpoint")]
+    pub http_endpoint: String,
+
+    /// URLs of the Hermes WebSocket endpoints that stream the latest prices.
+    #[serde(default = "default_hermes_ws_endpoints")]
+    pub ws_endpoints: Vec<String>,
+
+    /// The number of redundant WebSocket connections, spread over `ws_endpoints`.
+    #[serde(default = "default_hermes_num_connections")]
+    pub num_connections: usize,
+}
+
+fn default_hermes_http_endpoint() -> String {
+    "https://hermes.pyth.network/".to_string()
+}
+
+fn default_hermes_ws_endpoints() -> Vec<String> {
+    vec!["wss://hermes.pyth.network/ws".to_string()]
+}
+
+fn default_hermes_num_connections() -> usize {
+    2
+}
+
+impl Default for HermesConfig {
+    fn default() -> Self {
+        Self {
+            http_endpoint: default_hermes_http_endpoint(),
+            ws_endpoints: default_hermes_ws_endpoints(),
+            num_connections: default_hermes_num_connections(),
+        }
+    }
 }
 
 /// Configuration values for the keeper service that are shared across chains.
@@ -186,10 +262,14 @@ pub struct KeeperConfig {
     )]
     pub chain_price_poll_interval: Duration,
 
-    /// Interval for polling Pyth prices
+    /// Interval for checking the set of price feeds streamed from Hermes against the active subscriptions
     #[serde(default = "default_pyth_price_poll_interval", with = "humantime_serde")]
     pub pyth_price_poll_interval: Duration,
 
+    /// Interval for polling the balances of the subscriptions
+    #[serde(default = "default_balance_poll_interval", with = "humantime_serde")]
+    pub balance_poll_interval: Duration,
+
     /// Interval for controller updates
     #[serde(
         default = "default_controller_update_interval",
@@ -212,6 +292,58 @@ pub struct KeeperConfig {
     /// Maximum elapsed time for backoff
     #[serde(default = "default_backoff_max_elapsed_time", with = "humantime_serde")]
     pub backoff_max_elapsed_time: Duration,
+
+    /// Configuration for running multiple keepers on the same chains. Each keeper must use a
+    /// different private key. If not provided, this keeper updates all the subscriptions.
+    #[serde(default)]
+    pub replica_config: Option<ReplicaConfig>,
+}
+
+/// Splits the subscriptions among several keepers, so that running them for redundancy doesn't
+/// push each update several times. Each subscription has a primary keeper, chosen by its id, which
+/// updates it as soon as its update criteria are met. The other keepers act as backups, and only
+/// update it if it still needs an update after their delay (see [`ReplicaConfig::delay`]), so that
+/// they don't all push the update at once when the primary keeper is down.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReplicaConfig {
+    /// The index of this keeper, from 0 to `total_replicas - 1`.
+    pub replica_id: u64,
+    pub total_replicas: u64,
+    /// How long each backup keeper waits for the previous keepers to update a subscription.
+    /// This should leave time for the update to land on-chain and for the on-chain prices to be polled.
+    #[serde(default = "default_backup_delay", with = "humantime_serde")]
+    pub backup_delay: Duration,
+}
+
+impl ReplicaConfig {
+    /// Whether this keeper is the primary keeper of the subscription.
+    pub fn is_primary(&self, subscription_id: SubscriptionId) -> bool {
+        subscription_id
+            .checked_rem(U256::from(self.total_replicas))
+            .is_none_or(|index| index == U256::from(self.replica_id))
+    }
+
+    /// The rank of this keeper among the keepers of the subscription: 0 for its primary keeper,
+    /// then 1 for the next replica, 2 for the one after it, and so on.
+    pub fn backup_rank(&self, subscription_id: SubscriptionId) -> u64 {
+        match subscription_id.checked_rem(U256::from(self.total_replicas)) {
+            Some(primary) => {
+                (self.replica_id + self.total_replicas - primary.as_u64()) % self.total_replicas
+            }
+            None => 0,
+        }
+    }
+
+    /// How long this keeper waits before updating the subscription: `backup_delay` times its
+    /// backup rank, so that the backups take over one after the other.
+    pub fn delay(&self, subscription_id: SubscriptionId) -> Duration {
+        let rank = u32::try_from(self.backup_rank(subscription_id)).unwrap_or(u32::MAX);
+        self.backup_delay.saturating_mul(rank)
+    }
+}
+
+fn default_backup_delay() -> Duration {
+    Duration::from_secs(30)
 }
 
 // A secret is a string that can be provided either as a literal in the config,
@@ -251,6 +383,10 @@ fn default_pyth_price_poll_interval() -> Duration {
     Duration::from_secs(10)
 }
 
+fn default_balance_poll_interval() -> Duration {
+    Duration::from_secs(60)
+}
+
 fn default_controller_update_interval() -> Duration {
     Duration::from_secs(10)
 }
@@ -281,11 +417,102 @@ impl Default for KeeperConfig {
             subscription_poll_interval: default_subscription_poll_interval(),
             chain_price_poll_interval: default_chain_price_poll_interval(),
             pyth_price_poll_interval: default_pyth_price_poll_interval(),
+            balance_poll_interval: default_balance_poll_interval(),
             controller_update_interval: default_controller_update_interval(),
             backoff_initial_interval: default_backoff_initial_interval(),
             backoff_max_interval: default_backoff_max_interval(),
             backoff_multiplier: default_backoff_multiplier(),
             backoff_max_elapsed_time: default_backoff_max_elapsed_time(),
+            replica_config: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escalation_policy_multipliers() {
+        let policy = EscalationPolicyConfig {
+            gas_limit_tolerance_pct: 110,
+            initial_gas_multiplier_pct: 125,
+            gas_multiplier_pct: 200,
+            gas_multiplier_cap_pct: 600,
+            fee_multiplier_pct: 150,
+            fee_multiplier_cap_pct: 300,
+        };
+
+        assert_eq!(policy.get_gas_multiplier_pct(0), 125);
+        assert_eq!(policy.get_gas_multiplier_pct(1), 250);
+        assert_eq!(policy.get_gas_multiplier_pct(2), 500);
+        assert_eq!(policy.get_gas_multiplier_pct(3), 600);
+        assert_eq!(policy.get_gas_multiplier_pct(100), 600);
+
+        assert_eq!(policy.get_fee_multiplier_pct(0), 100);
+        assert_eq!(policy.get_fee_multiplier_pct(1), 150);
+        assert_eq!(policy.get_fee_multiplier_pct(2), 225);
+        assert_eq!(policy.get_fee_multiplier_pct(3), 300);
+        assert_eq!(policy.get_fee_multiplier_pct(100), 300);
+    }
+
+    #[test]
+    fn test_replica_sharding() {
+        let replica = |replica_id| ReplicaConfig {
+            replica_id,
+            total_replicas: 3,
+            backup_delay: default_backup_delay(),
+        };
+
+        // Each subscription has exactly one primary keeper.
+        for subscription_id in 0..30u64 {
+            let primaries: Vec<u64> = (0..3)
+                .filter(|id| replica(*id).is_primary(SubscriptionId::from(subscription_id)))
+                .collect();
+            assert_eq!(primaries, vec![subscription_id % 3]);
+        }
+        assert!(replica(2).is_primary(SubscriptionId::MAX - 1));
+
+        // A single keeper is the primary keeper of all the subscriptions.
+        let single = ReplicaConfig {
+            replica_id: 0,
+            total_replicas: 1,
+            backup_delay: default_backup_delay(),
+        };
+        assert!(single.is_primary(SubscriptionId::from(7)));
+        assert_eq!(single.delay(SubscriptionId::from(7)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_staggered_backup_delay() {
+        let replica = |replica_id| ReplicaConfig {
+            replica_id,
+            total_replicas: 3,
+            backup_delay: Duration::from_secs(30),
+        };
+
+        // The primary keeper of subscription 4 is replica 1, then replica 2 and replica 0 take over.
+        let subscription_id = SubscriptionId::from(4);
+        let delays: Vec<Duration> = (0..3)
+            .map(|id| replica(id).delay(subscription_id))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_secs(60),
+                Duration::ZERO,
+                Duration::from_secs(30)
+            ]
+        );
+
+        // The delays of the replicas differ for every subscription.
+        for subscription_id in 0..30u64 {
+            let mut delays: Vec<Duration> = (0..3)
+                .map(|id| replica(id).delay(SubscriptionId::from(subscription_id)))
+                .collect();
+            delays.sort();
+            delays.dedup();
+            assert_eq!(delays.len(), 3);
         }
     }
 }
@@ -51,6 +51,14 @@ pub struct KeeperMetrics {
     pub gas_price_estimate: Family<ChainNameLabel, Gauge<f64, AtomicU64>>,
     /// Keeper wallet balance (in native token) per chain
     pub keeper_wallet_balance: Family<KeeperIdLabel, Gauge<f64, AtomicU64>>,
+    /// Balance (in native token) of an active subscription
+    pub subscription_balance: Family<SubscriptionIdLabel, Gauge<f64, AtomicU64>>,
+    /// Estimated cost (in native token) of the next update of an active subscription
+    pub subscription_next_update_cost: Family<SubscriptionIdLabel, Gauge<f64, AtomicU64>>,
+    /// Number of active subscriptions per chain whose balance is below the minimum balance
+    pub low_balance_subscriptions: Family<ChainNameLabel, Gauge>,
+    /// Number of active subscriptions per chain whose balance cannot pay for their next update
+    pub underfunded_subscriptions: Family<ChainNameLabel, Gauge>,
     /// Duration from the time the keeper notices an eligible update criteria to the time the keeper lands the update on-chain in milliseconds per chain
     pub price_update_latency_ms: Family<PriceFeedIdLabel, Histogram>,
 }
@@ -67,6 +75,10 @@ impl Default for KeeperMetrics {
             failed_price_updates: Family::default(),
             gas_price_estimate: Family::default(),
             keeper_wallet_balance: Family::default(),
+            subscription_balance: Family::default(),
+            subscription_next_update_cost: Family::default(),
+            low_balance_subscriptions: Family::default(),
+            underfunded_subscriptions: Family::default(),
             price_update_latency_ms: Family::new_with_constructor(|| {
                 Histogram::new(vec![
                     100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 20000.0, 30000.0, 60000.0,
@@ -135,6 +147,30 @@ impl KeeperMetrics {
             keeper_metrics.keeper_wallet_balance.clone(),
         );
 
+        writable_registry.register(
+            "subscription_balance",
+            "Balance (in native token) of an active subscription",
+            keeper_metrics.subscription_balance.clone(),
+        );
+
+        writable_registry.register(
+            "subscription_next_update_cost",
+            "Estimated cost (in native token) of the next update of an active subscription",
+            keeper_metrics.subscription_next_update_cost.clone(),
+        );
+
+        writable_registry.register(
+            "low_balance_subscriptions",
+            "Number of active subscriptions per chain whose balance is below the minimum balance",
+            keeper_metrics.low_balance_subscriptions.clone(),
+        );
+
+        writable_registry.register(
+            "underfunded_subscriptions",
+            "Number of active subscriptions per chain whose balance cannot pay for their next update",
+            keeper_metrics.underfunded_subscriptions.clone(),
+        );
+
         writable_registry.register(
             "price_update_latency_ms",
             "Duration from the time the keeper notices an eligible update criteria to the time the keeper lands the update on-chain in milliseconds per chain",
@@ -166,9 +202,15 @@ impl KeeperMetrics {
             let _ = keeper_metrics
                 .gas_price_estimate
                 .get_or_create(&chain_label);
-            // Note: Metrics labeled by KeeperIdLabel or PriceFeedIdLabel (keeper_wallet_balance,
-            // last_published_time_s, price_update_latency_ms) are created dynamically
-            // when their respective identifiers become known.
+            let _ = keeper_metrics
+                .low_balance_subscriptions
+                .get_or_create(&chain_label);
+            let _ = keeper_metrics
+                .underfunded_subscriptions
+                .get_or_create(&chain_label);
+            // Note: Metrics labeled by KeeperIdLabel, PriceFeedIdLabel or SubscriptionIdLabel (keeper_wallet_balance,
+            // last_published_time_s, price_update_latency_ms, subscription_balance, subscription_next_update_cost)
+            // are created dynamically when their respective identifiers become known.
         }
 
         keeper_metrics
@@ -1,3 +1,4 @@
+pub mod balance_service;
 pub mod chain_price_service;
 pub mod controller_service;
 pub mod price_pusher_service;
@@ -5,6 +6,7 @@ pub mod pyth_price_service;
 pub mod subscription_service;
 pub mod types;
 
+pub use balance_service::BalanceService;
 pub use chain_price_service::ChainPriceService;
 pub use controller_service::ControllerService;
 pub use price_pusher_service::PricePusherService;
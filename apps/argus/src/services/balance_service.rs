@@ -0,0 +1,271 @@
+//! Balance Service
+//!
+//! This service periodically reads the balance of each active subscription, along with the
+//! minimum balance the contract requires it to hold, and estimates the cost of its next update.
+//! The results are stored in the SubscriptionState, where the Controller service reads them to
+//! skip the subscriptions that cannot pay for their updates, and are exported as metrics.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::{Address, U256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time;
+use tracing;
+
+use crate::adapters::contract::{ReadSubscriptionBalances, UpdateFees};
+use crate::adapters::types::SubscriptionId;
+use crate::metrics::{ChainNameLabel, KeeperMetrics, SubscriptionIdLabel};
+use crate::services::Service;
+use crate::state::{ChainName, FundingStatus, SubscriptionBalance, SubscriptionState};
+
+/// Estimated gas used by an update, excluding the feeds.
+const UPDATE_GAS_BASE: u64 = 100_000;
+/// Estimated gas used by an update for each of its feeds.
+const UPDATE_GAS_PER_FEED: u64 = 30_000;
+/// The gas the contract charges on top of the gas used by an update, see `GAS_OVERHEAD` in the Scheduler.
+const UPDATE_GAS_OVERHEAD: u64 = 30_000;
+
+pub struct BalanceService {
+    chain_name: ChainName,
+    name: String,
+    contract: Arc<dyn ReadSubscriptionBalances + Send + Sync>,
+    poll_interval: Duration,
+    /// The Pyth contract used by the Pulse contract, to include its fee in the update cost.
+    pyth_contract_addr: Option<Address>,
+    subscription_state: Arc<SubscriptionState>,
+    metrics: Arc<KeeperMetrics>,
+}
+
+impl BalanceService {
+    pub fn new(
+        chain_name: ChainName,
+        contract: Arc<dyn ReadSubscriptionBalances + Send + Sync>,
+        poll_interval: Duration,
+        pyth_contract_addr: Option<Address>,
+        subscription_state: Arc<SubscriptionState>,
+        metrics: Arc<KeeperMetrics>,
+    ) -> Self {
+        Self {
+            chain_name: chain_name.clone(),
+            name: format!("BalanceService-{chain_name}"),
+            contract,
+            poll_interval,
+            pyth_contract_addr,
+            subscription_state,
+            metrics,
+        }
+    }
+
+    async fn poll_balances(&self) {
+        let fees = match self.contract.get_update_fees(self.pyth_contract_addr).await {
+            Ok(fees) => fees,
+            Err(e) => {
+                tracing::error!(
+                    service = self.name,
+                    error = %e,
+                    "Failed to read the update fees"
+                );
+                return;
+            }
+        };
+        self.metrics
+            .gas_price_estimate
+            .get_or_create(&ChainNameLabel {
+                chain_name: self.chain_name.clone(),
+            })
+            .set(to_native_units(fees.gas_price) * 1e9);
+
+        let subscriptions = self.subscription_state.get_subscriptions();
+        let mut low_count: i64 = 0;
+        let mut underfunded_count: i64 = 0;
+        for (subscription_id, params) in &subscriptions {
+            let num_price_feeds = params.price_ids.len();
+            let balance = match self
+                .read_balance(*subscription_id, num_price_feeds, &fees)
+                .await
+            {
+                Ok(balance) => balance,
+                Err(e) => {
+                    tracing::error!(
+                        service = self.name,
+                        subscription_id = subscription_id.to_string(),
+                        error = %e,
+                        "Failed to read subscription balance"
+                    );
+                    continue;
+                }
+            };
+
+            match balance.funding_status() {
+                FundingStatus::Funded => {}
+                FundingStatus::Low => {
+                    low_count += 1;
+                    tracing::warn!(
+                        service = self.name,
+                        subscription_id = subscription_id.to_string(),
+                        balance = %balance.balance,
+                        minimum_balance = %balance.minimum_balance,
+                        "Subscription balance is below the minimum balance"
+                    );
+                }
+                FundingStatus::Underfunded => {
+                    underfunded_count += 1;
+                    tracing::warn!(
+                        service = self.name,
+                        subscription_id = subscription_id.to_string(),
+                        balance = %balance.balance,
+                        next_update_cost = %balance.next_update_cost,
+                        "Subscription balance cannot pay for the next update, skipping its updates"
+                    );
+                }
+            }
+
+            let label = SubscriptionIdLabel {
+                chain_name: self.chain_name.clone(),
+                subscription_id: subscription_id.to_string(),
+            };
+            self.metrics
+                .subscription_balance
+                .get_or_create(&label)
+                .set(to_native_units(balance.balance));
+            self.metrics
+                .subscription_next_update_cost
+                .get_or_create(&label)
+                .set(to_native_units(balance.next_update_cost));
+            self.subscription_state
+                .update_balance(*subscription_id, balance);
+        }
+
+        let chain_label = ChainNameLabel {
+            chain_name: self.chain_name.clone(),
+        };
+        self.metrics
+            .low_balance_subscriptions
+            .get_or_create(&chain_label)
+            .set(low_count);
+        self.metrics
+            .underfunded_subscriptions
+            .get_or_create(&chain_label)
+            .set(underfunded_count);
+
+        tracing::debug!(
+            service = self.name,
+            subscription_count = subscriptions.len(),
+            low_count,
+            underfunded_count,
+            "Polled subscription balances"
+        );
+    }
+
+    async fn read_balance(
+        &self,
+        subscription_id: SubscriptionId,
+        num_price_feeds: usize,
+        fees: &UpdateFees,
+    ) -> Result<SubscriptionBalance> {
+        Ok(SubscriptionBalance {
+            balance: self
+                .contract
+                .get_subscription_balance(subscription_id)
+                .await?,
+            minimum_balance: self.contract.get_required_balance(num_price_feeds).await?,
+            next_update_cost: estimate_update_cost(fees, num_price_feeds),
+        })
+    }
+}
+
+/// Estimate the amount charged to a subscription of `num_price_feeds` feeds for an update:
+/// the gas of the update at the current gas price, plus the keeper and Pyth fees of each feed.
+fn estimate_update_cost(fees: &UpdateFees, num_price_feeds: usize) -> U256 {
+    let num_price_feeds = U256::from(num_price_feeds);
+    let gas = U256::from(UPDATE_GAS_BASE + UPDATE_GAS_OVERHEAD)
+        .saturating_add(U256::from(UPDATE_GAS_PER_FEED).saturating_mul(num_price_feeds));
+    fees.gas_price.saturating_mul(gas).saturating_add(
+        fees.keeper_fee_per_feed
+            .saturating_add(fees.pyth_fee_per_feed)
+            .saturating_mul(num_price_feeds),
+    )
+}
+
+/// Convert an amount in wei to the native token, for the metrics.
+fn to_native_units(amount: U256) -> f64 {
+    // The amounts of the subscriptions never practically exceed u128.
+    u128::try_from(amount).unwrap_or(u128::MAX) as f64 / 1e18
+}
+
+#[async_trait]
+impl Service for BalanceService {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn start(&self, mut stop_rx: watch::Receiver<bool>) -> Result<()> {
+        let mut interval = time::interval(self.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    self.poll_balances().await;
+                }
+                _ = stop_rx.changed() => {
+                    if *stop_rx.borrow() {
+                        tracing::info!(
+                            service = self.name,
+                            "Stopping balance service"
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_estimate_update_cost() {
+        let fees = UpdateFees {
+            gas_price: U256::from(10),
+            keeper_fee_per_feed: U256::from(1_000),
+            pyth_fee_per_feed: U256::from(1),
+        };
+
+        assert_eq!(estimate_update_cost(&fees, 0), U256::from(1_300_000));
+        assert_eq!(
+            estimate_update_cost(&fees, 2),
+            U256::from(10 * (130_000 + 2 * 30_000) + 2 * 1_001)
+        );
+        assert_eq!(
+            estimate_update_cost(
+                &UpdateFees {
+                    gas_price: U256::MAX,
+                    ..fees
+                },
+                1
+            ),
+            U256::MAX
+        );
+    }
+
+    #[test]
+    fn test_funding_status() {
+        let balance = |balance: u64| SubscriptionBalance {
+            balance: U256::from(balance),
+            minimum_balance: U256::from(100),
+            next_update_cost: U256::from(10),
+        };
+
+        assert_eq!(balance(100).funding_status(), FundingStatus::Funded);
+        assert_eq!(balance(99).funding_status(), FundingStatus::Low);
+        assert_eq!(balance(10).funding_status(), FundingStatus::Low);
+        assert_eq!(balance(9).funding_status(), FundingStatus::Underfunded);
+        assert!(FundingStatus::Funded < FundingStatus::Low);
+        assert!(FundingStatus::Low < FundingStatus::Underfunded);
+    }
+}
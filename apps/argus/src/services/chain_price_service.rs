@@ -4,9 +4,11 @@
 //! with latest prices from the target blockchain network. It updates the ChainPriceState
 //! which is read by the Controller service to compare the latest off-chain price with the
 //! on-chain price when deciding whether to update the on-chain price.
+//! The on-chain prices are stored per subscription, as each subscription is updated independently.
 
 use anyhow::Result;
 use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::watch;
@@ -14,17 +16,18 @@ use tokio::time;
 use tracing;
 
 use crate::adapters::contract::GetChainPrices;
+use crate::adapters::types::PriceId;
 use crate::services::Service;
 use crate::state::ChainName;
-use crate::state::ChainPriceState;
+use crate::state::{ChainPriceState, SubscriptionState};
 
 pub struct ChainPriceService {
     #[allow(dead_code, reason = "unknown")]
     chain_name: ChainName,
     name: String,
-    #[allow(dead_code, reason = "unknown")]
     contract: Arc<dyn GetChainPrices + Send + Sync>,
     poll_interval: Duration,
+    subscription_state: Arc<SubscriptionState>,
     chain_price_state: Arc<ChainPriceState>,
 }
 
@@ -33,6 +36,7 @@ impl ChainPriceService {
         chain_name: ChainName,
         contract: Arc<dyn GetChainPrices + Send + Sync>,
         poll_interval: Duration,
+        subscription_state: Arc<SubscriptionState>,
         chain_price_state: Arc<ChainPriceState>,
     ) -> Self {
         Self {
@@ -40,16 +44,54 @@ impl ChainPriceService {
             name: format!("ChainPriceService-{chain_name}"),
             contract,
             poll_interval,
+            subscription_state,
             chain_price_state,
         }
     }
 
     async fn poll_prices(&self, state: Arc<ChainPriceState>) {
-        let feed_ids = state.get_feed_ids();
+        let subscriptions = self.subscription_state.get_subscriptions();
+
+        for (subscription_id, params) in &subscriptions {
+            let mut prices = HashMap::new();
+            let mut complete = true;
+            for feed_id in &params.price_ids {
+                let feed_id = PriceId::new(*feed_id);
+                match self
+                    .contract
+                    .get_price_unsafe(*subscription_id, &feed_id)
+                    .await
+                {
+                    Ok(Some(price)) => {
+                        prices.insert(feed_id, price);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::error!(
+                            service = self.name,
+                            subscription_id = subscription_id.to_string(),
+                            feed_id = feed_id.to_string(),
+                            error = %e,
+                            "Failed to read on-chain price"
+                        );
+                        complete = false;
+                        break;
+                    }
+                }
+            }
+
+            // Keep the previous prices if any read failed, as a missing on-chain price
+            // would be taken for a feed that was never updated.
+            if complete {
+                state.update_subscription_prices(*subscription_id, prices);
+            }
+        }
+
+        state.retain_subscriptions(&subscriptions.keys().cloned().collect::<HashSet<_>>());
 
         tracing::debug!(
             service = self.name,
-            feed_count = feed_ids.len(),
+            subscription_count = subscriptions.len(),
             "Polled for on-chain price updates"
         );
     }
@@ -4,48 +4,82 @@
 //! It reads from the SubscriptionState, PythPriceState, and ChainPriceState to determine
 //! whether to update the on-chain price for a given subscription. It also triggers the
 //! PricePusherService to push the update to the target blockchain network.
+//! Subscriptions whose balance cannot pay for their next update are skipped, and those
+//! with a low balance are handled after the funded ones.
+//! When several keepers run on the same chain, each subscription is pushed right away by its
+//! primary keeper only; the backup keepers push it if it still needs an update after a delay.
 
 use anyhow::Result;
 use async_trait::async_trait;
+use dashmap::DashMap;
+use pyth_sdk::Price;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::watch;
-use tokio::time;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{self, Instant};
 use tracing;
 
+use crate::adapters::ethereum::SubscriptionParams;
 use crate::adapters::types::{PriceId, SubscriptionId};
+use crate::config::ReplicaConfig;
 use crate::services::types::PushRequest;
 use crate::services::Service;
 use crate::state::ChainName;
-use crate::state::{ChainPriceState, PythPriceState, SubscriptionState};
+use crate::state::{ChainPriceState, FundingStatus, PythPriceState, SubscriptionState};
 
 pub struct ControllerService {
     name: String,
     update_interval: Duration,
+    /// How long to wait for a triggered push to land on-chain before triggering it again.
+    push_timeout: Duration,
     subscription_state: Arc<SubscriptionState>,
     pyth_price_state: Arc<PythPriceState>,
     chain_price_state: Arc<ChainPriceState>,
+    request_tx: mpsc::Sender<PushRequest>,
+    /// The Pyth publish time of the last push triggered for each subscription, and when it was triggered.
+    pending_pushes: DashMap<SubscriptionId, (i64, Instant)>,
+    replica_config: Option<ReplicaConfig>,
+    /// When the subscriptions of other keepers started needing an update, while this keeper waits
+    /// for the backup delay.
+    due_since: DashMap<SubscriptionId, Instant>,
 }
 
 impl ControllerService {
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "the service reads all the chain states"
+    )]
     pub fn new(
         chain_name: ChainName,
         update_interval: Duration,
+        push_timeout: Duration,
         subscription_state: Arc<SubscriptionState>,
         pyth_price_state: Arc<PythPriceState>,
         chain_price_state: Arc<ChainPriceState>,
+        request_tx: mpsc::Sender<PushRequest>,
+        replica_config: Option<ReplicaConfig>,
     ) -> Self {
         Self {
             name: format!("ControllerService-{chain_name}"),
             update_interval,
+            push_timeout,
             subscription_state,
             pyth_price_state,
             chain_price_state,
+            request_tx,
+            pending_pushes: DashMap::new(),
+            replica_config,
+            due_since: DashMap::new(),
         }
     }
 
     async fn perform_update(&self) {
         let subscriptions = self.subscription_state.get_subscriptions();
+        self.pending_pushes
+            .retain(|id, _| subscriptions.contains_key(id));
+        self.due_since
+            .retain(|id, _| subscriptions.contains_key(id));
 
         tracing::debug!(
             service = self.name,
@@ -53,30 +87,105 @@ impl ControllerService {
             "Checking subscriptions for updates"
         );
 
-        for (sub_id, params) in subscriptions {
-            let needs_update = false;
-            let mut feed_ids: Vec<PriceId> = Vec::new();
+        // Subscriptions whose balance was not read yet are assumed to be funded; the contract
+        // rejects the update anyway if they are not.
+        let mut subscriptions: Vec<_> = subscriptions
+            .into_iter()
+            .map(|(sub_id, params)| {
+                let funding_status = self
+                    .subscription_state
+                    .get_balance(&sub_id)
+                    .map_or(FundingStatus::Funded, |balance| balance.funding_status());
+                (sub_id, params, funding_status)
+            })
+            .collect();
+        subscriptions.sort_by_key(|(_, _, funding_status)| *funding_status);
 
-            for feed_id in &params.price_ids {
-                let feed_id = PriceId::new(*feed_id);
-                let pyth_price = self.pyth_price_state.get_price(&feed_id);
-                let chain_price = self.chain_price_state.get_price(&feed_id);
+        for (sub_id, params, funding_status) in subscriptions {
+            if funding_status == FundingStatus::Underfunded {
+                tracing::debug!(
+                    service = self.name,
+                    subscription_id = sub_id.to_string(),
+                    "Skipping underfunded subscription"
+                );
+                continue;
+            }
+
+            // The contract only accepts updates of all the feeds of a subscription at once.
+            let feed_ids: Vec<PriceId> = params
+                .price_ids
+                .iter()
+                .map(|id| PriceId::new(*id))
+                .collect();
+            let pyth_prices: Option<HashMap<PriceId, Price>> = feed_ids
+                .iter()
+                .map(|feed_id| Some((*feed_id, self.pyth_price_state.get_price(feed_id)?)))
+                .collect();
+            let Some(pyth_prices) = pyth_prices else {
+                continue;
+            };
+            // Wait for the on-chain prices to be polled, so we don't take them for missing.
+            let Some(chain_prices) = self.chain_price_state.get_subscription_prices(&sub_id) else {
+                continue;
+            };
 
-                if pyth_price.is_none() || chain_price.is_none() {
+            if let Some(pending) = self.pending_pushes.get(&sub_id).map(|r| *r.value()) {
+                let (publish_time, triggered_at) = pending;
+                let on_chain_publish_time = chain_prices.values().map(|p| p.publish_time).max();
+                if on_chain_publish_time < Some(publish_time)
+                    && triggered_at.elapsed() < self.push_timeout
+                {
                     continue;
                 }
-
-                feed_ids.push(feed_id);
+                self.pending_pushes.remove(&sub_id);
             }
 
-            // TODO: this never happens
-            if needs_update && !feed_ids.is_empty() {
-                self.trigger_update(sub_id, feed_ids).await;
+            if !should_update(&params, &pyth_prices, &chain_prices) {
+                // Nothing to do, e.g. because the primary keeper already updated it.
+                self.due_since.remove(&sub_id);
+                continue;
             }
+            if self.is_waiting_for_primary(sub_id, Instant::now()) {
+                continue;
+            }
+
+            let publish_time = pyth_prices
+                .values()
+                .map(|p| p.publish_time)
+                .max()
+                .unwrap_or_default();
+            self.trigger_update(sub_id, feed_ids, publish_time).await;
+        }
+    }
+
+    /// Whether this keeper is a backup of the subscription and should leave its update to the
+    /// keepers before it. Its delay (see [`ReplicaConfig::delay`]) starts at `now` the first time
+    /// the subscription needs an update, and resets once it no longer does.
+    fn is_waiting_for_primary(&self, subscription_id: SubscriptionId, now: Instant) -> bool {
+        let Some(replica_config) = &self.replica_config else {
+            return false;
+        };
+        if replica_config.is_primary(subscription_id) {
+            return false;
         }
+        let due_since = *self.due_since.entry(subscription_id).or_insert(now);
+        if now.saturating_duration_since(due_since) < replica_config.delay(subscription_id) {
+            return true;
+        }
+        tracing::info!(
+            service = self.name,
+            subscription_id = subscription_id.to_string(),
+            "Subscription still needs an update after the backup delay, pushing as backup keeper"
+        );
+        false
     }
 
-    async fn trigger_update(&self, subscription_id: SubscriptionId, price_ids: Vec<PriceId>) {
+    async fn trigger_update(
+        &self,
+        subscription_id: SubscriptionId,
+        price_ids: Vec<PriceId>,
+        publish_time: i64,
+    ) {
         tracing::info!(
             service = self.name,
             subscription_id = subscription_id.to_string(),
@@ -84,19 +193,84 @@ impl ControllerService {
             "Triggering price update"
         );
 
-        let _request = PushRequest {
+        let request = PushRequest {
             subscription_id,
             price_ids,
+            publish_time,
         };
 
-        tracing::debug!(
-            service = self.name,
-            "Would push update for subscription {}",
-            subscription_id
-        );
+        match self.request_tx.send(request).await {
+            Ok(()) => {
+                self.pending_pushes
+                    .insert(subscription_id, (publish_time, Instant::now()));
+            }
+            Err(e) => {
+                tracing::error!(
+                    service = self.name,
+                    subscription_id = subscription_id.to_string(),
+                    error = %e,
+                    "Failed to send push request"
+                );
+            }
+        }
     }
 }
 
+/// Whether the latest Pyth prices of all the feeds of a subscription satisfy its update criteria
+/// against its on-chain prices. This mirrors the checks of the Pulse contract, which rejects updates
+/// that do not meet the criteria.
+fn should_update(
+    params: &SubscriptionParams,
+    pyth_prices: &HashMap<PriceId, Price>,
+    chain_prices: &HashMap<PriceId, Price>,
+) -> bool {
+    let Some(update_time) = pyth_prices.values().map(|p| p.publish_time).max() else {
+        return false;
+    };
+    let last_update_time = chain_prices.values().map(|p| p.publish_time).max();
+
+    // The contract rejects updates that are not newer than the on-chain prices.
+    if let Some(last_update_time) = last_update_time {
+        if update_time <= last_update_time {
+            return false;
+        }
+    }
+
+    let criteria = &params.update_criteria;
+    if criteria.update_on_heartbeat {
+        match last_update_time {
+            None => return true,
+            Some(last_update_time)
+                if update_time
+                    >= last_update_time.saturating_add(criteria.heartbeat_seconds.into()) =>
+            {
+                return true
+            }
+            Some(_) => {}
+        }
+    }
+
+    if criteria.update_on_deviation {
+        for (feed_id, pyth_price) in pyth_prices {
+            let Some(chain_price) = chain_prices.get(feed_id) else {
+                return true;
+            };
+            if chain_price.price == 0 || pyth_price.price == 0 {
+                continue;
+            }
+            let deviation_bps = (i128::from(pyth_price.price) - i128::from(chain_price.price))
+                .unsigned_abs()
+                * 10_000
+                / u128::from(chain_price.price.unsigned_abs());
+            if deviation_bps >= u128::from(criteria.deviation_threshold_bps) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
 #[async_trait]
 impl Service for ControllerService {
     fn name(&self) -> &str {
@@ -126,3 +300,221 @@ impl Service for ControllerService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::adapters::ethereum::UpdateCriteria};
+
+    fn btc() -> PriceId {
+        PriceId::new([1; 32])
+    }
+
+    fn eth() -> PriceId {
+        PriceId::new([2; 32])
+    }
+
+    fn subscription(update_criteria: UpdateCriteria) -> SubscriptionParams {
+        SubscriptionParams {
+            price_ids: vec![btc().to_bytes(), eth().to_bytes()],
+            reader_whitelist: vec![],
+            whitelist_enabled: false,
+            is_active: true,
+            is_permanent: false,
+            update_criteria,
+        }
+    }
+
+    fn heartbeat(heartbeat_seconds: u32) -> UpdateCriteria {
+        UpdateCriteria {
+            update_on_heartbeat: true,
+            heartbeat_seconds,
+            update_on_deviation: false,
+            deviation_threshold_bps: 0,
+        }
+    }
+
+    fn deviation(deviation_threshold_bps: u32) -> UpdateCriteria {
+        UpdateCriteria {
+            update_on_heartbeat: false,
+            heartbeat_seconds: 0,
+            update_on_deviation: true,
+            deviation_threshold_bps,
+        }
+    }
+
+    fn prices(btc_price: (i64, i64), eth_price: (i64, i64)) -> HashMap<PriceId, Price> {
+        let price = |(price, publish_time)| Price {
+            price,
+            conf: 1,
+            expo: -8,
+            publish_time,
+        };
+        [(btc(), price(btc_price)), (eth(), price(eth_price))]
+            .into_iter()
+            .collect()
+    }
+
+    fn controller(replica_config: Option<ReplicaConfig>) -> ControllerService {
+        let (request_tx, _) = mpsc::channel(1);
+        ControllerService::new(
+            "ethereum".to_string(),
+            Duration::from_secs(1),
+            Duration::from_secs(60),
+            Arc::new(SubscriptionState::new()),
+            Arc::new(PythPriceState::new()),
+            Arc::new(ChainPriceState::new()),
+            request_tx,
+            replica_config,
+        )
+    }
+
+    #[test]
+    fn test_backup_delay() {
+        let now = Instant::now();
+        let primary = SubscriptionId::from(2);
+        let backup = SubscriptionId::from(3);
+
+        // A single keeper never waits.
+        assert!(!controller(None).is_waiting_for_primary(backup, now));
+
+        let controller = controller(Some(ReplicaConfig {
+            replica_id: 0,
+            total_replicas: 2,
+            backup_delay: Duration::from_secs(30),
+        }));
+        assert!(!controller.is_waiting_for_primary(primary, now));
+
+        // The backup keeper waits for the delay from the first time the update was due.
+        assert!(controller.is_waiting_for_primary(backup, now));
+        assert!(controller.is_waiting_for_primary(backup, now + Duration::from_secs(29)));
+        assert!(!controller.is_waiting_for_primary(backup, now + Duration::from_secs(30)));
+
+        // The delay starts over once the subscription was updated.
+        controller.due_since.remove(&backup);
+        assert!(controller.is_waiting_for_primary(backup, now + Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn test_heartbeat_criteria() {
+        let params = subscription(heartbeat(60));
+        let chain_prices = prices((100, 1000), (200, 1000));
+
+        // Never updated on-chain.
+        assert!(should_update(
+            &params,
+            &prices((100, 1000), (200, 1000)),
+            &HashMap::new()
+        ));
+        // Not newer than the on-chain prices.
+        assert!(!should_update(
+            &params,
+            &prices((100, 1000), (200, 1000)),
+            &chain_prices
+        ));
+        // Within the heartbeat, no matter how much the price moved.
+        assert!(!should_update(
+            &params,
+            &prices((1000, 1059), (200, 1030)),
+            &chain_prices
+        ));
+        // The heartbeat is measured from the latest publish time of the feeds.
+        assert!(should_update(
+            &params,
+            &prices((100, 1000), (200, 1060)),
+            &chain_prices
+        ));
+        assert!(should_update(
+            &params,
+            &prices((100, 2000), (200, 2000)),
+            &chain_prices
+        ));
+    }
+
+    #[test]
+    fn test_deviation_criteria() {
+        // 1%
+        let params = subscription(deviation(100));
+        let chain_prices = prices((10_000, 1000), (-20_000, 1000));
+
+        // Never updated on-chain.
+        assert!(should_update(
+            &params,
+            &prices((10_000, 1001), (-20_000, 1001)),
+            &HashMap::new()
+        ));
+        // One of the feeds was never updated on-chain.
+        assert!(should_update(
+            &params,
+            &prices((10_000, 1001), (-20_000, 1001)),
+            &prices((10_000, 1000), (-20_000, 1000))
+                .into_iter()
+                .filter(|(id, _)| *id == btc())
+                .collect()
+        ));
+        // Below the threshold, no matter how old the on-chain prices are.
+        assert!(!should_update(
+            &params,
+            &prices((10_099, 100_000), (-19_801, 100_000)),
+            &chain_prices
+        ));
+        // At the threshold, in either direction.
+        assert!(should_update(
+            &params,
+            &prices((10_100, 1001), (-20_000, 1001)),
+            &chain_prices
+        ));
+        assert!(should_update(
+            &params,
+            &prices((10_000, 1001), (-19_800, 1001)),
+            &chain_prices
+        ));
+        // A deviated price that is not newer than the on-chain prices is rejected.
+        assert!(!should_update(
+            &params,
+            &prices((20_000, 1000), (-20_000, 1000)),
+            &chain_prices
+        ));
+        // Zero prices are skipped.
+        assert!(!should_update(
+            &params,
+            &prices((0, 1001), (-20_000, 1001)),
+            &chain_prices
+        ));
+    }
+
+    #[test]
+    fn test_combined_criteria() {
+        let params = subscription(UpdateCriteria {
+            update_on_heartbeat: true,
+            heartbeat_seconds: 60,
+            update_on_deviation: true,
+            deviation_threshold_bps: 100,
+        });
+        let chain_prices = prices((10_000, 1000), (20_000, 1000));
+
+        // Neither criterion is met.
+        assert!(!should_update(
+            &params,
+            &prices((10_050, 1030), (20_000, 1030)),
+            &chain_prices
+        ));
+        // Only the heartbeat is met.
+        assert!(should_update(
+            &params,
+            &prices((10_050, 1060), (20_000, 1060)),
+            &chain_prices
+        ));
+        // Only the deviation is met.
+        assert!(should_update(
+            &params,
+            &prices((10_000, 1030), (20_200, 1030)),
+            &chain_prices
+        ));
+        // Both are met.
+        assert!(should_update(
+            &params,
+            &prices((10_000, 1060), (20_200, 1060)),
+            &chain_prices
+        ));
+    }
+}
@@ -5,16 +5,20 @@
 //! It is used by the Controller service to update the on-chain price when the update criteria
 //! is met for a given subscription.
 //! The service handles retries and gas escalation to ensure the price update is successful.
+//! Before each attempt, it checks the on-chain publish time of the subscription, and cancels
+//! the push if the on-chain prices are already as recent as the ones that triggered it.
 
 use anyhow::{anyhow, Context as _, Result};
 use async_trait::async_trait;
 use backoff::ExponentialBackoff;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::sync::{mpsc, watch};
 use tracing;
 
-use crate::adapters::contract::UpdateChainPrices;
+use crate::adapters::contract::{GetChainPrices, UpdateChainPrices};
 use crate::adapters::hermes::ReadPythPrices;
+use crate::config::EscalationPolicyConfig;
 use crate::services::types::PushRequest;
 use crate::services::Service;
 use crate::state::ChainName;
@@ -24,9 +28,10 @@ pub struct PricePusherService {
     chain_name: ChainName,
     name: String,
     contract: Arc<dyn UpdateChainPrices + Send + Sync>,
+    chain_price_reader: Arc<dyn GetChainPrices + Send + Sync>,
     pyth_price_client: Arc<dyn ReadPythPrices + Send + Sync>,
-    #[allow(dead_code, reason = "unknown")]
     backoff_policy: ExponentialBackoff,
+    escalation_policy: EscalationPolicyConfig,
     request_rx: Mutex<Option<mpsc::Receiver<PushRequest>>>,
     request_tx: mpsc::Sender<PushRequest>,
 }
@@ -35,8 +40,10 @@ impl PricePusherService {
     pub fn new(
         chain_name: ChainName,
         contract: Arc<dyn UpdateChainPrices + Send + Sync>,
+        chain_price_reader: Arc<dyn GetChainPrices + Send + Sync>,
         pyth_price_client: Arc<dyn ReadPythPrices + Send + Sync>,
         backoff_policy: ExponentialBackoff,
+        escalation_policy: EscalationPolicyConfig,
     ) -> Self {
         let (request_tx, request_rx) = mpsc::channel(100);
 
@@ -44,8 +51,10 @@ impl PricePusherService {
             chain_name: chain_name.clone(),
             name: format!("PricePusherService-{chain_name}"),
             contract,
+            chain_price_reader,
             pyth_price_client,
             backoff_policy,
+            escalation_policy,
             request_rx: Mutex::new(Some(request_rx)),
             request_tx,
         }
@@ -65,38 +74,91 @@ impl PricePusherService {
     )]
     async fn handle_request(&self, request: PushRequest) {
         let price_ids = request.price_ids.clone();
+        let num_retries = AtomicU64::new(0);
 
-        match self.pyth_price_client.get_latest_prices(&price_ids).await {
-            Ok(update_data) => {
+        // Fetch fresh update data on each attempt, as the contract rejects updates that are
+        // not newer than the on-chain prices. The gas and fee escalate with the retries.
+        let result = backoff::future::retry_notify(
+            self.backoff_policy.clone(),
+            || async {
+                let retry = num_retries.load(Ordering::Relaxed);
+                // Don't pay for a push that the on-chain prices already made obsolete. If the
+                // publish time can't be read, push anyway; the contract rejects stale updates.
                 match self
-                    .contract
-                    .update_price_feeds(request.subscription_id, &price_ids, &update_data)
+                    .chain_price_reader
+                    .get_last_published_time(request.subscription_id)
                     .await
                 {
-                    Ok(tx_hash) => {
-                        tracing::info!(
-                            service = self.name,
-                            subscription_id = request.subscription_id.to_string(),
-                            tx_hash = tx_hash.to_string(),
-                            "Successfully pushed price updates"
-                        );
+                    Ok(last_published_time) if last_published_time >= request.publish_time => {
+                        return Ok(None);
                     }
+                    Ok(_) => {}
                     Err(e) => {
-                        tracing::error!(
+                        tracing::warn!(
                             service = self.name,
                             subscription_id = request.subscription_id.to_string(),
                             error = %e,
-                            "Failed to push price updates"
+                            "Failed to read the on-chain publish time, pushing anyway"
                         );
                     }
                 }
+                let update_data = self
+                    .pyth_price_client
+                    .get_latest_prices(&price_ids)
+                    .await
+                    .map_err(|e| {
+                        backoff::Error::transient(e.context("Failed to get Pyth price update data"))
+                    })?;
+                self.contract
+                    .update_price_feeds(
+                        request.subscription_id,
+                        &price_ids,
+                        &update_data,
+                        self.escalation_policy.get_gas_multiplier_pct(retry),
+                        self.escalation_policy.get_fee_multiplier_pct(retry),
+                    )
+                    .await
+                    .map(Some)
+                    .map_err(backoff::Error::transient)
+            },
+            |e, dur| {
+                let retry = num_retries.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    service = self.name,
+                    subscription_id = request.subscription_id.to_string(),
+                    "Error on retry {} at duration {:?}: {:?}",
+                    retry,
+                    dur,
+                    e
+                );
+            },
+        )
+        .await;
+
+        match result {
+            Ok(None) => {
+                tracing::info!(
+                    service = self.name,
+                    subscription_id = request.subscription_id.to_string(),
+                    publish_time = request.publish_time,
+                    "On-chain prices are already up to date, cancelled the push"
+                );
+            }
+            Ok(Some(tx_hash)) => {
+                tracing::info!(
+                    service = self.name,
+                    subscription_id = request.subscription_id.to_string(),
+                    tx_hash = tx_hash.to_string(),
+                    num_retries = num_retries.load(Ordering::Relaxed),
+                    "Successfully pushed price updates"
+                );
             }
             Err(e) => {
                 tracing::error!(
                     service = self.name,
                     subscription_id = request.subscription_id.to_string(),
                     error = %e,
-                    "Failed to get Pyth price update data"
+                    "Failed to push price updates"
                 );
             }
         }
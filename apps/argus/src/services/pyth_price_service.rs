@@ -4,9 +4,12 @@
 //! with latest prices from the Pyth Network. It updates the PythPriceState, which is read
 //! by the Controller service to compare the latest off-chain price with the on-chain price
 //! when deciding whether to update the on-chain price.
+//! The prices are streamed from Hermes. The streamed feeds follow the tracked feeds, which
+//! the Subscription service updates as subscriptions change.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use futures::StreamExt;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::watch;
@@ -49,30 +52,44 @@ impl Service for PythPriceService {
     }
 
     async fn start(&self, mut exit_rx: watch::Receiver<bool>) -> Result<()> {
+        let mut feed_ids = self.pyth_price_state.get_feed_ids();
+        let feed_ids_vec: Vec<_> = feed_ids.iter().cloned().collect();
+        let mut price_updates = self
+            .pyth_price_client
+            .subscribe_to_price_updates(&feed_ids_vec)
+            .await?;
+
         let mut interval_timer = tokio::time::interval(self.poll_interval);
 
         loop {
             tokio::select! {
+                update = price_updates.next() => {
+                    let Some((feed_id, price)) = update else {
+                        return Err(anyhow!("The Pyth price stream ended"));
+                    };
+                    self.pyth_price_state.update_price(feed_id, price);
+                }
                 _ = interval_timer.tick() => {
-                    let feed_ids = self.pyth_price_state.get_feed_ids();
-                    if !feed_ids.is_empty() {
-                        let feed_ids_vec: Vec<_> = feed_ids.iter().cloned().collect();
-                        match self.pyth_price_client.get_latest_prices(&feed_ids_vec).await {
-                            Ok(_prices_data) => {
+                    let tracked_feed_ids = self.pyth_price_state.get_feed_ids();
+                    if tracked_feed_ids != feed_ids {
+                        match self
+                            .pyth_price_client
+                            .update_price_subscription(&tracked_feed_ids)
+                            .await
+                        {
+                            Ok(()) => {
                                 tracing::debug!(
                                     service = self.name,
-                                    feed_count = feed_ids_vec.len(),
-                                    "Successfully polled Pyth prices"
+                                    feed_count = tracked_feed_ids.len(),
+                                    "Updated the streamed Pyth price feeds"
                                 );
-                                // TODO: update the prices in the state
-                                // self.pyth_price_state.update_prices(prices_data);
-
+                                feed_ids = tracked_feed_ids;
                             }
                             Err(e) => {
                                 tracing::error!(
                                     service = self.name,
                                     error = %e,
-                                    "Failed to poll Pyth prices"
+                                    "Failed to update the streamed Pyth price feeds"
                                 );
                             }
                         }
@@ -17,4 +17,7 @@ pub trait Service: Send + Sync {
 pub struct PushRequest {
     pub subscription_id: crate::adapters::types::SubscriptionId,
     pub price_ids: Vec<crate::adapters::types::PriceId>,
+    /// The latest publish time of the Pyth prices that triggered the push. The push is cancelled
+    /// once the on-chain prices are at least as recent, e.g. because another keeper pushed them.
+    pub publish_time: i64,
 }
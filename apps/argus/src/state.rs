@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use crate::adapters::ethereum::SubscriptionParams;
 use crate::adapters::types::{PriceId, SubscriptionId};
+use ethers::types::U256;
 use pyth_sdk::Price;
 
 pub type ChainName = String;
@@ -26,10 +27,46 @@ impl ArgusState {
 }
 
 /// The state of active subscriptions for a single blockchain.
-/// Updated by the SubscriptionService.
+/// Updated by the SubscriptionService, and by the BalanceService for the balances.
 #[derive(Default)]
 pub struct SubscriptionState {
     subscriptions: DashMap<SubscriptionId, SubscriptionParams>,
+    balances: DashMap<SubscriptionId, SubscriptionBalance>,
+}
+
+/// The funds of a subscription, in wei.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionBalance {
+    pub balance: U256,
+    /// The minimum balance the contract requires the subscription to hold.
+    pub minimum_balance: U256,
+    /// The estimated amount charged to the subscription for its next update.
+    pub next_update_cost: U256,
+}
+
+impl SubscriptionBalance {
+    pub fn funding_status(&self) -> FundingStatus {
+        if self.balance < self.next_update_cost {
+            FundingStatus::Underfunded
+        } else if self.balance < self.minimum_balance {
+            FundingStatus::Low
+        } else {
+            FundingStatus::Funded
+        }
+    }
+}
+
+/// Whether a subscription can pay for its updates, from best to worst.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FundingStatus {
+    Funded,
+    /// The balance is below the minimum balance, but can still pay for the next update.
+    Low,
+    /// The balance cannot pay for the next update, which would revert.
+    Underfunded,
 }
 
 impl SubscriptionState {
@@ -50,11 +87,23 @@ impl SubscriptionState {
 
     pub fn update_subscriptions(&self, subscriptions: HashMap<SubscriptionId, SubscriptionParams>) {
         self.subscriptions.clear();
+        self.balances.retain(|id, _| subscriptions.contains_key(id));
         for (id, params) in subscriptions {
             self.subscriptions.insert(id, params);
         }
     }
 
+    /// The balance of the subscription, or None if it was not read yet.
+    pub fn get_balance(&self, id: &SubscriptionId) -> Option<SubscriptionBalance> {
+        self.balances.get(id).map(|r| *r.value())
+    }
+
+    pub fn update_balance(&self, id: SubscriptionId, balance: SubscriptionBalance) {
+        if self.subscriptions.contains_key(&id) {
+            self.balances.insert(id, balance);
+        }
+    }
+
     pub fn get_feed_ids(&self) -> HashSet<PriceId> {
         let mut feed_ids: HashSet<PriceId> = HashSet::new();
         for entry in self.subscriptions.iter() {
@@ -105,11 +154,13 @@ impl PythPriceState {
     }
 }
 
-/// Stores the latest on-chain prices for a given set of price feeds.
+/// Stores the latest on-chain prices of the price feeds of each subscription.
 /// Updated by the ChainPriceService.
 #[derive(Default)]
 pub struct ChainPriceState {
-    prices: DashMap<PriceId, Price>,
+    /// The on-chain prices of each subscription that was polled. Feeds that were never
+    /// updated for a subscription are absent from its prices.
+    prices: DashMap<SubscriptionId, HashMap<PriceId, Price>>,
     feed_ids: DashMap<PriceId, ()>,
 }
 
@@ -118,18 +169,31 @@ impl ChainPriceState {
         Self::default()
     }
 
-    pub fn get_price(&self, feed_id: &PriceId) -> Option<Price> {
-        self.prices.get(feed_id).map(|r| *r.value())
+    pub fn get_price(&self, subscription_id: &SubscriptionId, feed_id: &PriceId) -> Option<Price> {
+        self.prices
+            .get(subscription_id)
+            .and_then(|r| r.value().get(feed_id).copied())
     }
 
-    pub fn update_price(&self, feed_id: PriceId, price: Price) {
-        self.prices.insert(feed_id, price);
+    /// The on-chain prices of the subscription, or None if they were not polled yet.
+    pub fn get_subscription_prices(
+        &self,
+        subscription_id: &SubscriptionId,
+    ) -> Option<HashMap<PriceId, Price>> {
+        self.prices.get(subscription_id).map(|r| r.value().clone())
     }
 
-    pub fn update_prices(&self, prices: HashMap<PriceId, Price>) {
-        for (feed_id, price) in prices {
-            self.prices.insert(feed_id, price);
-        }
+    pub fn update_subscription_prices(
+        &self,
+        subscription_id: SubscriptionId,
+        prices: HashMap<PriceId, Price>,
+    ) {
+        self.prices.insert(subscription_id, prices);
+    }
+
+    /// Forget the prices of the subscriptions that are not in `subscription_ids`.
+    pub fn retain_subscriptions(&self, subscription_ids: &HashSet<SubscriptionId>) {
+        self.prices.retain(|id, _| subscription_ids.contains(id));
     }
 
     pub fn update_feed_ids(&self, feed_ids: HashSet<PriceId>) {
@@ -0,0 +1,100 @@
+//! Compares the in-memory sampled hash chain with the hierarchical one, with and without on-disk
+//! checkpoints. Run with `cargo bench --bench hash_chain`.
+
+use {
+    criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion},
+    fortuna::state::{hierarchical::HierarchicalHashChain, HashChain, PebbleHashChain},
+};
+
+const SECRET: [u8; 32] = [7u8; 32];
+const LENGTHS: [usize; 2] = [10_000, 100_000];
+const REVEALS: usize = 1_000;
+
+fn construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construction");
+    group.sample_size(10);
+    for length in LENGTHS {
+        group.bench_with_input(BenchmarkId::new("sampled/1", length), &length, |b, &n| {
+            b.iter(|| PebbleHashChain::new(SECRET, n, 1))
+        });
+        group.bench_with_input(BenchmarkId::new("sampled/100", length), &length, |b, &n| {
+            b.iter(|| PebbleHashChain::new(SECRET, n, 100))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("hierarchical", length),
+            &length,
+            |b, &n| {
+                b.iter(|| {
+                    let chain = HierarchicalHashChain::new(SECRET, n);
+                    chain.reveal_ith(0).unwrap();
+                    chain
+                })
+            },
+        );
+
+        // Loading from an existing checkpoint file is what happens on every restart.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain");
+        HierarchicalHashChain::with_checkpoints(SECRET, length, &path, 100).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("hierarchical/checkpoints", length),
+            &length,
+            |b, &n| {
+                b.iter(|| {
+                    let chain =
+                        HierarchicalHashChain::with_checkpoints(SECRET, n, &path, 100).unwrap();
+                    chain.reveal_ith(0).unwrap();
+                    chain
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn sequential_reveals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sequential_reveals");
+    for length in LENGTHS {
+        let sampled_1 = PebbleHashChain::new(SECRET, length, 1);
+        group.bench_with_input(BenchmarkId::new("sampled/1", length), &length, |b, _| {
+            b.iter(|| {
+                for i in 0..REVEALS {
+                    sampled_1.reveal_ith(i).unwrap();
+                }
+            })
+        });
+        let sampled_100 = PebbleHashChain::new(SECRET, length, 100);
+        group.bench_with_input(BenchmarkId::new("sampled/100", length), &length, |b, _| {
+            b.iter(|| {
+                for i in 0..REVEALS {
+                    sampled_100.reveal_ith(i).unwrap();
+                }
+            })
+        });
+
+        // Each iteration starts from a warmed up chain, as the server does after startup.
+        group.bench_with_input(
+            BenchmarkId::new("hierarchical", length),
+            &length,
+            |b, &n| {
+                b.iter_batched(
+                    || {
+                        let chain = HierarchicalHashChain::new(SECRET, n);
+                        chain.reveal_ith(0).unwrap();
+                        chain
+                    },
+                    |chain| {
+                        for i in 0..REVEALS {
+                            chain.reveal_ith(i).unwrap();
+                        }
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, construction, sequential_reveals);
+criterion_main!(benches);
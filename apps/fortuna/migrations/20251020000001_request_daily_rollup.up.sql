@@ -0,0 +1,13 @@
+CREATE TABLE request_daily_rollup(
+    chain_id VARCHAR(64) NOT NULL,
+    network_id BIGINT NOT NULL,
+    day VARCHAR(10) NOT NULL,
+    request_count BIGINT NOT NULL,
+    failed_count BIGINT NOT NULL,
+    callback_failed_count BIGINT NOT NULL,
+    median_reveal_latency_ms BIGINT,
+    gas_used VARCHAR(100) NOT NULL,
+    updated_at BIGINT NOT NULL,
+    PRIMARY KEY (chain_id, day)
+);
+CREATE INDEX request_daily_rollup__day ON request_daily_rollup(day);
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS request__provider__created_at;
@@ -0,0 +1,2 @@
+-- Used by the provider filter of the explorer.
+CREATE INDEX request__provider__created_at ON request(provider, created_at);
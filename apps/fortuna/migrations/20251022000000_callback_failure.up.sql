@@ -0,0 +1,3 @@
+-- The decoded reason of the callback failures, see `CallbackFailure`.
+ALTER TABLE request ADD COLUMN callback_failure_kind VARCHAR(16);
+ALTER TABLE request ADD COLUMN callback_failure_reason TEXT;
@@ -0,0 +1,11 @@
+CREATE TABLE transaction_replacement(
+    chain_id VARCHAR(20) NOT NULL,
+    network_id INTEGER NOT NULL,
+    address VARCHAR(40) NOT NULL,
+    nonce INTEGER NOT NULL,
+    transaction_hash VARCHAR(64) NOT NULL,
+    pending_since BIGINT NOT NULL,
+    replaced_at BIGINT NOT NULL,
+    PRIMARY KEY (network_id, address, nonce, transaction_hash)
+);
+CREATE INDEX transaction_replacement__chain_id__replaced_at ON transaction_replacement(chain_id, replaced_at);
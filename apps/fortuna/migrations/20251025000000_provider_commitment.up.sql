@@ -0,0 +1,10 @@
+CREATE TABLE provider_commitment(
+    chain_id VARCHAR(20) NOT NULL,
+    network_id INTEGER NOT NULL,
+    provider VARCHAR(40) NOT NULL,
+    original_commitment_sequence_number INTEGER NOT NULL,
+    seed VARCHAR(64) NOT NULL,
+    chain_length INTEGER NOT NULL,
+    registered_at BIGINT NOT NULL,
+    PRIMARY KEY (network_id, provider, original_commitment_sequence_number)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE replica_lease(
+    chain_id VARCHAR(64) NOT NULL,
+    replica VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (chain_id, replica)
+);
@@ -0,0 +1,12 @@
+ALTER TABLE request
+    ALTER COLUMN chain_id TYPE VARCHAR(20),
+    ALTER COLUMN network_id TYPE INTEGER,
+    ALTER COLUMN sequence TYPE INTEGER,
+    ALTER COLUMN created_at TYPE BIGINT USING (EXTRACT(EPOCH FROM created_at) * 1000)::BIGINT,
+    ALTER COLUMN last_updated_at TYPE BIGINT USING (EXTRACT(EPOCH FROM last_updated_at) * 1000)::BIGINT,
+    ALTER COLUMN request_block_number TYPE INTEGER,
+    ALTER COLUMN reveal_block_number TYPE INTEGER,
+    ALTER COLUMN gas_used TYPE VARCHAR(100),
+    ALTER COLUMN gas_limit TYPE VARCHAR(100),
+    ALTER COLUMN callback_failed TYPE INTEGER USING callback_failed::INTEGER,
+    ALTER COLUMN callback_gas_used TYPE VARCHAR(100);
@@ -0,0 +1,15 @@
+-- The init migration was written for SQLite, whose integers are always 64 bits, which ignores the
+-- length of VARCHAR columns and which has no decimal, timestamp or boolean types. The amounts of
+-- gas and fees are U256 on chain, so they fit in 78 decimal digits.
+ALTER TABLE request
+    ALTER COLUMN chain_id TYPE VARCHAR(64),
+    ALTER COLUMN network_id TYPE BIGINT,
+    ALTER COLUMN sequence TYPE BIGINT,
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING to_timestamp(created_at / 1000.0),
+    ALTER COLUMN last_updated_at TYPE TIMESTAMPTZ USING to_timestamp(last_updated_at / 1000.0),
+    ALTER COLUMN request_block_number TYPE BIGINT,
+    ALTER COLUMN reveal_block_number TYPE BIGINT,
+    ALTER COLUMN gas_used TYPE NUMERIC(78, 0) USING gas_used::NUMERIC,
+    ALTER COLUMN gas_limit TYPE NUMERIC(78, 0) USING gas_limit::NUMERIC,
+    ALTER COLUMN callback_failed TYPE BOOLEAN USING callback_failed <> 0,
+    ALTER COLUMN callback_gas_used TYPE NUMERIC(78, 0) USING callback_gas_used::NUMERIC;
@@ -0,0 +1,2 @@
+ALTER TABLE request DROP COLUMN callback_failure_reason;
+ALTER TABLE request DROP COLUMN callback_failure_kind;
@@ -0,0 +1,2 @@
+-- The estimated cost of the reveals simulated by keepers in dry-run mode.
+ALTER TABLE request ADD COLUMN estimated_fee NUMERIC(78, 0);
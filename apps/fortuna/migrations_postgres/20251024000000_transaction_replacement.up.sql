@@ -0,0 +1,11 @@
+CREATE TABLE transaction_replacement(
+    chain_id VARCHAR(64) NOT NULL,
+    network_id BIGINT NOT NULL,
+    address VARCHAR(40) NOT NULL,
+    nonce BIGINT NOT NULL,
+    transaction_hash VARCHAR(64) NOT NULL,
+    pending_since TIMESTAMPTZ NOT NULL,
+    replaced_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (network_id, address, nonce, transaction_hash)
+);
+CREATE INDEX transaction_replacement__chain_id__replaced_at ON transaction_replacement(chain_id, replaced_at);
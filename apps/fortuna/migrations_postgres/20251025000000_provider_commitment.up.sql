@@ -0,0 +1,10 @@
+CREATE TABLE provider_commitment(
+    chain_id VARCHAR(64) NOT NULL,
+    network_id BIGINT NOT NULL,
+    provider VARCHAR(40) NOT NULL,
+    original_commitment_sequence_number BIGINT NOT NULL,
+    seed VARCHAR(64) NOT NULL,
+    chain_length BIGINT NOT NULL,
+    registered_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (network_id, provider, original_commitment_sequence_number)
+);
@@ -25,6 +25,7 @@ use {
 };
 pub use {
     chain_ids::*, config::*, explorer::*, index::*, live::*, metrics::*, ready::*, revelation::*,
+    stats::*,
 };
 
 mod chain_ids;
@@ -35,6 +36,7 @@ mod live;
 mod metrics;
 mod ready;
 mod revelation;
+mod stats;
 
 pub type ChainId = String;
 pub type NetworkId = u64;
@@ -45,6 +47,8 @@ pub enum StateTag {
     Failed,
     Completed,
     CallbackErrored,
+    /// Reveals simulated by keepers in dry-run mode.
+    Simulated,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
@@ -56,9 +60,16 @@ pub struct ApiMetrics {
     pub http_requests: Family<RequestLabel, Counter>,
 }
 
+/// The state of the randomness service of a provider on each blockchain.
+pub type ProviderChains = Arc<RwLock<HashMap<ChainId, ApiBlockChainState>>>;
+
 #[derive(Clone)]
 pub struct ApiState {
-    pub chains: Arc<RwLock<HashMap<ChainId, ApiBlockChainState>>>,
+    /// The chains of the main provider, served by the routes that aren't namespaced by provider.
+    pub chains: ProviderChains,
+
+    /// The chains of every provider served by this instance, including the main provider.
+    pub providers: HashMap<Address, ProviderChains>,
 
     pub history: Arc<History>,
 
@@ -74,7 +85,7 @@ pub struct ApiState {
 
 impl ApiState {
     pub async fn new(
-        chains: Arc<RwLock<HashMap<ChainId, ApiBlockChainState>>>,
+        chains: ProviderChains,
         metrics_registry: Arc<RwLock<Registry>>,
         history: Arc<History>,
         config: &Config,
@@ -93,6 +104,7 @@ impl ApiState {
         );
 
         ApiState {
+            providers: HashMap::from([(config.provider.address, chains.clone())]),
             chains,
             metrics: Arc::new(metrics),
             explorer_metrics,
@@ -101,6 +113,19 @@ impl ApiState {
             config: config.clone(),
         }
     }
+
+    /// Serve the chains of other providers, besides the main provider.
+    pub fn with_providers(mut self, providers: HashMap<Address, ProviderChains>) -> Self {
+        self.providers.extend(providers);
+        self
+    }
+
+    /// The chains of a provider served by this instance.
+    pub fn provider_chains(&self, provider: &Address) -> Result<&ProviderChains, RestError> {
+        self.providers
+            .get(provider)
+            .ok_or(RestError::InvalidProvider)
+    }
 }
 
 /// The state of the randomness service for a single blockchain.
@@ -136,6 +161,8 @@ pub enum RestError {
     InvalidSequenceNumber,
     /// The caller passed an unsupported chain id
     InvalidChainId,
+    /// The caller passed a provider that isn't served by this instance
+    InvalidProvider,
     /// The query is not parsable to a transaction hash, address, or sequence number
     InvalidQueryString,
     /// The caller requested a random value that can't currently be revealed (because it
@@ -165,6 +192,9 @@ impl IntoResponse for RestError {
             RestError::InvalidChainId => {
                 (StatusCode::BAD_REQUEST, "The chain id is not supported").into_response()
             }
+            RestError::InvalidProvider => {
+                (StatusCode::BAD_REQUEST, "The provider is not supported").into_response()
+            }
             RestError::InvalidQueryString => (
                 StatusCode::BAD_REQUEST,
                 "The query string is not parsable to a transaction hash, address, or sequence number",
@@ -206,11 +236,18 @@ pub fn routes(state: ApiState) -> Router<(), Body> {
         .route("/ready", get(ready))
         .route("/v1/chains", get(chain_ids))
         .route("/v1/logs", get(explorer))
+        .route("/v1/stats", get(stats))
         .route(
             "/v1/chains/:chain_id/revelations/:sequence",
             get(revelation),
         )
         .route("/v1/chains/configs", get(get_chain_configs))
+        .route("/v1/providers", get(provider_ids))
+        .route("/v1/providers/:provider/chains", get(provider_chain_ids))
+        .route(
+            "/v1/providers/:provider/chains/:chain_id/revelations/:sequence",
+            get(provider_revelation),
+        )
         .with_state(state)
 }
 
@@ -224,6 +261,19 @@ pub fn get_register_uri(base_uri: &str, chain_id: &str) -> Result<String> {
     Ok(uri.to_string())
 }
 
+/// The url registered on chain for a provider other than the main provider of this instance:
+/// `{base_uri}/v1/providers/{provider}/chains/{chain_id}`
+pub fn get_provider_register_uri(
+    base_uri: &str,
+    provider: &Address,
+    chain_id: &str,
+) -> Result<String> {
+    let base_uri = Url::parse(base_uri)?;
+    let path = format!("/v1/providers/{provider:?}/chains/{chain_id}");
+    let uri = base_uri.join(&path)?;
+    Ok(uri.to_string())
+}
+
 #[cfg(test)]
 mod test {
     use {
@@ -259,6 +309,10 @@ mod test {
             100,
             PebbleHashChain::new([1u8; 32], 1000, 1),
         ));
+        static ref OTHER_PROVIDER_ETH_CHAIN: Arc<HashChainState> = Arc::new(HashChainState::from_chain_at_offset(
+            0,
+            PebbleHashChain::new([2u8; 32], 1000, 1),
+        ));
     }
 
     async fn test_server() -> (TestServer, Arc<MockEntropyReader>, Arc<MockEntropyReader>) {
@@ -302,6 +356,22 @@ mod test {
             confirmed_block_status: BlockStatus::Latest,
         };
 
+        // The other provider is served on ethereum with the same contract.
+        let other_provider_eth_state = BlockchainState {
+            state: Arc::new(MonitoredHashChainState::new(
+                OTHER_PROVIDER_ETH_CHAIN.clone(),
+                Default::default(),
+                "ethereum".into(),
+                *OTHER_PROVIDER,
+            )),
+            provider_address: *OTHER_PROVIDER,
+            ..eth_state.clone()
+        };
+        let other_provider_chains = HashMap::from([(
+            "ethereum".to_string(),
+            ApiBlockChainState::Initialized(other_provider_eth_state),
+        )]);
+
         let mut chains = HashMap::new();
         chains.insert(
             "ethereum".into(),
@@ -315,6 +385,7 @@ mod test {
         // Create a minimal config for testing
         let config = Config {
             chains: HashMap::new(),
+            cosmwasm_chains: HashMap::new(),
             provider: crate::config::ProviderConfig {
                 uri: "http://localhost:8080/".to_string(),
                 address: PROVIDER,
@@ -328,8 +399,14 @@ mod test {
                 },
                 chain_length: 100000,
                 chain_sample_interval: 10,
+                hash_chain_storage: Default::default(),
                 fee_manager: None,
+                fee_manager_private_key: None,
+                commitment_rotation: None,
+                chains: HashMap::new(),
             },
+            providers: vec![],
+            history: Default::default(),
             keeper: crate::config::KeeperConfig {
                 private_key: crate::config::SecretString {
                     value: Some("0xabcd".to_string()),
@@ -338,6 +415,8 @@ mod test {
                 fee_manager_private_key: None,
                 other_keeper_addresses: vec![],
                 replica_config: None,
+                pool_private_keys: vec![],
+                dry_run: false,
             },
         };
 
@@ -347,7 +426,11 @@ mod test {
             Arc::new(History::new().await.unwrap()),
             &config,
         )
-        .await;
+        .await
+        .with_providers(HashMap::from([(
+            *OTHER_PROVIDER,
+            Arc::new(RwLock::new(other_provider_chains)),
+        )]));
 
         let app = api::routes(api_state);
         (TestServer::new(app).unwrap(), eth_read, avax_read)
@@ -363,6 +446,80 @@ mod test {
         response
     }
 
+    #[tokio::test]
+    async fn test_provider_revelation() {
+        let (server, eth_contract, _) = test_server().await;
+        eth_contract.insert(PROVIDER, 100, 1, false);
+        eth_contract.insert(*OTHER_PROVIDER, 101, 1, false);
+
+        let response = get_and_assert_status(&server, "/v1/providers", StatusCode::OK).await;
+        response.assert_json(&vec![PROVIDER, *OTHER_PROVIDER]);
+        let response = get_and_assert_status(
+            &server,
+            &format!("/v1/providers/{:?}/chains", *OTHER_PROVIDER),
+            StatusCode::OK,
+        )
+        .await;
+        response.assert_json(&vec!["ethereum".to_string()]);
+
+        // The main provider is also served under its address.
+        let response = get_and_assert_status(
+            &server,
+            &format!("/v1/providers/{PROVIDER:?}/chains/ethereum/revelations/100"),
+            StatusCode::OK,
+        )
+        .await;
+        response.assert_json(&GetRandomValueResponse {
+            value: Blob::new(BinaryEncoding::Hex, ETH_CHAIN.reveal(100).unwrap()),
+        });
+
+        // Each provider reveals its own requests from its own hash chain.
+        let response = get_and_assert_status(
+            &server,
+            &format!(
+                "/v1/providers/{:?}/chains/ethereum/revelations/101",
+                *OTHER_PROVIDER
+            ),
+            StatusCode::OK,
+        )
+        .await;
+        response.assert_json(&GetRandomValueResponse {
+            value: Blob::new(
+                BinaryEncoding::Hex,
+                OTHER_PROVIDER_ETH_CHAIN.reveal(101).unwrap(),
+            ),
+        });
+        get_and_assert_status(
+            &server,
+            &format!(
+                "/v1/providers/{:?}/chains/ethereum/revelations/100",
+                *OTHER_PROVIDER
+            ),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+
+        // The other provider isn't served on avalanche, and unknown providers aren't served at all.
+        get_and_assert_status(
+            &server,
+            &format!(
+                "/v1/providers/{:?}/chains/avalanche/revelations/101",
+                *OTHER_PROVIDER
+            ),
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+        get_and_assert_status(
+            &server,
+            &format!(
+                "/v1/providers/{:?}/chains/ethereum/revelations/101",
+                Address::from_low_u64_be(2)
+            ),
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_revelation() {
         let (server, eth_contract, avax_contract) = test_server().await;
@@ -596,6 +753,7 @@ mod test {
             "ethereum".to_string(),
             crate::config::EthereumConfig {
                 geth_rpc_addr: "http://localhost:8545".to_string(),
+                geth_rpc_wss: None,
                 contract_addr: Address::from_low_u64_be(0x1234),
                 reveal_delay_blocks: 1,
                 confirmed_block_status: BlockStatus::Latest,
@@ -612,7 +770,9 @@ mod test {
                 surge_threshold_1: 40000,
                 surge_threshold_2: 100000,
                 surge_threshold_3: 200000,
+                gas_oracle: Default::default(),
                 escalation_policy: crate::config::EscalationPolicyConfig::default(),
+                stuck_tx_threshold_seconds: 300,
                 min_profit_pct: 0,
                 target_profit_pct: 20,
                 max_profit_pct: 100,
@@ -623,12 +783,15 @@ mod test {
                 commitments: None,
                 max_num_hashes: None,
                 block_delays: vec![5],
+                consumer_abis: HashMap::new(),
+                callback_retry: None,
             },
         );
         config_chains.insert(
             "avalanche".to_string(),
             crate::config::EthereumConfig {
                 geth_rpc_addr: "http://localhost:9650".to_string(),
+                geth_rpc_wss: None,
                 contract_addr: Address::from_low_u64_be(0x5678),
                 reveal_delay_blocks: 2,
                 confirmed_block_status: BlockStatus::Latest,
@@ -645,7 +808,9 @@ mod test {
                 surge_threshold_1: 40000,
                 surge_threshold_2: 100000,
                 surge_threshold_3: 200000,
+                gas_oracle: Default::default(),
                 escalation_policy: crate::config::EscalationPolicyConfig::default(),
+                stuck_tx_threshold_seconds: 300,
                 min_profit_pct: 0,
                 target_profit_pct: 20,
                 max_profit_pct: 100,
@@ -656,11 +821,14 @@ mod test {
                 commitments: None,
                 max_num_hashes: None,
                 block_delays: vec![5],
+                consumer_abis: HashMap::new(),
+                callback_retry: None,
             },
         );
 
         let config = Config {
             chains: config_chains,
+            cosmwasm_chains: HashMap::new(),
             provider: crate::config::ProviderConfig {
                 uri: "http://localhost:8080/".to_string(),
                 address: PROVIDER,
@@ -674,8 +842,14 @@ mod test {
                 },
                 chain_length: 100000,
                 chain_sample_interval: 10,
+                hash_chain_storage: Default::default(),
                 fee_manager: None,
+                fee_manager_private_key: None,
+                commitment_rotation: None,
+                chains: HashMap::new(),
             },
+            providers: vec![],
+            history: Default::default(),
             keeper: crate::config::KeeperConfig {
                 private_key: crate::config::SecretString {
                     value: Some("0xabcd".to_string()),
@@ -684,6 +858,8 @@ mod test {
                 fee_manager_private_key: None,
                 other_keeper_addresses: vec![],
                 replica_config: None,
+                pool_private_keys: vec![],
+                dry_run: false,
             },
         };
 
@@ -737,8 +913,10 @@ mod test {
         );
 
         // Minimal ApiState for this endpoint
+        let chains = Arc::new(RwLock::new(chains));
         let api_state = ApiState {
-            chains: Arc::new(RwLock::new(chains)),
+            providers: HashMap::from([(config.provider.address, chains.clone())]),
+            chains,
             history: Arc::new(History::new().await.unwrap()),
             metrics_registry: Arc::new(RwLock::new(Registry::default())),
             metrics: Arc::new(crate::api::ApiMetrics {
@@ -1,7 +1,12 @@
 use {
     crate::api::{ChainId, RestError},
     anyhow::Result,
-    axum::{extract::State, Json},
+    axum::{
+        extract::{Path, State},
+        Json,
+    },
+    ethers::types::Address,
+    utoipa::IntoParams,
 };
 
 /// Get the list of supported chain ids
@@ -18,3 +23,50 @@ pub async fn chain_ids(
     let chain_ids = state.chains.read().await.keys().cloned().collect();
     Ok(Json(chain_ids))
 }
+
+/// Get the list of providers served by this instance
+#[utoipa::path(
+get,
+path = "/v1/providers",
+responses(
+(status = 200, description = "Successfully retrieved the list of providers", body = Vec<String>),
+)
+)]
+pub async fn provider_ids(
+    State(state): State<crate::api::ApiState>,
+) -> Result<Json<Vec<Address>>, RestError> {
+    let mut providers: Vec<Address> = state.providers.keys().cloned().collect();
+    providers.sort();
+    Ok(Json(providers))
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in=Path)]
+pub struct ProviderPathParams {
+    #[param(value_type = String)]
+    pub provider: Address,
+}
+
+/// Get the list of supported chain ids of a provider
+#[utoipa::path(
+get,
+path = "/v1/providers/{provider}/chains",
+responses(
+(status = 200, description = "Successfully retrieved the list of chain ids", body = Vec<String>),
+(status = 400, description = "The provider is not served by this instance", body = String)
+),
+params(ProviderPathParams)
+)]
+pub async fn provider_chain_ids(
+    State(state): State<crate::api::ApiState>,
+    Path(ProviderPathParams { provider }): Path<ProviderPathParams>,
+) -> Result<Json<Vec<ChainId>>, RestError> {
+    let chain_ids = state
+        .provider_chains(&provider)?
+        .read()
+        .await
+        .keys()
+        .cloned()
+        .collect();
+    Ok(Json(chain_ids))
+}
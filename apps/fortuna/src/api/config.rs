@@ -32,5 +32,15 @@ pub async fn get_chain_configs(
             default_fee: chain.fee,
         });
     }
+    for (name, chain) in state.config.cosmwasm_chains.iter() {
+        configs.push(ChainConfigSummary {
+            name: name.clone(),
+            network_id: chain.network_id,
+            contract_addr: chain.contract_addr.clone(),
+            reveal_delay_blocks: chain.reveal_delay_blocks,
+            gas_limit: chain.gas_limit,
+            default_fee: chain.fee,
+        });
+    }
     Ok(Json(configs))
 }
@@ -2,13 +2,20 @@ use {
     crate::{
         api::{ApiBlockChainState, NetworkId, RestError, StateTag},
         config::LATENCY_BUCKETS,
-        history::{RequestQueryBuilder, RequestStatus, SearchField},
+        history::{
+            CallbackFailure, RequestEntryState, RequestQueryBuilder, RequestStatus, SearchField,
+        },
     },
     axum::{
+        body::StreamBody,
         extract::{Query, State},
+        http::header,
+        response::{IntoResponse, Response},
         Json,
     },
-    chrono::{DateTime, Utc},
+    chrono::{DateTime, SecondsFormat, Utc},
+    ethers::types::Address,
+    futures::{stream, StreamExt, TryStreamExt},
     prometheus_client::{
         encoding::{EncodeLabelSet, EncodeLabelValue},
         metrics::{family::Family, histogram::Histogram},
@@ -30,10 +37,14 @@ pub struct QueryTags {
     search_type: Option<SearchType>,
     has_network_id_filter: bool,
     has_state_filter: bool,
+    has_provider_filter: bool,
+    has_callback_failed_filter: bool,
+    has_gas_used_filter: bool,
+    has_cursor: bool,
 }
 
-impl<'a> From<RequestQueryBuilder<'a>> for QueryTags {
-    fn from(builder: RequestQueryBuilder<'a>) -> Self {
+impl From<RequestQueryBuilder> for QueryTags {
+    fn from(builder: RequestQueryBuilder) -> Self {
         QueryTags {
             search_type: builder.search.map(|val| match val {
                 SearchField::TxHash(_) => SearchType::TxHash,
@@ -42,6 +53,10 @@ impl<'a> From<RequestQueryBuilder<'a>> for QueryTags {
             }),
             has_network_id_filter: builder.network_id.is_some(),
             has_state_filter: builder.state.is_some(),
+            has_provider_filter: builder.provider.is_some(),
+            has_callback_failed_filter: builder.callback_failed.is_some(),
+            has_gas_used_filter: builder.min_gas_used.is_some() || builder.max_gas_used.is_some(),
+            has_cursor: builder.cursor.is_some(),
         }
     }
 }
@@ -83,6 +98,21 @@ impl ExplorerMetrics {
     }
 }
 
+/// The format of the logs returned by the explorer.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// A page of logs in an [`ExplorerResponse`].
+    #[default]
+    Json,
+    /// Up to [`MAX_EXPORTED_LOGS`] matching logs, one per line with a header line.
+    Csv,
+    /// Up to [`MAX_EXPORTED_LOGS`] matching logs, one JSON object per line.
+    Ndjson,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, IntoParams)]
 #[into_params(parameter_in=Query)]
 pub struct ExplorerQueryParams {
@@ -99,34 +129,64 @@ pub struct ExplorerQueryParams {
     pub network_id: Option<NetworkId>,
     /// The state to filter the results by.
     pub state: Option<StateTag>,
+    /// Only return the logs of the requests to this provider.
+    #[param(value_type = Option<String>, example = "0x6cc14824ea2918f5de5c2f75a9da968ad4bd6344")]
+    pub provider: Option<Address>,
+    /// Only return the completed requests whose callback failed (true) or succeeded (false).
+    pub callback_failed: Option<bool>,
+    /// Only return the completed requests whose reveal transaction used at least this much gas.
+    pub min_gas_used: Option<u64>,
+    /// Only return the completed requests whose reveal transaction used at most this much gas.
+    pub max_gas_used: Option<u64>,
+    /// Return the logs after this cursor, i.e. the `next_cursor` of the previous page.
+    pub cursor: Option<String>,
     /// The maximum number of logs to return. Max value is 1000.
     #[param(default = 1000)]
     pub limit: Option<u64>,
-    /// The offset to start returning logs from.
+    /// The offset to start returning logs from. Prefer `cursor` to page through the logs.
     #[param(default = 0)]
     pub offset: Option<u64>,
+    /// Whether to count the total number of matching logs. Counting can be slow for broad
+    /// filters, so set this to false if `total_results` is not needed.
+    #[param(default = true)]
+    pub include_total: Option<bool>,
+    /// The format of the response. The csv and ndjson formats stream up to 100000 matching logs
+    /// instead of a single page, `limit` logs at a time. Narrow the time range to export more.
+    #[param(default = "json")]
+    pub format: Option<LogFormat>,
 }
 
 #[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 pub struct ExplorerResponse {
     pub requests: Vec<RequestStatus>,
-    pub total_results: i64,
+    /// The total number of matching logs. Omitted if `include_total` is false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_results: Option<i64>,
+    /// The cursor of the next page of logs. Omitted if this is the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Returns the logs of all requests captured by the keeper.
 ///
 /// This endpoint allows you to filter the logs by a specific network ID, a query string (which can be a transaction hash, sender address, or sequence number), and a time range.
 /// This is useful for debugging and monitoring the requests made to the Entropy contracts on various chains.
+///
+/// The logs are returned from the newest to the oldest. Pass the `next_cursor` of a response as
+/// the `cursor` of the next request to page through them. For bulk analysis, the csv and ndjson
+/// formats stream up to 100000 matching logs in a single response.
 #[utoipa::path(
     get,
     path = "/v1/logs",
-    responses((status = 200, description = "A list of Entropy request logs", body = ExplorerResponse)),
+    responses(
+        (status = 200, description = "A list of Entropy request logs", body = ExplorerResponse),
+    ),
     params(ExplorerQueryParams)
 )]
 pub async fn explorer(
     State(state): State<crate::api::ApiState>,
     Query(query_params): Query<ExplorerQueryParams>,
-) -> anyhow::Result<Json<ExplorerResponse>, RestError> {
+) -> anyhow::Result<Response, RestError> {
     if let Some(network_id) = &query_params.network_id {
         if !state
             .chains
@@ -153,6 +213,27 @@ pub async fn explorer(
     if let Some(state) = query_params.state {
         query = query.state(state);
     }
+    if let Some(provider) = query_params.provider {
+        query = query.provider(provider);
+    }
+    if let Some(callback_failed) = query_params.callback_failed {
+        query = query.callback_failed(callback_failed);
+    }
+    if let Some(min_gas_used) = query_params.min_gas_used {
+        query = query
+            .min_gas_used(min_gas_used)
+            .map_err(|_| RestError::InvalidQueryString)?;
+    }
+    if let Some(max_gas_used) = query_params.max_gas_used {
+        query = query
+            .max_gas_used(max_gas_used)
+            .map_err(|_| RestError::InvalidQueryString)?;
+    }
+    if let Some(cursor) = &query_params.cursor {
+        query = query
+            .cursor(cursor)
+            .map_err(|_| RestError::InvalidQueryString)?;
+    }
     if let Some(limit) = query_params.limit {
         query = query
             .limit(limit)
@@ -168,20 +249,224 @@ pub async fn explorer(
         query = query.max_timestamp(max_timestamp);
     }
 
+    let format = query_params.format.unwrap_or_default();
+    if format != LogFormat::Json {
+        return Ok(export(query, format));
+    }
+
     let results_latency = &state.explorer_metrics.results_latency;
     let count_latency = &state.explorer_metrics.count_latency;
     let query_tags = &query.clone().into();
-    let (requests, total_results) = tokio::join!(
-        measure_latency(results_latency, query_tags, query.execute()),
-        measure_latency(count_latency, query_tags, query.count_results())
+    let count = async {
+        if query_params.include_total.unwrap_or(true) {
+            measure_latency(count_latency, query_tags, query.count_results())
+                .await
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let (page, total_results) = tokio::join!(
+        measure_latency(results_latency, query_tags, query.execute_page()),
+        count
     );
-    let requests = requests.map_err(|_| RestError::TemporarilyUnavailable)?;
+    let (requests, next_cursor) = page.map_err(|_| RestError::TemporarilyUnavailable)?;
     let total_results = total_results.map_err(|_| RestError::TemporarilyUnavailable)?;
 
     Ok(Json(ExplorerResponse {
         requests,
         total_results,
-    }))
+        next_cursor: next_cursor.map(|cursor| cursor.encode()),
+    })
+    .into_response())
+}
+
+/// Stream all the results of `query` in `format`. The status code is sent before the results are
+/// fetched, so a database error aborts the response midway.
+fn export(query: RequestQueryBuilder, format: LogFormat) -> Response {
+    let content_type = match format {
+        LogFormat::Csv => "text/csv; charset=utf-8",
+        _ => "application/x-ndjson",
+    };
+    let header_line = match format {
+        LogFormat::Csv => Some(Ok(format!("{}\n", CSV_HEADER.join(",")))),
+        _ => None,
+    };
+    let lines = query
+        .pages(MAX_EXPORTED_LOGS)
+        .map_ok(move |requests| {
+            requests
+                .iter()
+                .map(|request| match format {
+                    LogFormat::Csv => csv_line(request),
+                    _ => ndjson_line(request),
+                })
+                .collect::<String>()
+        })
+        .inspect_err(|e| tracing::error!("Failed to export logs: {:?}", e));
+    let body = StreamBody::new(stream::iter(header_line).chain(lines));
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// The maximum number of logs returned by a csv or ndjson export.
+pub const MAX_EXPORTED_LOGS: i64 = 100_000;
+
+const CSV_HEADER: [&str; 24] = [
+    "chain_id",
+    "network_id",
+    "provider",
+    "sequence",
+    "created_at",
+    "last_updated_at",
+    "state",
+    "request_block_number",
+    "request_tx_hash",
+    "sender",
+    "gas_limit",
+    "user_random_number",
+    "reveal_block_number",
+    "reveal_tx_hash",
+    "provider_random_number",
+    "gas_used",
+    "estimated_fee",
+    "combined_random_number",
+    "callback_failed",
+    "callback_return_value",
+    "callback_gas_used",
+    "callback_failure_kind",
+    "callback_failure_reason",
+    "reason",
+];
+
+fn csv_line(request: &RequestStatus) -> String {
+    let mut fields = vec![
+        csv_field(&request.chain_id),
+        request.network_id.to_string(),
+        format!("{:?}", request.provider),
+        request.sequence.to_string(),
+        request
+            .created_at
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+        request
+            .last_updated_at
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+    ];
+    let state = match &request.state {
+        RequestEntryState::Pending => "pending",
+        RequestEntryState::Completed { .. } => "completed",
+        RequestEntryState::Failed { .. } => "failed",
+        RequestEntryState::Simulated { .. } => "simulated",
+    };
+    fields.extend([
+        state.to_string(),
+        request.request_block_number.to_string(),
+        format!("{:?}", request.request_tx_hash),
+        format!("{:?}", request.sender),
+        request.gas_limit.to_string(),
+        hex::encode(request.user_random_number),
+    ]);
+    match &request.state {
+        RequestEntryState::Pending => fields.extend(vec![String::new(); 12]),
+        RequestEntryState::Completed {
+            reveal_block_number,
+            reveal_tx_hash,
+            provider_random_number,
+            gas_used,
+            combined_random_number,
+            callback_failed,
+            callback_return_value,
+            callback_gas_used,
+            callback_failure,
+        } => fields.extend([
+            reveal_block_number.to_string(),
+            format!("{reveal_tx_hash:?}"),
+            hex::encode(provider_random_number),
+            gas_used.to_string(),
+            String::new(),
+            hex::encode(combined_random_number),
+            callback_failed.to_string(),
+            callback_return_value.to_string(),
+            callback_gas_used.to_string(),
+            callback_failure_kind(callback_failure),
+            callback_failure_reason(callback_failure),
+            String::new(),
+        ]),
+        RequestEntryState::Simulated {
+            provider_random_number,
+            combined_random_number,
+            gas_estimate,
+            estimated_fee,
+            callback_failed,
+            callback_return_value,
+            callback_gas_used,
+            callback_failure,
+        } => fields.extend([
+            String::new(),
+            String::new(),
+            hex::encode(provider_random_number),
+            gas_estimate.to_string(),
+            estimated_fee.to_string(),
+            hex::encode(combined_random_number),
+            callback_failed.to_string(),
+            callback_return_value.to_string(),
+            callback_gas_used.to_string(),
+            callback_failure_kind(callback_failure),
+            callback_failure_reason(callback_failure),
+            String::new(),
+        ]),
+        RequestEntryState::Failed {
+            reason,
+            provider_random_number,
+        } => fields.extend([
+            String::new(),
+            String::new(),
+            provider_random_number.map(hex::encode).unwrap_or_default(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            csv_field(reason),
+        ]),
+    }
+    format!("{}\n", fields.join(","))
+}
+
+fn callback_failure_kind(callback_failure: &Option<CallbackFailure>) -> String {
+    callback_failure
+        .as_ref()
+        .map(|failure| failure.kind.as_str().to_string())
+        .unwrap_or_default()
+}
+
+fn callback_failure_reason(callback_failure: &Option<CallbackFailure>) -> String {
+    callback_failure
+        .as_ref()
+        .and_then(|failure| failure.reason.as_deref())
+        .map(csv_field)
+        .unwrap_or_default()
+}
+
+/// Quote `value` if it contains a separator, a quote or a line break, as per RFC 4180.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn ndjson_line(request: &RequestStatus) -> String {
+    match serde_json::to_string(request) {
+        Ok(json) => format!("{json}\n"),
+        Err(e) => {
+            tracing::error!("Failed to serialize log: {:?}", e);
+            String::new()
+        }
+    }
 }
 
 async fn measure_latency<T, F>(
@@ -199,3 +484,55 @@ where
         .observe(start.elapsed().as_secs_f64());
     return_value
 }
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::history::CallbackFailureKind, ethers::types::TxHash};
+
+    #[test]
+    fn test_csv_line() {
+        let mut request = RequestStatus {
+            chain_id: "ethereum".to_string(),
+            network_id: 1,
+            provider: Address::zero(),
+            sequence: 7,
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            last_updated_at: DateTime::from_timestamp(1_700_000_005, 0).unwrap(),
+            request_block_number: 10,
+            request_tx_hash: TxHash::zero(),
+            gas_limit: 500_000,
+            user_random_number: [1; 32],
+            sender: Address::zero(),
+            state: RequestEntryState::Pending,
+        };
+        let line = csv_line(&request);
+        assert_eq!(line.matches(',').count(), CSV_HEADER.len() - 1);
+        assert!(line.starts_with(
+            "ethereum,1,0x0000000000000000000000000000000000000000,7,2023-11-14T22:13:20.000Z,"
+        ));
+
+        request.state = RequestEntryState::Completed {
+            reveal_block_number: 11,
+            reveal_tx_hash: TxHash::zero(),
+            provider_random_number: [2; 32],
+            gas_used: 200_000.into(),
+            combined_random_number: [3; 32],
+            callback_failed: true,
+            callback_return_value: Default::default(),
+            callback_gas_used: 20_000,
+            callback_failure: Some(CallbackFailure {
+                kind: CallbackFailureKind::Revert,
+                reason: Some("Error(\"nope, too low\")".to_string()),
+            }),
+        };
+        let line = csv_line(&request);
+        assert!(line.ends_with(",20000,revert,\"Error(\"\"nope, too low\"\")\",\n"));
+
+        request.state = RequestEntryState::Failed {
+            reason: "reverted: \"out of gas\", retrying".to_string(),
+            provider_random_number: None,
+        };
+        let line = csv_line(&request);
+        assert!(line.ends_with(",\"reverted: \"\"out of gas\"\", retrying\"\n"));
+    }
+}
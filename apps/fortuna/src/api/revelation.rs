@@ -1,6 +1,6 @@
 use {
     crate::{
-        api::{ApiBlockChainState, ChainId, RequestLabel, RestError},
+        api::{ApiBlockChainState, ChainId, ProviderChains, RequestLabel, RestError},
         chain::reader::BlockNumber,
     },
     anyhow::Result,
@@ -8,6 +8,7 @@ use {
         extract::{Path, Query, State},
         Json,
     },
+    ethers::types::Address,
     pythnet_sdk::wire::array,
     serde_with::serde_as,
     tokio::try_join,
@@ -46,8 +47,58 @@ pub async fn revelation(
         })
         .inc();
 
-    let state = state
-        .chains
+    reveal(&state.chains, chain_id, sequence, encoding, block_number).await
+}
+
+/// Reveal the random value for a given sequence number and blockchain of one of the providers
+/// served by this service.
+///
+/// This endpoint is the equivalent of `/v1/chains/{chain_id}/revelations/{sequence}` for the
+/// providers other than the main provider of this service.
+#[utoipa::path(
+get,
+path = "/v1/providers/{provider}/chains/{chain_id}/revelations/{sequence}",
+responses(
+(status = 200, description = "Random value successfully retrieved", body = GetRandomValueResponse),
+(status = 400, description = "The provider or chain is not served by this service", body = String),
+(status = 403, description = "Random value cannot currently be retrieved", body = String)
+),
+params(ProviderRevelationPathParams, RevelationQueryParams)
+)]
+pub async fn provider_revelation(
+    State(state): State<crate::api::ApiState>,
+    Path(ProviderRevelationPathParams {
+        provider,
+        chain_id,
+        sequence,
+    }): Path<ProviderRevelationPathParams>,
+    Query(RevelationQueryParams {
+        encoding,
+        block_number,
+    }): Query<RevelationQueryParams>,
+) -> Result<Json<GetRandomValueResponse>, RestError> {
+    state
+        .metrics
+        .http_requests
+        .get_or_create(&RequestLabel {
+            value: "/v1/providers/{provider}/chains/{chain_id}/revelations/{sequence}".to_string(),
+        })
+        .inc();
+
+    let chains = state.provider_chains(&provider)?;
+    reveal(chains, chain_id, sequence, encoding, block_number).await
+}
+
+/// Reveal the random value of a request to a provider, if the request was made on-chain and has
+/// enough confirmations.
+async fn reveal(
+    chains: &ProviderChains,
+    chain_id: ChainId,
+    sequence: u64,
+    encoding: Option<BinaryEncoding>,
+    block_number: Option<BlockNumber>,
+) -> Result<Json<GetRandomValueResponse>, RestError> {
+    let state = chains
         .read()
         .await
         .get(&chain_id)
@@ -135,6 +186,16 @@ pub struct RevelationPathParams {
     pub sequence: u64,
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in=Path)]
+pub struct ProviderRevelationPathParams {
+    #[param(value_type = String)]
+    pub provider: Address,
+    #[param(value_type = String)]
+    pub chain_id: ChainId,
+    pub sequence: u64,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, IntoParams)]
 #[into_params(parameter_in=Query)]
 pub struct RevelationQueryParams {
@@ -0,0 +1,65 @@
+use {
+    crate::{
+        api::{ChainId, RestError},
+        history::stats::DailyStats,
+    },
+    axum::{
+        extract::{Query, State},
+        Json,
+    },
+    chrono::{Days, Utc},
+    utoipa::IntoParams,
+};
+
+const DEFAULT_STATS_DAYS: u64 = 30;
+const MAX_STATS_DAYS: u64 = 366;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in=Query)]
+pub struct StatsQueryParams {
+    /// Only return the statistics of this chain.
+    pub chain_id: Option<ChainId>,
+    /// The number of days to return the statistics of, including today. Max value is 366.
+    #[param(default = 30)]
+    pub days: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct StatsResponse {
+    pub stats: Vec<DailyStats>,
+}
+
+/// Returns daily statistics of the requests of each chain.
+///
+/// The statistics are grouped by the UTC day the requests were made on, and are kept after the
+/// requests themselves are deleted by the retention policy. The statistics of the last two days
+/// are refreshed every few minutes.
+#[utoipa::path(
+    get,
+    path = "/v1/stats",
+    responses((status = 200, description = "Daily statistics of the requests", body = StatsResponse)),
+    params(StatsQueryParams)
+)]
+pub async fn stats(
+    State(state): State<crate::api::ApiState>,
+    Query(query_params): Query<StatsQueryParams>,
+) -> Result<Json<StatsResponse>, RestError> {
+    if let Some(chain_id) = &query_params.chain_id {
+        if !state.chains.read().await.contains_key(chain_id) {
+            return Err(RestError::InvalidChainId);
+        }
+    }
+    let days = query_params.days.unwrap_or(DEFAULT_STATS_DAYS);
+    if days == 0 || days > MAX_STATS_DAYS {
+        return Err(RestError::InvalidQueryString);
+    }
+
+    let max_day = Utc::now().date_naive();
+    let min_day = max_day - Days::new(days - 1);
+    let stats = state
+        .history
+        .daily_stats(query_params.chain_id.as_ref(), min_day, max_day)
+        .await
+        .map_err(|_| RestError::TemporarilyUnavailable)?;
+    Ok(Json(StatsResponse { stats }))
+}
@@ -1,2 +1,4 @@
+pub mod cosmwasm;
 pub mod ethereum;
 pub mod reader;
+pub mod writer;
@@ -0,0 +1,1616 @@
+//! Entropy backend for chains running the CosmWasm Entropy contract.
+//!
+//! Reads go through the REST (LCD) endpoint of a node. Transactions are built and signed locally
+//! (`SIGN_MODE_DIRECT` with a secp256k1 key), simulated to get their gas limit, then broadcast and
+//! polled until they are included in a block. Cosmos account addresses are 20 bytes, so they are
+//! represented as `Address` like on EVM chains and converted to bech32 at the boundary.
+//!
+//! The execute and query messages (`ExecuteMsg`, `QueryMsg`) and the events of the contract
+//! (`REQUESTED_EVENT`, `REVEALED_EVENT`) mirror the functions and events of the EVM Entropy
+//! contract. They are the interface that a CosmWasm deployment of Entropy must implement to be
+//! served by the keeper.
+
+use {
+    crate::{
+        chain::{
+            reader::{
+                BlockNumber, BlockStatus, EntropyReader, ProviderInfo, Request,
+                RequestCallbackStatus, RequestedV2Event, RevealedV2Event,
+            },
+            writer::{EntropyWriter, RevealError, RevealReceipt},
+        },
+        config::CosmWasmConfig,
+    },
+    anyhow::{anyhow, Result},
+    axum::async_trait,
+    backoff::{backoff::Backoff, ExponentialBackoff},
+    base64::{
+        engine::general_purpose::{STANDARD as BASE64_STANDARD, URL_SAFE as BASE64_URL_SAFE},
+        Engine as _,
+    },
+    bech32::{FromBase32, ToBase32, Variant},
+    ethers::{
+        core::k256::ecdsa::{signature::Signer, Signature, SigningKey},
+        prelude::LogMeta,
+        types::{Address, H256, U256, U64},
+    },
+    prost::Message,
+    ripemd::Ripemd160,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    serde_with::{serde_as, DisplayFromStr},
+    sha2::{Digest, Sha256},
+    std::{
+        str::FromStr,
+        time::{Duration, Instant},
+    },
+    tokio::sync::{Mutex, OnceCell},
+};
+
+pub mod proto;
+
+/// How long to wait for a broadcast transaction to be included in a block.
+const TX_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30);
+const TX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to keep retrying a reveal before giving up, like on EVM chains.
+const REVEAL_TIMEOUT: Duration = Duration::from_secs(300);
+/// The maximum number of transactions returned by a single page of a tx search.
+const TX_SEARCH_PAGE_SIZE: usize = 100;
+
+const REQUESTED_EVENT: &str = "wasm-requested_with_callback";
+const REVEALED_EVENT: &str = "wasm-revealed";
+
+/// Decode a bech32 account address. Account addresses are 20 bytes, like EVM addresses.
+pub fn decode_account_address(address: &str) -> Result<Address> {
+    let (_, data) = decode_bech32(address)?;
+    if data.len() != 20 {
+        return Err(anyhow!("{} is not an account address", address));
+    }
+    Ok(Address::from_slice(&data))
+}
+
+fn decode_bech32(address: &str) -> Result<(String, Vec<u8>)> {
+    let (hrp, data, _) = bech32::decode(address)
+        .map_err(|e| anyhow!("Invalid bech32 address {}: {}", address, e))?;
+    Ok((hrp, Vec::<u8>::from_base32(&data)?))
+}
+
+fn encode_account_address(hrp: &str, address: Address) -> Result<String> {
+    Ok(bech32::encode(
+        hrp,
+        address.as_bytes().to_base32(),
+        Variant::Bech32,
+    )?)
+}
+
+/// The account address of a secp256k1 key: ripemd160(sha256(compressed public key)).
+fn account_address(key: &SigningKey) -> Address {
+    let public_key = key.verifying_key().to_encoded_point(true);
+    Address::from_slice(&Ripemd160::digest(Sha256::digest(public_key.as_bytes())))
+}
+
+/// An error response of the LCD endpoint. Errors returned by the contract (e.g. when simulating a
+/// transaction) are reported this way.
+#[derive(Debug, thiserror::Error)]
+#[error("LCD request failed with status {status}: {message}")]
+pub struct LcdError {
+    pub status: u16,
+    pub message: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TxError {
+    #[error("Error preparing the transaction: {0:?}")]
+    Preparation(anyhow::Error),
+    #[error("Error simulating the transaction: {0:?}")]
+    Simulation(anyhow::Error),
+    #[error("Error submitting the transaction: {0:?}")]
+    Submission(anyhow::Error),
+    #[error("Transaction {0:?} was submitted, but never confirmed")]
+    ConfirmationTimeout(H256),
+    #[error("Transaction {hash:?} failed on-chain: {raw_log}")]
+    Failed { hash: H256, raw_log: String },
+}
+
+impl TxError {
+    /// The error returned by the contract when simulating the transaction, if any.
+    fn contract_error(&self) -> Option<&str> {
+        match self {
+            TxError::Simulation(e) => e.downcast_ref::<LcdError>().map(|e| e.message.as_str()),
+            _ => None,
+        }
+    }
+
+    fn is_no_such_request(&self) -> bool {
+        self.contract_error()
+            .is_some_and(|message| message.contains("NoSuchRequest"))
+    }
+
+    /// A description of the error that is safe to show to users.
+    fn reason(&self) -> String {
+        if let Some(message) = self.contract_error() {
+            return format!("Reverted: {message}");
+        }
+        match self {
+            TxError::Preparation(_) => "Unable to prepare the transaction".to_string(),
+            TxError::Simulation(_) => "Unable to estimate gas usage".to_string(),
+            TxError::Submission(_) => "Error submitting the transaction on-chain".to_string(),
+            TxError::ConfirmationTimeout(hash) => format!(
+                "Transaction was submitted, but never confirmed. Hash: {:?}",
+                hash
+            ),
+            TxError::Failed { hash, .. } => {
+                format!("Reveal transaction failed on-chain. Hash: {:?}", hash)
+            }
+        }
+    }
+}
+
+// The variants are named after the queries of the contract.
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QueryMsg {
+    GetRequest {
+        provider: String,
+        sequence_number: u64,
+    },
+    GetProviderInfo {
+        provider: String,
+    },
+    GetAccruedPythFees {},
+}
+
+#[serde_as]
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ExecuteMsg {
+    RevealWithCallback {
+        provider: String,
+        sequence_number: u64,
+        #[serde(with = "hex::serde")]
+        user_random_number: [u8; 32],
+        #[serde(with = "hex::serde")]
+        provider_revelation: [u8; 32],
+    },
+    AdvanceProviderCommitment {
+        provider: String,
+        advanced_sequence_number: u64,
+        #[serde(with = "hex::serde")]
+        provider_revelation: [u8; 32],
+    },
+    SetProviderFeeAsFeeManager {
+        provider: String,
+        #[serde_as(as = "DisplayFromStr")]
+        fee: u128,
+    },
+    WithdrawAsFeeManager {
+        provider: String,
+        #[serde_as(as = "DisplayFromStr")]
+        amount: u128,
+    },
+}
+
+#[derive(Deserialize)]
+struct SmartQueryResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct RequestResponse {
+    provider: String,
+    sequence_number: u64,
+    block_number: u64,
+    use_blockhash: bool,
+    callback_status: u8,
+    gas_limit_10k: u16,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct ProviderInfoResponse {
+    #[serde_as(as = "DisplayFromStr")]
+    fee_in_wei: u128,
+    #[serde_as(as = "DisplayFromStr")]
+    accrued_fees_in_wei: u128,
+    #[serde(with = "hex::serde")]
+    original_commitment: [u8; 32],
+    original_commitment_sequence_number: u64,
+    #[serde_as(as = "serde_with::base64::Base64")]
+    commitment_metadata: Vec<u8>,
+    end_sequence_number: u64,
+    sequence_number: u64,
+    current_commitment_sequence_number: u64,
+    max_num_hashes: u32,
+    default_gas_limit: u32,
+    fee_manager: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LatestBlockResponse {
+    block: Block,
+}
+
+#[derive(Deserialize)]
+struct Block {
+    header: BlockHeader,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct BlockHeader {
+    #[serde_as(as = "DisplayFromStr")]
+    height: u64,
+    time: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+struct NodeInfoResponse {
+    default_node_info: DefaultNodeInfo,
+}
+
+#[derive(Deserialize)]
+struct DefaultNodeInfo {
+    network: String,
+}
+
+#[derive(Deserialize)]
+struct AccountResponse {
+    account: BaseAccount,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct BaseAccount {
+    #[serde_as(as = "DisplayFromStr")]
+    account_number: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    sequence: u64,
+}
+
+#[derive(Deserialize)]
+struct BalanceResponse {
+    balance: Coin,
+}
+
+#[derive(Deserialize)]
+struct Coin {
+    amount: String,
+}
+
+#[derive(Deserialize)]
+struct SimulateResponse {
+    gas_info: GasInfo,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct GasInfo {
+    #[serde_as(as = "DisplayFromStr")]
+    gas_used: u64,
+}
+
+#[derive(Deserialize)]
+struct TxSearchResponse {
+    #[serde(default)]
+    tx_responses: Vec<TxResponse>,
+}
+
+#[derive(Deserialize)]
+struct GetTxResponse {
+    tx_response: TxResponse,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+struct TxResponse {
+    #[serde_as(as = "DisplayFromStr")]
+    height: u64,
+    txhash: String,
+    code: u32,
+    #[serde(default)]
+    raw_log: String,
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(default)]
+    gas_used: u64,
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+impl TxResponse {
+    fn hash(&self) -> Result<H256> {
+        Ok(H256::from_str(&self.txhash)?)
+    }
+
+    fn log_meta(&self, log_index: usize) -> Result<LogMeta> {
+        Ok(LogMeta {
+            // Contract addresses are 32 bytes, so they don't fit here. The contract is filtered
+            // on in the queries anyway.
+            address: Address::zero(),
+            block_number: U64::from(self.height),
+            block_hash: H256::zero(),
+            transaction_hash: self.hash()?,
+            transaction_index: U64::zero(),
+            log_index: U256::from(log_index),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    attributes: Vec<EventAttribute>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct EventAttribute {
+    key: String,
+    value: String,
+}
+
+impl Event {
+    fn attribute(&self, key: &str) -> Result<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.as_str())
+            .ok_or_else(|| anyhow!("Event {} is missing attribute {}", self.kind, key))
+    }
+
+    fn parse_attribute<T: FromStr>(&self, key: &str) -> Result<T>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.attribute(key)?
+            .parse()
+            .map_err(|e| anyhow!("Invalid attribute {} of event {}: {}", key, self.kind, e))
+    }
+
+    fn hex_attribute(&self, key: &str) -> Result<Vec<u8>> {
+        Ok(hex::decode(self.attribute(key)?)?)
+    }
+}
+
+fn to_bytes32(bytes: Vec<u8>) -> Result<[u8; 32]> {
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Expected a 32 bytes value"))
+}
+
+/// The keeper's key on the chain.
+struct CosmWasmSigner {
+    key: SigningKey,
+    address: Address,
+    /// Transactions of an account must be sent with consecutive sequence numbers, so they are
+    /// submitted one at a time.
+    lock: Mutex<()>,
+}
+
+/// The Entropy contract on a CosmWasm chain. It can only send transactions if it was created with
+/// a private key.
+pub struct CosmWasmEntropy {
+    client: reqwest::Client,
+    lcd_addr: String,
+    contract_addr: String,
+    hrp: String,
+    fee_denom: String,
+    gas_price: f64,
+    gas_adjustment_pct: u64,
+    /// The chain id (e.g. `osmosis-1`) used in the signed transactions. It is fetched once.
+    chain_id: OnceCell<String>,
+    signer: Option<CosmWasmSigner>,
+}
+
+impl CosmWasmEntropy {
+    pub fn from_config(config: &CosmWasmConfig, private_key: Option<&str>) -> Result<Self> {
+        let (hrp, _) = decode_bech32(&config.contract_addr)?;
+        let signer = private_key
+            .map(|private_key| -> Result<CosmWasmSigner> {
+                let key =
+                    SigningKey::from_slice(&hex::decode(private_key.trim_start_matches("0x"))?)?;
+                Ok(CosmWasmSigner {
+                    address: account_address(&key),
+                    key,
+                    lock: Mutex::new(()),
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+            lcd_addr: config.lcd_addr.trim_end_matches('/').to_string(),
+            contract_addr: config.contract_addr.clone(),
+            hrp,
+            fee_denom: config.fee_denom.clone(),
+            gas_price: config.gas_price,
+            gas_adjustment_pct: config.gas_adjustment_pct,
+            chain_id: OnceCell::new(),
+            signer,
+        })
+    }
+
+    /// The raw bytes of the contract address, used to derive the provider's hash chain secret.
+    pub fn contract_address_bytes(&self) -> Result<Vec<u8>> {
+        Ok(decode_bech32(&self.contract_addr)?.1)
+    }
+
+    fn bech32(&self, address: Address) -> Result<String> {
+        encode_account_address(&self.hrp, address)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.lcd_addr, path))
+            .query(query)
+            .send()
+            .await?;
+        Self::parse_response(response).await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> Result<T> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.lcd_addr, path))
+            .json(body)
+            .send()
+            .await?;
+        Self::parse_response(response).await
+    }
+
+    async fn parse_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+        let status = response.status();
+        if !status.is_success() {
+            #[derive(Deserialize)]
+            struct ErrorResponse {
+                message: String,
+            }
+            let message = response
+                .json::<ErrorResponse>()
+                .await
+                .map(|e| e.message)
+                .unwrap_or_default();
+            return Err(LcdError {
+                status: status.as_u16(),
+                message,
+            }
+            .into());
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn query_contract<T: DeserializeOwned>(&self, query: &QueryMsg) -> Result<T> {
+        let query = BASE64_URL_SAFE.encode(serde_json::to_vec(query)?);
+        let response: SmartQueryResponse<T> = self
+            .get(
+                &format!(
+                    "/cosmwasm/wasm/v1/contract/{}/smart/{}",
+                    self.contract_addr, query
+                ),
+                &[],
+            )
+            .await?;
+        Ok(response.data)
+    }
+
+    async fn get_latest_block(&self) -> Result<BlockHeader> {
+        let response: LatestBlockResponse = self
+            .get("/cosmos/base/tendermint/v1beta1/blocks/latest", &[])
+            .await?;
+        Ok(response.block.header)
+    }
+
+    /// Search the transactions matching `query`, in ascending block order.
+    async fn search_txs(&self, query: &str) -> Result<Vec<TxResponse>> {
+        let mut txs = vec![];
+        for page in 1.. {
+            let response: TxSearchResponse = self
+                .get(
+                    "/cosmos/tx/v1beta1/txs",
+                    &[
+                        ("query", query.to_string()),
+                        ("page", page.to_string()),
+                        ("limit", TX_SEARCH_PAGE_SIZE.to_string()),
+                        ("order_by", "ORDER_BY_ASC".to_string()),
+                    ],
+                )
+                .await?;
+            let num_txs = response.tx_responses.len();
+            txs.extend(response.tx_responses);
+            if num_txs < TX_SEARCH_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(txs)
+    }
+
+    /// The events of type `kind` emitted by the Entropy contract in a transaction, along with
+    /// their index in the transaction.
+    fn contract_events<'a>(
+        &'a self,
+        tx: &'a TxResponse,
+        kind: &'a str,
+    ) -> impl Iterator<Item = (usize, &'a Event)> + 'a {
+        tx.events.iter().enumerate().filter(move |(_, event)| {
+            event.kind == kind
+                && event
+                    .attribute("_contract_address")
+                    .is_ok_and(|contract| contract == self.contract_addr)
+        })
+    }
+
+    fn parse_revealed_event(
+        &self,
+        tx: &TxResponse,
+        log_index: usize,
+        event: &Event,
+    ) -> Result<RevealedV2Event> {
+        Ok(RevealedV2Event {
+            provider_revelation: to_bytes32(event.hex_attribute("provider_revelation")?)?,
+            random_number: to_bytes32(event.hex_attribute("random_number")?)?,
+            callback_failed: event.parse_attribute("callback_failed")?,
+            callback_return_value: event.hex_attribute("callback_return_value")?.into(),
+            callback_gas_used: event.parse_attribute("callback_gas_used")?,
+            gas_used: U256::from(tx.gas_used),
+            log_meta: tx.log_meta(log_index)?,
+        })
+    }
+
+    fn signer(&self) -> Result<&CosmWasmSigner> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| anyhow!("No private key configured to send transactions"))
+    }
+
+    async fn chain_id(&self) -> Result<&String> {
+        self.chain_id
+            .get_or_try_init(|| async {
+                let response: NodeInfoResponse = self
+                    .get("/cosmos/base/tendermint/v1beta1/node_info", &[])
+                    .await?;
+                Ok(response.default_node_info.network)
+            })
+            .await
+    }
+
+    fn fee_amount(&self, gas: u128) -> u128 {
+        (gas as f64 * self.gas_price).ceil() as u128
+    }
+
+    fn execute_msg(&self, msg: &ExecuteMsg) -> Result<proto::Any> {
+        Ok(proto::Any {
+            type_url: proto::MSG_EXECUTE_CONTRACT_TYPE_URL.to_string(),
+            value: proto::MsgExecuteContract {
+                sender: self.bech32(self.signer()?.address)?,
+                contract: self.contract_addr.clone(),
+                msg: serde_json::to_vec(msg)?,
+                funds: vec![],
+            }
+            .encode_to_vec(),
+        })
+    }
+
+    /// Sign the transaction body with the signer's key, paying for `gas_limit` gas.
+    fn sign_tx(
+        &self,
+        body_bytes: &[u8],
+        chain_id: &str,
+        account: &BaseAccount,
+        gas_limit: u64,
+    ) -> Result<Vec<u8>> {
+        let signer = self.signer()?;
+        let public_key = signer.key.verifying_key().to_encoded_point(true);
+        let auth_info_bytes = proto::AuthInfo {
+            signer_infos: vec![proto::SignerInfo {
+                public_key: Some(proto::Any {
+                    type_url: proto::PUB_KEY_TYPE_URL.to_string(),
+                    value: proto::PubKey {
+                        key: public_key.as_bytes().to_vec(),
+                    }
+                    .encode_to_vec(),
+                }),
+                mode_info: Some(proto::ModeInfo {
+                    single: Some(proto::ModeInfoSingle {
+                        mode: proto::SIGN_MODE_DIRECT,
+                    }),
+                }),
+                sequence: account.sequence,
+            }],
+            fee: Some(proto::Fee {
+                amount: vec![proto::Coin {
+                    denom: self.fee_denom.clone(),
+                    amount: self.fee_amount(gas_limit.into()).to_string(),
+                }],
+                gas_limit,
+                payer: String::new(),
+                granter: String::new(),
+            }),
+        }
+        .encode_to_vec();
+        let sign_doc = proto::SignDoc {
+            body_bytes: body_bytes.to_vec(),
+            auth_info_bytes: auth_info_bytes.clone(),
+            chain_id: chain_id.to_string(),
+            account_number: account.account_number,
+        };
+        let signature: Signature = signer.key.sign(&sign_doc.encode_to_vec());
+        Ok(proto::TxRaw {
+            body_bytes: body_bytes.to_vec(),
+            auth_info_bytes,
+            signatures: vec![signature.to_bytes().to_vec()],
+        }
+        .encode_to_vec())
+    }
+
+    /// Simulate, sign and broadcast a transaction carrying `messages`, then wait for it to be
+    /// included in a block.
+    async fn send_tx(&self, messages: Vec<proto::Any>) -> Result<TxResponse, TxError> {
+        let signer = self.signer().map_err(TxError::Preparation)?;
+        let _guard = signer.lock.lock().await;
+
+        let chain_id = self.chain_id().await.map_err(TxError::Preparation)?;
+        let account: AccountResponse = self
+            .get(
+                &format!(
+                    "/cosmos/auth/v1beta1/accounts/{}",
+                    self.bech32(signer.address).map_err(TxError::Preparation)?
+                ),
+                &[],
+            )
+            .await
+            .map_err(TxError::Preparation)?;
+        let body_bytes = proto::TxBody {
+            messages,
+            memo: String::new(),
+            timeout_height: 0,
+        }
+        .encode_to_vec();
+
+        // Signature verification is skipped during simulation, but the signer info is still
+        // needed to account for its gas.
+        let simulation_tx = self
+            .sign_tx(&body_bytes, chain_id, &account.account, 0)
+            .map_err(TxError::Preparation)?;
+        let simulation: SimulateResponse = self
+            .post(
+                "/cosmos/tx/v1beta1/simulate",
+                &serde_json::json!({ "tx_bytes": BASE64_STANDARD.encode(simulation_tx) }),
+            )
+            .await
+            .map_err(TxError::Simulation)?;
+        let gas_limit = simulation.gas_info.gas_used * self.gas_adjustment_pct / 100;
+
+        let tx = self
+            .sign_tx(&body_bytes, chain_id, &account.account, gas_limit)
+            .map_err(TxError::Preparation)?;
+        let broadcast: GetTxResponse = self
+            .post(
+                "/cosmos/tx/v1beta1/txs",
+                &serde_json::json!({
+                    "tx_bytes": BASE64_STANDARD.encode(tx),
+                    "mode": "BROADCAST_MODE_SYNC",
+                }),
+            )
+            .await
+            .map_err(TxError::Submission)?;
+        let hash = broadcast.tx_response.hash().map_err(TxError::Submission)?;
+        // A non-zero code at this point means the transaction was rejected by the mempool.
+        if broadcast.tx_response.code != 0 {
+            return Err(TxError::Submission(anyhow!(
+                "Transaction {:?} rejected: {}",
+                hash,
+                broadcast.tx_response.raw_log
+            )));
+        }
+
+        let start = Instant::now();
+        while start.elapsed() < TX_CONFIRMATION_TIMEOUT {
+            tokio::time::sleep(TX_POLL_INTERVAL).await;
+            match self
+                .get::<GetTxResponse>(
+                    &format!("/cosmos/tx/v1beta1/txs/{}", broadcast.tx_response.txhash),
+                    &[],
+                )
+                .await
+            {
+                Ok(GetTxResponse { tx_response }) if tx_response.code == 0 => {
+                    return Ok(tx_response);
+                }
+                Ok(GetTxResponse { tx_response }) => {
+                    return Err(TxError::Failed {
+                        hash,
+                        raw_log: tx_response.raw_log,
+                    });
+                }
+                // The transaction is not indexed until it is included in a block.
+                Err(e) => tracing::debug!("Transaction {:?} not found yet: {:?}", hash, e),
+            }
+        }
+        Err(TxError::ConfirmationTimeout(hash))
+    }
+
+    async fn execute(&self, msg: &ExecuteMsg) -> Result<TxResponse> {
+        let message = self.execute_msg(msg)?;
+        Ok(self.send_tx(vec![message]).await?)
+    }
+}
+
+#[async_trait]
+impl EntropyReader for CosmWasmEntropy {
+    async fn get_request_v2(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+    ) -> Result<Option<Request>> {
+        let request: Option<RequestResponse> = self
+            .query_contract(&QueryMsg::GetRequest {
+                provider: self.bech32(provider)?,
+                sequence_number,
+            })
+            .await?;
+        request
+            .map(|request| {
+                Ok(Request {
+                    provider: decode_account_address(&request.provider)?,
+                    sequence_number: request.sequence_number,
+                    block_number: request.block_number,
+                    use_blockhash: request.use_blockhash,
+                    callback_status: RequestCallbackStatus::try_from(request.callback_status)?,
+                    gas_limit_10k: request.gas_limit_10k,
+                })
+            })
+            .transpose()
+    }
+
+    /// Blocks are final as soon as they are committed, so all block statuses are the latest
+    /// block.
+    async fn get_block_number(&self, _confirmed_block_status: BlockStatus) -> Result<BlockNumber> {
+        Ok(self.get_latest_block().await?.height)
+    }
+
+    async fn get_request_with_callback_events(
+        &self,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+        provider: Address,
+    ) -> Result<Vec<RequestedV2Event>> {
+        let query = format!(
+            "{REQUESTED_EVENT}._contract_address='{}' AND {REQUESTED_EVENT}.provider='{}' AND tx.height>={} AND tx.height<={}",
+            self.contract_addr,
+            self.bech32(provider)?,
+            from_block,
+            to_block
+        );
+        let mut events = vec![];
+        for tx in self.search_txs(&query).await? {
+            if tx.code != 0 {
+                continue;
+            }
+            for (log_index, event) in self.contract_events(&tx, REQUESTED_EVENT) {
+                let provider_address = decode_account_address(event.attribute("provider")?)?;
+                if provider_address != provider {
+                    continue;
+                }
+                events.push(RequestedV2Event {
+                    sequence_number: event.parse_attribute("sequence_number")?,
+                    user_random_number: to_bytes32(event.hex_attribute("user_random_number")?)?,
+                    provider_address,
+                    sender: decode_account_address(event.attribute("requester")?)?,
+                    gas_limit: event.parse_attribute("gas_limit")?,
+                    log_meta: tx.log_meta(log_index)?,
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    async fn get_revealed_event(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        from_block: BlockNumber,
+    ) -> Result<Option<RevealedV2Event>> {
+        let query = format!(
+            "{REVEALED_EVENT}._contract_address='{}' AND {REVEALED_EVENT}.provider='{}' AND {REVEALED_EVENT}.sequence_number='{}' AND tx.height>={}",
+            self.contract_addr,
+            self.bech32(provider)?,
+            sequence_number,
+            from_block
+        );
+        for tx in self.search_txs(&query).await? {
+            if tx.code != 0 {
+                continue;
+            }
+            for (log_index, event) in self.contract_events(&tx, REVEALED_EVENT) {
+                if decode_account_address(event.attribute("provider")?)? == provider
+                    && event.parse_attribute::<u64>("sequence_number")? == sequence_number
+                {
+                    return Ok(Some(self.parse_revealed_event(&tx, log_index, event)?));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn estimate_reveal_with_callback_gas(
+        &self,
+        _sender: Address,
+        _provider: Address,
+        _sequence_number: u64,
+        _user_random_number: [u8; 32],
+        _provider_revelation: [u8; 32],
+    ) -> Result<U256> {
+        // Simulating a transaction requires the public key of the sender, which can't be
+        // recovered from its address.
+        Err(anyhow!(
+            "Estimating the gas of an arbitrary sender is not supported on CosmWasm chains"
+        ))
+    }
+
+    async fn get_provider_info(
+        &self,
+        provider: Address,
+        _block: Option<BlockNumber>,
+    ) -> Result<ProviderInfo> {
+        // LCD smart queries are always executed against the latest block.
+        let info: ProviderInfoResponse = self
+            .query_contract(&QueryMsg::GetProviderInfo {
+                provider: self.bech32(provider)?,
+            })
+            .await?;
+        Ok(ProviderInfo {
+            fee_in_wei: info.fee_in_wei,
+            accrued_fees_in_wei: info.accrued_fees_in_wei,
+            original_commitment: info.original_commitment,
+            original_commitment_sequence_number: info.original_commitment_sequence_number,
+            commitment_metadata: info.commitment_metadata.into(),
+            end_sequence_number: info.end_sequence_number,
+            sequence_number: info.sequence_number,
+            current_commitment_sequence_number: info.current_commitment_sequence_number,
+            max_num_hashes: info.max_num_hashes,
+            default_gas_limit: info.default_gas_limit,
+            fee_manager: info
+                .fee_manager
+                .map(|fee_manager| decode_account_address(&fee_manager))
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn get_accrued_pyth_fees(&self) -> Result<u128> {
+        let fees: String = self
+            .query_contract(&QueryMsg::GetAccruedPythFees {})
+            .await?;
+        Ok(fees.parse()?)
+    }
+
+    async fn get_balance(&self, address: Address) -> Result<U256> {
+        let response: BalanceResponse = self
+            .get(
+                &format!(
+                    "/cosmos/bank/v1beta1/balances/{}/by_denom",
+                    self.bech32(address)?
+                ),
+                &[("denom", self.fee_denom.clone())],
+            )
+            .await?;
+        Ok(U256::from_dec_str(&response.balance.amount)?)
+    }
+
+    async fn get_latest_block_timestamp(&self) -> Result<(BlockNumber, u64)> {
+        let header = self.get_latest_block().await?;
+        Ok((header.height, header.time.timestamp().try_into()?))
+    }
+}
+
+#[async_trait]
+impl EntropyWriter for CosmWasmEntropy {
+    fn address(&self) -> Address {
+        self.signer
+            .as_ref()
+            .map(|signer| signer.address)
+            .unwrap_or_default()
+    }
+
+    async fn reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+    ) -> Result<RevealReceipt, RevealError> {
+        let start = Instant::now();
+        let msg = self
+            .bech32(provider)
+            .and_then(|provider| {
+                self.execute_msg(&ExecuteMsg::RevealWithCallback {
+                    provider,
+                    sequence_number,
+                    user_random_number,
+                    provider_revelation,
+                })
+            })
+            .map_err(|e| RevealError {
+                reason: "Unable to prepare the transaction".to_string(),
+                error: e,
+            })?;
+
+        let mut backoff = ExponentialBackoff {
+            max_elapsed_time: Some(REVEAL_TIMEOUT),
+            ..ExponentialBackoff::default()
+        };
+        let mut num_retries = 0;
+        let tx = loop {
+            let error = match self.send_tx(vec![msg.clone()]).await {
+                Ok(tx) => break tx,
+                Err(e) => e,
+            };
+            let retry_after = if error.is_no_such_request() {
+                // Slow down the retries if the request is not found.
+                // This probably means that the request is already fulfilled via another process.
+                // After 5 retries, we return the error permanently.
+                match num_retries {
+                    0 => Some(Duration::from_secs(5)),
+                    1 => Some(Duration::from_secs(10)),
+                    2..=4 => Some(Duration::from_secs(60)),
+                    _ => None,
+                }
+            } else {
+                backoff.next_backoff()
+            };
+            match retry_after {
+                Some(retry_after) => {
+                    tracing::warn!(
+                        "Reveal failed. Retrying in {:?}. error: {:?}",
+                        retry_after,
+                        error
+                    );
+                    tokio::time::sleep(retry_after).await;
+                    num_retries += 1;
+                }
+                None => {
+                    return Err(RevealError {
+                        reason: error.reason(),
+                        error: error.into(),
+                    })
+                }
+            }
+        };
+
+        let to_reveal_error = |error: anyhow::Error| RevealError {
+            reason: "Unable to parse the reveal transaction".to_string(),
+            error,
+        };
+        let revealed = self
+            .contract_events(&tx, REVEALED_EVENT)
+            .next()
+            .ok_or_else(|| anyhow!("Revealed event not found in transaction {}", tx.txhash))
+            .and_then(|(log_index, event)| self.parse_revealed_event(&tx, log_index, event))
+            .map_err(to_reveal_error)?;
+        Ok(RevealReceipt {
+            block_number: tx.height,
+            transaction_hash: revealed.log_meta.transaction_hash,
+            gas_used: Some(revealed.gas_used),
+            effective_gas_price: None,
+            num_retries,
+            fee_multiplier: 100,
+            duration: start.elapsed(),
+            callback_failed: revealed.callback_failed,
+            callback_return_value: revealed.callback_return_value,
+            callback_gas_used: revealed.callback_gas_used,
+        })
+    }
+
+    async fn advance_provider_commitment(
+        &self,
+        provider: Address,
+        advanced_sequence_number: u64,
+        provider_revelation: [u8; 32],
+    ) -> Result<()> {
+        self.execute(&ExecuteMsg::AdvanceProviderCommitment {
+            provider: self.bech32(provider)?,
+            advanced_sequence_number,
+            provider_revelation,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn set_provider_fee_as_fee_manager(&self, provider: Address, fee: u128) -> Result<()> {
+        self.execute(&ExecuteMsg::SetProviderFeeAsFeeManager {
+            provider: self.bech32(provider)?,
+            fee,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn withdraw_as_fee_manager(&self, provider: Address, amount: u128) -> Result<()> {
+        self.execute(&ExecuteMsg::WithdrawAsFeeManager {
+            provider: self.bech32(provider)?,
+            amount,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn transfer(&self, destination: Address, amount: U256) -> Result<()> {
+        let message = proto::Any {
+            type_url: proto::MSG_SEND_TYPE_URL.to_string(),
+            value: proto::MsgSend {
+                from_address: self.bech32(self.signer()?.address)?,
+                to_address: self.bech32(destination)?,
+                amount: vec![proto::Coin {
+                    denom: self.fee_denom.clone(),
+                    amount: amount.to_string(),
+                }],
+            }
+            .encode_to_vec(),
+        };
+        self.send_tx(vec![message]).await?;
+        Ok(())
+    }
+
+    async fn estimate_tx_cost(&self, gas: u128) -> Result<u128> {
+        Ok(self.fee_amount(gas))
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use {
+        super::{encode_account_address, proto, REQUESTED_EVENT, REVEALED_EVENT},
+        crate::config::CosmWasmConfig,
+        axum::{
+            extract::{Path, Query, State},
+            http::StatusCode,
+            response::{IntoResponse, Response},
+            routing::{get, post},
+            Json, Router,
+        },
+        base64::{
+            engine::general_purpose::{STANDARD as BASE64_STANDARD, URL_SAFE as BASE64_URL_SAFE},
+            Engine as _,
+        },
+        bech32::{ToBase32, Variant},
+        ethers::{
+            core::k256::ecdsa::{signature::Verifier, Signature, VerifyingKey},
+            types::Address,
+        },
+        prost::Message,
+        serde_json::{json, Value},
+        sha2::{Digest, Sha256},
+        std::{
+            collections::{BTreeMap, HashMap},
+            net::SocketAddr,
+            sync::{Arc, Mutex},
+        },
+    };
+
+    /// The keeper key used in tests.
+    pub const KEEPER_PRIVATE_KEY: &str =
+        "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    /// The address of the mock contract.
+    pub fn contract_addr() -> String {
+        bech32::encode("neutron", [2u8; 32].to_base32(), Variant::Bech32).unwrap()
+    }
+
+    /// The config of a chain served by the mock LCD at `lcd_addr`.
+    pub fn chain_config(lcd_addr: &str) -> CosmWasmConfig {
+        serde_yaml::from_str(&format!(
+            r#"
+lcd_addr: {lcd_addr}
+contract_addr: {}
+provider_addr: {}
+network_id: 1
+fee_denom: untrn
+gas_price: 0.025
+gas_limit: 100000
+min_profit_pct: 0
+target_profit_pct: 20
+max_profit_pct: 100
+commitments: null
+"#,
+            contract_addr(),
+            encode_account_address("neutron", Address::from_low_u64_be(1)).unwrap()
+        ))
+        .unwrap()
+    }
+
+    pub const CHAIN_ID: &str = "mock-1";
+    pub const ACCOUNT_NUMBER: u64 = 7;
+    pub const SIMULATED_GAS: u64 = 100_000;
+
+    /// An event type and its (key, value) attributes.
+    type MockEvent = (String, Vec<(String, String)>);
+
+    struct MockTx {
+        height: u64,
+        hash: String,
+        events: Vec<MockEvent>,
+    }
+
+    impl MockTx {
+        fn to_json(&self) -> Value {
+            json!({
+                "height": self.height.to_string(),
+                "txhash": self.hash,
+                "code": 0,
+                "raw_log": "",
+                "gas_used": SIMULATED_GAS.to_string(),
+                "events": self.events.iter().map(|(kind, attributes)| json!({
+                    "type": kind,
+                    "attributes": attributes
+                        .iter()
+                        .map(|(key, value)| json!({ "key": key, "value": value }))
+                        .collect::<Vec<_>>(),
+                })).collect::<Vec<_>>(),
+            })
+        }
+
+        /// Evaluate the subset of the CometBFT query language used by `CosmWasmEntropy`.
+        fn matches(&self, query: &str) -> bool {
+            query.split(" AND ").all(|clause| {
+                if let Some(height) = clause.strip_prefix("tx.height>=") {
+                    return self.height >= height.parse().unwrap();
+                }
+                if let Some(height) = clause.strip_prefix("tx.height<=") {
+                    return self.height <= height.parse().unwrap();
+                }
+                let (key, value) = clause.split_once('=').unwrap();
+                let (kind, attribute) = key.split_once('.').unwrap();
+                let value = value.trim_matches('\'');
+                self.events.iter().any(|(k, attributes)| {
+                    k == kind && attributes.iter().any(|(a, v)| a == attribute && v == value)
+                })
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct MockLcdState {
+        contract: String,
+        height: u64,
+        /// The block number of the open requests, keyed by (provider, sequence number).
+        requests: BTreeMap<(String, u64), u64>,
+        txs: Vec<MockTx>,
+        account_sequence: u64,
+        executed: Vec<Value>,
+    }
+
+    impl MockLcdState {
+        /// Execute a contract message, returning the events it emits.
+        fn execute(&mut self, msg: &Value, dry_run: bool) -> Result<Vec<MockEvent>, String> {
+            let Some(reveal) = msg.get("reveal_with_callback") else {
+                return Ok(vec![]);
+            };
+            let provider = reveal["provider"].as_str().unwrap().to_string();
+            let sequence_number = reveal["sequence_number"].as_u64().unwrap();
+            if !self
+                .requests
+                .contains_key(&(provider.clone(), sequence_number))
+            {
+                return Err("failed to execute message; message index: 0: NoSuchRequest".into());
+            }
+            if !dry_run {
+                self.requests.remove(&(provider.clone(), sequence_number));
+            }
+            let provider_revelation = reveal["provider_revelation"].as_str().unwrap();
+            Ok(vec![(
+                REVEALED_EVENT.to_string(),
+                vec![
+                    ("_contract_address".into(), self.contract.clone()),
+                    ("provider".into(), provider),
+                    ("sequence_number".into(), sequence_number.to_string()),
+                    ("provider_revelation".into(), provider_revelation.into()),
+                    ("random_number".into(), provider_revelation.into()),
+                    ("callback_failed".into(), "false".into()),
+                    ("callback_return_value".into(), "".into()),
+                    ("callback_gas_used".into(), "50000".into()),
+                ],
+            )])
+        }
+    }
+
+    /// A mock LCD endpoint of a chain running the Entropy contract. Every transaction it receives
+    /// produces a new block. Broadcast transactions must be correctly signed.
+    #[derive(Clone)]
+    pub struct MockLcd {
+        state: Arc<Mutex<MockLcdState>>,
+    }
+
+    impl MockLcd {
+        /// Start the mock on a local port and return it along with its URL.
+        pub async fn start(contract: &str) -> (Self, String) {
+            let lcd = Self {
+                state: Arc::new(Mutex::new(MockLcdState {
+                    contract: contract.to_string(),
+                    height: 1,
+                    ..Default::default()
+                })),
+            };
+            let app = Router::new()
+                .route(
+                    "/cosmos/base/tendermint/v1beta1/blocks/latest",
+                    get(latest_block),
+                )
+                .route("/cosmos/base/tendermint/v1beta1/node_info", get(node_info))
+                .route("/cosmos/auth/v1beta1/accounts/:address", get(account))
+                .route(
+                    "/cosmos/bank/v1beta1/balances/:address/by_denom",
+                    get(balance),
+                )
+                .route(
+                    "/cosmwasm/wasm/v1/contract/:address/smart/:query",
+                    get(smart_query),
+                )
+                .route("/cosmos/tx/v1beta1/txs", get(search_txs).post(broadcast))
+                .route("/cosmos/tx/v1beta1/txs/:hash", get(get_tx))
+                .route("/cosmos/tx/v1beta1/simulate", post(simulate))
+                .with_state(lcd.clone());
+            let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+                .serve(app.into_make_service());
+            let url = format!("http://{}", server.local_addr());
+            tokio::spawn(server);
+            (lcd, url)
+        }
+
+        /// Make a request with callback in a new block.
+        pub fn request(
+            &self,
+            provider: &str,
+            requester: &str,
+            sequence_number: u64,
+            user_random_number: [u8; 32],
+        ) {
+            let mut state = self.state.lock().unwrap();
+            state.height += 1;
+            let height = state.height;
+            state
+                .requests
+                .insert((provider.to_string(), sequence_number), height);
+            let event = (
+                REQUESTED_EVENT.to_string(),
+                vec![
+                    ("_contract_address".into(), state.contract.clone()),
+                    ("provider".into(), provider.to_string()),
+                    ("sequence_number".into(), sequence_number.to_string()),
+                    ("user_random_number".into(), hex::encode(user_random_number)),
+                    ("requester".into(), requester.to_string()),
+                    ("gas_limit".into(), "100000".into()),
+                ],
+            );
+            state.txs.push(MockTx {
+                height,
+                hash: hex::encode_upper(Sha256::digest(format!("request-{sequence_number}"))),
+                events: vec![event],
+            });
+        }
+
+        pub fn height(&self) -> u64 {
+            self.state.lock().unwrap().height
+        }
+
+        /// The contract messages executed so far.
+        pub fn executed(&self) -> Vec<Value> {
+            self.state.lock().unwrap().executed.clone()
+        }
+    }
+
+    fn error(status: StatusCode, message: &str) -> Response {
+        (status, Json(json!({ "code": 2, "message": message }))).into_response()
+    }
+
+    /// Decode a transaction, returning its signer info, fee and contract messages.
+    fn decode_tx(body: &Value) -> (proto::TxRaw, proto::AuthInfo, Vec<Value>) {
+        let tx_bytes = BASE64_STANDARD
+            .decode(body["tx_bytes"].as_str().unwrap())
+            .unwrap();
+        let tx = proto::TxRaw::decode(tx_bytes.as_slice()).unwrap();
+        let auth_info = proto::AuthInfo::decode(tx.auth_info_bytes.as_slice()).unwrap();
+        let messages = proto::TxBody::decode(tx.body_bytes.as_slice())
+            .unwrap()
+            .messages
+            .into_iter()
+            .filter(|message| message.type_url == proto::MSG_EXECUTE_CONTRACT_TYPE_URL)
+            .map(|message| {
+                let message = proto::MsgExecuteContract::decode(message.value.as_slice()).unwrap();
+                serde_json::from_slice(&message.msg).unwrap()
+            })
+            .collect();
+        (tx, auth_info, messages)
+    }
+
+    async fn latest_block(State(lcd): State<MockLcd>) -> Json<Value> {
+        Json(json!({
+            "block": { "header": { "height": lcd.height().to_string(), "time": "2025-01-01T00:00:00Z" } }
+        }))
+    }
+
+    async fn node_info() -> Json<Value> {
+        Json(json!({ "default_node_info": { "network": CHAIN_ID } }))
+    }
+
+    async fn account(State(lcd): State<MockLcd>) -> Json<Value> {
+        let sequence = lcd.state.lock().unwrap().account_sequence;
+        Json(json!({
+            "account": {
+                "@type": "/cosmos.auth.v1beta1.BaseAccount",
+                "account_number": ACCOUNT_NUMBER.to_string(),
+                "sequence": sequence.to_string(),
+            }
+        }))
+    }
+
+    async fn balance() -> Json<Value> {
+        Json(json!({ "balance": { "denom": "untrn", "amount": "1000000000" } }))
+    }
+
+    async fn smart_query(
+        State(lcd): State<MockLcd>,
+        Path((_, query)): Path<(String, String)>,
+    ) -> Json<Value> {
+        let query: Value = serde_json::from_slice(&BASE64_URL_SAFE.decode(query).unwrap()).unwrap();
+        let state = lcd.state.lock().unwrap();
+        let data = if let Some(request) = query.get("get_request") {
+            let provider = request["provider"].as_str().unwrap().to_string();
+            let sequence_number = request["sequence_number"].as_u64().unwrap();
+            match state.requests.get(&(provider.clone(), sequence_number)) {
+                Some(block_number) => json!({
+                    "provider": provider,
+                    "sequence_number": sequence_number,
+                    "block_number": block_number,
+                    "use_blockhash": false,
+                    "callback_status": 1,
+                    "gas_limit_10k": 10,
+                }),
+                None => Value::Null,
+            }
+        } else if query.get("get_provider_info").is_some() {
+            json!({
+                "fee_in_wei": "100",
+                "accrued_fees_in_wei": "0",
+                "original_commitment": hex::encode([0u8; 32]),
+                "original_commitment_sequence_number": 0,
+                "commitment_metadata": "",
+                "end_sequence_number": 1000,
+                "sequence_number": state.requests.len() + 1,
+                "current_commitment_sequence_number": 0,
+                "max_num_hashes": 0,
+                "default_gas_limit": 100000,
+                "fee_manager": null,
+            })
+        } else {
+            json!("0")
+        };
+        Json(json!({ "data": data }))
+    }
+
+    async fn search_txs(
+        State(lcd): State<MockLcd>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> Json<Value> {
+        let page: usize = params["page"].parse().unwrap();
+        let limit: usize = params["limit"].parse().unwrap();
+        let state = lcd.state.lock().unwrap();
+        let txs: Vec<Value> = state
+            .txs
+            .iter()
+            .filter(|tx| tx.matches(&params["query"]))
+            .skip((page - 1) * limit)
+            .take(limit)
+            .map(MockTx::to_json)
+            .collect();
+        Json(json!({ "tx_responses": txs }))
+    }
+
+    async fn get_tx(State(lcd): State<MockLcd>, Path(hash): Path<String>) -> Response {
+        let state = lcd.state.lock().unwrap();
+        match state.txs.iter().find(|tx| tx.hash == hash) {
+            Some(tx) => Json(json!({ "tx_response": tx.to_json() })).into_response(),
+            None => error(StatusCode::NOT_FOUND, "tx not found"),
+        }
+    }
+
+    async fn simulate(State(lcd): State<MockLcd>, Json(body): Json<Value>) -> Response {
+        let (_, _, messages) = decode_tx(&body);
+        let mut state = lcd.state.lock().unwrap();
+        for message in &messages {
+            if let Err(e) = state.execute(message, true) {
+                return error(StatusCode::BAD_REQUEST, &e);
+            }
+        }
+        Json(json!({ "gas_info": { "gas_used": SIMULATED_GAS.to_string() } })).into_response()
+    }
+
+    async fn broadcast(State(lcd): State<MockLcd>, Json(body): Json<Value>) -> Response {
+        let (tx, auth_info, messages) = decode_tx(&body);
+        let signer_info = &auth_info.signer_infos[0];
+        let public_key =
+            proto::PubKey::decode(signer_info.public_key.as_ref().unwrap().value.as_slice())
+                .unwrap();
+        let sign_doc = proto::SignDoc {
+            body_bytes: tx.body_bytes.clone(),
+            auth_info_bytes: tx.auth_info_bytes.clone(),
+            chain_id: CHAIN_ID.to_string(),
+            account_number: ACCOUNT_NUMBER,
+        }
+        .encode_to_vec();
+        let verified = VerifyingKey::from_sec1_bytes(&public_key.key)
+            .ok()
+            .zip(Signature::from_slice(&tx.signatures[0]).ok())
+            .is_some_and(|(key, signature)| key.verify(&sign_doc, &signature).is_ok());
+        if !verified {
+            return error(StatusCode::BAD_REQUEST, "signature verification failed");
+        }
+
+        let mut state = lcd.state.lock().unwrap();
+        if signer_info.sequence != state.account_sequence {
+            return error(StatusCode::BAD_REQUEST, "account sequence mismatch");
+        }
+        let mut events = vec![];
+        for message in &messages {
+            match state.execute(message, false) {
+                Ok(message_events) => events.extend(message_events),
+                Err(e) => return error(StatusCode::BAD_REQUEST, &e),
+            }
+            state.executed.push(message.clone());
+        }
+        state.account_sequence += 1;
+        state.height += 1;
+        let hash = hex::encode_upper(Sha256::digest(tx.encode_to_vec()));
+        let height = state.height;
+        state.txs.push(MockTx {
+            height,
+            hash: hash.clone(),
+            events,
+        });
+        Json(json!({ "tx_response": { "height": "0", "txhash": hash, "code": 0 } })).into_response()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        mock::{chain_config, contract_addr, MockLcd, KEEPER_PRIVATE_KEY},
+        *,
+    };
+
+    #[test]
+    fn test_account_address_roundtrip() {
+        let address = Address::from_low_u64_be(42);
+        let encoded = encode_account_address("neutron", address).unwrap();
+        assert!(encoded.starts_with("neutron1"));
+        assert_eq!(decode_account_address(&encoded).unwrap(), address);
+
+        // Contract addresses are 32 bytes, so they are not account addresses.
+        assert!(decode_account_address(&contract_addr()).is_err());
+        assert!(decode_account_address("not an address").is_err());
+    }
+
+    #[test]
+    fn test_execute_msg_encoding() {
+        let msg = ExecuteMsg::RevealWithCallback {
+            provider: "neutron1provider".to_string(),
+            sequence_number: 5,
+            user_random_number: [1u8; 32],
+            provider_revelation: [2u8; 32],
+        };
+        assert_eq!(
+            serde_json::to_value(&msg).unwrap(),
+            serde_json::json!({
+                "reveal_with_callback": {
+                    "provider": "neutron1provider",
+                    "sequence_number": 5,
+                    "user_random_number": hex::encode([1u8; 32]),
+                    "provider_revelation": hex::encode([2u8; 32]),
+                }
+            })
+        );
+        let msg = ExecuteMsg::WithdrawAsFeeManager {
+            provider: "neutron1provider".to_string(),
+            amount: u128::MAX,
+        };
+        assert_eq!(
+            serde_json::to_value(&msg).unwrap()["withdraw_as_fee_manager"]["amount"],
+            u128::MAX.to_string()
+        );
+    }
+
+    #[test]
+    fn test_tx_error_reason() {
+        let no_such_request = TxError::Simulation(
+            LcdError {
+                status: 400,
+                message: "failed to execute message; message index: 0: NoSuchRequest".to_string(),
+            }
+            .into(),
+        );
+        assert!(no_such_request.is_no_such_request());
+        assert_eq!(
+            no_such_request.reason(),
+            "Reverted: failed to execute message; message index: 0: NoSuchRequest"
+        );
+
+        let rpc_error = TxError::Simulation(anyhow!("connection refused to http://secret-rpc"));
+        assert!(!rpc_error.is_no_such_request());
+        assert_eq!(rpc_error.reason(), "Unable to estimate gas usage");
+    }
+
+    #[tokio::test]
+    async fn test_reveal_with_callback() {
+        let (lcd, url) = MockLcd::start(&contract_addr()).await;
+        let config = chain_config(&url);
+        let contract = CosmWasmEntropy::from_config(&config, Some(KEEPER_PRIVATE_KEY)).unwrap();
+        let provider = decode_account_address(&config.provider_addr).unwrap();
+        let requester = encode_account_address("neutron", Address::from_low_u64_be(3)).unwrap();
+        lcd.request(&config.provider_addr, &requester, 7, [5u8; 32]);
+
+        let latest_block = contract
+            .get_block_number(BlockStatus::Latest)
+            .await
+            .unwrap();
+        let events = contract
+            .get_request_with_callback_events(0, latest_block, provider)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].sequence_number, 7);
+        assert_eq!(events[0].user_random_number, [5u8; 32]);
+        assert_eq!(events[0].sender, Address::from_low_u64_be(3));
+        assert_eq!(events[0].log_meta.block_number.as_u64(), latest_block);
+        // Events of other providers are filtered out
+        assert!(contract
+            .get_request_with_callback_events(0, latest_block, Address::from_low_u64_be(2))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let request = contract.get_request_v2(provider, 7).await.unwrap().unwrap();
+        assert_eq!(
+            request.callback_status,
+            RequestCallbackStatus::CallbackNotStarted
+        );
+
+        let receipt = contract
+            .reveal_with_callback(provider, 7, [5u8; 32], [6u8; 32])
+            .await
+            .unwrap();
+        assert_eq!(receipt.block_number, latest_block + 1);
+        assert_eq!(receipt.gas_used, Some(U256::from(mock::SIMULATED_GAS)));
+        assert!(!receipt.callback_failed);
+        assert_eq!(receipt.callback_gas_used, 50000);
+        assert_eq!(
+            lcd.executed(),
+            vec![serde_json::json!({
+                "reveal_with_callback": {
+                    "provider": config.provider_addr,
+                    "sequence_number": 7,
+                    "user_random_number": hex::encode([5u8; 32]),
+                    "provider_revelation": hex::encode([6u8; 32]),
+                }
+            })]
+        );
+
+        assert!(contract
+            .get_request_v2(provider, 7)
+            .await
+            .unwrap()
+            .is_none());
+        let revealed = contract
+            .get_revealed_event(provider, 7, latest_block)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(revealed.provider_revelation, [6u8; 32]);
+        assert_eq!(revealed.log_meta.transaction_hash, receipt.transaction_hash);
+        assert!(contract
+            .get_revealed_event(provider, 8, latest_block)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
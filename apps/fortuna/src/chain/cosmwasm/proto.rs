@@ -0,0 +1,128 @@
+//! The subset of the Cosmos SDK and CosmWasm protobuf messages needed to build and sign
+//! transactions. Field tags match `cosmos.tx.v1beta1`, `cosmos.bank.v1beta1` and
+//! `cosmwasm.wasm.v1`.
+
+pub const SIGN_MODE_DIRECT: i32 = 1;
+
+pub const PUB_KEY_TYPE_URL: &str = "/cosmos.crypto.secp256k1.PubKey";
+pub const MSG_EXECUTE_CONTRACT_TYPE_URL: &str = "/cosmwasm.wasm.v1.MsgExecuteContract";
+pub const MSG_SEND_TYPE_URL: &str = "/cosmos.bank.v1beta1.MsgSend";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Any {
+    #[prost(string, tag = "1")]
+    pub type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Coin {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PubKey {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgExecuteContract {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(string, tag = "2")]
+    pub contract: String,
+    /// The JSON encoded execute message.
+    #[prost(bytes = "vec", tag = "3")]
+    pub msg: Vec<u8>,
+    #[prost(message, repeated, tag = "5")]
+    pub funds: Vec<Coin>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgSend {
+    #[prost(string, tag = "1")]
+    pub from_address: String,
+    #[prost(string, tag = "2")]
+    pub to_address: String,
+    #[prost(message, repeated, tag = "3")]
+    pub amount: Vec<Coin>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TxBody {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: Vec<Any>,
+    #[prost(string, tag = "2")]
+    pub memo: String,
+    #[prost(uint64, tag = "3")]
+    pub timeout_height: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ModeInfoSingle {
+    #[prost(int32, tag = "1")]
+    pub mode: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ModeInfo {
+    #[prost(message, optional, tag = "1")]
+    pub single: Option<ModeInfoSingle>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SignerInfo {
+    #[prost(message, optional, tag = "1")]
+    pub public_key: Option<Any>,
+    #[prost(message, optional, tag = "2")]
+    pub mode_info: Option<ModeInfo>,
+    #[prost(uint64, tag = "3")]
+    pub sequence: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Fee {
+    #[prost(message, repeated, tag = "1")]
+    pub amount: Vec<Coin>,
+    #[prost(uint64, tag = "2")]
+    pub gas_limit: u64,
+    #[prost(string, tag = "3")]
+    pub payer: String,
+    #[prost(string, tag = "4")]
+    pub granter: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AuthInfo {
+    #[prost(message, repeated, tag = "1")]
+    pub signer_infos: Vec<SignerInfo>,
+    #[prost(message, optional, tag = "2")]
+    pub fee: Option<Fee>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SignDoc {
+    #[prost(bytes = "vec", tag = "1")]
+    pub body_bytes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub auth_info_bytes: Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub chain_id: String,
+    #[prost(uint64, tag = "4")]
+    pub account_number: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TxRaw {
+    #[prost(bytes = "vec", tag = "1")]
+    pub body_bytes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub auth_info_bytes: Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "3")]
+    pub signatures: Vec<Vec<u8>>,
+}
@@ -3,31 +3,43 @@
 use {
     crate::{
         api::ChainId,
-        chain::reader::{
-            self, BlockNumber, BlockStatus, EntropyReader, RequestedV2Event, RevealedV2Event,
+        chain::{
+            reader::{
+                self, BlockNumber, BlockStatus, EntropyReader, ProviderInfo, RequestedV2Event,
+                RevealedV2Event,
+            },
+            writer::{AccountNonces, EntropyWriter, RevealError, RevealReceipt, SimulatedReveal},
         },
         config::EthereumConfig,
         eth_utils::{
-            eth_gas_oracle::EthProviderOracle,
+            gas_oracle::{self, estimate_tx_cost, GasOracle, GasOracleAdapter},
             legacy_tx_middleware::LegacyTxMiddleware,
             nonce_manager::NonceManagerMiddleware,
             traced_client::{RpcMetrics, TracedClient},
+            utils::{
+                send_and_confirm, submit_cancel_tx, submit_transfer_tx, submit_tx_with_backoff,
+                EscalationPolicy, SubmitTxError, SubmitTxResult,
+            },
         },
+        history::RequestStatus,
     },
     anyhow::{anyhow, Error, Result},
     axum::async_trait,
     ethers::{
-        abi::RawLog,
-        contract::{abigen, EthLogDecode, LogMeta},
+        abi::{self, AbiDecode, RawLog, Token},
+        contract::{abigen, ContractError, EthLogDecode, LogMeta},
         core::types::Address,
         middleware::{gas_oracle::GasOracleMiddleware, SignerMiddleware},
         prelude::JsonRpcClient,
-        providers::{Http, Middleware, Provider},
+        providers::{Http, Middleware, MiddlewareError, Provider},
         signers::{LocalWallet, Signer},
-        types::{BlockNumber as EthersBlockNumber, H256, U256},
+        types::{
+            transaction::eip2718::TypedTransaction, BlockNumber as EthersBlockNumber, Bytes,
+            Eip1559TransactionRequest, TransactionRequest, H256, U256,
+        },
     },
     sha3::{Digest, Keccak256},
-    std::sync::Arc,
+    std::{sync::Arc, time::Duration},
 };
 
 // TODO: Programmatically generate this so we don't have to keep committed ABI in sync with the
@@ -39,10 +51,13 @@ abigen!(
     "../../target_chains/ethereum/entropy_sdk/solidity/abis/EntropyErrors.json"
 );
 
+/// The signature of the function the contract calls on the requester of a request with callback.
+const ENTROPY_CALLBACK_SIGNATURE: &str = "_entropyCallback(uint64,address,bytes32)";
+
 pub type MiddlewaresWrapper<T> = LegacyTxMiddleware<
     GasOracleMiddleware<
         NonceManagerMiddleware<SignerMiddleware<Provider<T>, LocalWallet>>,
-        EthProviderOracle<Provider<T>>,
+        GasOracleAdapter,
     >,
 >;
 
@@ -169,19 +184,7 @@ impl<T: JsonRpcClient + 'static + Clone> SignablePythContractInner<T> {
         provider: Provider<T>,
         network_id: u64,
     ) -> Result<SignablePythContractInner<T>> {
-        let gas_oracle = EthProviderOracle::new(
-            provider.clone(),
-            chain_config.priority_fee_multiplier_pct,
-            chain_config.min_reward_samples,
-            chain_config.fee_estimation_past_blocks,
-            chain_config.fee_estimation_reward_percentile,
-            chain_config.eip1559_fee_estimation_default_priority_fee,
-            chain_config.eip1559_fee_estimation_priority_fee_trigger,
-            chain_config.eip1559_fee_estimation_threshold_max_change,
-            chain_config.surge_threshold_1,
-            chain_config.surge_threshold_2,
-            chain_config.surge_threshold_3,
-        );
+        let gas_oracle = GasOracleAdapter(gas_oracle::from_config(chain_config, provider.clone()));
         let wallet__ = private_key
             .parse::<LocalWallet>()?
             .with_chain_id(network_id);
@@ -405,4 +408,455 @@ impl<T: JsonRpcClient + 'static> EntropyReader for PythRandom<Provider<T>> {
 
         result.map_err(|e| e.into())
     }
+
+    async fn get_provider_info(
+        &self,
+        provider: Address,
+        block: Option<BlockNumber>,
+    ) -> Result<ProviderInfo> {
+        let mut call = self.get_provider_info_v2(provider);
+        if let Some(block) = block {
+            call = call.block(block);
+        }
+        let info = call.call().await?;
+        Ok(ProviderInfo {
+            fee_in_wei: info.fee_in_wei,
+            accrued_fees_in_wei: info.accrued_fees_in_wei,
+            original_commitment: info.original_commitment,
+            original_commitment_sequence_number: info.original_commitment_sequence_number,
+            commitment_metadata: info.commitment_metadata,
+            end_sequence_number: info.end_sequence_number,
+            sequence_number: info.sequence_number,
+            current_commitment_sequence_number: info.current_commitment_sequence_number,
+            max_num_hashes: info.max_num_hashes,
+            default_gas_limit: info.default_gas_limit,
+            fee_manager: info.fee_manager,
+        })
+    }
+
+    async fn get_accrued_pyth_fees(&self) -> Result<u128> {
+        Ok(self.get_accrued_pyth_fees().call().await?)
+    }
+
+    async fn get_balance(&self, address: Address) -> Result<U256> {
+        Ok(self.client().get_balance(address, None).await?)
+    }
+
+    async fn get_latest_block_timestamp(&self) -> Result<(BlockNumber, u64)> {
+        let block = self
+            .client()
+            .get_block(EthersBlockNumber::Latest)
+            .await?
+            .ok_or_else(|| anyhow!("block was none"))?;
+        let block_number = block
+            .number
+            .ok_or_else(|| anyhow!("block number was none"))?
+            .as_u64();
+        Ok((block_number, block.timestamp.as_u64()))
+    }
+}
+
+/// Signs and submits transactions to the Entropy contract on an EVM chain.
+pub struct EthereumWriter {
+    contract: Arc<InstrumentedSignablePythContract>,
+    gas_oracle: Arc<dyn GasOracle>,
+    escalation_policy: EscalationPolicy,
+    legacy_tx: bool,
+}
+
+impl EthereumWriter {
+    pub fn from_config(
+        chain_config: &EthereumConfig,
+        private_key: &str,
+        chain_id: ChainId,
+        metrics: Arc<RpcMetrics>,
+        network_id: u64,
+    ) -> Result<Self> {
+        let contract = InstrumentedSignablePythContract::from_config(
+            chain_config,
+            private_key,
+            chain_id,
+            metrics,
+            network_id,
+        )?;
+        Ok(Self {
+            gas_oracle: gas_oracle::from_config(chain_config, contract.provider()),
+            contract: Arc::new(contract),
+            escalation_policy: chain_config.escalation_policy.to_policy(),
+            legacy_tx: chain_config.legacy_tx,
+        })
+    }
+
+    fn check_account(&self, account: Address) -> Result<()> {
+        if account != self.address() {
+            return Err(anyhow!(
+                "{:?} is not the signing account {:?}",
+                account,
+                self.address()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Convert the result of a reveal transaction into its receipt or error.
+    fn reveal_receipt(
+        result: Result<SubmitTxResult, SubmitTxError<MiddlewaresWrapper<TracedClient>>>,
+    ) -> Result<RevealReceipt, RevealError> {
+        match result {
+            Ok(result) => Ok(RevealReceipt {
+                block_number: result.receipt.block_number.unwrap_or_default().as_u64(),
+                transaction_hash: result.receipt.transaction_hash,
+                gas_used: result.receipt.gas_used,
+                effective_gas_price: result.receipt.effective_gas_price,
+                num_retries: result.num_retries,
+                fee_multiplier: result.fee_multiplier,
+                duration: result.duration,
+                callback_failed: result.revealed_event.callback_failed,
+                callback_return_value: result.revealed_event.callback_return_value,
+                callback_gas_used: result.revealed_event.callback_gas_used,
+            }),
+            Err(e) => {
+                // Do not display the internal error, it might include RPC details.
+                let reason = match &e {
+                    SubmitTxError::GasUsageEstimateError(_, ContractError::Revert(revert)) => {
+                        format!("Reverted: {revert}")
+                    }
+                    SubmitTxError::GasLimitExceeded { limit, estimate } => {
+                        format!("Gas limit exceeded: limit = {limit}, estimate = {estimate}")
+                    }
+                    SubmitTxError::GasUsageEstimateError(_, _) => {
+                        "Unable to estimate gas usage".to_string()
+                    }
+                    SubmitTxError::GasPriceEstimateError(_) => {
+                        "Unable to estimate gas price".to_string()
+                    }
+                    SubmitTxError::SubmissionError(_, _) => {
+                        "Error submitting the transaction on-chain".to_string()
+                    }
+                    SubmitTxError::ConfirmationTimeout(tx) => format!(
+                        "Transaction was submitted, but never confirmed. Hash: {}",
+                        tx.sighash()
+                    ),
+                    SubmitTxError::ConfirmationError(tx, _) => format!(
+                        "Transaction was submitted, but never confirmed. Hash: {}",
+                        tx.sighash()
+                    ),
+                    SubmitTxError::ReceiptError(tx, _) => {
+                        format!("Reveal transaction failed on-chain. Hash: {}", tx.sighash())
+                    }
+                };
+                Err(RevealError {
+                    reason,
+                    error: anyhow!("{}", e),
+                })
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EntropyWriter for EthereumWriter {
+    fn address(&self) -> Address {
+        self.contract.wallet().address()
+    }
+
+    async fn reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+    ) -> Result<RevealReceipt, RevealError> {
+        let contract_call = self.contract.reveal_with_callback(
+            provider,
+            sequence_number,
+            user_random_number,
+            provider_revelation,
+        );
+        let error_mapper = |num_retries, e| {
+            if let backoff::Error::Transient {
+                err: SubmitTxError::GasUsageEstimateError(tx, ContractError::Revert(revert)),
+                ..
+            } = &e
+            {
+                if let Ok(PythRandomErrorsErrors::NoSuchRequest(_)) =
+                    PythRandomErrorsErrors::decode(revert)
+                {
+                    let err = SubmitTxError::GasUsageEstimateError(
+                        tx.clone(),
+                        ContractError::Revert(revert.clone()),
+                    );
+                    // Slow down the retries if the request is not found.
+                    // This probably means that the request is already fulfilled via another process.
+                    // After 5 retries, we return the error permanently.
+                    if num_retries >= 5 {
+                        return backoff::Error::Permanent(err);
+                    }
+                    let retry_after_seconds = match num_retries {
+                        0 => 5,
+                        1 => 10,
+                        _ => 60,
+                    };
+                    return backoff::Error::Transient {
+                        err,
+                        retry_after: Some(Duration::from_secs(retry_after_seconds)),
+                    };
+                }
+            }
+            e
+        };
+
+        Self::reveal_receipt(
+            submit_tx_with_backoff(
+                self.contract.client(),
+                contract_call,
+                self.escalation_policy.clone(),
+                Some(error_mapper),
+            )
+            .await,
+        )
+    }
+
+    async fn retry_reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+        gas_limit: u64,
+    ) -> Result<RevealReceipt, RevealError> {
+        // The gas limit of the call caps the gas estimate, so the transaction is not submitted if
+        // the callback needs more gas than that.
+        let contract_call = self
+            .contract
+            .reveal_with_callback(
+                provider,
+                sequence_number,
+                user_random_number,
+                provider_revelation,
+            )
+            .gas(gas_limit);
+        // The callback of a failed request is invoked without catching its errors, so the gas
+        // estimate fails if the callback fails again. Retrying right away would most likely fail
+        // the same way.
+        let error_mapper = |_num_retries, e| match e {
+            backoff::Error::Transient {
+                err: err @ SubmitTxError::GasUsageEstimateError(_, _),
+                ..
+            } => backoff::Error::Permanent(err),
+            e => e,
+        };
+        Self::reveal_receipt(
+            submit_tx_with_backoff(
+                self.contract.client(),
+                contract_call,
+                self.escalation_policy.clone(),
+                Some(error_mapper),
+            )
+            .await,
+        )
+    }
+
+    async fn simulate_reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+    ) -> Result<SimulatedReveal, RevealError> {
+        let to_reveal_error = |reason: &str, e: anyhow::Error| RevealError {
+            reason: reason.to_string(),
+            error: e,
+        };
+        let contract_call = self.contract.reveal_with_callback(
+            provider,
+            sequence_number,
+            user_random_number,
+            provider_revelation,
+        );
+        let gas_estimate = contract_call.estimate_gas().await.map_err(|e| match &e {
+            ContractError::Revert(revert) => RevealError {
+                reason: format!("Reverted: {revert}"),
+                error: anyhow!("{}", e),
+            },
+            _ => to_reveal_error("Unable to estimate gas usage", anyhow!("{}", e)),
+        })?;
+        let estimated_fee = estimate_tx_cost(
+            self.gas_oracle.as_ref(),
+            self.legacy_tx,
+            gas_estimate.as_u128(),
+            self.contract.address(),
+            &contract_call.calldata().unwrap_or_default(),
+        )
+        .await
+        .map_err(|e| to_reveal_error("Unable to estimate the transaction cost", e))?;
+
+        // The reveal succeeds even if the callback fails, so simulate the callback separately, as
+        // the contract would call it.
+        let request = self
+            .contract
+            .get_request_v2(provider, sequence_number)
+            .call()
+            .await
+            .map_err(|e| to_reveal_error("Unable to fetch the request", anyhow!("{}", e)))?;
+        let callback = TransactionRequest::new()
+            .from(self.contract.address())
+            .to(request.requester)
+            .data(
+                [
+                    ethers::utils::id(ENTROPY_CALLBACK_SIGNATURE).to_vec(),
+                    abi::encode(&[
+                        Token::Uint(sequence_number.into()),
+                        Token::Address(provider),
+                        Token::FixedBytes(
+                            RequestStatus::generate_combined_random_number(
+                                &user_random_number,
+                                &provider_revelation,
+                            )
+                            .to_vec(),
+                        ),
+                    ]),
+                ]
+                .concat(),
+            );
+        let callback_gas_limit = u64::from(request.gas_limit_1_0k) * 10_000;
+        let capped_callback: TypedTransaction = if callback_gas_limit > 0 {
+            callback.clone().gas(callback_gas_limit).into()
+        } else {
+            callback.clone().into()
+        };
+        let client = self.contract.client();
+        let (callback_failed, callback_return_value, callback_gas_used) =
+            match client.call(&capped_callback, None).await {
+                Ok(_) => {
+                    let callback_gas_used = client
+                        .estimate_gas(&capped_callback, None)
+                        .await
+                        .map(|gas| gas.as_u64())
+                        .unwrap_or(callback_gas_limit);
+                    (false, Bytes::default(), callback_gas_used)
+                }
+                Err(e) => {
+                    let return_value = e
+                        .as_error_response()
+                        .and_then(|response| response.as_revert_data())
+                        .unwrap_or_default();
+                    // The callback runs out of gas if it succeeds without the gas limit.
+                    let out_of_gas =
+                        callback_gas_limit > 0 && client.call(&callback.into(), None).await.is_ok();
+                    let callback_gas_used = if out_of_gas { callback_gas_limit } else { 0 };
+                    (true, return_value, callback_gas_used)
+                }
+            };
+        Ok(SimulatedReveal {
+            gas_estimate,
+            estimated_fee: U256::from(estimated_fee),
+            callback_failed,
+            // The contract truncates the return value of callbacks to 256 bytes.
+            callback_return_value: callback_return_value
+                .iter()
+                .take(256)
+                .copied()
+                .collect::<Vec<_>>()
+                .into(),
+            callback_gas_used: callback_gas_used.try_into().unwrap_or(u32::MAX),
+        })
+    }
+
+    async fn advance_provider_commitment(
+        &self,
+        provider: Address,
+        advanced_sequence_number: u64,
+        provider_revelation: [u8; 32],
+    ) -> Result<()> {
+        let contract_call = self.contract.advance_provider_commitment(
+            provider,
+            advanced_sequence_number,
+            provider_revelation,
+        );
+        send_and_confirm(contract_call).await
+    }
+
+    async fn register_provider(
+        &self,
+        fee: u128,
+        commitment: [u8; 32],
+        commitment_metadata: Bytes,
+        chain_length: u64,
+        uri: Bytes,
+    ) -> Result<()> {
+        let contract_call =
+            self.contract
+                .register(fee, commitment, commitment_metadata, chain_length, uri);
+        // Pad the gas estimate like the register-provider command.
+        let gas_estimate = contract_call.estimate_gas().await?;
+        send_and_confirm(contract_call.gas(gas_estimate * 2)).await
+    }
+
+    async fn set_provider_fee_as_fee_manager(&self, provider: Address, fee: u128) -> Result<()> {
+        let contract_call = self.contract.set_provider_fee_as_fee_manager(provider, fee);
+        send_and_confirm(contract_call).await
+    }
+
+    async fn withdraw_as_fee_manager(&self, provider: Address, amount: u128) -> Result<()> {
+        let contract_call = self.contract.withdraw_as_fee_manager(provider, amount);
+        send_and_confirm(contract_call).await
+    }
+
+    async fn transfer(&self, destination: Address, amount: U256) -> Result<()> {
+        submit_transfer_tx(self.contract.clone(), destination, amount).await?;
+        Ok(())
+    }
+
+    async fn estimate_tx_cost(&self, gas: u128) -> Result<u128> {
+        // The L1 fee depends on the calldata, so estimate it for a reveal with non-zero arguments,
+        // which are the most expensive.
+        let data = self
+            .contract
+            .reveal_with_callback(Address::repeat_byte(0xff), u64::MAX, [0xff; 32], [0xff; 32])
+            .calldata()
+            .unwrap_or_default();
+        estimate_tx_cost(
+            self.gas_oracle.as_ref(),
+            self.legacy_tx,
+            gas,
+            self.contract.address(),
+            &data,
+        )
+        .await
+    }
+
+    async fn account_nonces(&self, account: Address) -> Result<AccountNonces> {
+        self.check_account(account)?;
+        let provider = self.contract.provider();
+        let mined = provider
+            .get_transaction_count(account, Some(EthersBlockNumber::Latest.into()))
+            .await?;
+        let pending = provider
+            .get_transaction_count(account, Some(EthersBlockNumber::Pending.into()))
+            .await?;
+        Ok(AccountNonces {
+            mined: mined.as_u64(),
+            pending: pending.as_u64(),
+        })
+    }
+
+    async fn cancel_transaction(&self, account: Address, nonce: u64) -> Result<H256> {
+        self.check_account(account)?;
+        let fee_multiplier_pct = self.escalation_policy.replacement_fee_multiplier_pct();
+        let escalate = |fee: U256| fee.saturating_mul(fee_multiplier_pct.into()) / 100;
+        let transaction: TypedTransaction = if self.legacy_tx {
+            TransactionRequest::new()
+                .gas_price(escalate(self.gas_oracle.gas_price().await?))
+                .into()
+        } else {
+            let (max_fee_per_gas, max_priority_fee_per_gas) =
+                self.gas_oracle.eip1559_fees().await?;
+            Eip1559TransactionRequest::new()
+                .max_fee_per_gas(escalate(max_fee_per_gas))
+                .max_priority_fee_per_gas(escalate(max_priority_fee_per_gas))
+                .into()
+        };
+        submit_cancel_tx(self.contract.clone(), nonce, transaction).await
+    }
 }
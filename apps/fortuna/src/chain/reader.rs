@@ -105,6 +105,40 @@ pub trait EntropyReader: Send + Sync {
         user_random_number: [u8; 32],
         provider_revelation: [u8; 32],
     ) -> Result<U256>;
+
+    /// Get the on-chain state of a provider. The state is read at `block` if provided, or at the
+    /// latest block otherwise.
+    async fn get_provider_info(
+        &self,
+        provider: Address,
+        block: Option<BlockNumber>,
+    ) -> Result<ProviderInfo>;
+
+    /// Get the fees accrued by the protocol (as opposed to the providers) in the contract.
+    async fn get_accrued_pyth_fees(&self) -> Result<u128>;
+
+    /// Get the native token balance of an account.
+    async fn get_balance(&self, address: Address) -> Result<U256>;
+
+    /// Get the number and the unix timestamp (in seconds) of the latest block.
+    async fn get_latest_block_timestamp(&self) -> Result<(BlockNumber, u64)>;
+}
+
+/// The state of a provider stored in the contract.
+/// (Like `Request`, this only contains the fields that fortuna uses.)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProviderInfo {
+    pub fee_in_wei: u128,
+    pub accrued_fees_in_wei: u128,
+    pub original_commitment: [u8; 32],
+    pub original_commitment_sequence_number: u64,
+    pub commitment_metadata: Bytes,
+    pub end_sequence_number: u64,
+    pub sequence_number: u64,
+    pub current_commitment_sequence_number: u64,
+    pub max_num_hashes: u32,
+    pub default_gas_limit: u32,
+    pub fee_manager: Address,
 }
 
 /// An in-flight request stored in the contract.
@@ -159,12 +193,16 @@ impl From<RequestCallbackStatus> for u8 {
 pub mod mock {
     use {
         crate::chain::reader::{
-            BlockNumber, BlockStatus, EntropyReader, Request, RequestCallbackStatus,
+            BlockNumber, BlockStatus, EntropyReader, ProviderInfo, Request, RequestCallbackStatus,
+            RequestedV2Event, RevealedV2Event,
         },
         anyhow::Result,
         axum::async_trait,
-        ethers::types::{Address, U256},
-        std::sync::RwLock,
+        ethers::{
+            prelude::LogMeta,
+            types::{Address, H256, U256, U64},
+        },
+        std::{collections::HashMap, sync::RwLock},
     };
 
     /// Mock version of the entropy contract intended for testing.
@@ -174,6 +212,12 @@ pub mod mock {
         block_number: RwLock<BlockNumber>,
         /// The set of requests that are currently in-flight.
         requests: RwLock<Vec<Request>>,
+        /// The request with callback events emitted so far.
+        events: RwLock<Vec<RequestedV2Event>>,
+        /// The reveals that cleared a request, by provider and sequence number.
+        revealed_events: RwLock<HashMap<(Address, u64), RevealedV2Event>>,
+        provider_info: RwLock<ProviderInfo>,
+        balances: RwLock<HashMap<Address, U256>>,
     }
 
     impl MockEntropyReader {
@@ -196,6 +240,10 @@ pub mod mock {
                         })
                         .collect(),
                 ),
+                events: RwLock::new(vec![]),
+                revealed_events: RwLock::new(HashMap::new()),
+                provider_info: RwLock::new(ProviderInfo::default()),
+                balances: RwLock::new(HashMap::new()),
             }
         }
 
@@ -218,10 +266,92 @@ pub mod mock {
             self
         }
 
+        /// Insert a new request with callback, emitting the corresponding event.
+        pub fn insert_with_callback(
+            &self,
+            provider: Address,
+            sequence: u64,
+            block_number: BlockNumber,
+            user_random_number: [u8; 32],
+        ) -> &Self {
+            self.requests.write().unwrap().push(Request {
+                provider,
+                sequence_number: sequence,
+                block_number,
+                use_blockhash: false,
+                callback_status: RequestCallbackStatus::CallbackNotStarted,
+                gas_limit_10k: 10,
+            });
+            self.events.write().unwrap().push(RequestedV2Event {
+                sequence_number: sequence,
+                user_random_number,
+                provider_address: provider,
+                sender: Address::zero(),
+                gas_limit: 100_000,
+                log_meta: LogMeta {
+                    address: Address::zero(),
+                    block_number: U64::from(block_number),
+                    block_hash: H256::zero(),
+                    transaction_hash: H256::from_low_u64_be(sequence),
+                    transaction_index: U64::zero(),
+                    log_index: U256::zero(),
+                },
+            });
+            self
+        }
+
+        /// Remove a request from the set of in-flight requests, as if it was revealed.
+        pub fn remove(&self, provider: Address, sequence: u64) -> &Self {
+            self.requests
+                .write()
+                .unwrap()
+                .retain(|r| !(r.provider == provider && r.sequence_number == sequence));
+            self
+        }
+
+        /// Record the reveal of a request, which clears it from the in-flight requests.
+        pub fn insert_revealed_event(
+            &self,
+            provider: Address,
+            sequence: u64,
+            event: RevealedV2Event,
+        ) -> &Self {
+            self.remove(provider, sequence);
+            self.revealed_events
+                .write()
+                .unwrap()
+                .insert((provider, sequence), event);
+            self
+        }
+
+        pub fn set_callback_status(
+            &self,
+            provider: Address,
+            sequence: u64,
+            callback_status: RequestCallbackStatus,
+        ) -> &Self {
+            for request in self.requests.write().unwrap().iter_mut() {
+                if request.provider == provider && request.sequence_number == sequence {
+                    request.callback_status = callback_status.clone();
+                }
+            }
+            self
+        }
+
         pub fn set_block_number(&self, block_number: BlockNumber) -> &Self {
             *(self.block_number.write().unwrap()) = block_number;
             self
         }
+
+        pub fn set_provider_info(&self, provider_info: ProviderInfo) -> &Self {
+            *(self.provider_info.write().unwrap()) = provider_info;
+            self
+        }
+
+        pub fn set_balance(&self, address: Address, balance: U256) -> &Self {
+            self.balances.write().unwrap().insert(address, balance);
+            self
+        }
     }
 
     #[async_trait]
@@ -249,20 +379,38 @@ pub mod mock {
 
         async fn get_request_with_callback_events(
             &self,
-            _from_block: BlockNumber,
-            _to_block: BlockNumber,
-            _provider: Address,
+            from_block: BlockNumber,
+            to_block: BlockNumber,
+            provider: Address,
         ) -> Result<Vec<super::RequestedV2Event>> {
-            Ok(vec![])
+            Ok(self
+                .events
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|e| {
+                    let block_number = e.log_meta.block_number.as_u64();
+                    e.provider_address == provider
+                        && from_block <= block_number
+                        && block_number <= to_block
+                })
+                .cloned()
+                .collect())
         }
 
         async fn get_revealed_event(
             &self,
-            _provider: Address,
-            _sequence_number: u64,
-            _from_block: BlockNumber,
+            provider: Address,
+            sequence_number: u64,
+            from_block: BlockNumber,
         ) -> Result<Option<super::RevealedV2Event>> {
-            Ok(None)
+            Ok(self
+                .revealed_events
+                .read()
+                .unwrap()
+                .get(&(provider, sequence_number))
+                .filter(|e| e.log_meta.block_number.as_u64() >= from_block)
+                .cloned())
         }
 
         async fn estimate_reveal_with_callback_gas(
@@ -275,5 +423,31 @@ pub mod mock {
         ) -> Result<U256> {
             Ok(U256::from(5))
         }
+
+        async fn get_provider_info(
+            &self,
+            _provider: Address,
+            _block: Option<BlockNumber>,
+        ) -> Result<ProviderInfo> {
+            Ok(self.provider_info.read().unwrap().clone())
+        }
+
+        async fn get_accrued_pyth_fees(&self) -> Result<u128> {
+            Ok(0)
+        }
+
+        async fn get_balance(&self, address: Address) -> Result<U256> {
+            Ok(self
+                .balances
+                .read()
+                .unwrap()
+                .get(&address)
+                .copied()
+                .unwrap_or_default())
+        }
+
+        async fn get_latest_block_timestamp(&self) -> Result<(BlockNumber, u64)> {
+            Ok((*self.block_number.read().unwrap(), 0))
+        }
     }
 }
@@ -0,0 +1,453 @@
+use {
+    crate::chain::reader::BlockNumber,
+    anyhow::Result,
+    axum::async_trait,
+    ethers::types::{Address, Bytes, H256, U256},
+    std::{fmt::Display, time::Duration},
+};
+
+/// A reveal transaction that landed on chain.
+#[derive(Clone, Debug)]
+pub struct RevealReceipt {
+    pub block_number: BlockNumber,
+    pub transaction_hash: H256,
+    pub gas_used: Option<U256>,
+    /// The price paid per unit of gas, if the chain reports it.
+    pub effective_gas_price: Option<U256>,
+    pub num_retries: u64,
+    pub fee_multiplier: u64,
+    /// The time it took to land the transaction, including retries.
+    pub duration: Duration,
+    pub callback_failed: bool,
+    pub callback_return_value: Bytes,
+    pub callback_gas_used: u32,
+}
+
+/// A reveal transaction that was simulated instead of sent, in dry-run mode.
+#[derive(Clone, Debug)]
+pub struct SimulatedReveal {
+    pub gas_estimate: U256,
+    /// The estimated cost of the transaction, including the L1 fee of rollups.
+    pub estimated_fee: U256,
+    pub callback_failed: bool,
+    pub callback_return_value: Bytes,
+    /// The gas the callback needs, or its gas limit if it runs out of gas.
+    pub callback_gas_used: u32,
+}
+
+/// The nonces of a signing account.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AccountNonces {
+    /// The nonce of the next transaction of the account to be mined.
+    pub mined: u64,
+    /// The nonce following the pending transactions of the account.
+    pub pending: u64,
+}
+
+/// A reveal that could not be landed on chain.
+#[derive(Debug)]
+pub struct RevealError {
+    /// A description of the failure that is safe to show to users. It must not include RPC
+    /// details.
+    pub reason: String,
+    pub error: anyhow::Error,
+}
+
+impl Display for RevealError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+/// EntropyWriter is the interface for submitting transactions to the Entropy contract with a
+/// single signing account.
+#[async_trait]
+pub trait EntropyWriter: Send + Sync {
+    /// The address of the account signing the transactions.
+    fn address(&self) -> Address;
+
+    /// The addresses of all the accounts signing the transactions, if several accounts share them.
+    fn addresses(&self) -> Vec<Address> {
+        vec![self.address()]
+    }
+
+    /// Reveal the provider's random number for a request with callback. Implementations retry
+    /// internally until the transaction lands or they give up.
+    async fn reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+    ) -> Result<RevealReceipt, RevealError>;
+
+    /// Reveal a request whose callback failed again. The contract invokes the callback of such a
+    /// request with all the gas of the transaction, so the transaction is not submitted if it
+    /// needs more than `gas_limit` gas, or if the callback fails again.
+    async fn retry_reveal_with_callback(
+        &self,
+        _provider: Address,
+        _sequence_number: u64,
+        _user_random_number: [u8; 32],
+        _provider_revelation: [u8; 32],
+        _gas_limit: u64,
+    ) -> Result<RevealReceipt, RevealError> {
+        Err(RevealError {
+            reason: "Retrying failed callbacks is not supported on this chain".to_string(),
+            error: anyhow::anyhow!("Retrying failed callbacks is not supported on this chain"),
+        })
+    }
+
+    /// Simulate the reveal of a request with callback, without sending any transaction.
+    async fn simulate_reveal_with_callback(
+        &self,
+        _provider: Address,
+        _sequence_number: u64,
+        _user_random_number: [u8; 32],
+        _provider_revelation: [u8; 32],
+    ) -> Result<SimulatedReveal, RevealError> {
+        Err(RevealError {
+            reason: "Simulating reveals is not supported on this chain".to_string(),
+            error: anyhow::anyhow!("Simulating reveals is not supported on this chain"),
+        })
+    }
+
+    /// Advance the provider's commitment to `advanced_sequence_number`, reducing the number of
+    /// hashes needed to reveal the following requests.
+    async fn advance_provider_commitment(
+        &self,
+        provider: Address,
+        advanced_sequence_number: u64,
+        provider_revelation: [u8; 32],
+    ) -> Result<()>;
+
+    /// Register the signing account as a provider committing to a new hash chain. This also sets
+    /// the fee and the URI of the provider.
+    async fn register_provider(
+        &self,
+        _fee: u128,
+        _commitment: [u8; 32],
+        _commitment_metadata: Bytes,
+        _chain_length: u64,
+        _uri: Bytes,
+    ) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Registering providers is not supported on this chain"
+        ))
+    }
+
+    /// Set the provider's fee. The signing account must be the provider's fee manager.
+    async fn set_provider_fee_as_fee_manager(&self, provider: Address, fee: u128) -> Result<()>;
+
+    /// Withdraw the provider's accrued fees to the signing account. The signing account must be
+    /// the provider's fee manager.
+    async fn withdraw_as_fee_manager(&self, provider: Address, amount: u128) -> Result<()>;
+
+    /// Transfer native tokens from the signing account.
+    async fn transfer(&self, destination: Address, amount: U256) -> Result<()>;
+
+    /// Estimate the cost of a transaction consuming `gas` gas at the current gas price.
+    async fn estimate_tx_cost(&self, gas: u128) -> Result<u128>;
+
+    /// The nonces of `account`, which must be one of the signing accounts.
+    async fn account_nonces(&self, _account: Address) -> Result<AccountNonces> {
+        Err(anyhow::anyhow!(
+            "Tracking pending transactions is not supported on this chain"
+        ))
+    }
+
+    /// Cancel the pending transaction of `account` with `nonce` by replacing it with a zero-value
+    /// transfer to itself at a higher fee, and wait for the cancellation to be mined. The nonces
+    /// handed out for the account are then reloaded from the chain. Returns the hash of the
+    /// cancellation.
+    async fn cancel_transaction(&self, _account: Address, _nonce: u64) -> Result<H256> {
+        Err(anyhow::anyhow!(
+            "Replacing pending transactions is not supported on this chain"
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use {
+        crate::chain::{
+            reader::{
+                mock::MockEntropyReader, BlockNumber, EntropyReader, ProviderInfo,
+                RequestCallbackStatus,
+            },
+            writer::{AccountNonces, EntropyWriter, RevealError, RevealReceipt, SimulatedReveal},
+        },
+        anyhow::{anyhow, Result},
+        axum::async_trait,
+        ethers::types::{Address, Bytes, H256, U256},
+        std::{
+            collections::HashMap,
+            sync::{Arc, RwLock},
+            time::Duration,
+        },
+    };
+
+    /// The gas used by the callbacks of the mock contract, unless set with `set_callback_gas`.
+    const DEFAULT_CALLBACK_GAS: u64 = 50_000;
+
+    /// Mock signer for the mock entropy contract. Reveals clear the request from the contract,
+    /// unless the callback needs more gas than the gas limit of the request.
+    pub struct MockEntropyWriter {
+        address: Address,
+        contract: Arc<MockEntropyReader>,
+        /// The (provider, sequence number, provider revelation) of the reveals sent so far.
+        pub reveals: RwLock<Vec<(Address, u64, [u8; 32])>>,
+        /// The gas needed by the callbacks of some sequence numbers.
+        callback_gas: RwLock<HashMap<u64, u64>>,
+        /// The nonces of the signing account. Cancelling a transaction mines it.
+        pub nonces: RwLock<AccountNonces>,
+        /// The nonces of the transactions cancelled so far.
+        pub cancelled: RwLock<Vec<u64>>,
+    }
+
+    impl MockEntropyWriter {
+        pub fn new(address: Address, contract: Arc<MockEntropyReader>) -> Self {
+            Self {
+                address,
+                contract,
+                reveals: RwLock::new(vec![]),
+                callback_gas: RwLock::new(HashMap::new()),
+                nonces: RwLock::new(AccountNonces::default()),
+                cancelled: RwLock::new(vec![]),
+            }
+        }
+
+        pub fn set_callback_gas(&self, sequence_number: u64, gas: u64) -> &Self {
+            self.callback_gas
+                .write()
+                .unwrap()
+                .insert(sequence_number, gas);
+            self
+        }
+
+        fn callback_gas(&self, sequence_number: u64) -> u64 {
+            self.callback_gas
+                .read()
+                .unwrap()
+                .get(&sequence_number)
+                .copied()
+                .unwrap_or(DEFAULT_CALLBACK_GAS)
+        }
+
+        fn receipt(
+            block_number: BlockNumber,
+            sequence_number: u64,
+            callback_gas_used: u64,
+        ) -> RevealReceipt {
+            RevealReceipt {
+                block_number: block_number + 1,
+                transaction_hash: H256::from_low_u64_be(sequence_number),
+                gas_used: Some(U256::from(100_000 + callback_gas_used)),
+                effective_gas_price: None,
+                num_retries: 0,
+                fee_multiplier: 100,
+                duration: Duration::ZERO,
+                callback_failed: false,
+                callback_return_value: Bytes::default(),
+                callback_gas_used: callback_gas_used as u32,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EntropyWriter for MockEntropyWriter {
+        fn address(&self) -> Address {
+            self.address
+        }
+
+        async fn reveal_with_callback(
+            &self,
+            provider: Address,
+            sequence_number: u64,
+            _user_random_number: [u8; 32],
+            provider_revelation: [u8; 32],
+        ) -> Result<RevealReceipt, RevealError> {
+            let request = self
+                .contract
+                .get_request_v2(provider, sequence_number)
+                .await
+                .ok()
+                .flatten()
+                .filter(|r| r.callback_status != RequestCallbackStatus::CallbackFailed)
+                .ok_or_else(|| RevealError {
+                    reason: "Reverted: NoSuchRequest".to_string(),
+                    error: anyhow!("No such request"),
+                })?;
+            self.reveals
+                .write()
+                .unwrap()
+                .push((provider, sequence_number, provider_revelation));
+            let gas_limit = request.gas_limit_10k as u64 * 10_000;
+            let callback_gas = self.callback_gas(sequence_number);
+            if request.gas_limit_10k != 0 && callback_gas > gas_limit {
+                // The callback runs out of gas, which leaves the request on the contract.
+                self.contract.set_callback_status(
+                    provider,
+                    sequence_number,
+                    RequestCallbackStatus::CallbackFailed,
+                );
+                return Ok(RevealReceipt {
+                    callback_failed: true,
+                    ..Self::receipt(request.block_number, sequence_number, gas_limit)
+                });
+            }
+            self.contract.remove(provider, sequence_number);
+            Ok(Self::receipt(
+                request.block_number,
+                sequence_number,
+                callback_gas,
+            ))
+        }
+
+        async fn retry_reveal_with_callback(
+            &self,
+            provider: Address,
+            sequence_number: u64,
+            _user_random_number: [u8; 32],
+            provider_revelation: [u8; 32],
+            gas_limit: u64,
+        ) -> Result<RevealReceipt, RevealError> {
+            let request = self
+                .contract
+                .get_request_v2(provider, sequence_number)
+                .await
+                .ok()
+                .flatten()
+                .filter(|r| r.callback_status == RequestCallbackStatus::CallbackFailed)
+                .ok_or_else(|| RevealError {
+                    reason: "Reverted: InvalidRevealCall".to_string(),
+                    error: anyhow!("The callback of the request did not fail"),
+                })?;
+            let callback_gas = self.callback_gas(sequence_number);
+            if callback_gas > gas_limit {
+                return Err(RevealError {
+                    reason: "Unable to estimate gas usage".to_string(),
+                    error: anyhow!("Out of gas"),
+                });
+            }
+            self.reveals
+                .write()
+                .unwrap()
+                .push((provider, sequence_number, provider_revelation));
+            self.contract.remove(provider, sequence_number);
+            Ok(Self::receipt(
+                request.block_number,
+                sequence_number,
+                callback_gas,
+            ))
+        }
+
+        async fn simulate_reveal_with_callback(
+            &self,
+            provider: Address,
+            sequence_number: u64,
+            _user_random_number: [u8; 32],
+            _provider_revelation: [u8; 32],
+        ) -> Result<SimulatedReveal, RevealError> {
+            let request = self
+                .contract
+                .get_request_v2(provider, sequence_number)
+                .await
+                .ok()
+                .flatten()
+                .ok_or_else(|| RevealError {
+                    reason: "Reverted: NoSuchRequest".to_string(),
+                    error: anyhow!("No such request"),
+                })?;
+            let gas_limit = request.gas_limit_10k as u64 * 10_000;
+            let callback_gas = self.callback_gas(sequence_number);
+            let callback_failed = request.gas_limit_10k != 0 && callback_gas > gas_limit;
+            let callback_gas_used = if callback_failed {
+                gas_limit
+            } else {
+                callback_gas
+            };
+            let gas_estimate = U256::from(100_000 + callback_gas_used);
+            Ok(SimulatedReveal {
+                gas_estimate,
+                estimated_fee: gas_estimate,
+                callback_failed,
+                callback_return_value: Bytes::default(),
+                callback_gas_used: callback_gas_used as u32,
+            })
+        }
+
+        async fn advance_provider_commitment(
+            &self,
+            _provider: Address,
+            _advanced_sequence_number: u64,
+            _provider_revelation: [u8; 32],
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn register_provider(
+            &self,
+            fee: u128,
+            commitment: [u8; 32],
+            commitment_metadata: Bytes,
+            chain_length: u64,
+            _uri: Bytes,
+        ) -> Result<()> {
+            let provider_info = self.contract.get_provider_info(self.address, None).await?;
+            let sequence_number = provider_info.sequence_number;
+            self.contract.set_provider_info(ProviderInfo {
+                fee_in_wei: fee,
+                original_commitment: commitment,
+                original_commitment_sequence_number: sequence_number,
+                current_commitment_sequence_number: sequence_number,
+                commitment_metadata,
+                end_sequence_number: sequence_number + chain_length,
+                sequence_number: sequence_number + 1,
+                ..provider_info
+            });
+            Ok(())
+        }
+
+        async fn set_provider_fee_as_fee_manager(
+            &self,
+            _provider: Address,
+            _fee: u128,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn withdraw_as_fee_manager(&self, _provider: Address, _amount: u128) -> Result<()> {
+            Ok(())
+        }
+
+        async fn transfer(&self, _destination: Address, _amount: U256) -> Result<()> {
+            Ok(())
+        }
+
+        async fn estimate_tx_cost(&self, gas: u128) -> Result<u128> {
+            Ok(gas)
+        }
+
+        async fn account_nonces(&self, account: Address) -> Result<AccountNonces> {
+            if account != self.address {
+                return Err(anyhow!("Unknown account {:?}", account));
+            }
+            Ok(*self.nonces.read().unwrap())
+        }
+
+        async fn cancel_transaction(&self, account: Address, nonce: u64) -> Result<H256> {
+            if account != self.address {
+                return Err(anyhow!("Unknown account {:?}", account));
+            }
+            let mut nonces = self.nonces.write().unwrap();
+            if nonce != nonces.mined {
+                return Err(anyhow!("Nonce too low or too high"));
+            }
+            nonces.mined = nonce + 1;
+            nonces.pending = nonces.pending.max(nonces.mined);
+            self.cancelled.write().unwrap().push(nonce);
+            Ok(H256::from_low_u64_be(nonce))
+        }
+    }
+}
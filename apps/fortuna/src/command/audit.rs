@@ -0,0 +1,562 @@
+use {
+    crate::{
+        api::{BlockchainState, ChainId},
+        chain::{
+            ethereum::PythContract,
+            reader::{BlockNumber, BlockStatus, EntropyReader, RequestCallbackStatus},
+        },
+        command::run::setup_chain_state,
+        config::{AuditOptions, Config},
+        history::{CallbackFailure, History, RequestEntryState, RequestStatus},
+        keeper::keeper_metrics::KeeperMetrics,
+    },
+    anyhow::{anyhow, Result},
+    ethers::types::{Address, TxHash},
+    serde::Serialize,
+    serde_with::serde_as,
+    std::{ops::RangeInclusive, sync::Arc},
+};
+
+/// The number of blocks to fetch the request events of at once.
+const BLOCK_BATCH_SIZE: u64 = 100;
+
+/// The requests of a provider to audit on a chain.
+pub struct AuditRange {
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+    pub sequence_numbers: RangeInclusive<u64>,
+    /// A request is expected to be revealed within this many blocks after the reveal delay.
+    pub max_reveal_lag_blocks: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditReport {
+    pub chain_id: ChainId,
+    pub network_id: u64,
+    pub provider: Address,
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+    /// The number of audited requests.
+    pub requests: usize,
+    pub findings: Vec<AuditFinding>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditFinding {
+    pub sequence_number: u64,
+    pub request_block_number: BlockNumber,
+    pub request_tx_hash: TxHash,
+    #[serde(flatten)]
+    pub issue: AuditIssue,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum AuditIssue {
+    /// The request was not revealed within `max_reveal_lag_blocks` after the reveal delay.
+    MissingReveal,
+    /// The provider contribution revealed on chain or recorded in the history is not the one of
+    /// the provider's hash chain.
+    MismatchedRandomNumber {
+        /// Where the wrong contribution was found, `on-chain` or `history`.
+        source: &'static str,
+        #[serde_as(as = "serde_with::hex::Hex")]
+        expected: [u8; 32],
+        #[serde_as(as = "serde_with::hex::Hex")]
+        actual: [u8; 32],
+    },
+    /// The request was revealed before `reveal_delay_blocks`, or more than `max_reveal_lag_blocks`
+    /// after.
+    RevealOutsideDelay {
+        reveal_block_number: BlockNumber,
+        reveal_delay_blocks: BlockNumber,
+    },
+    /// The callback of the request failed, so the request is still open on chain.
+    CallbackFailure {
+        callback_failure: Option<CallbackFailure>,
+    },
+    /// The history of the request doesn't match the chain.
+    HistoryMismatch { reason: String },
+    /// The hash chain of the request can't be computed from the config.
+    HashChainUnavailable { reason: String },
+}
+
+pub async fn audit(opts: &AuditOptions) -> Result<()> {
+    let config = Config::load(&opts.config.config)?;
+    let history = History::new().await?;
+    let chain_ids = match &opts.chain_id {
+        Some(chain_id) => vec![chain_id.clone()],
+        None => config.chains.keys().cloned().collect(),
+    };
+
+    let mut reports = vec![];
+    for chain_id in chain_ids {
+        tracing::info!("Auditing chain: {}", chain_id);
+        reports.push(audit_chain(&config, opts, &chain_id, &history).await?);
+    }
+    println!("{}", serde_json::to_string_pretty(&reports)?);
+
+    let findings: usize = reports.iter().map(|report| report.findings.len()).sum();
+    if opts.fail_on_findings && findings > 0 {
+        return Err(anyhow!("The audit found {} issues", findings));
+    }
+    Ok(())
+}
+
+async fn audit_chain(
+    config: &Config,
+    opts: &AuditOptions,
+    chain_id: &ChainId,
+    history: &History,
+) -> Result<AuditReport> {
+    let provider_config = config.get_provider_config(opts.provider)?;
+    let chain_config = config.provider_chain_config(provider_config, chain_id)?;
+    let secret = provider_config.secret.load()?.ok_or(anyhow!(
+        "Please specify a provider secret in the config file."
+    ))?;
+    let contract = Arc::new(PythContract::from_config(&chain_config)?);
+    let network_id = contract.get_network_id().await?.as_u64();
+    let chain_state = setup_chain_state(
+        &provider_config.address,
+        &secret,
+        provider_config.chain_sample_interval,
+        &provider_config.hash_chain_storage,
+        chain_id,
+        contract.clone(),
+        chain_config.contract_addr.as_bytes(),
+        network_id,
+        chain_config.commitments.clone(),
+        chain_config.reveal_delay_blocks,
+        chain_config.confirmed_block_status,
+        Arc::new(KeeperMetrics::default()),
+        history,
+    )
+    .await?;
+
+    let to_block = match opts.to_block {
+        Some(to_block) => to_block,
+        None => contract.get_block_number(BlockStatus::Latest).await?,
+    };
+    let range = AuditRange {
+        from_block: opts
+            .from_block
+            .unwrap_or(to_block.saturating_sub(opts.num_blocks)),
+        to_block,
+        sequence_numbers: opts.from_sequence.unwrap_or(0)..=opts.to_sequence.unwrap_or(u64::MAX),
+        max_reveal_lag_blocks: opts.max_reveal_lag_blocks,
+    };
+    audit_requests(&chain_state, history, &range).await
+}
+
+/// Cross-check the requests to the provider of `chain_state` in `range` against their reveals on
+/// chain, their history, and the provider's hash chain.
+pub async fn audit_requests(
+    chain_state: &BlockchainState,
+    history: &History,
+    range: &AuditRange,
+) -> Result<AuditReport> {
+    let latest_block = chain_state
+        .contract
+        .get_block_number(BlockStatus::Latest)
+        .await?;
+    let mut report = AuditReport {
+        chain_id: chain_state.id.clone(),
+        network_id: chain_state.network_id,
+        provider: chain_state.provider_address,
+        from_block: range.from_block,
+        to_block: range.to_block,
+        requests: 0,
+        findings: vec![],
+    };
+
+    let mut from_block = range.from_block;
+    while from_block <= range.to_block {
+        let to_block = (from_block + BLOCK_BATCH_SIZE - 1).min(range.to_block);
+        let events = chain_state
+            .contract
+            .get_request_with_callback_events(from_block, to_block, chain_state.provider_address)
+            .await?;
+        for event in events {
+            if !range.sequence_numbers.contains(&event.sequence_number) {
+                continue;
+            }
+            report.requests += 1;
+            let request_block_number = event.log_meta.block_number.as_u64();
+            let issues = audit_request(
+                chain_state,
+                history,
+                event.sequence_number,
+                request_block_number,
+                latest_block,
+                range.max_reveal_lag_blocks,
+            )
+            .await?;
+            report
+                .findings
+                .extend(issues.into_iter().map(|issue| AuditFinding {
+                    sequence_number: event.sequence_number,
+                    request_block_number,
+                    request_tx_hash: event.log_meta.transaction_hash,
+                    issue,
+                }));
+        }
+        from_block = to_block + 1;
+    }
+    Ok(report)
+}
+
+async fn audit_request(
+    chain_state: &BlockchainState,
+    history: &History,
+    sequence_number: u64,
+    request_block_number: BlockNumber,
+    latest_block: BlockNumber,
+    max_reveal_lag_blocks: u64,
+) -> Result<Vec<AuditIssue>> {
+    let mut issues = vec![];
+    let provider = chain_state.provider_address;
+    let expected = match chain_state.state.reveal(sequence_number) {
+        Ok(expected) => Some(expected),
+        Err(e) => {
+            issues.push(AuditIssue::HashChainUnavailable {
+                reason: e.to_string(),
+            });
+            None
+        }
+    };
+    let mut check_random_number = |source: &'static str, actual: [u8; 32]| {
+        if let Some(expected) = expected.filter(|&expected| expected != actual) {
+            issues.push(AuditIssue::MismatchedRandomNumber {
+                source,
+                expected,
+                actual,
+            });
+        }
+    };
+
+    let revealed = chain_state
+        .contract
+        .get_revealed_event(provider, sequence_number, request_block_number)
+        .await?;
+    let status = history
+        .query()
+        .network_id(chain_state.network_id)
+        .provider(provider)
+        .search(sequence_number.to_string())
+        .map_err(|e| anyhow!("Invalid history query: {:?}", e))?
+        .execute()
+        .await?
+        .into_iter()
+        .next();
+
+    if let Some(revealed) = &revealed {
+        check_random_number("on-chain", revealed.provider_revelation);
+    }
+    if let Some(RequestStatus {
+        state:
+            RequestEntryState::Completed {
+                provider_random_number,
+                ..
+            },
+        ..
+    }) = &status
+    {
+        check_random_number("history", *provider_random_number);
+    }
+
+    let reveal_delay_blocks = chain_state.reveal_delay_blocks;
+    let deadline = request_block_number + reveal_delay_blocks + max_reveal_lag_blocks;
+    match &revealed {
+        Some(revealed) => {
+            let reveal_block_number = revealed.log_meta.block_number.as_u64();
+            if reveal_block_number < request_block_number + reveal_delay_blocks
+                || reveal_block_number > deadline
+            {
+                issues.push(AuditIssue::RevealOutsideDelay {
+                    reveal_block_number,
+                    reveal_delay_blocks,
+                });
+            }
+        }
+        None => {
+            let request = chain_state
+                .contract
+                .get_request_v2(provider, sequence_number)
+                .await?;
+            if request.is_some_and(|r| r.callback_status == RequestCallbackStatus::CallbackFailed) {
+                let callback_failure = match &status {
+                    Some(RequestStatus {
+                        state:
+                            RequestEntryState::Completed {
+                                callback_failure, ..
+                            },
+                        ..
+                    }) => callback_failure.clone(),
+                    _ => None,
+                };
+                issues.push(AuditIssue::CallbackFailure { callback_failure });
+            } else if latest_block > deadline {
+                issues.push(AuditIssue::MissingReveal);
+            }
+        }
+    }
+
+    let history_mismatch = match (&status, &revealed) {
+        (None, _) => Some("The request is not in the history".to_string()),
+        (
+            Some(RequestStatus {
+                state:
+                    RequestEntryState::Completed {
+                        reveal_tx_hash,
+                        callback_failed,
+                        ..
+                    },
+                ..
+            }),
+            revealed,
+        ) => match revealed {
+            Some(revealed) if revealed.log_meta.transaction_hash != *reveal_tx_hash => {
+                Some(format!(
+                    "The reveal transaction is {:?} in the history but {:?} on chain",
+                    reveal_tx_hash, revealed.log_meta.transaction_hash
+                ))
+            }
+            None if !callback_failed => {
+                Some("The request is completed in the history but not revealed on chain".into())
+            }
+            _ => None,
+        },
+        (Some(status), Some(_)) => Some(format!(
+            "The request is revealed on chain but {} in the history",
+            match status.state {
+                RequestEntryState::Pending => "pending",
+                RequestEntryState::Failed { .. } => "failed",
+                RequestEntryState::Simulated { .. } => "simulated",
+                RequestEntryState::Completed { .. } => "completed",
+            }
+        )),
+        (Some(_), None) => None,
+    };
+    if let Some(reason) = history_mismatch {
+        issues.push(AuditIssue::HistoryMismatch { reason });
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            chain::reader::{mock::MockEntropyReader, RevealedV2Event},
+            history::CallbackFailureKind,
+            state::{HashChainState, MonitoredHashChainState, PebbleHashChain},
+        },
+        ethers::{
+            prelude::LogMeta,
+            types::{H256, U256, U64},
+        },
+        std::time::Duration,
+    };
+
+    fn revealed_event(
+        sequence_number: u64,
+        block_number: BlockNumber,
+        provider_revelation: [u8; 32],
+    ) -> RevealedV2Event {
+        RevealedV2Event {
+            provider_revelation,
+            random_number: [0u8; 32],
+            callback_failed: false,
+            callback_return_value: Default::default(),
+            callback_gas_used: 0,
+            gas_used: U256::zero(),
+            log_meta: LogMeta {
+                address: Address::zero(),
+                block_number: U64::from(block_number),
+                block_hash: H256::zero(),
+                transaction_hash: H256::from_low_u64_be(1000 + sequence_number),
+                transaction_index: U64::zero(),
+                log_index: U256::zero(),
+            },
+        }
+    }
+
+    /// Record a request in the history, which the keeper does as pending before its other states.
+    fn record_request(
+        history: &History,
+        provider: Address,
+        sequence: u64,
+        block_number: BlockNumber,
+        state: RequestEntryState,
+    ) {
+        let mut status = RequestStatus {
+            chain_id: "ethereum".into(),
+            network_id: 121,
+            provider,
+            sequence,
+            created_at: chrono::Utc::now(),
+            last_updated_at: chrono::Utc::now(),
+            request_block_number: block_number,
+            request_tx_hash: H256::from_low_u64_be(sequence),
+            gas_limit: 100_000,
+            user_random_number: [1u8; 32],
+            sender: Address::zero(),
+            state: RequestEntryState::Pending,
+        };
+        history.add(&status);
+        if state != RequestEntryState::Pending {
+            status.state = state;
+            history.add(&status);
+        }
+    }
+
+    fn completed(sequence: u64, provider_random_number: [u8; 32]) -> RequestEntryState {
+        RequestEntryState::Completed {
+            reveal_block_number: 0,
+            reveal_tx_hash: H256::from_low_u64_be(1000 + sequence),
+            provider_random_number,
+            gas_used: U256::zero(),
+            combined_random_number: [0u8; 32],
+            callback_failed: false,
+            callback_return_value: Default::default(),
+            callback_gas_used: 0,
+            callback_failure: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_requests() {
+        let provider = Address::from_low_u64_be(1);
+        let hash_chain = PebbleHashChain::new([0u8; 32], 100, 1);
+        let reveal = |sequence: u64| hash_chain.reveal_ith(sequence as usize).unwrap();
+        let reader = Arc::new(MockEntropyReader::with_requests(400, &[]));
+        let history = History::new_in_memory().await.unwrap();
+        let chain_state = BlockchainState {
+            id: "ethereum".into(),
+            network_id: 121,
+            state: Arc::new(MonitoredHashChainState::new(
+                Arc::new(HashChainState::from_chain_at_offset(0, hash_chain.clone())),
+                Default::default(),
+                "ethereum".into(),
+                provider,
+            )),
+            contract: reader.clone(),
+            provider_address: provider,
+            reveal_delay_blocks: 2,
+            confirmed_block_status: BlockStatus::Latest,
+        };
+
+        // Revealed as expected.
+        reader.insert_with_callback(provider, 1, 100, [1u8; 32]);
+        reader.insert_revealed_event(provider, 1, revealed_event(1, 103, reveal(1)));
+        record_request(&history, provider, 1, 100, completed(1, reveal(1)));
+        // Revealed with another contribution than the hash chain's.
+        reader.insert_with_callback(provider, 2, 100, [1u8; 32]);
+        reader.insert_revealed_event(provider, 2, revealed_event(2, 103, [9u8; 32]));
+        record_request(&history, provider, 2, 100, completed(2, [9u8; 32]));
+        // Never revealed.
+        reader.insert_with_callback(provider, 3, 150, [1u8; 32]);
+        record_request(&history, provider, 3, 150, RequestEntryState::Pending);
+        // Revealed late, without being recorded in the history.
+        reader.insert_with_callback(provider, 4, 150, [1u8; 32]);
+        reader.insert_revealed_event(provider, 4, revealed_event(4, 300, reveal(4)));
+        // The callback failed.
+        reader.insert_with_callback(provider, 5, 200, [1u8; 32]);
+        reader.set_callback_status(provider, 5, RequestCallbackStatus::CallbackFailed);
+        let mut state = completed(5, reveal(5));
+        if let RequestEntryState::Completed {
+            callback_failed,
+            callback_failure,
+            ..
+        } = &mut state
+        {
+            *callback_failed = true;
+            *callback_failure = Some(CallbackFailure {
+                kind: CallbackFailureKind::OutOfGas,
+                reason: None,
+            });
+        }
+        record_request(&history, provider, 5, 200, state);
+        // Pending, but not overdue yet.
+        reader.insert_with_callback(provider, 6, 390, [1u8; 32]);
+        record_request(&history, provider, 6, 390, RequestEntryState::Pending);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let range = AuditRange {
+            from_block: 0,
+            to_block: 400,
+            sequence_numbers: 0..=u64::MAX,
+            max_reveal_lag_blocks: 100,
+        };
+        let report = audit_requests(&chain_state, &history, &range)
+            .await
+            .unwrap();
+        assert_eq!(report.requests, 6);
+        let issues: Vec<(u64, AuditIssue)> = report
+            .findings
+            .into_iter()
+            .map(|finding| (finding.sequence_number, finding.issue))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    2,
+                    AuditIssue::MismatchedRandomNumber {
+                        source: "on-chain",
+                        expected: reveal(2),
+                        actual: [9u8; 32],
+                    }
+                ),
+                (
+                    2,
+                    AuditIssue::MismatchedRandomNumber {
+                        source: "history",
+                        expected: reveal(2),
+                        actual: [9u8; 32],
+                    }
+                ),
+                (3, AuditIssue::MissingReveal),
+                (
+                    4,
+                    AuditIssue::RevealOutsideDelay {
+                        reveal_block_number: 300,
+                        reveal_delay_blocks: 2,
+                    }
+                ),
+                (
+                    4,
+                    AuditIssue::HistoryMismatch {
+                        reason: "The request is not in the history".into(),
+                    }
+                ),
+                (
+                    5,
+                    AuditIssue::CallbackFailure {
+                        callback_failure: Some(CallbackFailure {
+                            kind: CallbackFailureKind::OutOfGas,
+                            reason: None,
+                        }),
+                    }
+                ),
+            ]
+        );
+
+        // Only the requests in the sequence range are audited.
+        let range = AuditRange {
+            sequence_numbers: 3..=3,
+            ..range
+        };
+        let report = audit_requests(&chain_state, &history, &range)
+            .await
+            .unwrap();
+        assert_eq!(report.requests, 1);
+        assert_eq!(report.findings.len(), 1);
+        let json = serde_json::to_value(&report.findings[0]).unwrap();
+        assert_eq!(json["kind"], "missing-reveal");
+        assert_eq!(json["sequence_number"], 3);
+    }
+}
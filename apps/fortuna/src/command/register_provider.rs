@@ -1,6 +1,6 @@
 use {
     crate::{
-        api::{get_register_uri, ChainId},
+        api::ChainId,
         chain::ethereum::SignablePythContract,
         config::{Config, EthereumConfig, ProviderConfig, RegisterProviderOptions},
         state::PebbleHashChain,
@@ -24,9 +24,11 @@ pub struct CommitmentMetadata {
 /// hash chain from the configured secret & a newly generated random value.
 pub async fn register_provider(opts: &RegisterProviderOptions) -> Result<()> {
     let config = Config::load(&opts.config.config)?;
-    let chain_config = config.get_chain_config(&opts.chain_id)?;
+    let provider_config = config.get_provider_config(opts.provider)?;
+    let chain_config = config.provider_chain_config(provider_config, &opts.chain_id)?;
+    let uri = config.register_uri(provider_config, &opts.chain_id)?;
 
-    register_provider_from_config(&config.provider, &opts.chain_id, &chain_config).await?;
+    register_provider_from_config(provider_config, &opts.chain_id, &chain_config, &uri).await?;
 
     Ok(())
 }
@@ -35,6 +37,7 @@ pub async fn register_provider_from_config(
     provider_config: &ProviderConfig,
     chain_id: &ChainId,
     chain_config: &EthereumConfig,
+    uri: &str,
 ) -> Result<()> {
     let private_key_string = provider_config.private_key.load()?.ok_or(anyhow!(
         "Please specify a provider private key in the config"
@@ -56,7 +59,7 @@ pub async fn register_provider_from_config(
         &secret,
         chain_id,
         &private_key_string.parse::<LocalWallet>()?.address(),
-        &chain_config.contract_addr,
+        chain_config.contract_addr.as_bytes(),
         &random,
         commitment_length,
         provider_config.chain_sample_interval,
@@ -73,7 +76,6 @@ pub async fn register_provider_from_config(
         seed: random,
         chain_length: commitment_length,
     };
-    let uri = get_register_uri(&provider_config.uri, chain_id)?;
     let call = contract.register(
         fee_in_wei,
         commitment,
@@ -81,7 +83,7 @@ pub async fn register_provider_from_config(
         commitment_length,
         // Use Bytes to serialize the uri. Most users will be using JS/TS to deserialize this uri.
         // Bincode is a different encoding mechanisms, and I didn't find any JS/TS library to parse bincode.
-        Bytes::from(uri.as_str()).into(),
+        Bytes::from(uri).into(),
     );
     let mut gas_estimate = call.estimate_gas().await?;
     let gas_multiplier = U256::from(2); //TODO: smarter gas estimation
@@ -1,19 +1,30 @@
 use {
     crate::{
-        api::{self, ApiBlockChainState, BlockchainState, ChainId},
-        chain::ethereum::InstrumentedPythContract,
+        api::{self, ApiBlockChainState, BlockchainState, ChainId, ProviderChains},
+        chain::{
+            cosmwasm::{decode_account_address, CosmWasmEntropy},
+            ethereum::InstrumentedPythContract,
+            reader::{BlockNumber, BlockStatus, EntropyReader},
+            writer::EntropyWriter,
+        },
         command::register_provider::CommitmentMetadata,
-        config::{Commitment, Config, EthereumConfig, KeeperConfig, ProviderConfig, RunOptions},
+        config::{
+            Commitment, Config, CosmWasmConfig, EthereumConfig, HashChainStorage, KeeperConfig,
+            ProviderConfig, RunOptions,
+        },
         eth_utils::traced_client::RpcMetrics,
-        history::History,
-        keeper::{self, keeper_metrics::KeeperMetrics},
-        state::{HashChainState, MonitoredHashChainState, PebbleHashChain},
+        history::{self, History},
+        keeper::{
+            self, block::BlockWatcher, commitment::CommitmentRotation,
+            keeper_metrics::KeeperMetrics, stuck_tx::StuckTransactionMonitor,
+        },
+        state::{load_hash_chain, HashChainState, MonitoredHashChainState},
     },
     anyhow::{anyhow, Error, Result},
     axum::Router,
     ethers::types::Address,
     prometheus_client::registry::Registry,
-    std::{collections::HashMap, net::SocketAddr, sync::Arc},
+    std::{collections::HashMap, future::Future, net::SocketAddr, sync::Arc, time::Duration},
     tokio::{
         spawn,
         sync::{watch, RwLock},
@@ -25,7 +36,7 @@ use {
 
 pub async fn run_api(
     socket_addr: SocketAddr,
-    chains: Arc<RwLock<HashMap<String, ApiBlockChainState>>>,
+    providers: HashMap<Address, ProviderChains>,
     metrics_registry: Arc<RwLock<Registry>>,
     history: Arc<History>,
     config: &Config,
@@ -36,7 +47,11 @@ pub async fn run_api(
     paths(
     crate::api::revelation,
     crate::api::chain_ids,
+    crate::api::provider_ids,
+    crate::api::provider_chain_ids,
+    crate::api::provider_revelation,
     crate::api::explorer,
+    crate::api::stats,
     ),
     components(
     schemas(
@@ -47,6 +62,9 @@ pub async fn run_api(
     crate::api::BinaryEncoding,
     crate::api::StateTag,
     crate::api::ExplorerResponse,
+    crate::api::LogFormat,
+    crate::api::StatsResponse,
+    crate::history::stats::DailyStats,
     )
     ),
     tags(
@@ -59,7 +77,13 @@ pub async fn run_api(
     )]
     struct ApiDoc;
 
-    let api_state = api::ApiState::new(chains, metrics_registry, history, config).await;
+    let chains = providers
+        .get(&config.provider.address)
+        .cloned()
+        .unwrap_or_default();
+    let api_state = api::ApiState::new(chains, metrics_registry, history, config)
+        .await
+        .with_providers(providers);
 
     // Initialize Axum Router. Note the type here is a `Router<State>` due to the use of the
     // `with_state` method which replaces `Body` with `State` in the type signature.
@@ -95,6 +119,12 @@ pub async fn run(opts: &RunOptions) -> Result<()> {
     let secret = config.provider.secret.load()?.ok_or(anyhow!(
         "Please specify a provider secret in the config file."
     ))?;
+    for provider_config in &config.providers {
+        provider_config.secret.load()?.ok_or(anyhow!(
+            "Please specify a secret for provider {:?} in the config file.",
+            provider_config.address
+        ))?;
+    }
     let (tx_exit, rx_exit) = watch::channel(false);
     let metrics_registry = Arc::new(RwLock::new(Registry::default()));
     let rpc_metrics = Arc::new(RpcMetrics::new(metrics_registry.clone()).await);
@@ -107,54 +137,77 @@ pub async fn run(opts: &RunOptions) -> Result<()> {
         tracing::info!("Not starting keeper service: no keeper private key specified. Please add one to the config if you would like to run the keeper service.")
     }
 
-    let chains: Arc<RwLock<HashMap<ChainId, ApiBlockChainState>>> = Arc::new(RwLock::new(
-        config
-            .chains
-            .keys()
-            .map(|chain_id| (chain_id.clone(), ApiBlockChainState::Uninitialized))
-            .collect(),
-    ));
+    // The other providers are only served on the EVM chains.
+    let providers: HashMap<Address, ProviderChains> = config
+        .all_providers()
+        .map(|provider_config| {
+            let cosmwasm_chain_ids = config
+                .cosmwasm_chains
+                .keys()
+                .filter(|_| config.is_main_provider(provider_config));
+            let chains = config
+                .chains
+                .keys()
+                .chain(cosmwasm_chain_ids)
+                .map(|chain_id| (chain_id.clone(), ApiBlockChainState::Uninitialized))
+                .collect();
+            (provider_config.address, Arc::new(RwLock::new(chains)))
+        })
+        .collect();
+    let chains = providers[&config.provider.address].clone();
     let history = Arc::new(History::new().await?);
-    for (chain_id, chain_config) in config.chains.clone() {
-        keeper_metrics.add_chain(chain_id.clone(), config.provider.address);
+    history.register_metrics(metrics_registry.clone()).await;
+    spawn(history::stats::run_maintenance(
+        history.clone(),
+        config.history.clone(),
+    ));
+    let keeper_config = keeper_private_key_option.map(|_| KeeperConfig {
+        dry_run: config.keeper.dry_run || opts.dry_run,
+        ..config.keeper.clone()
+    });
+    for chain_id in config.chains.keys() {
+        for provider_config in config.all_providers() {
+            keeper_metrics.add_chain(chain_id.clone(), provider_config.address);
+        }
+        let config = config.clone();
+        let chain_id = chain_id.clone();
         let keeper_metrics = keeper_metrics.clone();
-        let keeper_private_key_option = keeper_private_key_option.clone();
-        let chains = chains.clone();
-        let secret_copy = secret.clone();
+        let keeper_config = keeper_config.clone();
+        let providers = providers.clone();
         let rpc_metrics = rpc_metrics.clone();
+        let history = history.clone();
+        spawn_chain_setup(chain_id.clone(), move || {
+            setup_chain_and_run_keepers(
+                config.clone(),
+                chain_id.clone(),
+                keeper_metrics.clone(),
+                keeper_config.clone(),
+                providers.clone(),
+                history.clone(),
+                rpc_metrics.clone(),
+            )
+        });
+    }
+    for (chain_id, chain_config) in config.cosmwasm_chains.clone() {
+        let provider_address = decode_account_address(&chain_config.provider_addr)?;
+        keeper_metrics.add_chain(chain_id.clone(), provider_address);
+        let keeper_metrics = keeper_metrics.clone();
+        let keeper_config = keeper_config.clone();
+        let chains = chains.clone();
+        let secret = secret.clone();
         let provider_config = config.provider.clone();
         let history = history.clone();
-        let keeper_config_base = config.keeper.clone();
-        spawn(async move {
-            loop {
-                let keeper_config = if keeper_private_key_option.is_some() {
-                    Some(keeper_config_base.clone())
-                } else {
-                    None
-                };
-                let setup_result = setup_chain_and_run_keeper(
-                    provider_config.clone(),
-                    &chain_id,
-                    chain_config.clone(),
-                    keeper_metrics.clone(),
-                    keeper_config,
-                    chains.clone(),
-                    &secret_copy,
-                    history.clone(),
-                    rpc_metrics.clone(),
-                )
-                .await;
-                match setup_result {
-                    Ok(_) => {
-                        tracing::info!("Chain {} initialized successfully", chain_id);
-                        break;
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to initialize chain {}: {}", chain_id, e);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
-                    }
-                }
-            }
+        spawn_chain_setup(chain_id.clone(), move || {
+            setup_cosmwasm_chain_and_run_keeper(
+                provider_config.clone(),
+                chain_id.clone(),
+                chain_config.clone(),
+                keeper_metrics.clone(),
+                keeper_config.clone(),
+                chains.clone(),
+                secret.clone(),
+                history.clone(),
+            )
         });
     }
 
@@ -172,7 +225,7 @@ pub async fn run(opts: &RunOptions) -> Result<()> {
 
     run_api(
         opts.addr,
-        chains.clone(),
+        providers,
         metrics_registry.clone(),
         history,
         &config,
@@ -182,35 +235,190 @@ pub async fn run(opts: &RunOptions) -> Result<()> {
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn setup_chain_and_run_keeper(
-    provider_config: ProviderConfig,
-    chain_id: &ChainId,
+/// Set up the chain in a background task, retrying until it succeeds.
+fn spawn_chain_setup<F, Fut>(chain_id: ChainId, setup: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    spawn(async move {
+        loop {
+            match setup().await {
+                Ok(_) => {
+                    tracing::info!("Chain {} initialized successfully", chain_id);
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to initialize chain {}: {}", chain_id, e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
+                }
+            }
+        }
+    });
+}
+
+/// Connect to an EVM chain, then set up each provider on the chain in a background task. The
+/// providers share the connection to the chain and the keeper wallet.
+async fn setup_chain_and_run_keepers(
+    config: Config,
+    chain_id: ChainId,
+    keeper_metrics: Arc<KeeperMetrics>,
+    keeper_config: Option<KeeperConfig>,
+    providers: HashMap<Address, ProviderChains>,
+    history: Arc<History>,
+    rpc_metrics: Arc<RpcMetrics>,
+) -> Result<()> {
+    let chain_config = config.get_chain_config(&chain_id)?;
+    let contract = Arc::new(InstrumentedPythContract::from_config(
+        &chain_config,
+        chain_id.clone(),
+        rpc_metrics.clone(),
+    )?);
+    let network_id: u64 = contract
+        .get_network_id()
+        .await
+        .map_err(|e| anyhow!("Failed to get network id: {}. Chain id: {}", &chain_id, e))?
+        .as_u64();
+    let keeper = match &keeper_config {
+        Some(keeper_config) => Some(keeper::keeper_writer(
+            keeper_config,
+            &chain_config,
+            chain_id.clone(),
+            network_id,
+            keeper_metrics.clone(),
+            rpc_metrics.clone(),
+        )?),
+        None => None,
+    };
+
+    // The keeper wallets are shared by the providers, so their stuck transactions are monitored
+    // once per chain. No transaction is sent in dry-run mode.
+    if let (Some(keeper), Some(keeper_config)) = (&keeper, &keeper_config) {
+        if !keeper_config.dry_run {
+            spawn(
+                StuckTransactionMonitor::new(
+                    chain_id.clone(),
+                    network_id,
+                    keeper.clone(),
+                    Duration::from_secs(chain_config.stuck_tx_threshold_seconds),
+                    keeper_metrics.clone(),
+                    history.clone(),
+                )
+                .run(),
+            );
+        }
+    }
+
+    // The blocks of the chain are watched once for all the providers.
+    let block_watcher = Arc::new(BlockWatcher::new(chain_config.geth_rpc_wss.clone()));
+    for provider_config in config.all_providers() {
+        let chain = ProviderChain {
+            chain_id: chain_id.clone(),
+            chain_config: config.provider_chain_config(provider_config, &chain_id)?,
+            contract: contract.clone() as Arc<dyn EntropyReader>,
+            network_id,
+            keeper: keeper.clone(),
+            block_watcher: block_watcher.clone(),
+            fee_manager_private_key: config.fee_manager_private_key(provider_config)?,
+            register_uri: config.register_uri(provider_config, &chain_id)?,
+        };
+        let provider_config = provider_config.clone();
+        let keeper_metrics = keeper_metrics.clone();
+        let keeper_config = keeper_config.clone();
+        let chains = providers[&provider_config.address].clone();
+        let history = history.clone();
+        let rpc_metrics = rpc_metrics.clone();
+        spawn_chain_setup(
+            format!("{} for provider {:?}", chain_id, provider_config.address),
+            move || {
+                setup_provider_and_run_keeper(
+                    provider_config.clone(),
+                    chain.clone(),
+                    keeper_metrics.clone(),
+                    keeper_config.clone(),
+                    chains.clone(),
+                    history.clone(),
+                    rpc_metrics.clone(),
+                )
+            },
+        );
+    }
+    Ok(())
+}
+
+/// An EVM chain as seen by one of the providers served on it.
+#[derive(Clone)]
+struct ProviderChain {
+    chain_id: ChainId,
+    /// The configuration of the chain, with the overrides of the provider applied.
     chain_config: EthereumConfig,
+    contract: Arc<dyn EntropyReader>,
+    network_id: u64,
+    /// Sends the transactions of the keeper, if the keeper is enabled.
+    keeper: Option<Arc<dyn EntropyWriter>>,
+    block_watcher: Arc<BlockWatcher>,
+    fee_manager_private_key: Option<String>,
+    /// The URI the provider registers its hash chains with on this chain.
+    register_uri: String,
+}
+
+async fn setup_provider_and_run_keeper(
+    provider_config: ProviderConfig,
+    chain: ProviderChain,
     keeper_metrics: Arc<KeeperMetrics>,
     keeper_config: Option<KeeperConfig>,
-    chains: Arc<RwLock<HashMap<ChainId, ApiBlockChainState>>>,
-    secret_copy: &str,
+    chains: ProviderChains,
     history: Arc<History>,
     rpc_metrics: Arc<RpcMetrics>,
 ) -> Result<()> {
+    let ProviderChain {
+        chain_id,
+        chain_config,
+        contract,
+        network_id,
+        keeper,
+        block_watcher,
+        fee_manager_private_key,
+        register_uri,
+    } = chain;
+    let secret = provider_config.secret.load()?.ok_or(anyhow!(
+        "Please specify a provider secret in the config file."
+    ))?;
     let state = setup_chain_state(
         &provider_config.address,
-        secret_copy,
+        &secret,
         provider_config.chain_sample_interval,
-        chain_id,
-        &chain_config,
-        rpc_metrics.clone(),
+        &provider_config.hash_chain_storage,
+        &chain_id,
+        contract,
+        chain_config.contract_addr.as_bytes(),
+        network_id,
+        chain_config.commitments.clone(),
+        chain_config.reveal_delay_blocks,
+        chain_config.confirmed_block_status,
         keeper_metrics.clone(),
+        &history,
     )
     .await?;
     chains.write().await.insert(
         chain_id.clone(),
         ApiBlockChainState::Initialized(state.clone()),
     );
-    if let Some(keeper_config) = keeper_config {
+    if let (Some(keeper_config), Some(keeper)) = (keeper_config, keeper) {
+        let commitment_rotation = CommitmentRotation::from_config(
+            &provider_config,
+            &chain_config,
+            &chain_id,
+            network_id,
+            register_uri,
+            rpc_metrics.clone(),
+        )?;
         keeper::run_keeper_threads(
             keeper_config,
+            keeper,
+            block_watcher,
+            fee_manager_private_key,
+            commitment_rotation,
             chain_config,
             state,
             keeper_metrics.clone(),
@@ -222,34 +430,78 @@ async fn setup_chain_and_run_keeper(
     Ok(())
 }
 
-async fn setup_chain_state(
+#[allow(clippy::too_many_arguments)]
+async fn setup_cosmwasm_chain_and_run_keeper(
+    provider_config: ProviderConfig,
+    chain_id: ChainId,
+    chain_config: CosmWasmConfig,
+    keeper_metrics: Arc<KeeperMetrics>,
+    keeper_config: Option<KeeperConfig>,
+    chains: ProviderChains,
+    secret: String,
+    history: Arc<History>,
+) -> Result<()> {
+    let contract = Arc::new(CosmWasmEntropy::from_config(&chain_config, None)?);
+    let state = setup_chain_state(
+        &decode_account_address(&chain_config.provider_addr)?,
+        &secret,
+        provider_config.chain_sample_interval,
+        &provider_config.hash_chain_storage,
+        &chain_id,
+        contract.clone(),
+        &contract.contract_address_bytes()?,
+        chain_config.network_id,
+        chain_config.commitments.clone(),
+        chain_config.reveal_delay_blocks,
+        BlockStatus::Latest,
+        keeper_metrics.clone(),
+        &history,
+    )
+    .await?;
+    chains.write().await.insert(
+        chain_id.clone(),
+        ApiBlockChainState::Initialized(state.clone()),
+    );
+    if let Some(keeper_config) = keeper_config {
+        keeper::run_cosmwasm_keeper_threads(
+            keeper_config,
+            chain_config,
+            state,
+            keeper_metrics.clone(),
+            history,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Regenerate the provider's hash chains for a chain and check them against the on-chain
+/// commitment. The hash chain recorded in `history` before the on-chain one is served too, so
+/// that the requests made before a commitment rotation can still be revealed after a restart.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn setup_chain_state(
     provider: &Address,
     secret: &str,
     chain_sample_interval: u64,
+    hash_chain_storage: &HashChainStorage,
     chain_id: &ChainId,
-    chain_config: &EthereumConfig,
-    rpc_metrics: Arc<RpcMetrics>,
+    contract: Arc<dyn EntropyReader>,
+    contract_address: &[u8],
+    network_id: u64,
+    commitments: Option<Vec<Commitment>>,
+    reveal_delay_blocks: BlockNumber,
+    confirmed_block_status: BlockStatus,
     keeper_metrics: Arc<KeeperMetrics>,
+    history: &History,
 ) -> Result<BlockchainState> {
-    let contract = Arc::new(InstrumentedPythContract::from_config(
-        chain_config,
-        chain_id.clone(),
-        rpc_metrics,
-    )?);
-    let network_id: u64 = contract
-        .get_network_id()
-        .await
-        .map_err(|e| anyhow!("Failed to get network id: {}. Chain id: {}", &chain_id, e))?
-        .as_u64();
-    let mut provider_commitments = chain_config.commitments.clone().unwrap_or_default();
+    let mut provider_commitments = commitments.unwrap_or_default();
     provider_commitments.sort_by(|c1, c2| {
         c1.original_commitment_sequence_number
             .cmp(&c2.original_commitment_sequence_number)
     });
 
     let provider_info = contract
-        .get_provider_info_v2(*provider)
-        .call()
+        .get_provider_info(*provider, None)
         .await
         .map_err(|e| anyhow!("Failed to get provider info: {}", e))?;
     let latest_metadata = bincode::deserialize::<CommitmentMetadata>(
@@ -273,6 +525,32 @@ async fn setup_chain_state(
         return Err(anyhow!("The current hash chain for chain id {} has configured commitments for sequence numbers greater than the current on-chain sequence number. Are the commitments configured correctly?", &chain_id));
     }
 
+    match history.commitments(network_id, *provider).await {
+        Ok(recorded_commitments) => {
+            let previous_commitment = recorded_commitments.into_iter().rfind(|c| {
+                c.original_commitment_sequence_number
+                    < provider_info.original_commitment_sequence_number
+            });
+            if let Some(previous_commitment) = previous_commitment {
+                if !provider_commitments.iter().any(|c| {
+                    c.original_commitment_sequence_number
+                        == previous_commitment.original_commitment_sequence_number
+                }) {
+                    provider_commitments.push(previous_commitment);
+                    provider_commitments.sort_by(|c1, c2| {
+                        c1.original_commitment_sequence_number
+                            .cmp(&c2.original_commitment_sequence_number)
+                    });
+                }
+            }
+        }
+        Err(e) => tracing::warn!(
+            "Chain: {} - Failed to load the recorded commitments: {:?}",
+            &chain_id,
+            e
+        ),
+    }
+
     provider_commitments.push(Commitment {
         seed: latest_metadata.seed,
         chain_length: latest_metadata.chain_length,
@@ -284,24 +562,25 @@ async fn setup_chain_state(
     // later when a user request comes in for that chain.
 
     let mut offsets = Vec::<usize>::new();
-    let mut hash_chains = Vec::<PebbleHashChain>::new();
+    let mut hash_chains = vec![];
 
     for commitment in &provider_commitments {
         let offset = commitment.original_commitment_sequence_number.try_into()?;
         offsets.push(offset);
 
-        let pebble_hash_chain = PebbleHashChain::from_config_async(
+        let hash_chain = load_hash_chain(
+            hash_chain_storage,
             secret,
             chain_id,
             provider,
-            &chain_config.contract_addr,
+            contract_address,
             &commitment.seed,
             commitment.chain_length,
             chain_sample_interval,
         )
         .await
         .map_err(|e| anyhow!("Failed to create hash chain: {}", e))?;
-        hash_chains.push(pebble_hash_chain);
+        hash_chains.push(hash_chain);
     }
 
     let chain_state = HashChainState::new(offsets, hash_chains)?;
@@ -327,8 +606,8 @@ async fn setup_chain_state(
         network_id,
         contract,
         provider_address: *provider,
-        reveal_delay_blocks: chain_config.reveal_delay_blocks,
-        confirmed_block_status: chain_config.confirmed_block_status,
+        reveal_delay_blocks,
+        confirmed_block_status,
     };
     Ok(state)
 }
@@ -1,9 +1,9 @@
 use {
     crate::{
-        api::{get_register_uri, ChainId},
+        api::ChainId,
         chain::ethereum::{EntropyStructsV2ProviderInfo, SignablePythContract},
         command::register_provider::{register_provider_from_config, CommitmentMetadata},
-        config::{Config, EthereumConfig, SetupProviderOptions},
+        config::{Config, ProviderConfig, SetupProviderOptions},
         state::{HashChainState, PebbleHashChain},
     },
     anyhow::{anyhow, Result},
@@ -18,31 +18,39 @@ use {
     tracing::Instrument,
 };
 
-/// Setup provider for all the chains.
+/// Setup all the providers for all the chains.
 pub async fn setup_provider(opts: &SetupProviderOptions) -> Result<()> {
     let config = Config::load(&opts.config.config)?;
     let setup_tasks = config
-        .chains
-        .clone()
-        .into_iter()
-        .map(|(chain_id, chain_config)| {
-            let config = config.clone();
-            spawn(async move {
-                (
-                    setup_chain_provider(&config, &chain_id, &chain_config).await,
-                    chain_id,
-                )
+        .all_providers()
+        .flat_map(|provider_config| {
+            config.chains.keys().map(|chain_id| {
+                let config = config.clone();
+                let provider_config = provider_config.clone();
+                let chain_id = chain_id.clone();
+                spawn(async move {
+                    (
+                        setup_chain_provider(&config, &provider_config, &chain_id).await,
+                        chain_id,
+                        provider_config.address,
+                    )
+                })
             })
         })
         .collect::<Vec<_>>();
     let join_results = join_all(setup_tasks).await;
     let mut all_ok = true;
     for join_result in join_results {
-        let (setup_result, chain_id) = join_result?;
+        let (setup_result, chain_id, provider) = join_result?;
         match setup_result {
             Ok(()) => {}
             Err(e) => {
-                tracing::error!("Failed to setup {} {}", chain_id, e);
+                tracing::error!(
+                    "Failed to setup {} for provider {:?} {}",
+                    chain_id,
+                    provider,
+                    e
+                );
                 all_ok = false;
             }
         }
@@ -60,14 +68,15 @@ pub async fn setup_provider(opts: &SetupProviderOptions) -> Result<()> {
 /// 3. Re-register if there is a mismatch in generated hash chain.
 /// 4. Update provider fee if there is a mismatch with the fee set on contract.
 /// 5. Update provider uri if there is a mismatch with the uri set on contract.
-#[tracing::instrument(name = "setup_chain_provider", skip_all, fields(chain_id = chain_id))]
+#[tracing::instrument(name = "setup_chain_provider", skip_all, fields(chain_id = chain_id, provider = ?provider_config.address))]
 async fn setup_chain_provider(
     config: &Config,
+    provider_config: &ProviderConfig,
     chain_id: &ChainId,
-    chain_config: &EthereumConfig,
 ) -> Result<()> {
     tracing::info!("Setting up provider for chain: {0}", chain_id);
-    let provider_config = &config.provider;
+    let chain_config = &config.provider_chain_config(provider_config, chain_id)?;
+    let uri = config.register_uri(provider_config, chain_id)?;
     let private_key = provider_config.private_key.load()?.ok_or(anyhow!(
         "Please specify a provider private key in the config file."
     ))?;
@@ -119,7 +128,7 @@ async fn setup_chain_provider(
                 &secret,
                 chain_id,
                 &provider_address,
-                &chain_config.contract_addr,
+                chain_config.contract_addr.as_bytes(),
                 &metadata.seed,
                 provider_config.chain_length,
                 provider_config.chain_sample_interval,
@@ -144,7 +153,7 @@ async fn setup_chain_provider(
     }
     if register {
         tracing::info!("Registering");
-        register_provider_from_config(provider_config, chain_id, chain_config)
+        register_provider_from_config(provider_config, chain_id, chain_config, &uri)
             .await
             .map_err(|e| anyhow!("Chain: {} - Failed to register provider: {}", &chain_id, e))?;
         tracing::info!("Registered");
@@ -161,7 +170,6 @@ async fn setup_chain_provider(
             .await?;
     }
 
-    let uri = get_register_uri(&provider_config.uri, chain_id)?;
     sync_uri(&contract, &provider_info, uri)
         .in_current_span()
         .await?;
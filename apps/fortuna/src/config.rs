@@ -1,8 +1,9 @@
 use {
     crate::{
-        api::ChainId,
+        api::{get_provider_register_uri, get_register_uri, ChainId},
         chain::reader::{BlockNumber, BlockStatus},
         eth_utils::utils::EscalationPolicy,
+        history::CallbackFailureKind,
     },
     anyhow::{anyhow, Result},
     clap::{crate_authors, crate_description, crate_name, crate_version, Args, Parser},
@@ -10,12 +11,13 @@ use {
     std::{collections::HashMap, fs},
 };
 pub use {
-    generate::GenerateOptions, get_request::GetRequestOptions, inspect::InspectOptions,
-    prometheus_client::metrics::histogram::Histogram, register_provider::RegisterProviderOptions,
-    request_randomness::RequestRandomnessOptions, run::RunOptions,
-    setup_provider::SetupProviderOptions, withdraw_fees::WithdrawFeesOptions,
+    audit::AuditOptions, generate::GenerateOptions, get_request::GetRequestOptions,
+    inspect::InspectOptions, prometheus_client::metrics::histogram::Histogram,
+    register_provider::RegisterProviderOptions, request_randomness::RequestRandomnessOptions,
+    run::RunOptions, setup_provider::SetupProviderOptions, withdraw_fees::WithdrawFeesOptions,
 };
 
+mod audit;
 mod generate;
 mod get_request;
 mod inspect;
@@ -50,6 +52,10 @@ pub enum Options {
     /// Inspect recent requests and find unfulfilled requests with callback.
     Inspect(InspectOptions),
 
+    /// Cross-check the requests of a block range against the reveals on chain, the history
+    /// database and the locally computed hash chain, and report the issues as JSON.
+    Audit(AuditOptions),
+
     /// Generate a random number by running the entire protocol end-to-end
     Generate(GenerateOptions),
 
@@ -74,16 +80,30 @@ pub struct ConfigOptions {
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Config {
     pub chains: HashMap<ChainId, EthereumConfig>,
+    /// Chains running the CosmWasm Entropy contract. Chain ids must not overlap with `chains`.
+    #[serde(default)]
+    pub cosmwasm_chains: HashMap<ChainId, CosmWasmConfig>,
     pub provider: ProviderConfig,
+    /// Other providers served by this instance on the EVM chains, besides `provider`. They share
+    /// the chain connections, the keeper wallet and the history database with `provider`.
+    #[serde(default)]
+    pub providers: Vec<ProviderConfig>,
     pub keeper: KeeperConfig,
+    /// The history database is configured with the `DATABASE_URL` environment variable.
+    #[serde(default)]
+    pub history: HistoryConfig,
 }
 
 impl Config {
     pub fn load(path: &str) -> Result<Config> {
         // Open and read the YAML file
-        // TODO: the default serde deserialization doesn't enforce unique keys
         let yaml_content = fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&yaml_content)?;
+        Self::parse(&yaml_content)
+    }
+
+    fn parse(yaml_content: &str) -> Result<Config> {
+        // TODO: the default serde deserialization doesn't enforce unique keys
+        let config: Config = serde_yaml::from_str(yaml_content)?;
 
         // Run correctness checks for the config and fail if there are any issues.
         for (chain_id, config) in config.chains.iter() {
@@ -92,6 +112,41 @@ impl Config {
             {
                 return Err(anyhow!("chain id {:?} configuration is invalid. Config must satisfy min_profit_pct <= target_profit_pct <= max_profit_pct.", chain_id));
             }
+            if let Some(callback_retry) = &config.callback_retry {
+                if callback_retry.gas_limit_multiplier_pct < 100 {
+                    return Err(anyhow!("chain id {:?} configuration is invalid. callback_retry.gas_limit_multiplier_pct must be at least 100.", chain_id));
+                }
+            }
+        }
+        let mut provider_addresses = std::collections::HashSet::new();
+        for provider_config in config.all_providers() {
+            if !provider_addresses.insert(provider_config.address) {
+                return Err(anyhow!(
+                    "provider {:?} is configured more than once.",
+                    provider_config.address
+                ));
+            }
+            for chain_id in provider_config.chains.keys() {
+                let chain_config = config.provider_chain_config(provider_config, chain_id)?;
+                if !(chain_config.min_profit_pct <= chain_config.target_profit_pct
+                    && chain_config.target_profit_pct <= chain_config.max_profit_pct)
+                {
+                    return Err(anyhow!("provider {:?} configuration for chain id {:?} is invalid. Config must satisfy min_profit_pct <= target_profit_pct <= max_profit_pct.", provider_config.address, chain_id));
+                }
+            }
+        }
+        for (chain_id, chain_config) in config.cosmwasm_chains.iter() {
+            if config.chains.contains_key(chain_id) {
+                return Err(anyhow!(
+                    "chain id {:?} is configured both as an EVM and a CosmWasm chain.",
+                    chain_id
+                ));
+            }
+            if !(chain_config.min_profit_pct <= chain_config.target_profit_pct
+                && chain_config.target_profit_pct <= chain_config.max_profit_pct)
+            {
+                return Err(anyhow!("chain id {:?} configuration is invalid. Config must satisfy min_profit_pct <= target_profit_pct <= max_profit_pct.", chain_id));
+            }
         }
 
         if let Some(replica_config) = &config.keeper.replica_config {
@@ -109,6 +164,19 @@ impl Config {
             if replica_config.backup_delay_seconds == 0 {
                 return Err(anyhow!("Keeper replica configuration is invalid. backup_delay_seconds must be greater than 0 to prevent race conditions."));
             }
+            if let Some(lease) = &replica_config.lease {
+                if lease.renew_interval_seconds == 0
+                    || lease.duration_seconds <= lease.renew_interval_seconds
+                {
+                    return Err(anyhow!("Keeper replica lease configuration is invalid. Config must satisfy 0 < renew_interval_seconds < duration_seconds."));
+                }
+            }
+        }
+
+        if config.history.retention_days == Some(0) {
+            return Err(anyhow!(
+                "History configuration is invalid. retention_days must be greater than 0."
+            ));
         }
 
         Ok(config)
@@ -120,6 +188,76 @@ impl Config {
             &chain_id
         ))
     }
+
+    /// The providers served by this instance, starting with the main `provider`.
+    pub fn all_providers(&self) -> impl Iterator<Item = &ProviderConfig> {
+        std::iter::once(&self.provider).chain(self.providers.iter())
+    }
+
+    /// The configuration of the provider with the given address, or of the main provider if
+    /// `address` is `None`.
+    pub fn get_provider_config(&self, address: Option<Address>) -> Result<&ProviderConfig> {
+        match address {
+            None => Ok(&self.provider),
+            Some(address) => self
+                .all_providers()
+                .find(|provider_config| provider_config.address == address)
+                .ok_or(anyhow!(
+                    "Could not find provider {:?} in the configuration",
+                    address
+                )),
+        }
+    }
+
+    /// Whether `provider_config` is the main provider, which is served by the routes that aren't
+    /// namespaced by provider.
+    pub fn is_main_provider(&self, provider_config: &ProviderConfig) -> bool {
+        provider_config.address == self.provider.address
+    }
+
+    /// The configuration of a chain for a provider, with the overrides of the provider applied.
+    /// The commitments of the chain configuration belong to the main provider.
+    pub fn provider_chain_config(
+        &self,
+        provider_config: &ProviderConfig,
+        chain_id: &ChainId,
+    ) -> Result<EthereumConfig> {
+        let mut chain_config = self.get_chain_config(chain_id)?;
+        if !self.is_main_provider(provider_config) {
+            chain_config.commitments = None;
+        }
+        if let Some(overrides) = provider_config.chains.get(chain_id) {
+            overrides.apply(&mut chain_config);
+        }
+        Ok(chain_config)
+    }
+
+    /// The private key of the fee manager of a provider, if any.
+    pub fn fee_manager_private_key(
+        &self,
+        provider_config: &ProviderConfig,
+    ) -> Result<Option<String>> {
+        let secret = match &provider_config.fee_manager_private_key {
+            Some(secret) => Some(secret),
+            None if self.is_main_provider(provider_config) => {
+                self.keeper.fee_manager_private_key.as_ref()
+            }
+            None => None,
+        };
+        match secret {
+            Some(secret) => secret.load(),
+            None => Ok(None),
+        }
+    }
+
+    /// The uri to register on chain for a provider, where this instance serves its random values.
+    pub fn register_uri(&self, provider_config: &ProviderConfig, chain_id: &str) -> Result<String> {
+        if self.is_main_provider(provider_config) {
+            get_register_uri(&provider_config.uri, chain_id)
+        } else {
+            get_provider_register_uri(&provider_config.uri, &provider_config.address, chain_id)
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -128,6 +266,12 @@ pub struct EthereumConfig {
     /// TODO: Change type from String to Url
     pub geth_rpc_addr: String,
 
+    /// URL of a Geth RPC wss endpoint to subscribe to new blocks with. If provided, the keeper
+    /// handles the events of new blocks as soon as they are received, and only polls
+    /// `geth_rpc_addr` occasionally to fill any gaps.
+    #[serde(default)]
+    pub geth_rpc_wss: Option<String>,
+
     /// Address of a Pyth Randomness contract to interact with.
     pub contract_addr: Address,
 
@@ -205,10 +349,21 @@ pub struct EthereumConfig {
     #[serde(default = "default_surge_threshold_3")]
     pub surge_threshold_3: u64,
 
+    /// How to estimate the gas price of transactions and the cost of callbacks on this chain.
+    /// Defaults to the EIP-1559 fee history heuristic configured above.
+    #[serde(default)]
+    pub gas_oracle: GasOracleConfig,
+
     /// The escalation policy governs how the gas limit and fee are increased during backoff retries.
     #[serde(default)]
     pub escalation_policy: EscalationPolicyConfig,
 
+    /// Cancel the transactions of the keeper wallets that stay pending for longer than this many
+    /// seconds, so that they stop blocking the following transactions of the wallet.
+    /// Defaults to 300 seconds if not provided.
+    #[serde(default = "default_stuck_tx_threshold_seconds")]
+    pub stuck_tx_threshold_seconds: u64,
+
     /// The minimum percentage profit to earn as a function of the callback cost.
     /// For example, 20 means a profit of 20% over the cost of a callback that uses the full gas limit.
     /// The fee will be raised if the profit is less than this number.
@@ -260,6 +415,21 @@ pub struct EthereumConfig {
     /// at each specified delay. For example: [5, 10, 20].
     #[serde(default = "default_block_delays")]
     pub block_delays: Vec<u64>,
+
+    /// The ABIs of consumer contracts, used to decode the custom errors of their failed callbacks.
+    /// Maps the address of a consumer contract to the path of a JSON file with its ABI, either the
+    /// ABI itself or a compiler artifact with an `abi` field.
+    #[serde(default)]
+    pub consumer_abis: HashMap<Address, String>,
+
+    /// Reveal the requests whose callback failed again, with more gas for the callback.
+    /// Failed callbacks are not retried if this is not set.
+    #[serde(default)]
+    pub callback_retry: Option<CallbackRetryConfig>,
+}
+
+fn default_stuck_tx_threshold_seconds() -> u64 {
+    300
 }
 
 fn default_sync_fee_only_on_register() -> bool {
@@ -314,6 +484,91 @@ fn default_backlog_range() -> u64 {
     1000
 }
 
+/// Configuration of a chain running the CosmWasm Entropy contract. Transactions are built and
+/// signed locally with the keeper's secp256k1 key, and submitted through the chain's REST (LCD)
+/// endpoint.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CosmWasmConfig {
+    /// URL of the REST (LCD) endpoint of a node of the chain.
+    pub lcd_addr: String,
+
+    /// Bech32 address of the Entropy contract. Its prefix is used for all the other addresses
+    /// on the chain.
+    pub contract_addr: String,
+
+    /// Bech32 address of the provider's account on this chain. Unlike EVM chains, it is not
+    /// derived from `provider.address`, as accounts are derived from public keys differently.
+    pub provider_addr: String,
+
+    /// The numeric id recorded for this chain in the request history. CosmWasm chain ids are
+    /// strings (e.g. `osmosis-1`), so they are not used here.
+    pub network_id: u64,
+
+    /// The denom that transaction fees are paid in, e.g. `uosmo`.
+    pub fee_denom: String,
+
+    /// The price of a unit of gas, in `fee_denom`.
+    pub gas_price: f64,
+
+    /// The percentage applied to the simulated gas usage of a transaction to get its gas limit.
+    #[serde(default = "default_gas_adjustment_pct")]
+    pub gas_adjustment_pct: u64,
+
+    /// The number of blocks to wait after a request before revealing it. Blocks are final as
+    /// soon as they are committed, so this is usually 0.
+    #[serde(default)]
+    pub reveal_delay_blocks: BlockNumber,
+
+    /// The number of blocks to look back for events that might be missed when starting the keeper
+    #[serde(default = "default_backlog_range")]
+    pub backlog_range: u64,
+
+    /// A list of delays (in blocks) at which blocks are processed again, see `EthereumConfig`.
+    #[serde(default = "default_block_delays")]
+    pub block_delays: Vec<u64>,
+
+    /// The gas limit to use for entropy callbacks.
+    pub gas_limit: u32,
+
+    /// The profit bounds used for adjusting the fee, see `EthereumConfig`.
+    pub min_profit_pct: i64,
+    pub target_profit_pct: i64,
+    pub max_profit_pct: i64,
+
+    /// Minimum balance of the keeper account, in `fee_denom`.
+    #[serde(default)]
+    pub min_keeper_balance: u128,
+
+    /// How much the provider charges for a request on this chain, in `fee_denom`.
+    #[serde(default)]
+    pub fee: u128,
+
+    /// Optional hard cap on the fee, in `fee_denom`.
+    #[serde(default)]
+    pub max_fee: Option<u128>,
+
+    /// Bech32 addresses of the other keepers in the replica set on this chain.
+    #[serde(default)]
+    pub other_keeper_addrs: Vec<String>,
+
+    /// Historical commitments made by the provider.
+    pub commitments: Option<Vec<Commitment>>,
+}
+
+fn default_gas_adjustment_pct() -> u64 {
+    150
+}
+
+impl CosmWasmConfig {
+    /// The addresses of the other keepers, decoded from bech32.
+    pub fn other_keeper_addresses(&self) -> Result<Vec<Address>> {
+        self.other_keeper_addrs
+            .iter()
+            .map(|address| crate::chain::cosmwasm::decode_account_address(address))
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct EscalationPolicyConfig {
     /// The fee multiplier to apply to the fee during backoff retries.
@@ -351,6 +606,115 @@ impl EscalationPolicyConfig {
     }
 }
 
+/// The gas price oracle of a chain.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum GasOracleConfig {
+    /// Estimate the fees from the fee history of the RPC provider, using the `eip1559_*`,
+    /// `surge_threshold_*` and `priority_fee_multiplier_pct` parameters of the chain.
+    #[default]
+    FeeHistory,
+    /// Like `fee-history`, plus the L1 data fee charged by OP stack chains (Optimism, Base, ...)
+    /// for the calldata of the transaction.
+    OpStack {
+        /// The address of the `GasPriceOracle` predeploy.
+        #[serde(default = "default_op_stack_gas_price_oracle")]
+        gas_price_oracle: Address,
+    },
+    /// Like `fee-history`, plus the L1 component of the gas of Arbitrum chains, as estimated by
+    /// the `NodeInterface` precompile.
+    Arbitrum {
+        /// The address of the `NodeInterface` precompile.
+        #[serde(default = "default_arbitrum_node_interface")]
+        node_interface: Address,
+    },
+    /// Fixed fees, for chains without a reliable fee market or when the RPC estimates are broken.
+    Static {
+        /// The gas price of legacy transactions, in wei.
+        gas_price: u64,
+        /// The max fee per gas of EIP-1559 transactions, in wei. Defaults to `gas_price`.
+        #[serde(default)]
+        max_fee_per_gas: Option<u64>,
+        /// The max priority fee per gas of EIP-1559 transactions, in wei. Defaults to 0.
+        #[serde(default)]
+        max_priority_fee_per_gas: Option<u64>,
+    },
+}
+
+fn default_op_stack_gas_price_oracle() -> Address {
+    "0x420000000000000000000000000000000000000F"
+        .parse()
+        .expect("valid address")
+}
+
+fn default_arbitrum_node_interface() -> Address {
+    Address::from_low_u64_be(0xc8)
+}
+
+/// The policy for revealing a request again after its callback failed. The contract invokes the
+/// callback of such a request with all the gas of the transaction, and the transaction reverts if
+/// the callback fails again.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CallbackRetryConfig {
+    /// The kinds of callback failures to retry. Defaults to out-of-gas failures only, as callbacks
+    /// that revert with an error usually revert again.
+    #[serde(default = "default_callback_retry_on")]
+    pub retry_on: Vec<CallbackFailureKind>,
+
+    /// The maximum number of times to reveal a request again.
+    #[serde(default = "default_callback_retry_max_attempts")]
+    pub max_attempts: u32,
+
+    /// How long to wait before each attempt.
+    #[serde(default = "default_callback_retry_delay_seconds")]
+    pub delay_seconds: u64,
+
+    /// The gas available to the callback is multiplied by this percentage on each attempt,
+    /// starting from the gas limit of the request. For example, 200 doubles the gas on each attempt.
+    #[serde(default = "default_callback_retry_gas_limit_multiplier_pct")]
+    pub gas_limit_multiplier_pct: u64,
+
+    /// The maximum gas available to the callback on any attempt.
+    #[serde(default = "default_callback_retry_max_gas_limit")]
+    pub max_gas_limit: u64,
+}
+
+fn default_callback_retry_on() -> Vec<CallbackFailureKind> {
+    vec![CallbackFailureKind::OutOfGas]
+}
+
+fn default_callback_retry_max_attempts() -> u32 {
+    2
+}
+
+fn default_callback_retry_delay_seconds() -> u64 {
+    30
+}
+
+fn default_callback_retry_gas_limit_multiplier_pct() -> u64 {
+    200
+}
+
+fn default_callback_retry_max_gas_limit() -> u64 {
+    5_000_000
+}
+
+impl CallbackRetryConfig {
+    /// The gas available to the callback on the `attempt`-th retry (starting from 1) of a request
+    /// with `gas_limit`.
+    pub fn callback_gas_limit(&self, gas_limit: u32, attempt: u32) -> u64 {
+        let mut callback_gas_limit = gas_limit as u64;
+        for _ in 0..attempt {
+            if callback_gas_limit >= self.max_gas_limit {
+                break;
+            }
+            callback_gas_limit =
+                callback_gas_limit.saturating_mul(self.gas_limit_multiplier_pct) / 100;
+        }
+        callback_gas_limit.min(self.max_gas_limit)
+    }
+}
+
 /// A commitment that the provider used to generate random numbers at some point in the past.
 /// These historical commitments need to be stored in the configuration to support transition points where
 /// the commitment changes. In theory, this information is stored on the blockchain, but unfortunately it
@@ -389,15 +753,98 @@ pub struct ProviderConfig {
     #[serde(default = "default_chain_sample_interval")]
     pub chain_sample_interval: u64,
 
+    /// How the hash chains are stored while the server runs.
+    #[serde(default)]
+    pub hash_chain_storage: HashChainStorage,
+
     /// The address of the fee manager for the provider. Only used for syncing the fee manager address to the contract.
     /// Fee withdrawals are handled by the fee manager private key defined in the keeper config.
     pub fee_manager: Option<Address>,
+
+    /// The fee manager's private key for this provider. Defaults to `keeper.fee_manager_private_key`
+    /// for the main provider. Fees aren't withdrawn or adjusted for other providers without one.
+    #[serde(default)]
+    pub fee_manager_private_key: Option<SecretString>,
+
+    /// Register a new hash chain on the EVM chains before the current one is exhausted, if set.
+    /// Requires `private_key`.
+    #[serde(default)]
+    pub commitment_rotation: Option<CommitmentRotationConfig>,
+
+    /// Overrides of the EVM chain configurations for this provider, by chain id.
+    #[serde(default)]
+    pub chains: HashMap<ChainId, ProviderChainConfig>,
+}
+
+/// When the keeper registers the next hash chain of a provider.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CommitmentRotationConfig {
+    /// Register the next hash chain once fewer than this many sequence numbers remain on the
+    /// current one.
+    pub min_remaining_sequence_numbers: u64,
+}
+
+/// The configuration of a provider on a chain that differs from the chain configuration.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProviderChainConfig {
+    /// Historical commitments of the provider on the chain (see `EthereumConfig::commitments`).
+    #[serde(default)]
+    pub commitments: Option<Vec<Commitment>>,
+    #[serde(default)]
+    pub fee: Option<u128>,
+    #[serde(default)]
+    pub max_fee: Option<u128>,
+    #[serde(default)]
+    pub min_profit_pct: Option<i64>,
+    #[serde(default)]
+    pub target_profit_pct: Option<i64>,
+    #[serde(default)]
+    pub max_profit_pct: Option<i64>,
+}
+
+impl ProviderChainConfig {
+    fn apply(&self, chain_config: &mut EthereumConfig) {
+        if self.commitments.is_some() {
+            chain_config.commitments = self.commitments.clone();
+        }
+        if let Some(fee) = self.fee {
+            chain_config.fee = fee;
+        }
+        if self.max_fee.is_some() {
+            chain_config.max_fee = self.max_fee;
+        }
+        if let Some(min_profit_pct) = self.min_profit_pct {
+            chain_config.min_profit_pct = min_profit_pct;
+        }
+        if let Some(target_profit_pct) = self.target_profit_pct {
+            chain_config.target_profit_pct = target_profit_pct;
+        }
+        if let Some(max_profit_pct) = self.max_profit_pct {
+            chain_config.max_profit_pct = max_profit_pct;
+        }
+    }
 }
 
 fn default_chain_sample_interval() -> u64 {
     1
 }
 
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HashChainStorage {
+    /// Keep every `chain_sample_interval`-th hash of the chains in memory.
+    #[default]
+    Sampled,
+    /// Keep O(log n) hashes of each chain in memory. If `checkpoint_dir` is set, every
+    /// `chain_sample_interval`-th hash is also stored in a file of that directory, so that chains
+    /// aren't recomputed on restart and any value is recomputed in at most `chain_sample_interval`
+    /// hashes. These files contain unrevealed random values and must be kept private.
+    Hierarchical {
+        #[serde(default)]
+        checkpoint_dir: Option<String>,
+    },
+}
+
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct RunConfig {
     /// Disable automatic fee adjustment threads
@@ -405,18 +852,51 @@ pub struct RunConfig {
     pub disable_fee_adjustment: bool,
 }
 
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct HistoryConfig {
+    /// Delete the requests created more than this many days ago. The daily rollups of the requests
+    /// are kept. If not provided, requests are never deleted.
+    #[serde(default)]
+    pub retention_days: Option<u64>,
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct ReplicaConfig {
     pub replica_id: u64,
     pub total_replicas: u64,
     #[serde(default = "default_backup_delay_seconds")]
     pub backup_delay_seconds: u64,
+    /// If provided, the replicas hold leases in the shared history database and requests are
+    /// split among the replicas with a live lease. The replicas then take over the requests of a
+    /// replica as soon as its lease expires, and can be added or removed without editing this
+    /// config. `replica_id` and `total_replicas` are only used if the database is unavailable.
+    #[serde(default)]
+    pub lease: Option<ReplicaLeaseConfig>,
 }
 
 fn default_backup_delay_seconds() -> u64 {
     30
 }
 
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReplicaLeaseConfig {
+    /// How often each replica renews its lease.
+    #[serde(default = "default_lease_renew_interval_seconds")]
+    pub renew_interval_seconds: u64,
+    /// How long a lease lasts without being renewed. This should be a few renew intervals, plus
+    /// the maximum clock difference between the replicas.
+    #[serde(default = "default_lease_duration_seconds")]
+    pub duration_seconds: u64,
+}
+
+fn default_lease_renew_interval_seconds() -> u64 {
+    5
+}
+
+fn default_lease_duration_seconds() -> u64 {
+    20
+}
+
 /// Configuration values for the keeper service that are shared across chains.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct KeeperConfig {
@@ -440,6 +920,17 @@ pub struct KeeperConfig {
 
     #[serde(default)]
     pub replica_config: Option<ReplicaConfig>,
+
+    /// The private keys of more keeper wallets. The reveals are spread across `private_key` and
+    /// these wallets so that they are submitted in parallel, and the fee manager keeps all of
+    /// them funded. The other transactions are sent from `private_key`.
+    #[serde(default)]
+    pub pool_private_keys: Vec<SecretString>,
+
+    /// If true, the keeper simulates the reveals of the requests and records their estimated gas
+    /// and fees in the history, without sending any transaction.
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 // A secret is a string that can be provided either as a literal in the config,
@@ -472,3 +963,112 @@ impl SecretString {
 pub const LATENCY_BUCKETS: [f64; 11] = [
     0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0,
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+chains:
+  ethereum:
+    geth_rpc_addr: http://localhost:8545
+    contract_addr: 0x0000000000000000000000000000000000001234
+    reveal_delay_blocks: 1
+    gas_limit: 500000
+    min_profit_pct: 0
+    target_profit_pct: 20
+    max_profit_pct: 100
+    fee: 1000
+    commitments:
+      - seed: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        chain_length: 100
+        original_commitment_sequence_number: 10
+provider:
+  uri: http://localhost:34000/
+  address: 0x0000000000000000000000000000000000000001
+  private_key:
+    value: 0xabcd
+  secret:
+    value: abcd
+  chain_length: 100000
+providers:
+  - uri: http://localhost:34000/
+    address: 0x0000000000000000000000000000000000000002
+    private_key:
+      value: 0xabcd
+    secret:
+      value: abcd
+    chain_length: 100000
+    fee_manager_private_key:
+      value: 0xfee
+    chains:
+      ethereum:
+        fee: 2000
+        target_profit_pct: 50
+keeper:
+  private_key:
+    value: 0xabcd
+  fee_manager_private_key:
+    value: 0xbeef
+"#;
+
+    #[test]
+    fn test_provider_chain_config() {
+        let config = Config::parse(CONFIG).unwrap();
+        let chain_id = "ethereum".to_string();
+        let main = config.get_provider_config(None).unwrap();
+        let other = config
+            .get_provider_config(Some(Address::from_low_u64_be(2)))
+            .unwrap();
+        assert!(config.is_main_provider(main));
+        assert!(!config.is_main_provider(other));
+        assert!(config
+            .get_provider_config(Some(Address::from_low_u64_be(3)))
+            .is_err());
+
+        let main_chain_config = config.provider_chain_config(main, &chain_id).unwrap();
+        assert_eq!(main_chain_config.fee, 1000);
+        assert_eq!(main_chain_config.commitments.unwrap().len(), 1);
+        // The commitments of the chain belong to the main provider.
+        let other_chain_config = config.provider_chain_config(other, &chain_id).unwrap();
+        assert_eq!(other_chain_config.fee, 2000);
+        assert_eq!(other_chain_config.target_profit_pct, 50);
+        assert_eq!(other_chain_config.max_profit_pct, 100);
+        assert!(other_chain_config.commitments.is_none());
+
+        assert_eq!(
+            config.fee_manager_private_key(main).unwrap(),
+            Some("0xbeef".to_string())
+        );
+        assert_eq!(
+            config.fee_manager_private_key(other).unwrap(),
+            Some("0xfee".to_string())
+        );
+
+        assert_eq!(
+            config.register_uri(main, &chain_id).unwrap(),
+            "http://localhost:34000/v1/chains/ethereum"
+        );
+        assert_eq!(
+            config.register_uri(other, &chain_id).unwrap(),
+            "http://localhost:34000/v1/providers/0x0000000000000000000000000000000000000002/chains/ethereum"
+        );
+    }
+
+    #[test]
+    fn test_invalid_providers() {
+        // Providers must be unique.
+        let duplicate = CONFIG.replace(
+            "address: 0x0000000000000000000000000000000000000002",
+            "address: 0x0000000000000000000000000000000000000001",
+        );
+        assert!(Config::parse(&duplicate).is_err());
+        // The overrides of a provider must be valid.
+        let invalid_profit = CONFIG.replace("target_profit_pct: 50", "target_profit_pct: 500");
+        assert!(Config::parse(&invalid_profit).is_err());
+        // Overrides can only be set for the configured chains.
+        let unknown_chain =
+            CONFIG.replace("      ethereum:\n        fee", "      base:\n        fee");
+        assert!(Config::parse(&unknown_chain).is_err());
+    }
+}
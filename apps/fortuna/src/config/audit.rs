@@ -0,0 +1,49 @@
+use {
+    crate::{api::ChainId, config::ConfigOptions},
+    clap::Args,
+    ethers::types::Address,
+};
+
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Audit Options")]
+#[group(id = "Audit")]
+pub struct AuditOptions {
+    #[command(flatten)]
+    pub config: ConfigOptions,
+
+    /// Audit the requests on this chain, or all chains if not specified.
+    #[arg(long = "chain-id")]
+    pub chain_id: Option<ChainId>,
+
+    /// Audit the requests to this provider, or to the main provider of the config if not specified.
+    #[arg(long = "provider")]
+    pub provider: Option<Address>,
+
+    /// The first block to audit. Defaults to `num-blocks` blocks before `to-block`.
+    #[arg(long = "from-block")]
+    pub from_block: Option<u64>,
+
+    /// The last block to audit. Defaults to the latest block.
+    #[arg(long = "to-block")]
+    pub to_block: Option<u64>,
+
+    /// The number of blocks to audit if `from-block` is not specified.
+    #[arg(long = "num-blocks", default_value = "1000")]
+    pub num_blocks: u64,
+
+    /// Only audit the requests with a sequence number of at least this value.
+    #[arg(long = "from-sequence")]
+    pub from_sequence: Option<u64>,
+
+    /// Only audit the requests with a sequence number of at most this value.
+    #[arg(long = "to-sequence")]
+    pub to_sequence: Option<u64>,
+
+    /// A request is expected to be revealed within this many blocks after its `reveal_delay_blocks`.
+    #[arg(long = "max-reveal-lag-blocks", default_value = "100")]
+    pub max_reveal_lag_blocks: u64,
+
+    /// Exit with an error if the audit finds any issue.
+    #[arg(long = "fail-on-findings")]
+    pub fail_on_findings: bool,
+}
@@ -1,6 +1,7 @@
 use {
     crate::{api::ChainId, config::ConfigOptions},
     clap::Args,
+    ethers::types::Address,
 };
 
 #[derive(Args, Clone, Debug)]
@@ -14,4 +15,8 @@ pub struct RegisterProviderOptions {
     #[arg(long = "chain-id")]
     #[arg(env = "FORTUNA_CHAIN_ID")]
     pub chain_id: ChainId,
+
+    /// Register this provider from the config file. Defaults to the main provider.
+    #[arg(long = "provider")]
+    pub provider: Option<Address>,
 }
@@ -11,4 +11,9 @@ pub struct RunOptions {
     #[arg(default_value = super::DEFAULT_RPC_ADDR)]
     #[arg(env = "RPC_ADDR")]
     pub addr: SocketAddr,
+
+    /// Simulate the reveals of the keeper instead of sending them (see `keeper.dry_run`).
+    #[arg(long = "dry-run")]
+    #[arg(default_value = "false")]
+    pub dry_run: bool,
 }
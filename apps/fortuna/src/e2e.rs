@@ -0,0 +1,521 @@
+//! End-to-end tests of the keeper against a local anvil node running the Entropy contracts.
+//!
+//! The tests only run if `FORTUNA_TEST_E2E` is set. They require `anvil` on the `PATH` and the
+//! contracts built with `forge build` in `target_chains/ethereum/contracts`:
+//! `FORTUNA_TEST_E2E=1 cargo test e2e`.
+
+use {
+    crate::{
+        api::BlockchainState,
+        chain::{
+            ethereum::{InstrumentedPythContract, SignablePythContract},
+            reader::{EntropyReader, RequestCallbackStatus},
+        },
+        command::{self, CommitmentMetadata},
+        config::{Config, ConfigOptions, RegisterProviderOptions},
+        eth_utils::traced_client::RpcMetrics,
+        history::{CallbackFailureKind, History, RequestEntryState, RequestStatus},
+        keeper::{self, block::BlockWatcher, keeper_metrics::KeeperMetrics},
+        state::{HashChainState, MonitoredHashChainState, PebbleHashChain},
+        test_utils::{anvil_keys, deploy, deployer, AnvilClient},
+    },
+    anyhow::{anyhow, Result},
+    ethers::{
+        abi::Token,
+        contract::Contract,
+        signers::{LocalWallet, Signer},
+        types::{Address, Bytes, U256},
+        utils::{id, Anvil, AnvilInstance},
+    },
+    prometheus_client::registry::Registry,
+    std::{future::Future, io::Write, sync::Arc, time::Duration},
+    tokio::sync::RwLock,
+};
+
+const CHAIN_ID: &str = "anvil";
+/// The fee of the provider for each request, in wei.
+const PROVIDER_FEE: u128 = 1_000_000_000_000_000;
+/// The gas limit of the callbacks requested by the tests.
+const CALLBACK_GAS_LIMIT: u32 = 300_000;
+/// How long to wait for the keeper to act.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Poll `condition` until it returns a value, or fail after `TIMEOUT`.
+async fn wait_for<T, F: Future<Output = Result<Option<T>>>>(
+    description: &str,
+    mut condition: impl FnMut() -> F,
+) -> T {
+    let start = tokio::time::Instant::now();
+    loop {
+        match condition().await {
+            Ok(Some(value)) => return value,
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Error while waiting for {}: {:?}", description, e),
+        }
+        if start.elapsed() > TIMEOUT {
+            panic!("Timed out waiting for {description}");
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// A local anvil chain with the Entropy contract behind a proxy, and an `EntropyTester` consumer
+/// whose callbacks succeed or revert on demand.
+struct TestChain {
+    anvil: AnvilInstance,
+    entropy: Address,
+    tester: Contract<AnvilClient>,
+    /// The private keys of the funded anvil accounts used by the tests, in hex.
+    provider_key: String,
+    keeper_key: String,
+    fee_manager_key: String,
+}
+
+impl TestChain {
+    async fn start() -> Result<Self> {
+        let anvil = Anvil::new().block_time(1u64).spawn();
+        let keys = anvil_keys(&anvil);
+        let client = deployer(&anvil)?;
+        let deployer = client.address();
+        let provider = keys[1].parse::<LocalWallet>()?.address();
+
+        let implementation = deploy(client.clone(), "EntropyUpgradable", ()).await?;
+        let initialize = implementation
+            .abi()
+            .function("initialize")?
+            .encode_input(&[
+                Token::Address(deployer),
+                Token::Address(deployer),
+                Token::Uint(U256::zero()),
+                Token::Address(provider),
+                Token::Bool(false),
+            ])?;
+        let proxy = deploy(
+            client.clone(),
+            "ERC1967Proxy",
+            (implementation.address(), Bytes::from(initialize)),
+        )
+        .await?;
+        let tester = deploy(client.clone(), "EntropyTester", proxy.address()).await?;
+
+        Ok(Self {
+            entropy: proxy.address(),
+            tester,
+            provider_key: keys[1].clone(),
+            keeper_key: keys[2].clone(),
+            fee_manager_key: keys[3].clone(),
+            anvil,
+        })
+    }
+
+    /// A fortuna config for the chain, written to a temporary file.
+    fn write_config(&self) -> Result<tempfile::NamedTempFile> {
+        let provider = self.provider_key.parse::<LocalWallet>()?.address();
+        let mut file = tempfile::NamedTempFile::new()?;
+        write!(
+            file,
+            r#"
+chains:
+  {CHAIN_ID}:
+    geth_rpc_addr: {rpc}
+    contract_addr: {entropy:?}
+    reveal_delay_blocks: 0
+    confirmed_block_status: latest
+    gas_limit: 500000
+    min_profit_pct: 0
+    target_profit_pct: 10
+    max_profit_pct: 100
+    fee: {PROVIDER_FEE}
+    # Above the balance of the funded anvil accounts, so that the fees are always withdrawn.
+    min_keeper_balance: 100000000000000000000000
+provider:
+  uri: http://localhost:8080/
+  address: {provider:?}
+  private_key:
+    value: {provider_key}
+  secret:
+    value: {secret}
+  chain_length: 100
+  chain_sample_interval: 10
+keeper:
+  private_key:
+    value: {keeper_key}
+  fee_manager_private_key:
+    value: {fee_manager_key}
+"#,
+            rpc = self.anvil.endpoint(),
+            entropy = self.entropy,
+            provider_key = self.provider_key,
+            secret = hex::encode([7u8; 32]),
+            keeper_key = self.keeper_key,
+            fee_manager_key = self.fee_manager_key,
+        )?;
+        Ok(file)
+    }
+
+    /// Request a random number through the tester contract, whose callback reverts if
+    /// `callback_reverts`. Returns the sequence number of the request.
+    async fn request(
+        &self,
+        entropy: &dyn EntropyReader,
+        provider: Address,
+        callback_reverts: bool,
+    ) -> Result<u64> {
+        let sequence_number = entropy
+            .get_provider_info(provider, None)
+            .await?
+            .sequence_number;
+        let selector: [u8; 4] = id("requestV2(uint32,bool,uint32)");
+        self.tester
+            .method_hash::<_, u64>(selector, (CALLBACK_GAS_LIMIT, callback_reverts, 100_000u32))?
+            // The tester forwards the fee of the request and keeps the rest.
+            .value(U256::exp10(18))
+            .send()
+            .await?
+            .await?;
+        Ok(sequence_number)
+    }
+}
+
+/// The state of the provider on the chain, as set up by `fortuna run`.
+async fn chain_state(
+    config: &Config,
+    contract: Arc<dyn EntropyReader>,
+    network_id: u64,
+    metrics: Arc<KeeperMetrics>,
+) -> Result<BlockchainState> {
+    let provider_config = &config.provider;
+    let chain_config = config.get_chain_config(&CHAIN_ID.into())?;
+    let provider_info = contract
+        .get_provider_info(provider_config.address, None)
+        .await?;
+    let metadata: CommitmentMetadata = bincode::deserialize(&provider_info.commitment_metadata)?;
+    let hash_chain = PebbleHashChain::from_config(
+        &provider_config.secret.load()?.unwrap(),
+        &CHAIN_ID.into(),
+        &provider_config.address,
+        chain_config.contract_addr.as_bytes(),
+        &metadata.seed,
+        metadata.chain_length,
+        provider_config.chain_sample_interval,
+    )?;
+    Ok(BlockchainState {
+        id: CHAIN_ID.into(),
+        network_id,
+        state: Arc::new(MonitoredHashChainState::new(
+            Arc::new(HashChainState::from_chain_at_offset(
+                provider_info.original_commitment_sequence_number as usize,
+                hash_chain,
+            )),
+            metrics,
+            CHAIN_ID.into(),
+            provider_config.address,
+        )),
+        contract,
+        provider_address: provider_config.address,
+        reveal_delay_blocks: chain_config.reveal_delay_blocks,
+        confirmed_block_status: chain_config.confirmed_block_status,
+    })
+}
+
+/// Register the provider of the config at `config_path` and set its fee manager.
+async fn register_provider(chain: &TestChain, config_path: &str) -> Result<Config> {
+    command::register_provider(&RegisterProviderOptions {
+        config: ConfigOptions {
+            config: config_path.to_string(),
+        },
+        chain_id: CHAIN_ID.into(),
+        provider: None,
+    })
+    .await?;
+    let config = Config::load(config_path)?;
+    let chain_config = config.get_chain_config(&CHAIN_ID.into())?;
+    let fee_manager = chain.fee_manager_key.parse::<LocalWallet>()?.address();
+    SignablePythContract::from_config(&chain_config, &chain.provider_key)
+        .await?
+        .set_fee_manager(fee_manager)
+        .send()
+        .await?
+        .await?;
+    Ok(config)
+}
+
+/// Spawn the keeper threads of the chain, recording the requests in the returned history.
+async fn spawn_keeper(
+    chain: &TestChain,
+    config: &Config,
+    contract: Arc<dyn EntropyReader>,
+    network_id: u64,
+    rpc_metrics: Arc<RpcMetrics>,
+    dry_run: bool,
+) -> Result<(BlockchainState, Arc<History>)> {
+    let chain_config = config.get_chain_config(&CHAIN_ID.into())?;
+    let metrics = Arc::new(KeeperMetrics::default());
+    let history = Arc::new(History::new_in_memory().await?);
+    let chain_state = chain_state(config, contract, network_id, metrics.clone()).await?;
+    let keeper = keeper::keeper_writer(
+        &config.keeper,
+        &chain_config,
+        CHAIN_ID.into(),
+        network_id,
+        metrics.clone(),
+        rpc_metrics.clone(),
+    )?;
+    let mut keeper_config = config.keeper.clone();
+    keeper_config.dry_run = dry_run;
+    tokio::spawn(keeper::run_keeper_threads(
+        keeper_config,
+        keeper,
+        Arc::new(BlockWatcher::new(chain_config.geth_rpc_wss.clone())),
+        Some(chain.fee_manager_key.clone()),
+        None,
+        chain_config,
+        chain_state.clone(),
+        metrics,
+        history.clone(),
+        rpc_metrics,
+    ));
+    Ok((chain_state, history))
+}
+
+/// The request of `provider` with `sequence_number` in the history, once it reaches a final state
+/// matching `is_final`.
+async fn wait_for_request(
+    description: &str,
+    history: &Arc<History>,
+    provider: Address,
+    sequence_number: u64,
+    is_final: fn(&RequestEntryState) -> bool,
+) -> RequestStatus {
+    wait_for(description, || {
+        let history = history.clone();
+        async move {
+            let requests = history
+                .query()
+                .provider(provider)
+                .search(sequence_number.to_string())
+                .map_err(|e| anyhow!("{:?}", e))?
+                .execute()
+                .await?;
+            Ok(requests.into_iter().find(|r| is_final(&r.state)))
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_keeper_reveals_and_withdraws_fees() {
+    if std::env::var("FORTUNA_TEST_E2E").is_err() {
+        return;
+    }
+    let chain = TestChain::start().await.unwrap();
+    let config_file = chain.write_config().unwrap();
+    let config = register_provider(&chain, config_file.path().to_str().unwrap())
+        .await
+        .unwrap();
+    let chain_config = config.get_chain_config(&CHAIN_ID.into()).unwrap();
+    let provider = config.provider.address;
+
+    let rpc_metrics = Arc::new(RpcMetrics::new(Arc::new(RwLock::new(Registry::default()))).await);
+    let contract = Arc::new(
+        InstrumentedPythContract::from_config(&chain_config, CHAIN_ID.into(), rpc_metrics.clone())
+            .unwrap(),
+    );
+
+    // The requests are made before the keeper starts, so it fulfills them from the backlog and
+    // withdraws their fees right away.
+    let succeeding = chain.request(&*contract, provider, false).await.unwrap();
+    let failing = chain.request(&*contract, provider, true).await.unwrap();
+
+    let network_id = contract.get_network_id().await.unwrap().as_u64();
+    let contract: Arc<dyn EntropyReader> = contract;
+    let accrued_fees = contract
+        .get_provider_info(provider, None)
+        .await
+        .unwrap()
+        .accrued_fees_in_wei;
+    assert!(accrued_fees > 0);
+    let (chain_state, history) = spawn_keeper(
+        &chain,
+        &config,
+        contract.clone(),
+        network_id,
+        rpc_metrics,
+        false,
+    )
+    .await
+    .unwrap();
+
+    // Both requests are revealed, with the random number of the provider's hash chain.
+    let is_completed =
+        |state: &RequestEntryState| matches!(state, RequestEntryState::Completed { .. });
+    let check_revelation = |request: &RequestStatus| {
+        let RequestEntryState::Completed {
+            provider_random_number,
+            ..
+        } = request.state
+        else {
+            unreachable!()
+        };
+        assert_eq!(
+            provider_random_number,
+            chain_state.state.reveal(request.sequence).unwrap()
+        );
+    };
+
+    let request = wait_for_request(
+        "the successful callback",
+        &history,
+        provider,
+        succeeding,
+        is_completed,
+    )
+    .await;
+    check_revelation(&request);
+    assert!(matches!(
+        request.state,
+        RequestEntryState::Completed {
+            callback_failed: false,
+            ..
+        }
+    ));
+    assert!(contract
+        .get_request_v2(provider, succeeding)
+        .await
+        .unwrap()
+        .is_none());
+
+    // The failing callback is revealed, and the request stays open on chain.
+    let request = wait_for_request(
+        "the failed callback",
+        &history,
+        provider,
+        failing,
+        is_completed,
+    )
+    .await;
+    check_revelation(&request);
+    let RequestEntryState::Completed {
+        callback_failed,
+        callback_failure,
+        ..
+    } = &request.state
+    else {
+        unreachable!()
+    };
+    assert!(callback_failed);
+    let callback_failure = callback_failure.clone().unwrap();
+    assert_eq!(callback_failure.kind, CallbackFailureKind::Revert);
+    assert!(callback_failure
+        .reason
+        .unwrap_or_default()
+        .contains("Callback failed"));
+    assert_eq!(
+        contract
+            .get_request_v2(provider, failing)
+            .await
+            .unwrap()
+            .unwrap()
+            .callback_status,
+        RequestCallbackStatus::CallbackFailed
+    );
+
+    // The fee manager withdraws the fees of the provider to fund itself and the keeper.
+    wait_for("the fee withdrawal", || {
+        let contract = contract.clone();
+        async move {
+            let provider_info = contract.get_provider_info(provider, None).await?;
+            Ok((provider_info.accrued_fees_in_wei < accrued_fees).then_some(()))
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_keeper_dry_run() {
+    if std::env::var("FORTUNA_TEST_E2E").is_err() {
+        return;
+    }
+    let chain = TestChain::start().await.unwrap();
+    let config_file = chain.write_config().unwrap();
+    let config = register_provider(&chain, config_file.path().to_str().unwrap())
+        .await
+        .unwrap();
+    let chain_config = config.get_chain_config(&CHAIN_ID.into()).unwrap();
+    let provider = config.provider.address;
+
+    let rpc_metrics = Arc::new(RpcMetrics::new(Arc::new(RwLock::new(Registry::default()))).await);
+    let contract = Arc::new(
+        InstrumentedPythContract::from_config(&chain_config, CHAIN_ID.into(), rpc_metrics.clone())
+            .unwrap(),
+    );
+    let succeeding = chain.request(&*contract, provider, false).await.unwrap();
+    let failing = chain.request(&*contract, provider, true).await.unwrap();
+
+    let network_id = contract.get_network_id().await.unwrap().as_u64();
+    let contract: Arc<dyn EntropyReader> = contract;
+    let provider_info = contract.get_provider_info(provider, None).await.unwrap();
+    let (chain_state, history) = spawn_keeper(
+        &chain,
+        &config,
+        contract.clone(),
+        network_id,
+        rpc_metrics,
+        true,
+    )
+    .await
+    .unwrap();
+
+    // Both requests are simulated against the chain, and the failing callback is diagnosed.
+    let is_simulated =
+        |state: &RequestEntryState| matches!(state, RequestEntryState::Simulated { .. });
+    for (sequence_number, callback_reverts) in [(succeeding, false), (failing, true)] {
+        let request = wait_for_request(
+            "the simulated reveal",
+            &history,
+            provider,
+            sequence_number,
+            is_simulated,
+        )
+        .await;
+        let RequestEntryState::Simulated {
+            provider_random_number,
+            gas_estimate,
+            callback_failure,
+            ..
+        } = request.state
+        else {
+            unreachable!()
+        };
+        assert_eq!(
+            provider_random_number,
+            chain_state.state.reveal(request.sequence).unwrap()
+        );
+        assert!(gas_estimate > U256::zero());
+        assert_eq!(
+            callback_failure.map(|failure| failure.kind),
+            callback_reverts.then_some(CallbackFailureKind::Revert)
+        );
+    }
+
+    // No transaction was sent: the requests are still open and no fee was withdrawn.
+    for sequence_number in [succeeding, failing] {
+        let request = contract
+            .get_request_v2(provider, sequence_number)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            request.callback_status,
+            RequestCallbackStatus::CallbackNotStarted
+        );
+    }
+    assert_eq!(
+        contract
+            .get_provider_info(provider, None)
+            .await
+            .unwrap()
+            .accrued_fees_in_wei,
+        provider_info.accrued_fees_in_wei
+    );
+}
@@ -1,4 +1,5 @@
 pub mod eth_gas_oracle;
+pub mod gas_oracle;
 pub mod legacy_tx_middleware;
 pub mod nonce_manager;
 pub mod traced_client;
@@ -0,0 +1,306 @@
+use {
+    super::eth_gas_oracle::EthProviderOracle,
+    crate::config::{EthereumConfig, GasOracleConfig},
+    anyhow::{anyhow, Result},
+    axum::async_trait,
+    ethers::{
+        contract::abigen,
+        middleware::gas_oracle::{GasOracle as EthersGasOracle, GasOracleError},
+        providers::Middleware,
+        types::{Address, Bytes, U256},
+    },
+    std::{fmt, sync::Arc},
+};
+
+abigen!(
+    OpStackGasPriceOracle,
+    r#"[
+        function getL1Fee(bytes memory data) external view returns (uint256)
+    ]"#
+);
+
+abigen!(
+    ArbitrumNodeInterface,
+    r#"[
+        function gasEstimateL1Component(address to, bool contractCreation, bytes calldata data) external payable returns (uint64 gasEstimateForL1, uint256 baseFee, uint256 l1BaseFeeEstimate)
+    ]"#
+);
+
+/// Estimates the fees of the transactions sent to a chain.
+#[async_trait]
+pub trait GasOracle: Send + Sync {
+    /// The gas price of legacy transactions.
+    async fn gas_price(&self) -> Result<U256>;
+
+    /// The max fee per gas and the max priority fee per gas of EIP-1559 transactions.
+    async fn eip1559_fees(&self) -> Result<(U256, U256)>;
+
+    /// The fee charged on top of the gas used by a transaction calling `to` with `data`, e.g. the
+    /// L1 data fee of rollups.
+    async fn l1_fee(&self, _to: Address, _data: &Bytes) -> Result<U256> {
+        Ok(U256::zero())
+    }
+}
+
+/// Build the gas oracle of a chain from its configuration.
+pub fn from_config<M>(chain_config: &EthereumConfig, provider: M) -> Arc<dyn GasOracle>
+where
+    M: Middleware + Clone + 'static,
+    M::Error: 'static,
+{
+    let fee_history: Arc<dyn GasOracle> = Arc::new(EthProviderOracle::new(
+        provider.clone(),
+        chain_config.priority_fee_multiplier_pct,
+        chain_config.min_reward_samples,
+        chain_config.fee_estimation_past_blocks,
+        chain_config.fee_estimation_reward_percentile,
+        chain_config.eip1559_fee_estimation_default_priority_fee,
+        chain_config.eip1559_fee_estimation_priority_fee_trigger,
+        chain_config.eip1559_fee_estimation_threshold_max_change,
+        chain_config.surge_threshold_1,
+        chain_config.surge_threshold_2,
+        chain_config.surge_threshold_3,
+    ));
+    match &chain_config.gas_oracle {
+        GasOracleConfig::FeeHistory => fee_history,
+        GasOracleConfig::OpStack { gas_price_oracle } => Arc::new(OpStackOracle {
+            fees: fee_history,
+            gas_price_oracle: OpStackGasPriceOracle::new(*gas_price_oracle, Arc::new(provider)),
+        }),
+        GasOracleConfig::Arbitrum { node_interface } => Arc::new(ArbitrumOracle {
+            fees: fee_history,
+            node_interface: ArbitrumNodeInterface::new(*node_interface, Arc::new(provider)),
+        }),
+        GasOracleConfig::Static {
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        } => Arc::new(StaticOracle {
+            gas_price: U256::from(*gas_price),
+            max_fee_per_gas: U256::from(max_fee_per_gas.unwrap_or(*gas_price)),
+            max_priority_fee_per_gas: U256::from(max_priority_fee_per_gas.unwrap_or_default()),
+        }),
+    }
+}
+
+/// Estimate the cost (in wei) of a transaction calling `to` with `data` and consuming `gas_used`
+/// gas, including its L1 fee.
+pub async fn estimate_tx_cost(
+    oracle: &dyn GasOracle,
+    use_legacy_tx: bool,
+    gas_used: u128,
+    to: Address,
+    data: &Bytes,
+) -> Result<u128> {
+    let gas_price = if use_legacy_tx {
+        oracle
+            .gas_price()
+            .await
+            .map_err(|e| anyhow!("Failed to fetch gas price. error: {:?}", e))?
+    } else {
+        let (max_fee_per_gas, max_priority_fee_per_gas) = oracle.eip1559_fees().await?;
+        max_fee_per_gas + max_priority_fee_per_gas
+    };
+    let gas_price: u128 = gas_price
+        .try_into()
+        .map_err(|e| anyhow!("gas price doesn't fit into 128 bits. error: {:?}", e))?;
+    let l1_fee: u128 = oracle
+        .l1_fee(to, data)
+        .await
+        .map_err(|e| anyhow!("Failed to estimate the L1 fee. error: {:?}", e))?
+        .try_into()
+        .map_err(|e| anyhow!("L1 fee doesn't fit into 128 bits. error: {:?}", e))?;
+    Ok(gas_price * gas_used + l1_fee)
+}
+
+#[async_trait]
+impl<M: Middleware> GasOracle for EthProviderOracle<M>
+where
+    M::Error: 'static,
+{
+    async fn gas_price(&self) -> Result<U256> {
+        Ok(EthersGasOracle::fetch(self).await?)
+    }
+
+    async fn eip1559_fees(&self) -> Result<(U256, U256)> {
+        Ok(EthersGasOracle::estimate_eip1559_fees(self).await?)
+    }
+}
+
+/// Adds the L1 data fee of OP stack chains, as computed by their `GasPriceOracle` predeploy, to
+/// the fees of another oracle.
+pub struct OpStackOracle<M> {
+    fees: Arc<dyn GasOracle>,
+    gas_price_oracle: OpStackGasPriceOracle<M>,
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> GasOracle for OpStackOracle<M> {
+    async fn gas_price(&self) -> Result<U256> {
+        self.fees.gas_price().await
+    }
+
+    async fn eip1559_fees(&self) -> Result<(U256, U256)> {
+        self.fees.eip1559_fees().await
+    }
+
+    /// The predeploy expects a whole unsigned transaction, so the fee of the calldata alone is
+    /// slightly lower than the actual fee.
+    async fn l1_fee(&self, _to: Address, data: &Bytes) -> Result<U256> {
+        Ok(self
+            .gas_price_oracle
+            .get_l1_fee(data.clone())
+            .call()
+            .await?)
+    }
+}
+
+/// Adds the L1 component of the gas of Arbitrum chains, as estimated by their `NodeInterface`
+/// precompile, to the fees of another oracle. Arbitrum charges the L1 component as extra gas at
+/// the L2 base fee.
+pub struct ArbitrumOracle<M> {
+    fees: Arc<dyn GasOracle>,
+    node_interface: ArbitrumNodeInterface<M>,
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> GasOracle for ArbitrumOracle<M> {
+    async fn gas_price(&self) -> Result<U256> {
+        self.fees.gas_price().await
+    }
+
+    async fn eip1559_fees(&self) -> Result<(U256, U256)> {
+        self.fees.eip1559_fees().await
+    }
+
+    async fn l1_fee(&self, to: Address, data: &Bytes) -> Result<U256> {
+        let (gas_estimate_for_l1, base_fee, _) = self
+            .node_interface
+            .gas_estimate_l1_component(to, false, data.clone())
+            .call()
+            .await?;
+        Ok(U256::from(gas_estimate_for_l1) * base_fee)
+    }
+}
+
+/// Fixed fees from the configuration.
+pub struct StaticOracle {
+    gas_price: U256,
+    max_fee_per_gas: U256,
+    max_priority_fee_per_gas: U256,
+}
+
+#[async_trait]
+impl GasOracle for StaticOracle {
+    async fn gas_price(&self) -> Result<U256> {
+        Ok(self.gas_price)
+    }
+
+    async fn eip1559_fees(&self) -> Result<(U256, U256)> {
+        Ok((self.max_fee_per_gas, self.max_priority_fee_per_gas))
+    }
+}
+
+/// Exposes a [`GasOracle`] to the `GasOracleMiddleware` of ethers, which sets the fees of the
+/// transactions sent to the chain.
+#[derive(Clone)]
+pub struct GasOracleAdapter(pub Arc<dyn GasOracle>);
+
+impl fmt::Debug for GasOracleAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GasOracleAdapter")
+    }
+}
+
+#[async_trait]
+impl EthersGasOracle for GasOracleAdapter {
+    async fn fetch(&self) -> Result<U256, GasOracleError> {
+        self.0
+            .gas_price()
+            .await
+            .map_err(|e| GasOracleError::ProviderError(e.into()))
+    }
+
+    async fn estimate_eip1559_fees(&self) -> Result<(U256, U256), GasOracleError> {
+        self.0
+            .eip1559_fees()
+            .await
+            .map_err(|e| GasOracleError::ProviderError(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct MockL1FeeOracle;
+
+    #[async_trait]
+    impl GasOracle for MockL1FeeOracle {
+        async fn gas_price(&self) -> Result<U256> {
+            Ok(U256::from(7))
+        }
+
+        async fn eip1559_fees(&self) -> Result<(U256, U256)> {
+            Ok((U256::from(10), U256::from(2)))
+        }
+
+        async fn l1_fee(&self, _to: Address, data: &Bytes) -> Result<U256> {
+            Ok(U256::from(data.len() * 1_000))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_tx_cost() {
+        let data = Bytes::from(vec![1u8; 100]);
+        let to = Address::zero();
+        assert_eq!(
+            estimate_tx_cost(&MockL1FeeOracle, true, 50_000, to, &data)
+                .await
+                .unwrap(),
+            7 * 50_000 + 100_000
+        );
+        assert_eq!(
+            estimate_tx_cost(&MockL1FeeOracle, false, 50_000, to, &data)
+                .await
+                .unwrap(),
+            12 * 50_000 + 100_000
+        );
+
+        let oracle = StaticOracle {
+            gas_price: U256::from(5),
+            max_fee_per_gas: U256::from(5),
+            max_priority_fee_per_gas: U256::zero(),
+        };
+        assert_eq!(
+            estimate_tx_cost(&oracle, false, 50_000, to, &data)
+                .await
+                .unwrap(),
+            5 * 50_000
+        );
+    }
+
+    #[test]
+    fn test_gas_oracle_config() {
+        let config: GasOracleConfig = serde_yaml::from_str("type: op-stack").unwrap();
+        assert_eq!(
+            config,
+            GasOracleConfig::OpStack {
+                gas_price_oracle: "0x420000000000000000000000000000000000000F"
+                    .parse()
+                    .unwrap()
+            }
+        );
+        let config: GasOracleConfig =
+            serde_yaml::from_str("{type: static, gas_price: 1000, max_priority_fee_per_gas: 10}")
+                .unwrap();
+        assert_eq!(
+            config,
+            GasOracleConfig::Static {
+                gas_price: 1000,
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: Some(10),
+            }
+        );
+    }
+}
@@ -12,7 +12,8 @@ use {
         providers::{MiddlewareError, ProviderError},
         signers::Signer,
         types::{
-            transaction::eip2718::TypedTransaction, TransactionReceipt, TransactionRequest, U256,
+            transaction::eip2718::TypedTransaction, Address, BlockNumber, TransactionReceipt,
+            TransactionRequest, U256,
         },
     },
     std::{
@@ -53,6 +54,14 @@ impl EscalationPolicy {
         )
     }
 
+    /// The fee multiplier of the replacement of a stuck transaction. The stuck transaction may have
+    /// been sent at the capped fee, so the replacement pays one more escalation step on top of it.
+    pub fn replacement_fee_multiplier_pct(&self) -> u64 {
+        self.fee_multiplier_cap_pct
+            .saturating_mul(self.fee_multiplier_pct)
+            / 100
+    }
+
     fn apply_escalation_policy(
         &self,
         num_retries: u64,
@@ -104,34 +113,6 @@ pub async fn send_and_confirm<A: Middleware>(contract_call: ContractCall<A, ()>)
     Ok(())
 }
 
-/// Estimate the cost (in wei) of a transaction consuming gas_used gas.
-pub async fn estimate_tx_cost<T: Middleware + 'static>(
-    middleware: Arc<T>,
-    use_legacy_tx: bool,
-    gas_used: u128,
-) -> Result<u128> {
-    let gas_price: u128 = if use_legacy_tx {
-        middleware
-            .get_gas_price()
-            .await
-            .map_err(|e| anyhow!("Failed to fetch gas price. error: {:?}", e))?
-            .try_into()
-            .map_err(|e| anyhow!("gas price doesn't fit into 128 bits. error: {:?}", e))?
-    } else {
-        // This is not obvious but the implementation of estimate_eip1559_fees in ethers.rs
-        // for a middleware that has a GasOracleMiddleware inside is to ignore the passed-in callback
-        // and use whatever the gas oracle returns.
-        let (max_fee_per_gas, max_priority_fee_per_gas) =
-            middleware.estimate_eip1559_fees(None).await?;
-
-        (max_fee_per_gas + max_priority_fee_per_gas)
-            .try_into()
-            .map_err(|e| anyhow!("gas price doesn't fit into 128 bits. error: {:?}", e))?
-    };
-
-    Ok(gas_price * gas_used)
-}
-
 /// Submit a transaction, retrying on failure according to a configurable backoff policy.
 /// The transaction is retried with exponentially increasing delay between retries, and
 /// similarly escalating gas and fee multipliers.
@@ -313,33 +294,35 @@ pub async fn submit_tx<T: Middleware + NonceManaged + 'static>(
             backoff::Error::transient(SubmitTxError::SubmissionError(transaction.clone(), e))
         })?;
 
-    let reset_nonce = || {
-        client.reset();
-    };
+    let sender = transaction.from().copied().unwrap_or_default();
+    let reset_nonce = || reset_nonce_if_idle(&*client, sender);
 
-    let pending_receipt = timeout(
+    let Ok(pending_receipt) = timeout(
         Duration::from_secs(TX_CONFIRMATION_TIMEOUT_SECS),
         pending_tx,
     )
     .await
-    .map_err(|_| {
+    else {
         // Tx can get stuck in mempool without any progress if the nonce is too high
         // in this case ethers internal polling will not reduce the number of retries
         // and keep retrying indefinitely. So we set a manual timeout here and reset the nonce.
-        reset_nonce();
-        backoff::Error::transient(SubmitTxError::ConfirmationTimeout(transaction.clone()))
-    })?;
+        reset_nonce().await;
+        return Err(backoff::Error::transient(
+            SubmitTxError::ConfirmationTimeout(transaction.clone()),
+        ));
+    };
 
-    let receipt = pending_receipt
-        .map_err(|e| {
-            backoff::Error::transient(SubmitTxError::ConfirmationError(transaction.clone(), e))
-        })?
-        .ok_or_else(|| {
-            // RPC may not return an error on tx submission if the nonce is too high.
-            // But we will never get a receipt. So we reset the nonce manager to get the correct nonce.
-            reset_nonce();
-            backoff::Error::transient(SubmitTxError::ConfirmationTimeout(transaction.clone()))
-        })?;
+    let Some(receipt) = pending_receipt.map_err(|e| {
+        backoff::Error::transient(SubmitTxError::ConfirmationError(transaction.clone(), e))
+    })?
+    else {
+        // RPC may not return an error on tx submission if the nonce is too high.
+        // But we will never get a receipt. So we reset the nonce manager to get the correct nonce.
+        reset_nonce().await;
+        return Err(backoff::Error::transient(
+            SubmitTxError::ConfirmationTimeout(transaction.clone()),
+        ));
+    };
 
     if receipt.status == Some(U64::from(0)) {
         return Err(backoff::Error::transient(SubmitTxError::ReceiptError(
@@ -351,6 +334,27 @@ pub async fn submit_tx<T: Middleware + NonceManaged + 'static>(
     Ok(receipt)
 }
 
+/// Reset the nonce manager of `client` so that the next transactions of `address` use the nonce
+/// of the chain. The manager is only reset if the pending nonce of `address` on the chain equals
+/// its mined nonce: with transactions still pending, the next transaction would reuse the nonce
+/// of one of them.
+async fn reset_nonce_if_idle<T: Middleware + NonceManaged>(client: &T, address: Address) {
+    let nonces = futures::future::try_join(
+        client.get_transaction_count(address, Some(BlockNumber::Latest.into())),
+        client.get_transaction_count(address, Some(BlockNumber::Pending.into())),
+    )
+    .await;
+    match nonces {
+        Ok((mined, pending)) if mined == pending => client.reset(),
+        Ok((mined, pending)) => tracing::info!(
+            "Not resetting the nonce manager: the transactions with nonces {} to {} are pending",
+            mined,
+            pending.saturating_sub(1.into())
+        ),
+        Err(e) => tracing::warn!("Not resetting the nonce manager: {:?}", e),
+    }
+}
+
 /// Transfer funds from the signing wallet to the destination address.
 pub async fn submit_transfer_tx(
     contract: Arc<InstrumentedSignablePythContract>,
@@ -396,3 +400,57 @@ pub async fn submit_transfer_tx(
     tracing::info!("Transfer transaction confirmed: {:?}", tx_hash);
     Ok(tx_hash)
 }
+
+/// Cancel the pending transaction of the signing wallet with `nonce` by replacing it with a
+/// zero-value transfer to the wallet itself. `transaction` carries the fees of the replacement,
+/// which must be higher than the fees of the pending transaction.
+/// Once the cancellation is mined and no other transaction of the wallet is pending, the nonce
+/// manager is reset so that the following transactions use the nonces of the chain. It is not
+/// reset if the cancellation does not get mined, as the following nonces would still be blocked.
+pub async fn submit_cancel_tx(
+    contract: Arc<InstrumentedSignablePythContract>,
+    nonce: u64,
+    mut transaction: TypedTransaction,
+) -> Result<ethers::types::H256> {
+    let wallet_address = contract.wallet().address();
+    transaction.set_from(wallet_address);
+    transaction.set_to(wallet_address);
+    transaction.set_value(U256::zero());
+    transaction.set_nonce(nonce);
+
+    tracing::info!(
+        "Cancelling transaction with nonce {}: {:?}",
+        nonce,
+        transaction
+    );
+
+    // Bypass the nonce manager, which would resubmit the transaction with the next nonce of the
+    // chain if the submission fails.
+    let client = contract.client();
+    let signer = client.inner().inner().inner();
+    // A plain transfer costs 21000 gas on L1s, but rollups like Arbitrum charge more for it.
+    let gas = signer
+        .estimate_gas(&transaction, None)
+        .await
+        .map_err(|e| anyhow!("Error estimating the gas of the cancellation: {:?}", e))?;
+    transaction.set_gas(gas);
+    let pending_tx = signer
+        .send_transaction(transaction, None)
+        .await
+        .map_err(|e| anyhow!("Error submitting the cancellation: {:?}", e))?;
+
+    let tx_receipt = timeout(
+        Duration::from_secs(TX_CONFIRMATION_TIMEOUT_SECS),
+        pending_tx,
+    )
+    .await
+    .map_err(|_| anyhow!("Cancellation confirmation timeout"))?
+    .map_err(|e| anyhow!("Cancellation confirmation error: {:?}", e))?
+    .ok_or_else(|| anyhow!("Cancellation, probably dropped from mempool"))?;
+
+    reset_nonce_if_idle(&*client, wallet_address).await;
+
+    let tx_hash = tx_receipt.transaction_hash;
+    tracing::info!("Cancellation confirmed: {:?}", tx_hash);
+    Ok(tx_hash)
+}
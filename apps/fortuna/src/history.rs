@@ -1,6 +1,10 @@
 use {
-    crate::api::{ChainId, NetworkId, StateTag},
+    crate::{
+        api::{ChainId, NetworkId, StateTag},
+        config::Commitment,
+    },
     anyhow::Result,
+    base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _},
     chrono::DateTime,
     ethers::{
         core::utils::hex::ToHexExt,
@@ -8,14 +12,29 @@ use {
         types::{Address, Bytes, U256},
         utils::keccak256,
     },
-    serde::Serialize,
+    futures::{stream, Stream},
+    prometheus_client::{
+        metrics::{counter::Counter, gauge::Gauge},
+        registry::Registry,
+    },
+    serde::{Deserialize, Serialize},
     serde_with::serde_as,
-    sqlx::{any::AnyPoolOptions, migrate, AnyPool, FromRow},
+    sqlx::{
+        any::{AnyArguments, AnyPoolOptions},
+        migrate,
+        query::QueryAs,
+        Any, AnyPool, FromRow,
+    },
     std::{str::FromStr, sync::Arc},
-    tokio::{spawn, sync::mpsc},
+    tokio::{
+        spawn,
+        sync::{mpsc, RwLock},
+    },
     utoipa::ToSchema,
 };
 
+pub mod stats;
+
 const LOG_RETURN_LIMIT: u64 = 1000;
 const ONE_DAY: u64 = 60 * 60 * 24;
 const ONE_HOUR: u64 = 60 * 60;
@@ -57,6 +76,8 @@ pub enum RequestEntryState {
         #[schema(example = "567890", value_type = String)]
         #[serde(with = "crate::serde::u32")]
         callback_gas_used: u32,
+        /// Why the callback failed, decoded from the callback return value. Only set if the callback failed.
+        callback_failure: Option<CallbackFailure>,
     },
     Failed {
         reason: String,
@@ -65,6 +86,81 @@ pub enum RequestEntryState {
         #[serde_as(as = "Option<serde_with::hex::Hex>")]
         provider_random_number: Option<[u8; 32]>,
     },
+    /// The keeper runs in dry-run mode, and simulated the reveal transaction instead of sending it.
+    Simulated {
+        /// The provider contribution to the random number.
+        #[schema(example = "a905ab56567d31a7fda38ed819d97bc257f3ebe385fc5c72ce226d3bb855f0fe")]
+        #[serde_as(as = "serde_with::hex::Hex")]
+        provider_random_number: [u8; 32],
+        /// The combined random number the reveal would have generated.
+        #[schema(example = "a905ab56567d31a7fda38ed819d97bc257f3ebe385fc5c72ce226d3bb855f0fe")]
+        #[serde_as(as = "serde_with::hex::Hex")]
+        combined_random_number: [u8; 32],
+        /// The estimated gas of the reveal transaction.
+        #[schema(example = "567890", value_type = String)]
+        #[serde(with = "crate::serde::u256")]
+        gas_estimate: U256,
+        /// The estimated cost of the reveal transaction in the smallest unit of the chain,
+        /// including the L1 fee of rollups.
+        #[schema(example = "1234567890000", value_type = String)]
+        #[serde(with = "crate::serde::u256")]
+        estimated_fee: U256,
+        /// Whether the callback would fail.
+        callback_failed: bool,
+        /// The return value of the simulated callback, truncated to 256 bytes.
+        #[schema(example = "0x", value_type = String)]
+        callback_return_value: Bytes,
+        /// The estimated gas of the callback.
+        #[schema(example = "567890", value_type = String)]
+        #[serde(with = "crate::serde::u32")]
+        callback_gas_used: u32,
+        /// Why the callback would fail, decoded from the callback return value.
+        callback_failure: Option<CallbackFailure>,
+    },
+}
+
+/// The class of a callback failure, which tells whether revealing the request again may succeed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CallbackFailureKind {
+    /// The callback used all the gas it was given. It may succeed with more gas.
+    OutOfGas,
+    /// The callback reverted with an error, which usually happens again.
+    Revert,
+    /// The callback reverted without an error, e.g. with `revert()`.
+    Other,
+}
+
+impl CallbackFailureKind {
+    pub const ALL: [CallbackFailureKind; 3] = [Self::OutOfGas, Self::Revert, Self::Other];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OutOfGas => "out-of-gas",
+            Self::Revert => "revert",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl FromStr for CallbackFailureKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown callback failure kind: {}", s))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema, PartialEq)]
+pub struct CallbackFailure {
+    pub kind: CallbackFailureKind,
+    /// The decoded error, e.g. `Error("insufficient balance")`, `Panic(0x11: arithmetic overflow)`
+    /// or a custom error of the consumer contract if its ABI is configured.
+    #[schema(example = "Error(\"insufficient balance\")")]
+    pub reason: Option<String>,
 }
 
 #[serde_as]
@@ -135,6 +231,9 @@ struct RequestRow {
     callback_failed: Option<i64>,
     callback_return_value: Option<String>,
     callback_gas_used: Option<String>,
+    callback_failure_kind: Option<String>,
+    callback_failure_reason: Option<String>,
+    estimated_fee: Option<String>,
 }
 
 impl TryFrom<RequestRow> for RequestStatus {
@@ -158,6 +257,28 @@ impl TryFrom<RequestRow> for RequestStatus {
             .parse::<u32>()
             .map_err(|_| anyhow::anyhow!("Failed to parse gas limit"))?;
 
+        //  Sqlx::Any doesn't support boolean types, so we need to convert from integer
+        //  https://github.com/launchbadge/sqlx/issues/2778
+        let callback_failed = row.callback_failed.unwrap_or(0) == 1;
+        let callback_return_value = row
+            .callback_return_value
+            .map(|s| s.parse::<Bytes>().unwrap_or_default())
+            .unwrap_or_default();
+        let callback_gas_used = || {
+            row.callback_gas_used
+                .as_deref()
+                .unwrap_or_default()
+                .parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("Failed to parse callback_gas_used"))
+        };
+        let callback_failure = match row.callback_failure_kind {
+            Some(kind) => Some(CallbackFailure {
+                kind: kind.parse()?,
+                reason: row.callback_failure_reason,
+            }),
+            None => None,
+        };
+
         let state = match row.state.as_str() {
             "Pending" => RequestEntryState::Pending,
             "Completed" => {
@@ -189,18 +310,34 @@ impl TryFrom<RequestRow> for RequestStatus {
                         &user_random_number,
                         &provider_random_number,
                     ),
-                    //  Sqlx::Any doesn't support boolean types, so we need to convert from integer
-                    //  https://github.com/launchbadge/sqlx/issues/2778
-                    callback_failed: row.callback_failed.unwrap_or(0) == 1,
-                    callback_return_value: row
-                        .callback_return_value
-                        .map(|s| s.parse::<Bytes>().unwrap_or_default())
-                        .unwrap_or_default(),
-                    callback_gas_used: row
-                        .callback_gas_used
-                        .unwrap_or_default()
-                        .parse::<u32>()
-                        .map_err(|_| anyhow::anyhow!("Failed to parse callback_gas_used"))?,
+                    callback_failed,
+                    callback_return_value,
+                    callback_gas_used: callback_gas_used()?,
+                    callback_failure,
+                }
+            }
+            "Simulated" => {
+                let provider_random_number = row.provider_random_number.ok_or(anyhow::anyhow!(
+                    "Provider random number is missing for simulated request"
+                ))?;
+                let provider_random_number: [u8; 32] =
+                    hex::FromHex::from_hex(provider_random_number)?;
+                let gas_estimate = U256::from_dec_str(&row.gas_used.unwrap_or_default())
+                    .map_err(|_| anyhow::anyhow!("Failed to parse gas estimate"))?;
+                let estimated_fee = U256::from_dec_str(&row.estimated_fee.unwrap_or_default())
+                    .map_err(|_| anyhow::anyhow!("Failed to parse estimated fee"))?;
+                RequestEntryState::Simulated {
+                    provider_random_number,
+                    combined_random_number: Self::generate_combined_random_number(
+                        &user_random_number,
+                        &provider_random_number,
+                    ),
+                    gas_estimate,
+                    estimated_fee,
+                    callback_failed,
+                    callback_return_value,
+                    callback_gas_used: callback_gas_used()?,
+                    callback_failure,
                 }
             }
             "Failed" => RequestEntryState::Failed {
@@ -243,10 +380,171 @@ impl From<RequestRow> for Option<RequestStatus> {
     }
 }
 
+/// A stuck transaction of a keeper wallet that was replaced by a cancellation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionReplacement {
+    pub chain_id: ChainId,
+    pub network_id: NetworkId,
+    /// The keeper wallet that sent the stuck transaction.
+    pub address: Address,
+    pub nonce: u64,
+    /// The hash of the cancellation.
+    pub transaction_hash: TxHash,
+    /// When the transaction was first seen pending.
+    pub pending_since: DateTime<chrono::Utc>,
+    pub replaced_at: DateTime<chrono::Utc>,
+}
+
+#[derive(FromRow)]
+struct TransactionReplacementRow {
+    chain_id: String,
+    network_id: i64,
+    address: String,
+    nonce: i64,
+    transaction_hash: String,
+    pending_since: i64,
+    replaced_at: i64,
+}
+
+impl TryFrom<TransactionReplacementRow> for TransactionReplacement {
+    type Error = anyhow::Error;
+
+    fn try_from(row: TransactionReplacementRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            chain_id: row.chain_id,
+            network_id: row.network_id as NetworkId,
+            address: row.address.parse()?,
+            nonce: row.nonce as u64,
+            transaction_hash: row.transaction_hash.parse()?,
+            pending_since: DateTime::from_timestamp_millis(row.pending_since)
+                .ok_or(anyhow::anyhow!("Invalid pending_since timestamp"))?,
+            replaced_at: DateTime::from_timestamp_millis(row.replaced_at)
+                .ok_or(anyhow::anyhow!("Invalid replaced_at timestamp"))?,
+        })
+    }
+}
+
+#[derive(FromRow)]
+struct CommitmentRow {
+    original_commitment_sequence_number: i64,
+    seed: String,
+    chain_length: i64,
+}
+
+impl TryFrom<CommitmentRow> for Commitment {
+    type Error = anyhow::Error;
+
+    fn try_from(row: CommitmentRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            seed: hex::FromHex::from_hex(row.seed)?,
+            chain_length: row.chain_length as u64,
+            original_commitment_sequence_number: row.original_commitment_sequence_number as u64,
+        })
+    }
+}
+
+/// Metrics of the writes to the history database.
+#[derive(Default)]
+pub struct HistoryMetrics {
+    /// The number of request updates waiting to be written.
+    pub write_queue_length: Gauge,
+    /// The number of request updates dropped because the write queue was full.
+    pub dropped_writes: Counter,
+    /// The number of request updates that failed to be written.
+    pub failed_writes: Counter,
+    /// The number of requests deleted by the retention policy.
+    pub pruned_requests: Counter,
+}
+
+/// The database behind an [`AnyPool`]. The Postgres columns are typed, but the Any driver of sqlx
+/// only exchanges the types shared with SQLite, so the timestamps are converted to Unix
+/// milliseconds, the decimals to strings and the booleans to integers on the way in and out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    fn of(pool: &AnyPool) -> Self {
+        if History::is_postgres_url(pool.connect_options().database_url.as_str()) {
+            Backend::Postgres
+        } else {
+            Backend::Sqlite
+        }
+    }
+
+    /// The timestamp of the parameter `param`, bound as Unix milliseconds.
+    fn timestamp(self, param: &str) -> String {
+        match self {
+            Backend::Sqlite => param.to_string(),
+            Backend::Postgres => format!("to_timestamp({param} / 1000.0)"),
+        }
+    }
+
+    /// The timestamp `column` in Unix milliseconds.
+    fn millis(self, column: &str) -> String {
+        match self {
+            Backend::Sqlite => column.to_string(),
+            Backend::Postgres => format!("CAST(EXTRACT(EPOCH FROM {column}) * 1000 AS BIGINT)"),
+        }
+    }
+
+    /// The decimal of the parameter `param`, bound as a string.
+    fn decimal(self, param: &str) -> String {
+        match self {
+            Backend::Sqlite => param.to_string(),
+            Backend::Postgres => format!("CAST({param} AS NUMERIC)"),
+        }
+    }
+
+    /// The decimal `column` as a number. SQLite stores the decimals as strings.
+    fn number(self, column: &str) -> String {
+        match self {
+            Backend::Sqlite => format!("CAST({column} AS NUMERIC)"),
+            Backend::Postgres => column.to_string(),
+        }
+    }
+
+    /// The decimal `column` as a string.
+    fn decimal_string(self, column: &str) -> String {
+        match self {
+            Backend::Sqlite => column.to_string(),
+            Backend::Postgres => format!("CAST({column} AS TEXT)"),
+        }
+    }
+
+    /// The boolean `column` as 0 or 1.
+    fn flag(self, column: &str) -> String {
+        match self {
+            Backend::Sqlite => column.to_string(),
+            Backend::Postgres => {
+                format!("CASE WHEN {column} THEN 1 WHEN NOT {column} THEN 0 END")
+            }
+        }
+    }
+
+    /// The columns of `request` as read into a [`RequestRow`].
+    fn request_columns(self) -> String {
+        format!(
+            "chain_id, network_id, provider, sequence, {} AS created_at, {} AS last_updated_at, state, request_block_number, request_tx_hash, user_random_number, sender, {} AS gas_limit, reveal_block_number, reveal_tx_hash, provider_random_number, {} AS gas_used, info, {} AS callback_failed, callback_return_value, {} AS callback_gas_used, callback_failure_kind, callback_failure_reason, {} AS estimated_fee",
+            self.millis("created_at"),
+            self.millis("last_updated_at"),
+            self.decimal_string("gas_limit"),
+            self.decimal_string("gas_used"),
+            self.flag("callback_failed"),
+            self.decimal_string("callback_gas_used"),
+            self.decimal_string("estimated_fee"),
+        )
+    }
+}
+
 pub struct History {
     pool: AnyPool,
+    backend: Backend,
     write_queue: mpsc::Sender<RequestStatus>,
     _writer_thread: Arc<tokio::task::JoinHandle<()>>,
+    pub metrics: Arc<HistoryMetrics>,
 }
 
 impl History {
@@ -275,7 +573,8 @@ impl History {
         Self::new_with_pool(pool).await
     }
 
-    /// Create a History instance with production DB parameters
+    /// Create a History instance with production DB parameters. Postgres databases use their own
+    /// migrations with typed columns, any other URL is migrated as a SQLite database.
     pub async fn new_with_url(url: &str) -> Result<Self> {
         sqlx::any::install_default_drivers();
         let pool = AnyPoolOptions::new()
@@ -287,38 +586,89 @@ impl History {
             .max_lifetime(std::time::Duration::from_secs(ONE_DAY))
             .connect(url)
             .await?;
-        let migrator = migrate!("./migrations");
+        let migrator = if Self::is_postgres_url(url) {
+            migrate!("./migrations_postgres")
+        } else {
+            migrate!("./migrations")
+        };
         migrator.run(&pool).await?;
         Self::new_with_pool(pool).await
     }
+
+    fn is_postgres_url(url: &str) -> bool {
+        url.starts_with("postgres://") || url.starts_with("postgresql://")
+    }
+
     pub async fn new_with_pool(pool: AnyPool) -> Result<Self> {
         let (sender, mut receiver) = mpsc::channel(Self::MAX_WRITE_QUEUE);
         let pool_write_connection = pool.clone();
+        let metrics = Arc::new(HistoryMetrics::default());
+        let writer_metrics = metrics.clone();
         let writer_thread = spawn(async move {
             while let Some(log) = receiver.recv().await {
-                Self::update_request_status(&pool_write_connection, log).await;
+                writer_metrics.write_queue_length.set(receiver.len() as i64);
+                if !Self::update_request_status(&pool_write_connection, log).await {
+                    writer_metrics.failed_writes.inc();
+                }
             }
         });
         Ok(Self {
+            backend: Backend::of(&pool),
             pool,
             write_queue: sender,
             _writer_thread: Arc::new(writer_thread),
+            metrics,
         })
     }
 
-    async fn update_request_status(pool: &AnyPool, new_status: RequestStatus) {
+    /// Register the metrics of this instance in `metrics_registry`.
+    pub async fn register_metrics(&self, metrics_registry: Arc<RwLock<Registry>>) {
+        let mut guard = metrics_registry.write().await;
+        let sub_registry = guard.sub_registry_with_prefix("history");
+        sub_registry.register(
+            "write_queue_length",
+            "Number of request updates waiting to be written to the database",
+            self.metrics.write_queue_length.clone(),
+        );
+        sub_registry.register(
+            "dropped_writes",
+            "Number of request updates dropped because the write queue was full",
+            self.metrics.dropped_writes.clone(),
+        );
+        sub_registry.register(
+            "failed_writes",
+            "Number of request updates that failed to be written to the database",
+            self.metrics.failed_writes.clone(),
+        );
+        sub_registry.register(
+            "pruned_requests",
+            "Number of requests deleted by the retention policy",
+            self.metrics.pruned_requests.clone(),
+        );
+    }
+
+    /// Write `new_status` to the database. Returns false if the write failed.
+    async fn update_request_status(pool: &AnyPool, new_status: RequestStatus) -> bool {
         let sequence = new_status.sequence as i64;
         let chain_id = new_status.chain_id;
         let network_id = new_status.network_id as i64;
         let request_tx_hash: String = new_status.request_tx_hash.encode_hex();
         let provider: String = new_status.provider.encode_hex();
         let gas_limit = new_status.gas_limit.to_string();
+        let backend = Backend::of(pool);
+        let last_updated_at = backend.timestamp("$2");
         let result = match new_status.state {
             RequestEntryState::Pending => {
                 let block_number = new_status.request_block_number as i64;
                 let sender: String = new_status.sender.encode_hex();
                 let user_random_number: String = new_status.user_random_number.encode_hex();
-                sqlx::query("INSERT INTO request(chain_id, network_id, provider, sequence, created_at, last_updated_at, state, request_block_number, request_tx_hash, user_random_number, sender, gas_limit) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)")
+                let sql = format!(
+                    "INSERT INTO request(chain_id, network_id, provider, sequence, created_at, last_updated_at, state, request_block_number, request_tx_hash, user_random_number, sender, gas_limit) VALUES ($1, $2, $3, $4, {}, {}, $7, $8, $9, $10, $11, {})",
+                    backend.timestamp("$5"),
+                    backend.timestamp("$6"),
+                    backend.decimal("$12"),
+                );
+                sqlx::query(&sql)
                     .bind(chain_id.clone())
                     .bind(network_id)
                     .bind(provider.clone())
@@ -343,17 +693,24 @@ impl History {
                 callback_failed,
                 callback_return_value,
                 callback_gas_used,
+                callback_failure,
             } => {
                 let reveal_block_number = reveal_block_number as i64;
                 let reveal_tx_hash: String = reveal_tx_hash.encode_hex();
                 let provider_random_number: String = provider_random_number.encode_hex();
                 let gas_used: String = gas_used.to_string();
-                //  Sqlx::Any doesn't support boolean types, so we need to convert to integer
-                //  https://github.com/launchbadge/sqlx/issues/2778
-                let callback_failed: i64 = if callback_failed { 1 } else { 0 };
                 let callback_return_value: String = callback_return_value.encode_hex();
                 let callback_gas_used: String = callback_gas_used.to_string();
-                let result = sqlx::query("UPDATE request SET state = $1, last_updated_at = $2, reveal_block_number = $3, reveal_tx_hash = $4, provider_random_number = $5, gas_used = $6, callback_failed = $7, callback_return_value = $8, callback_gas_used = $9 WHERE network_id = $10 AND sequence = $11 AND provider = $12 AND request_tx_hash = $13")
+                let (callback_failure_kind, callback_failure_reason) = match callback_failure {
+                    Some(failure) => (Some(failure.kind.as_str()), failure.reason),
+                    None => (None, None),
+                };
+                let sql = format!(
+                    "UPDATE request SET state = $1, last_updated_at = {last_updated_at}, reveal_block_number = $3, reveal_tx_hash = $4, provider_random_number = $5, gas_used = {}, callback_failed = $7, callback_return_value = $8, callback_gas_used = {}, callback_failure_kind = $10, callback_failure_reason = $11 WHERE network_id = $12 AND sequence = $13 AND provider = $14 AND request_tx_hash = $15",
+                    backend.decimal("$6"),
+                    backend.decimal("$9"),
+                );
+                let result = sqlx::query(&sql)
                     .bind("Completed")
                     .bind(new_status.last_updated_at.timestamp_millis())
                     .bind(reveal_block_number)
@@ -363,6 +720,8 @@ impl History {
                     .bind(callback_failed)
                     .bind(callback_return_value)
                     .bind(callback_gas_used)
+                    .bind(callback_failure_kind)
+                    .bind(callback_failure_reason)
                     .bind(network_id)
                     .bind(sequence)
                     .bind(provider.clone())
@@ -382,7 +741,10 @@ impl History {
             } => {
                 let provider_random_number: Option<String> = provider_random_number
                     .map(|provider_random_number| provider_random_number.encode_hex());
-                sqlx::query("UPDATE request SET state = $1, last_updated_at = $2, info = $3, provider_random_number = $4 WHERE network_id = $5 AND sequence = $6 AND provider = $7 AND request_tx_hash = $8 AND state = 'Pending'")
+                let sql = format!(
+                    "UPDATE request SET state = $1, last_updated_at = {last_updated_at}, info = $3, provider_random_number = $4 WHERE network_id = $5 AND sequence = $6 AND provider = $7 AND request_tx_hash = $8 AND state = 'Pending'"
+                );
+                sqlx::query(&sql)
                     .bind("Failed")
                     .bind(new_status.last_updated_at.timestamp_millis())
                     .bind(reason)
@@ -394,53 +756,351 @@ impl History {
                     .execute(pool)
                     .await
             }
+            RequestEntryState::Simulated {
+                provider_random_number,
+                combined_random_number: _,
+                gas_estimate,
+                estimated_fee,
+                callback_failed,
+                callback_return_value,
+                callback_gas_used,
+                callback_failure,
+            } => {
+                let provider_random_number: String = provider_random_number.encode_hex();
+                let gas_estimate: String = gas_estimate.to_string();
+                let estimated_fee: String = estimated_fee.to_string();
+                let callback_return_value: String = callback_return_value.encode_hex();
+                let callback_gas_used: String = callback_gas_used.to_string();
+                let (callback_failure_kind, callback_failure_reason) = match callback_failure {
+                    Some(failure) => (Some(failure.kind.as_str()), failure.reason),
+                    None => (None, None),
+                };
+                let sql = format!(
+                    "UPDATE request SET state = $1, last_updated_at = {last_updated_at}, provider_random_number = $3, gas_used = {}, estimated_fee = {}, callback_failed = $6, callback_return_value = $7, callback_gas_used = {}, callback_failure_kind = $9, callback_failure_reason = $10 WHERE network_id = $11 AND sequence = $12 AND provider = $13 AND request_tx_hash = $14 AND state = 'Pending'",
+                    backend.decimal("$4"),
+                    backend.decimal("$5"),
+                    backend.decimal("$8"),
+                );
+                sqlx::query(&sql)
+                    .bind("Simulated")
+                    .bind(new_status.last_updated_at.timestamp_millis())
+                    .bind(provider_random_number)
+                    .bind(gas_estimate)
+                    .bind(estimated_fee)
+                    .bind(callback_failed)
+                    .bind(callback_return_value)
+                    .bind(callback_gas_used)
+                    .bind(callback_failure_kind)
+                    .bind(callback_failure_reason)
+                    .bind(network_id)
+                    .bind(sequence)
+                    .bind(provider)
+                    .bind(request_tx_hash)
+                    .execute(pool)
+                    .await
+            }
         };
-        if let Err(e) = result {
-            match e.as_database_error() {
+        match result {
+            Ok(_) => true,
+            Err(e) => match e.as_database_error() {
                 Some(db_error) if db_error.is_unique_violation() => {
                     tracing::info!(
                         "Failed to insert request, request already exists: Chain ID: {}, Sequence: {}",
                         network_id,
                         sequence
                     );
+                    true
                 }
                 _ => {
                     tracing::error!("Failed to update request status: {}", e);
+                    false
                 }
-            }
+            },
         }
     }
 
+    /// Queue an update of a request to be written to the database. The update is dropped if the
+    /// write queue is full, which is tracked by the `dropped_writes` metric.
     pub fn add(&self, log: &RequestStatus) {
         if let Err(e) = self.write_queue.try_send(log.clone()) {
             tracing::error!("Failed to send log to write queue: {}", e);
+            self.metrics.dropped_writes.inc();
         }
     }
 
-    pub fn query(&self) -> RequestQueryBuilder<'_> {
-        RequestQueryBuilder::new(&self.pool)
+    pub fn query(&self) -> RequestQueryBuilder {
+        RequestQueryBuilder::new(self.pool.clone())
+    }
+
+    /// Create or extend the lease of `replica` on `chain_id` until `expires_at`.
+    pub async fn renew_replica_lease(
+        &self,
+        chain_id: &ChainId,
+        replica: &str,
+        expires_at: DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let sql = format!(
+            "INSERT INTO replica_lease(chain_id, replica, expires_at) VALUES ($1, $2, {}) ON CONFLICT (chain_id, replica) DO UPDATE SET expires_at = excluded.expires_at",
+            self.backend.timestamp("$3")
+        );
+        sqlx::query(&sql)
+            .bind(chain_id.clone())
+            .bind(replica.to_string())
+            .bind(expires_at.timestamp_millis())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The replicas on `chain_id` whose lease has not expired at `now`, in ascending order.
+    pub async fn live_replicas(
+        &self,
+        chain_id: &ChainId,
+        now: DateTime<chrono::Utc>,
+    ) -> Result<Vec<String>> {
+        let sql = format!(
+            "SELECT replica FROM replica_lease WHERE chain_id = $1 AND expires_at > {} ORDER BY replica",
+            self.backend.timestamp("$2")
+        );
+        let replicas = sqlx::query_scalar::<_, String>(&sql)
+            .bind(chain_id.clone())
+            .bind(now.timestamp_millis())
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(replicas)
+    }
+
+    /// Record the replacement of a stuck transaction of a keeper wallet.
+    pub async fn record_transaction_replacement(
+        &self,
+        replacement: &TransactionReplacement,
+    ) -> Result<()> {
+        let address: String = replacement.address.encode_hex();
+        let transaction_hash: String = replacement.transaction_hash.encode_hex();
+        let sql = format!(
+            "INSERT INTO transaction_replacement(chain_id, network_id, address, nonce, transaction_hash, pending_since, replaced_at) VALUES ($1, $2, $3, $4, $5, {}, {})",
+            self.backend.timestamp("$6"),
+            self.backend.timestamp("$7"),
+        );
+        sqlx::query(&sql)
+            .bind(replacement.chain_id.clone())
+            .bind(replacement.network_id as i64)
+            .bind(address)
+            .bind(replacement.nonce as i64)
+            .bind(transaction_hash)
+            .bind(replacement.pending_since.timestamp_millis())
+            .bind(replacement.replaced_at.timestamp_millis())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Record a hash chain registered by `provider` on the contract, so that it can be loaded after
+    /// the provider moves on to another hash chain.
+    pub async fn record_commitment(
+        &self,
+        chain_id: &ChainId,
+        network_id: NetworkId,
+        provider: Address,
+        commitment: &Commitment,
+    ) -> Result<()> {
+        let provider: String = provider.encode_hex();
+        let seed: String = commitment.seed.encode_hex();
+        let sql = format!(
+            "INSERT INTO provider_commitment(chain_id, network_id, provider, original_commitment_sequence_number, seed, chain_length, registered_at) VALUES ($1, $2, $3, $4, $5, $6, {}) ON CONFLICT (network_id, provider, original_commitment_sequence_number) DO NOTHING",
+            self.backend.timestamp("$7")
+        );
+        sqlx::query(&sql)
+            .bind(chain_id.clone())
+            .bind(network_id as i64)
+            .bind(provider)
+            .bind(commitment.original_commitment_sequence_number as i64)
+            .bind(seed)
+            .bind(commitment.chain_length as i64)
+            .bind(chrono::Utc::now().timestamp_millis())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The hash chains recorded for `provider` on `network_id`, in increasing order of sequence
+    /// numbers.
+    pub async fn commitments(
+        &self,
+        network_id: NetworkId,
+        provider: Address,
+    ) -> Result<Vec<Commitment>> {
+        let provider: String = provider.encode_hex();
+        let rows = sqlx::query_as::<_, CommitmentRow>(
+            "SELECT original_commitment_sequence_number, seed, chain_length FROM provider_commitment WHERE network_id = $1 AND provider = $2 ORDER BY original_commitment_sequence_number",
+        )
+        .bind(network_id as i64)
+        .bind(provider)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter().map(Commitment::try_from).collect()
+    }
+
+    /// The replacements of stuck transactions on `chain_id`, from the newest to the oldest.
+    pub async fn transaction_replacements(
+        &self,
+        chain_id: &ChainId,
+    ) -> Result<Vec<TransactionReplacement>> {
+        let sql = format!(
+            "SELECT chain_id, network_id, address, nonce, transaction_hash, {} AS pending_since, {} AS replaced_at FROM transaction_replacement WHERE chain_id = $1 ORDER BY replaced_at DESC LIMIT $2",
+            self.backend.millis("pending_since"),
+            self.backend.millis("replaced_at"),
+        );
+        let rows = sqlx::query_as::<_, TransactionReplacementRow>(&sql)
+            .bind(chain_id.clone())
+            .bind(LOG_RETURN_LIMIT as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter()
+            .map(TransactionReplacement::try_from)
+            .collect()
     }
 }
 
+/// A position in the results of a query, right after the last request of a page. The requests are
+/// ordered from the newest to the oldest, so the next page starts with the first request that is
+/// older than the cursor. Unlike offsets, cursors stay valid as new requests are inserted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestCursor {
+    created_at: i64,
+    network_id: i64,
+    sequence: i64,
+    provider: String,
+    request_tx_hash: String,
+}
+
+impl RequestCursor {
+    fn after(row: &RequestRow) -> Self {
+        Self {
+            created_at: row.created_at,
+            network_id: row.network_id,
+            sequence: row.sequence,
+            provider: row.provider.clone(),
+            request_tx_hash: row.request_tx_hash.clone(),
+        }
+    }
+
+    /// The opaque string representation of the cursor used in the API.
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}:{}:{}:{}",
+            self.created_at, self.network_id, self.sequence, self.provider, self.request_tx_hash
+        ))
+    }
+}
+
+impl FromStr for RequestCursor {
+    type Err = RequestQueryBuilderError;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let decoded = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .ok_or(RequestQueryBuilderError::InvalidCursor)?;
+        let parts: Vec<&str> = decoded.split(':').collect();
+        match parts.as_slice() {
+            [created_at, network_id, sequence, provider, request_tx_hash] => Ok(Self {
+                created_at: created_at
+                    .parse()
+                    .map_err(|_| RequestQueryBuilderError::InvalidCursor)?,
+                network_id: network_id
+                    .parse()
+                    .map_err(|_| RequestQueryBuilderError::InvalidCursor)?,
+                sequence: sequence
+                    .parse()
+                    .map_err(|_| RequestQueryBuilderError::InvalidCursor)?,
+                provider: provider.to_string(),
+                request_tx_hash: request_tx_hash.to_string(),
+            }),
+            _ => Err(RequestQueryBuilderError::InvalidCursor),
+        }
+    }
+}
+
+/// A parameter of the SQL query built by [`RequestQueryBuilder`].
 #[derive(Debug, Clone)]
-pub struct RequestQueryBuilder<'a> {
-    pool: &'a AnyPool,
+enum QueryParam {
+    Bool(bool),
+    Int(i64),
+    Text(String),
+}
+
+/// The conditions of a WHERE clause, with `$n` placeholders for their parameters.
+struct Conditions {
+    sql: String,
+    params: Vec<QueryParam>,
+}
+
+impl Conditions {
+    /// Add `condition`, replacing each `{}` with the placeholder of the next parameter in `params`.
+    fn push(&mut self, condition: &str, params: Vec<QueryParam>) {
+        let mut parts = condition.split("{}");
+        self.sql.push_str(" AND ");
+        self.sql.push_str(parts.next().unwrap_or_default());
+        for (part, param) in parts.zip(params) {
+            self.params.push(param);
+            self.sql.push_str(&format!("${}", self.params.len()));
+            self.sql.push_str(part);
+        }
+    }
+
+    fn placeholder(&mut self, param: QueryParam) -> String {
+        self.params.push(param);
+        format!("${}", self.params.len())
+    }
+
+    fn bind<'q, O>(
+        &self,
+        mut query: QueryAs<'q, Any, O, AnyArguments<'q>>,
+    ) -> QueryAs<'q, Any, O, AnyArguments<'q>> {
+        for param in &self.params {
+            query = match param {
+                QueryParam::Bool(value) => query.bind(*value),
+                QueryParam::Int(value) => query.bind(*value),
+                QueryParam::Text(value) => query.bind(value.clone()),
+            };
+        }
+        query
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestQueryBuilder {
+    pool: AnyPool,
+    backend: Backend,
     pub search: Option<SearchField>,
     pub network_id: Option<i64>,
     pub state: Option<StateTag>,
+    pub provider: Option<Address>,
+    pub callback_failed: Option<bool>,
+    pub min_gas_used: Option<u64>,
+    pub max_gas_used: Option<u64>,
+    pub cursor: Option<RequestCursor>,
     pub limit: i64,
     pub offset: i64,
     pub min_timestamp: DateTime<chrono::Utc>,
     pub max_timestamp: DateTime<chrono::Utc>,
 }
 
-impl<'a> RequestQueryBuilder<'a> {
-    fn new(pool: &'a AnyPool) -> Self {
+impl RequestQueryBuilder {
+    fn new(pool: AnyPool) -> Self {
         Self {
+            backend: Backend::of(&pool),
             pool,
             search: None,
             network_id: None,
             state: None,
+            provider: None,
+            callback_failed: None,
+            min_gas_used: None,
+            max_gas_used: None,
+            cursor: None,
             limit: LOG_RETURN_LIMIT as i64,
             offset: 0,
             // UTC_MIN and UTC_MAX are not valid timestamps in SQLite
@@ -480,6 +1140,43 @@ impl<'a> RequestQueryBuilder<'a> {
         self
     }
 
+    pub fn provider(mut self, provider: Address) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Only return the completed requests whose callback failed (`true`) or succeeded (`false`).
+    pub fn callback_failed(mut self, callback_failed: bool) -> Self {
+        self.callback_failed = Some(callback_failed);
+        self
+    }
+
+    /// Only return the completed requests whose reveal used at least `min_gas_used` gas.
+    pub fn min_gas_used(mut self, min_gas_used: u64) -> Result<Self, RequestQueryBuilderError> {
+        self.min_gas_used = Some(Self::gas_param(min_gas_used)?);
+        Ok(self)
+    }
+
+    /// Only return the completed requests whose reveal used at most `max_gas_used` gas.
+    pub fn max_gas_used(mut self, max_gas_used: u64) -> Result<Self, RequestQueryBuilderError> {
+        self.max_gas_used = Some(Self::gas_param(max_gas_used)?);
+        Ok(self)
+    }
+
+    fn gas_param(gas: u64) -> Result<u64, RequestQueryBuilderError> {
+        if gas > i64::MAX as u64 {
+            Err(RequestQueryBuilderError::InvalidGasRange)
+        } else {
+            Ok(gas)
+        }
+    }
+
+    /// Start returning requests right after `cursor`, as returned with a previous page of results.
+    pub fn cursor(mut self, cursor: &str) -> Result<Self, RequestQueryBuilderError> {
+        self.cursor = Some(cursor.parse()?);
+        Ok(self)
+    }
+
     pub fn limit(mut self, limit: u64) -> Result<Self, RequestQueryBuilderError> {
         if limit > LOG_RETURN_LIMIT {
             Err(RequestQueryBuilderError::LimitTooLarge)
@@ -506,167 +1203,180 @@ impl<'a> RequestQueryBuilder<'a> {
         self
     }
 
-    pub async fn execute(&self) -> Result<Vec<RequestStatus>> {
-        let mut sql = "SELECT * FROM request WHERE created_at BETWEEN $1 AND $2".to_string();
-        let mut param_count = 2;
+    /// The WHERE clause matching all the filters of the query, including the cursor.
+    fn conditions(&self) -> Conditions {
+        let mut conditions = Conditions {
+            sql: format!(
+                "created_at BETWEEN {} AND {}",
+                self.backend.timestamp("$1"),
+                self.backend.timestamp("$2")
+            ),
+            params: vec![
+                QueryParam::Int(self.min_timestamp.timestamp_millis()),
+                QueryParam::Int(self.max_timestamp.timestamp_millis()),
+            ],
+        };
 
-        // Build the SQL string with parameter placeholders
         match &self.search {
-            Some(SearchField::TxHash(_)) => {
-                param_count += 1;
-                sql.push_str(&format!(" AND (request_tx_hash = ${param_count}"));
-                param_count += 1;
-                sql.push_str(&format!(" OR reveal_tx_hash = ${param_count})"));
+            Some(SearchField::TxHash(tx_hash)) => {
+                let tx_hash: String = tx_hash.encode_hex();
+                conditions.push(
+                    "(request_tx_hash = {} OR reveal_tx_hash = {})",
+                    vec![QueryParam::Text(tx_hash.clone()), QueryParam::Text(tx_hash)],
+                );
             }
-            Some(SearchField::Sender(_)) => {
-                param_count += 1;
-                sql.push_str(&format!(" AND sender = ${param_count}"));
+            Some(SearchField::Sender(sender)) => {
+                conditions.push("sender = {}", vec![QueryParam::Text(sender.encode_hex())]);
             }
-            Some(SearchField::SequenceNumber(_)) => {
-                param_count += 1;
-                sql.push_str(&format!(" AND sequence = ${param_count}"));
+            Some(SearchField::SequenceNumber(sequence_number)) => {
+                conditions.push("sequence = {}", vec![QueryParam::Int(*sequence_number)]);
             }
             None => (),
         }
 
-        if self.network_id.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND network_id = ${param_count}"));
+        if let Some(network_id) = self.network_id {
+            conditions.push("network_id = {}", vec![QueryParam::Int(network_id)]);
         }
 
         if let Some(state) = &self.state {
-            param_count += 1;
-            sql.push_str(&format!(" AND state = ${param_count}"));
+            let state_param = QueryParam::Text(
+                match state {
+                    StateTag::Pending => "Pending",
+                    StateTag::Failed => "Failed",
+                    StateTag::Completed | StateTag::CallbackErrored => "Completed",
+                    StateTag::Simulated => "Simulated",
+                }
+                .to_string(),
+            );
+            conditions.push("state = {}", vec![state_param]);
 
             if *state == StateTag::Completed {
-                sql.push_str(" AND callback_failed = 0");
+                conditions.push("callback_failed = FALSE", vec![]);
             } else if *state == StateTag::CallbackErrored {
-                sql.push_str(" AND callback_failed = 1");
+                conditions.push("callback_failed = TRUE", vec![]);
             }
         }
 
-        sql.push_str(" ORDER BY created_at DESC");
-
-        param_count += 1;
-        sql.push_str(&format!(" LIMIT ${param_count}"));
-        param_count += 1;
-        sql.push_str(&format!(" OFFSET ${param_count}"));
-
-        // Now bind all parameters in order
-        let mut query = sqlx::query_as::<_, RequestRow>(&sql)
-            .bind(self.min_timestamp.timestamp_millis())
-            .bind(self.max_timestamp.timestamp_millis());
-
-        match &self.search {
-            Some(SearchField::TxHash(tx_hash)) => {
-                let tx_hash: String = tx_hash.encode_hex();
-                query = query.bind(tx_hash.clone()).bind(tx_hash);
-            }
-            Some(SearchField::Sender(sender)) => {
-                let sender: String = sender.encode_hex();
-                query = query.bind(sender);
-            }
-            Some(SearchField::SequenceNumber(sequence_number)) => {
-                query = query.bind(sequence_number);
-            }
-            None => (),
+        if let Some(provider) = &self.provider {
+            conditions.push(
+                "provider = {}",
+                vec![QueryParam::Text(provider.encode_hex())],
+            );
         }
 
-        if let Some(network_id) = &self.network_id {
-            query = query.bind(network_id);
+        if let Some(callback_failed) = self.callback_failed {
+            conditions.push(
+                "callback_failed = {}",
+                vec![QueryParam::Bool(callback_failed)],
+            );
         }
 
-        if let Some(state) = &self.state {
-            query = query.bind(match state {
-                StateTag::Pending => "Pending",
-                StateTag::Failed => "Failed",
-                StateTag::Completed | StateTag::CallbackErrored => "Completed",
-            })
+        if let Some(min_gas_used) = self.min_gas_used {
+            conditions.push(
+                &format!("{} >= {{}}", self.backend.number("gas_used")),
+                vec![QueryParam::Int(min_gas_used as i64)],
+            );
         }
-
-        query = query.bind(self.limit).bind(self.offset);
-
-        let result: sqlx::Result<Vec<RequestRow>> = query.fetch_all(self.pool).await;
-
-        if let Err(e) = &result {
-            tracing::error!("Failed to fetch request: {}", e);
+        if let Some(max_gas_used) = self.max_gas_used {
+            conditions.push(
+                &format!("{} <= {{}}", self.backend.number("gas_used")),
+                vec![QueryParam::Int(max_gas_used as i64)],
+            );
         }
 
-        Ok(result?.into_iter().filter_map(|row| row.into()).collect())
-    }
-
-    pub async fn count_results(&self) -> Result<i64> {
-        let mut sql = "SELECT COUNT(*) FROM request WHERE created_at BETWEEN $1 AND $2".to_string();
-        let mut param_count = 2;
-
-        // Build the SQL string with parameter placeholders
-        match &self.search {
-            Some(SearchField::TxHash(_)) => {
-                param_count += 1;
-                sql.push_str(&format!(" AND (request_tx_hash = ${param_count}"));
-                param_count += 1;
-                sql.push_str(&format!(" OR reveal_tx_hash = ${param_count})"));
-            }
-            Some(SearchField::Sender(_)) => {
-                param_count += 1;
-                sql.push_str(&format!(" AND sender = ${param_count}"));
-            }
-            Some(SearchField::SequenceNumber(_)) => {
-                param_count += 1;
-                sql.push_str(&format!(" AND sequence = ${param_count}"));
-            }
-            None => (),
+        if let Some(cursor) = &self.cursor {
+            conditions.push(
+                &format!(
+                    "(created_at, network_id, sequence, provider, request_tx_hash) < ({}, {{}}, {{}}, {{}}, {{}})",
+                    self.backend.timestamp("{}")
+                ),
+                vec![
+                    QueryParam::Int(cursor.created_at),
+                    QueryParam::Int(cursor.network_id),
+                    QueryParam::Int(cursor.sequence),
+                    QueryParam::Text(cursor.provider.clone()),
+                    QueryParam::Text(cursor.request_tx_hash.clone()),
+                ],
+            );
         }
 
-        if self.network_id.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND network_id = ${param_count}"));
-        }
+        conditions
+    }
 
-        if let Some(state) = &self.state {
-            param_count += 1;
-            sql.push_str(&format!(" AND state = ${param_count}"));
+    pub async fn execute(&self) -> Result<Vec<RequestStatus>> {
+        Ok(self.execute_page().await?.0)
+    }
 
-            if *state == StateTag::Completed {
-                sql.push_str(" AND callback_failed = 0");
-            } else if *state == StateTag::CallbackErrored {
-                sql.push_str(" AND callback_failed = 1");
-            }
-        }
+    /// Execute the query and return the cursor of the next page of results, if there may be one.
+    pub async fn execute_page(&self) -> Result<(Vec<RequestStatus>, Option<RequestCursor>)> {
+        let mut conditions = self.conditions();
+        let limit = conditions.placeholder(QueryParam::Int(self.limit));
+        let offset = conditions.placeholder(QueryParam::Int(self.offset));
+        // The columns of the primary key break the ties between requests created at the same time,
+        // so that the cursors are unambiguous.
+        let sql = format!(
+            "SELECT {} FROM request WHERE {} ORDER BY created_at DESC, network_id DESC, sequence DESC, provider DESC, request_tx_hash DESC LIMIT {limit} OFFSET {offset}",
+            self.backend.request_columns(),
+            conditions.sql
+        );
 
-        // Now bind all parameters in order
-        let mut query = sqlx::query_scalar::<_, i64>(&sql)
-            .bind(self.min_timestamp.timestamp_millis())
-            .bind(self.max_timestamp.timestamp_millis());
+        let result: sqlx::Result<Vec<RequestRow>> = conditions
+            .bind(sqlx::query_as::<_, RequestRow>(&sql))
+            .fetch_all(&self.pool)
+            .await;
 
-        match &self.search {
-            Some(SearchField::TxHash(tx_hash)) => {
-                let tx_hash: String = tx_hash.encode_hex();
-                query = query.bind(tx_hash.clone()).bind(tx_hash);
-            }
-            Some(SearchField::Sender(sender)) => {
-                let sender: String = sender.encode_hex();
-                query = query.bind(sender);
-            }
-            Some(SearchField::SequenceNumber(sequence_number)) => {
-                query = query.bind(sequence_number);
-            }
-            None => (),
+        if let Err(e) = &result {
+            tracing::error!("Failed to fetch request: {}", e);
         }
 
-        if let Some(network_id) = &self.network_id {
-            query = query.bind(network_id);
-        }
+        let rows = result?;
+        let next_cursor = match rows.last() {
+            Some(last) if rows.len() as i64 == self.limit => Some(RequestCursor::after(last)),
+            _ => None,
+        };
+        Ok((
+            rows.into_iter().filter_map(|row| row.into()).collect(),
+            next_cursor,
+        ))
+    }
 
-        if let Some(state) = &self.state {
-            query = query.bind(match state {
-                StateTag::Pending => "Pending",
-                StateTag::Failed => "Failed",
-                StateTag::Completed | StateTag::CallbackErrored => "Completed",
-            })
-        }
+    /// Stream the first `max_results` results of the query, `limit` requests at a time. The pages
+    /// are fetched with cursors, so the requests inserted while streaming don't shift the results.
+    pub fn pages(self, max_results: i64) -> impl Stream<Item = Result<Vec<RequestStatus>>> {
+        let first_page = RequestQueryBuilder {
+            limit: self.limit.min(max_results),
+            ..self
+        };
+        stream::try_unfold(
+            (first_page.limit > 0).then_some((first_page, max_results)),
+            |page| async move {
+                let Some((query, remaining)) = page else {
+                    return Ok(None);
+                };
+                let (requests, next_cursor) = query.execute_page().await?;
+                let remaining = remaining - requests.len() as i64;
+                let next_page = next_cursor.filter(|_| remaining > 0).map(|cursor| {
+                    let next_query = RequestQueryBuilder {
+                        cursor: Some(cursor),
+                        offset: 0,
+                        limit: query.limit.min(remaining),
+                        ..query
+                    };
+                    (next_query, remaining)
+                });
+                Ok(Some((requests, next_page)))
+            },
+        )
+    }
 
-        query.fetch_one(self.pool).await.map_err(|err| err.into())
+    pub async fn count_results(&self) -> Result<i64> {
+        let conditions = self.conditions();
+        let sql = format!("SELECT COUNT(*) FROM request WHERE {}", conditions.sql);
+        let (count,) = conditions
+            .bind(sqlx::query_as::<_, (i64,)>(&sql))
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
     }
 }
 
@@ -675,6 +1385,8 @@ pub enum RequestQueryBuilderError {
     LimitTooLarge,
     ZeroLimit,
     InvalidSearch,
+    InvalidCursor,
+    InvalidGasRange,
 }
 
 #[derive(Debug, Clone)]
@@ -689,6 +1401,7 @@ mod test {
     use {
         super::*,
         chrono::{Duration, Timelike},
+        futures::TryStreamExt,
         tokio::time::sleep,
     };
 
@@ -709,6 +1422,116 @@ mod test {
         }
     }
 
+    /// Run against a Postgres database if `FORTUNA_TEST_POSTGRES_URL` is set, e.g.
+    /// `postgres://postgres@localhost:5432/fortuna_test`.
+    #[tokio::test]
+    async fn test_postgres_history() {
+        let Ok(url) = std::env::var("FORTUNA_TEST_POSTGRES_URL") else {
+            return;
+        };
+        let history = History::new_with_url(&url).await.unwrap();
+        let mut status = get_random_request_status();
+        status.chain_id = "a_chain_id_longer_than_20_characters".to_string();
+        status.sequence = u32::MAX as u64 + 1;
+        status.request_block_number = u32::MAX as u64 + 2;
+        History::update_request_status(&history.pool, status.clone()).await;
+        status.state = RequestEntryState::Completed {
+            reveal_block_number: u32::MAX as u64 + 3,
+            reveal_tx_hash: TxHash::random(),
+            provider_random_number: [40; 32],
+            gas_used: U256::from(567890),
+            combined_random_number: RequestStatus::generate_combined_random_number(
+                &status.user_random_number,
+                &[40; 32],
+            ),
+            callback_failed: true,
+            callback_return_value: Default::default(),
+            callback_gas_used: 100_000,
+            callback_failure: Some(CallbackFailure {
+                kind: CallbackFailureKind::Revert,
+                reason: Some("Error(\"nope\")".to_string()),
+            }),
+        };
+        History::update_request_status(&history.pool, status.clone()).await;
+        let logs = history
+            .query()
+            .search(status.sender.encode_hex())
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![status.clone()]);
+        assert_eq!(history.metrics.failed_writes.get(), 0);
+
+        let (logs, next_cursor) = history
+            .query()
+            .provider(status.provider)
+            .callback_failed(true)
+            .min_gas_used(567890)
+            .unwrap()
+            .limit(1)
+            .unwrap()
+            .execute_page()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![status.clone()]);
+        let logs = history
+            .query()
+            .provider(status.provider)
+            .cursor(&next_cursor.unwrap().encode())
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+        assert!(logs.is_empty());
+
+        let day = status.created_at.date_naive();
+        let stats = history.update_daily_rollups(day).await.unwrap();
+        assert!(stats
+            .iter()
+            .any(|stats| stats.chain_id == status.chain_id && stats.callback_failed_count > 0));
+        assert!(history
+            .daily_stats(Some(&status.chain_id), day, day)
+            .await
+            .unwrap()
+            .iter()
+            .any(|stats| stats.request_count > 0));
+
+        let replica = status.provider.encode_hex();
+        history
+            .renew_replica_lease(
+                &status.chain_id,
+                &replica,
+                status.created_at + Duration::days(1),
+            )
+            .await
+            .unwrap();
+        assert!(history
+            .live_replicas(&status.chain_id, status.created_at)
+            .await
+            .unwrap()
+            .contains(&replica));
+
+        let replacement = TransactionReplacement {
+            chain_id: status.chain_id.clone(),
+            network_id: status.network_id,
+            address: status.sender,
+            nonce: 7,
+            transaction_hash: TxHash::random(),
+            pending_since: status.created_at,
+            replaced_at: status.last_updated_at,
+        };
+        history
+            .record_transaction_replacement(&replacement)
+            .await
+            .unwrap();
+        assert!(history
+            .transaction_replacements(&status.chain_id)
+            .await
+            .unwrap()
+            .contains(&replacement));
+    }
+
     #[tokio::test]
     async fn test_history_return_correct_logs() {
         let history = History::new_in_memory().await.unwrap();
@@ -727,6 +1550,7 @@ mod test {
             callback_failed: false,
             callback_return_value: Default::default(),
             callback_gas_used: 100_000,
+            callback_failure: None,
         };
         History::update_request_status(&history.pool, status.clone()).await;
 
@@ -932,6 +1756,7 @@ mod test {
             callback_failed: false,
             callback_return_value: Default::default(),
             callback_gas_used: 0,
+            callback_failure: None,
         };
         History::update_request_status(&history.pool, status.clone()).await;
         let mut failed_status = status.clone();
@@ -971,6 +1796,44 @@ mod test {
         assert_eq!(logs, vec![status.clone()]);
     }
 
+    #[tokio::test]
+    async fn test_simulated_state() {
+        let history = History::new_in_memory().await.unwrap();
+        let mut status = get_random_request_status();
+        History::update_request_status(&history.pool, status.clone()).await;
+        status.state = RequestEntryState::Simulated {
+            provider_random_number: [40; 32],
+            combined_random_number: RequestStatus::generate_combined_random_number(
+                &status.user_random_number,
+                &[40; 32],
+            ),
+            gas_estimate: U256::from(567890),
+            estimated_fee: U256::from(1_000_000_000_000_000u64),
+            callback_failed: true,
+            callback_return_value: Bytes::from(vec![1, 2, 3]),
+            callback_gas_used: 100_000,
+            callback_failure: Some(CallbackFailure {
+                kind: CallbackFailureKind::Revert,
+                reason: Some("0x010203".to_string()),
+            }),
+        };
+        History::update_request_status(&history.pool, status.clone()).await;
+        let logs = history
+            .query()
+            .state(StateTag::Simulated)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![status.clone()]);
+        let logs = history
+            .query()
+            .state(StateTag::Pending)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![]);
+    }
+
     #[tokio::test]
     async fn test_generate_combined_random_number() {
         let user_random_number = hex::FromHex::from_hex(
@@ -1130,6 +1993,7 @@ mod test {
             callback_failed: false,
             callback_return_value: Default::default(),
             callback_gas_used: 100_000,
+            callback_failure: None,
         };
         History::update_request_status(&history.pool, completed_status.clone()).await;
 
@@ -1148,6 +2012,10 @@ mod test {
             callback_failed: true,
             callback_return_value: Default::default(),
             callback_gas_used: 100_000,
+            callback_failure: Some(CallbackFailure {
+                kind: CallbackFailureKind::OutOfGas,
+                reason: None,
+            }),
         };
         History::update_request_status(&history.pool, callback_errored_status.clone()).await;
 
@@ -1202,6 +2070,143 @@ mod test {
         assert_eq!(logs, vec![status]);
     }
 
+    #[tokio::test]
+    async fn test_replica_leases() {
+        let history = History::new_in_memory().await.unwrap();
+        let now = chrono::Utc::now();
+        let chain_id = "ethereum".to_string();
+        history
+            .renew_replica_lease(&chain_id, "b", now + Duration::seconds(10))
+            .await
+            .unwrap();
+        history
+            .renew_replica_lease(&chain_id, "a", now + Duration::seconds(5))
+            .await
+            .unwrap();
+        history
+            .renew_replica_lease(&"other".to_string(), "c", now + Duration::seconds(10))
+            .await
+            .unwrap();
+        assert_eq!(
+            history.live_replicas(&chain_id, now).await.unwrap(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            history
+                .live_replicas(&chain_id, now + Duration::seconds(5))
+                .await
+                .unwrap(),
+            vec!["b"]
+        );
+
+        // Renewing a lease extends it.
+        history
+            .renew_replica_lease(&chain_id, "a", now + Duration::seconds(20))
+            .await
+            .unwrap();
+        assert_eq!(
+            history
+                .live_replicas(&chain_id, now + Duration::seconds(15))
+                .await
+                .unwrap(),
+            vec!["a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transaction_replacements() {
+        let history = History::new_in_memory().await.unwrap();
+        let now = DateTime::from_timestamp_millis(chrono::Utc::now().timestamp_millis()).unwrap();
+        let replacement = TransactionReplacement {
+            chain_id: "ethereum".to_string(),
+            network_id: 121,
+            address: Address::random(),
+            nonce: 7,
+            transaction_hash: TxHash::random(),
+            pending_since: now - Duration::seconds(300),
+            replaced_at: now,
+        };
+        let later = TransactionReplacement {
+            nonce: 8,
+            transaction_hash: TxHash::random(),
+            replaced_at: now + Duration::seconds(10),
+            ..replacement.clone()
+        };
+        history
+            .record_transaction_replacement(&replacement)
+            .await
+            .unwrap();
+        history
+            .record_transaction_replacement(&later)
+            .await
+            .unwrap();
+        let other_chain = TransactionReplacement {
+            chain_id: "other".to_string(),
+            network_id: 122,
+            ..replacement.clone()
+        };
+        history
+            .record_transaction_replacement(&other_chain)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            history
+                .transaction_replacements(&"ethereum".to_string())
+                .await
+                .unwrap(),
+            vec![later, replacement]
+        );
+        assert_eq!(
+            history
+                .transaction_replacements(&"other".to_string())
+                .await
+                .unwrap(),
+            vec![other_chain]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commitments() {
+        let history = History::new_in_memory().await.unwrap();
+        let provider = Address::random();
+        let chain_id = "ethereum".to_string();
+        let first = Commitment {
+            seed: [1u8; 32],
+            chain_length: 100,
+            original_commitment_sequence_number: 0,
+        };
+        let second = Commitment {
+            seed: [2u8; 32],
+            chain_length: 200,
+            original_commitment_sequence_number: 95,
+        };
+        for commitment in [&second, &first, &first] {
+            history
+                .record_commitment(&chain_id, 121, provider, commitment)
+                .await
+                .unwrap();
+        }
+        history
+            .record_commitment(&chain_id, 121, Address::random(), &second)
+            .await
+            .unwrap();
+
+        let commitments = history.commitments(121, provider).await.unwrap();
+        assert_eq!(
+            commitments
+                .iter()
+                .map(|c| (
+                    c.seed,
+                    c.chain_length,
+                    c.original_commitment_sequence_number
+                ))
+                .collect::<Vec<_>>(),
+            vec![([1u8; 32], 100, 0), ([2u8; 32], 200, 95)]
+        );
+        assert!(history.commitments(122, provider).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_count_results() {
         let history = History::new_in_memory().await.unwrap();
@@ -1235,4 +2240,147 @@ mod test {
             .unwrap();
         assert_eq!(results, 2);
     }
+
+    fn completed(status: &RequestStatus, gas_used: u64, callback_failed: bool) -> RequestStatus {
+        RequestStatus {
+            state: RequestEntryState::Completed {
+                reveal_block_number: 2,
+                reveal_tx_hash: TxHash::random(),
+                provider_random_number: [40; 32],
+                gas_used: U256::from(gas_used),
+                combined_random_number: RequestStatus::generate_combined_random_number(
+                    &status.user_random_number,
+                    &[40; 32],
+                ),
+                callback_failed,
+                callback_return_value: Default::default(),
+                callback_gas_used: 100_000,
+                callback_failure: None,
+            },
+            ..status.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pagination() {
+        let history = History::new_in_memory().await.unwrap();
+        let mut statuses = vec![];
+        // Requests created at the same time are ordered by the columns of the unique key, down to
+        // the request transaction hash.
+        let provider = get_random_request_status().provider;
+        for sequence in [1, 1, 2, 2, 3] {
+            let mut status = get_random_request_status();
+            status.provider = provider;
+            status.sequence = sequence;
+            History::update_request_status(&history.pool, status.clone()).await;
+            statuses.push(status);
+        }
+        statuses.sort_by_key(|status| {
+            std::cmp::Reverse((status.created_at, status.sequence, status.request_tx_hash))
+        });
+
+        let mut logs = vec![];
+        let mut query = history.query().limit(2).unwrap();
+        loop {
+            let (page, next_cursor) = query.execute_page().await.unwrap();
+            logs.extend(page);
+            let Some(cursor) = next_cursor else {
+                break;
+            };
+            // A newer request inserted while paging doesn't shift the next pages.
+            let mut newer = get_random_request_status();
+            newer.created_at += Duration::hours(1);
+            History::update_request_status(&history.pool, newer).await;
+            query = query.cursor(&cursor.encode()).unwrap();
+        }
+        assert_eq!(logs, statuses);
+
+        let pages: Vec<Vec<RequestStatus>> = history
+            .query()
+            .limit(2)
+            .unwrap()
+            .pages(100)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![2, 2, 2, 1]
+        );
+        assert_eq!(pages.concat()[2..], statuses[..]);
+
+        let pages: Vec<Vec<RequestStatus>> = history
+            .query()
+            .limit(2)
+            .unwrap()
+            .pages(3)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 1]);
+
+        assert!(history.query().cursor("not a cursor").is_err());
+        assert!(history
+            .query()
+            .cursor(&URL_SAFE_NO_PAD.encode("1:2:3"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provider_callback_and_gas_filters() {
+        let history = History::new_in_memory().await.unwrap();
+        let pending = get_random_request_status();
+        let succeeded = completed(&get_random_request_status(), 100, false);
+        let failed = completed(&get_random_request_status(), 300, true);
+        for status in [&pending, &succeeded, &failed] {
+            let status = RequestStatus {
+                state: RequestEntryState::Pending,
+                ..status.clone()
+            };
+            History::update_request_status(&history.pool, status).await;
+        }
+        History::update_request_status(&history.pool, succeeded.clone()).await;
+        History::update_request_status(&history.pool, failed.clone()).await;
+
+        let logs = history
+            .query()
+            .provider(failed.provider)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![failed.clone()]);
+
+        let logs = history
+            .query()
+            .callback_failed(true)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![failed.clone()]);
+        let logs = history
+            .query()
+            .callback_failed(false)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![succeeded.clone()]);
+
+        let logs = history
+            .query()
+            .min_gas_used(200)
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(logs, vec![failed.clone()]);
+        let query = history
+            .query()
+            .min_gas_used(100)
+            .unwrap()
+            .max_gas_used(299)
+            .unwrap();
+        assert_eq!(query.execute().await.unwrap(), vec![succeeded.clone()]);
+        assert_eq!(query.count_results().await.unwrap(), 1);
+        assert!(history.query().max_gas_used(u64::MAX).is_err());
+    }
 }
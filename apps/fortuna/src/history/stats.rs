@@ -0,0 +1,492 @@
+use {
+    super::{History, ONE_DAY},
+    crate::{api::ChainId, config::HistoryConfig},
+    anyhow::Result,
+    chrono::{DateTime, Days, NaiveDate, Utc},
+    serde::Serialize,
+    sqlx::FromRow,
+    std::{collections::HashMap, sync::Arc},
+    tokio::time::{self, Duration},
+    utoipa::ToSchema,
+};
+
+/// How often the rollups of the current day are refreshed and old requests are pruned.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The statistics of the requests of a chain created on a UTC day.
+#[derive(Clone, Debug, Serialize, ToSchema, PartialEq)]
+pub struct DailyStats {
+    pub chain_id: ChainId,
+    pub network_id: u64,
+    #[schema(value_type = String, example = "2025-10-19")]
+    pub day: NaiveDate,
+    pub request_count: u64,
+    /// The number of requests that could not be revealed.
+    pub failed_count: u64,
+    /// The number of revealed requests whose callback failed.
+    pub callback_failed_count: u64,
+    /// The fraction of requests that could not be revealed.
+    pub failure_rate: f64,
+    /// The median time between the keeper seeing a request and revealing it, in milliseconds.
+    pub median_reveal_latency_ms: Option<u64>,
+    /// The total gas used by the reveal transactions, as a decimal string.
+    pub gas_used: String,
+}
+
+#[derive(FromRow)]
+struct RollupCountsRow {
+    chain_id: String,
+    network_id: i64,
+    request_count: i64,
+    failed_count: i64,
+    callback_failed_count: i64,
+    gas_used: String,
+}
+
+#[derive(FromRow)]
+struct MedianLatencyRow {
+    chain_id: String,
+    median_reveal_latency_ms: i64,
+}
+
+#[derive(FromRow)]
+struct RollupRow {
+    chain_id: String,
+    network_id: i64,
+    day: String,
+    request_count: i64,
+    failed_count: i64,
+    callback_failed_count: i64,
+    median_reveal_latency_ms: Option<i64>,
+    gas_used: String,
+}
+
+impl TryFrom<RollupRow> for DailyStats {
+    type Error = anyhow::Error;
+
+    fn try_from(row: RollupRow) -> Result<Self> {
+        let request_count = row.request_count as u64;
+        let failed_count = row.failed_count as u64;
+        Ok(DailyStats {
+            chain_id: row.chain_id,
+            network_id: row.network_id as u64,
+            day: row.day.parse()?,
+            request_count,
+            failed_count,
+            callback_failed_count: row.callback_failed_count as u64,
+            failure_rate: failure_rate(failed_count, request_count),
+            median_reveal_latency_ms: row.median_reveal_latency_ms.map(|ms| ms as u64),
+            gas_used: row.gas_used,
+        })
+    }
+}
+
+fn failure_rate(failed_count: u64, request_count: u64) -> f64 {
+    if request_count == 0 {
+        0.0
+    } else {
+        failed_count as f64 / request_count as f64
+    }
+}
+
+fn day_start(day: NaiveDate) -> DateTime<Utc> {
+    day.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+impl History {
+    /// Recompute and store the rollups of the requests created on `day`, for each chain with
+    /// requests on that day.
+    pub async fn update_daily_rollups(&self, day: NaiveDate) -> Result<Vec<DailyStats>> {
+        let start = day_start(day).timestamp_millis();
+        let end = start + ONE_DAY as i64 * 1000;
+        let backend = self.backend;
+        let created_between = format!(
+            "created_at >= {} AND created_at < {}",
+            backend.timestamp("$1"),
+            backend.timestamp("$2")
+        );
+        let sql = format!(
+            "SELECT chain_id, MAX(network_id) AS network_id, COUNT(*) AS request_count, SUM(CASE WHEN state = 'Failed' THEN 1 ELSE 0 END) AS failed_count, SUM(CASE WHEN state = 'Completed' AND callback_failed = TRUE THEN 1 ELSE 0 END) AS callback_failed_count, CAST(COALESCE(SUM(CASE WHEN state = 'Completed' THEN {} END), 0) AS TEXT) AS gas_used FROM request WHERE {created_between} GROUP BY chain_id ORDER BY chain_id",
+            backend.number("gas_used"),
+        );
+        let counts = sqlx::query_as::<_, RollupCountsRow>(&sql)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+        // The median is the average of the middle one or two latencies of each chain.
+        let sql = format!(
+            "SELECT chain_id, CAST(SUM(latency) AS BIGINT) / COUNT(*) AS median_reveal_latency_ms FROM (SELECT chain_id, latency, ROW_NUMBER() OVER (PARTITION BY chain_id ORDER BY latency) AS latency_rank, COUNT(*) OVER (PARTITION BY chain_id) AS latency_count FROM (SELECT chain_id, CASE WHEN last_updated_at > created_at THEN {} - {} ELSE 0 END AS latency FROM request WHERE state = 'Completed' AND {created_between}) AS latencies) AS ranked_latencies WHERE latency_rank IN ((latency_count + 1) / 2, (latency_count + 2) / 2) GROUP BY chain_id",
+            backend.millis("last_updated_at"),
+            backend.millis("created_at"),
+        );
+        let medians: HashMap<String, i64> = sqlx::query_as::<_, MedianLatencyRow>(&sql)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.chain_id, row.median_reveal_latency_ms))
+            .collect();
+
+        let now = Utc::now().timestamp_millis();
+        let mut stats = vec![];
+        for row in counts {
+            let request_count = row.request_count as u64;
+            let failed_count = row.failed_count as u64;
+            let day_stats = DailyStats {
+                median_reveal_latency_ms: medians.get(&row.chain_id).map(|ms| *ms as u64),
+                chain_id: row.chain_id,
+                network_id: row.network_id as u64,
+                day,
+                request_count,
+                failed_count,
+                callback_failed_count: row.callback_failed_count as u64,
+                failure_rate: failure_rate(failed_count, request_count),
+                gas_used: row.gas_used,
+            };
+            let sql = format!(
+                "INSERT INTO request_daily_rollup(chain_id, network_id, day, request_count, failed_count, callback_failed_count, median_reveal_latency_ms, gas_used, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, {}, {}) ON CONFLICT (chain_id, day) DO UPDATE SET network_id = excluded.network_id, request_count = excluded.request_count, failed_count = excluded.failed_count, callback_failed_count = excluded.callback_failed_count, median_reveal_latency_ms = excluded.median_reveal_latency_ms, gas_used = excluded.gas_used, updated_at = excluded.updated_at",
+                backend.decimal("$8"),
+                backend.timestamp("$9"),
+            );
+            sqlx::query(&sql)
+                .bind(day_stats.chain_id.clone())
+                .bind(day_stats.network_id as i64)
+                .bind(day.to_string())
+                .bind(day_stats.request_count as i64)
+                .bind(day_stats.failed_count as i64)
+                .bind(day_stats.callback_failed_count as i64)
+                .bind(day_stats.median_reveal_latency_ms.map(|ms| ms as i64))
+                .bind(day_stats.gas_used.clone())
+                .bind(now)
+                .execute(&self.pool)
+                .await?;
+            stats.push(day_stats);
+        }
+        Ok(stats)
+    }
+
+    /// The stored rollups from `min_day` to `max_day` included, optionally for a single chain,
+    /// ordered by day and chain.
+    pub async fn daily_stats(
+        &self,
+        chain_id: Option<&ChainId>,
+        min_day: NaiveDate,
+        max_day: NaiveDate,
+    ) -> Result<Vec<DailyStats>> {
+        let mut sql = format!(
+            "SELECT chain_id, network_id, day, request_count, failed_count, callback_failed_count, median_reveal_latency_ms, {} AS gas_used FROM request_daily_rollup WHERE day >= $1 AND day <= $2",
+            self.backend.decimal_string("gas_used")
+        );
+        if chain_id.is_some() {
+            sql.push_str(" AND chain_id = $3");
+        }
+        sql.push_str(" ORDER BY day, chain_id");
+        let mut query = sqlx::query_as::<_, RollupRow>(&sql)
+            .bind(min_day.to_string())
+            .bind(max_day.to_string());
+        if let Some(chain_id) = chain_id {
+            query = query.bind(chain_id.clone());
+        }
+        query
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(DailyStats::try_from)
+            .collect()
+    }
+
+    /// Delete the requests created before `before`. Returns the number of deleted requests.
+    pub async fn prune_requests(&self, before: DateTime<Utc>) -> Result<u64> {
+        let sql = format!(
+            "DELETE FROM request WHERE created_at < {}",
+            self.backend.timestamp("$1")
+        );
+        let result = sqlx::query(&sql)
+            .bind(before.timestamp_millis())
+            .execute(&self.pool)
+            .await?;
+        self.metrics.pruned_requests.inc_by(result.rows_affected());
+        Ok(result.rows_affected())
+    }
+
+    async fn oldest_request_day(&self) -> Result<Option<NaiveDate>> {
+        let sql = format!(
+            "SELECT {} FROM request",
+            self.backend.millis("MIN(created_at)")
+        );
+        let oldest = sqlx::query_scalar::<_, Option<i64>>(&sql)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(oldest
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|oldest| oldest.date_naive()))
+    }
+
+    /// Update the rollups of the days from `from` to today, then delete the requests older than the
+    /// retention period. Requests are pruned by whole days, so the rollups of the remaining days
+    /// stay complete.
+    async fn run_maintenance_once(&self, from: NaiveDate, config: &HistoryConfig) -> Result<()> {
+        let today = Utc::now().date_naive();
+        let mut day = from;
+        while day <= today {
+            self.update_daily_rollups(day).await?;
+            day = day + Days::new(1);
+        }
+
+        if let Some(retention_days) = config.retention_days {
+            let cutoff = day_start(today - Days::new(retention_days));
+            let pruned = self.prune_requests(cutoff).await?;
+            if pruned > 0 {
+                tracing::info!(pruned, "Pruned requests created before {}", cutoff);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Periodically update the daily rollups and apply the retention policy of `config`. The rollups
+/// of all the stored days are computed on the first run, then only the last two days are refreshed,
+/// as requests are only updated shortly after they are created.
+#[tracing::instrument(name = "history_maintenance", skip_all)]
+pub async fn run_maintenance(history: Arc<History>, config: HistoryConfig) {
+    let mut backfilled = false;
+    loop {
+        let today = Utc::now().date_naive();
+        let from = if backfilled {
+            Ok(Some(today - Days::new(1)))
+        } else {
+            history.oldest_request_day().await
+        };
+        let result = match from {
+            Ok(from) => {
+                history
+                    .run_maintenance_once(from.unwrap_or(today), &config)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => backfilled = true,
+            Err(e) => tracing::error!("Error while maintaining the history database: {:?}", e),
+        }
+        time::sleep(MAINTENANCE_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::history::{RequestEntryState, RequestStatus},
+        ethers::types::{Address, Bytes, TxHash, U256},
+    };
+
+    fn request(
+        chain_id: &str,
+        sequence: u64,
+        created_at: DateTime<Utc>,
+        state: RequestEntryState,
+        latency_ms: i64,
+    ) -> RequestStatus {
+        RequestStatus {
+            chain_id: chain_id.to_string(),
+            network_id: if chain_id == "ethereum" { 1 } else { 10 },
+            provider: Address::zero(),
+            sequence,
+            created_at,
+            last_updated_at: created_at + chrono::Duration::milliseconds(latency_ms),
+            request_block_number: 1,
+            request_tx_hash: TxHash::from_low_u64_be(sequence),
+            user_random_number: [0; 32],
+            sender: Address::zero(),
+            state,
+            gas_limit: 500_000,
+        }
+    }
+
+    fn completed(gas_used: u64, callback_failed: bool) -> RequestEntryState {
+        RequestEntryState::Completed {
+            reveal_block_number: 2,
+            reveal_tx_hash: TxHash::random(),
+            provider_random_number: [1; 32],
+            gas_used: U256::from(gas_used),
+            combined_random_number: [0; 32],
+            callback_failed,
+            callback_return_value: Bytes::default(),
+            callback_gas_used: 0,
+            callback_failure: None,
+        }
+    }
+
+    /// Insert the request as pending, then update it to its final state.
+    async fn insert(history: &History, status: RequestStatus) {
+        let mut pending = status.clone();
+        pending.state = RequestEntryState::Pending;
+        pending.last_updated_at = pending.created_at;
+        History::update_request_status(&history.pool, pending).await;
+        if status.state != RequestEntryState::Pending {
+            History::update_request_status(&history.pool, status).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_daily_rollups() {
+        let history = History::new_in_memory().await.unwrap();
+        let day: NaiveDate = "2025-10-01".parse().unwrap();
+        let noon = day_start(day) + chrono::Duration::hours(12);
+
+        insert(
+            &history,
+            request("ethereum", 1, noon, completed(100, false), 1000),
+        )
+        .await;
+        insert(
+            &history,
+            request("ethereum", 2, noon, completed(200, true), 3000),
+        )
+        .await;
+        insert(
+            &history,
+            request("ethereum", 3, noon, completed(300, false), 2000),
+        )
+        .await;
+        insert(
+            &history,
+            request(
+                "ethereum",
+                4,
+                noon,
+                RequestEntryState::Failed {
+                    reason: "error".to_string(),
+                    provider_random_number: None,
+                },
+                0,
+            ),
+        )
+        .await;
+        insert(
+            &history,
+            request("optimism", 5, noon, completed(50, false), 500),
+        )
+        .await;
+        insert(
+            &history,
+            request("optimism", 6, noon, completed(50, false), 1500),
+        )
+        .await;
+        insert(
+            &history,
+            request("optimism", 7, noon, RequestEntryState::Pending, 0),
+        )
+        .await;
+        // Requests of other days are not included.
+        insert(
+            &history,
+            request(
+                "ethereum",
+                8,
+                noon + chrono::Duration::days(1),
+                completed(100, false),
+                0,
+            ),
+        )
+        .await;
+
+        let stats = history.update_daily_rollups(day).await.unwrap();
+        let expected = vec![
+            DailyStats {
+                chain_id: "ethereum".to_string(),
+                network_id: 1,
+                day,
+                request_count: 4,
+                failed_count: 1,
+                callback_failed_count: 1,
+                failure_rate: 0.25,
+                median_reveal_latency_ms: Some(2000),
+                gas_used: "600".to_string(),
+            },
+            DailyStats {
+                chain_id: "optimism".to_string(),
+                network_id: 10,
+                day,
+                request_count: 3,
+                failed_count: 0,
+                callback_failed_count: 0,
+                failure_rate: 0.0,
+                median_reveal_latency_ms: Some(1000),
+                gas_used: "100".to_string(),
+            },
+        ];
+        assert_eq!(stats, expected);
+        assert_eq!(history.daily_stats(None, day, day).await.unwrap(), expected);
+        assert_eq!(
+            history
+                .daily_stats(Some(&"optimism".to_string()), day, day)
+                .await
+                .unwrap(),
+            expected[1..]
+        );
+
+        // Recomputing a day replaces its rollups.
+        insert(
+            &history,
+            request("optimism", 9, noon, completed(50, false), 500),
+        )
+        .await;
+        history.update_daily_rollups(day).await.unwrap();
+        let stats = history
+            .daily_stats(Some(&"optimism".to_string()), day, day)
+            .await
+            .unwrap();
+        assert_eq!(stats[0].request_count, 4);
+        assert_eq!(stats[0].median_reveal_latency_ms, Some(500));
+    }
+
+    #[tokio::test]
+    async fn test_retention_keeps_rollups() {
+        let history = History::new_in_memory().await.unwrap();
+        let today = Utc::now().date_naive();
+        let old_day = today - Days::new(10);
+        let recent_day = today - Days::new(2);
+        insert(
+            &history,
+            request("ethereum", 1, day_start(old_day), completed(100, false), 0),
+        )
+        .await;
+        insert(
+            &history,
+            request(
+                "ethereum",
+                2,
+                day_start(recent_day),
+                completed(100, false),
+                0,
+            ),
+        )
+        .await;
+
+        let config = HistoryConfig {
+            retention_days: Some(5),
+        };
+        let oldest = history.oldest_request_day().await.unwrap().unwrap();
+        assert_eq!(oldest, old_day);
+        history.run_maintenance_once(oldest, &config).await.unwrap();
+
+        let requests = history.query().execute().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].sequence, 2);
+        assert_eq!(history.metrics.pruned_requests.get(), 1);
+
+        // The rollups of the pruned days are kept, and not overwritten by later runs.
+        history.run_maintenance_once(oldest, &config).await.unwrap();
+        let stats = history.daily_stats(None, old_day, today).await.unwrap();
+        assert_eq!(
+            stats.iter().map(|s| s.day).collect::<Vec<_>>(),
+            vec![old_day, recent_day]
+        );
+    }
+}
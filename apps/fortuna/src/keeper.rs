@@ -1,43 +1,49 @@
 use {
     crate::{
         api::{BlockchainState, ChainId},
-        chain::ethereum::{InstrumentedPythContract, InstrumentedSignablePythContract},
-        config::{EthereumConfig, KeeperConfig},
+        chain::{cosmwasm::CosmWasmEntropy, ethereum::EthereumWriter, writer::EntropyWriter},
+        config::{
+            CallbackRetryConfig, CosmWasmConfig, EthereumConfig, KeeperConfig, ReplicaConfig,
+        },
         eth_utils::traced_client::RpcMetrics,
         history::History,
         keeper::{
             block::{
-                get_latest_safe_block, process_backlog, process_new_blocks, watch_blocks_wrapper,
-                BlockRange, ProcessParams,
+                get_latest_safe_block, process_backlog, process_new_blocks, BlockRange,
+                BlockWatcher, ProcessParams,
             },
-            commitment::update_commitments_loop,
+            callback::ConsumerAbis,
+            commitment::{rotate_commitment_loop, update_commitments_loop, CommitmentRotation},
             fee::{adjust_fee_wrapper, withdraw_fees_wrapper},
+            replica::{renew_lease_loop, ReplicaCoordinator},
             track::{
                 track_accrued_pyth_fees, track_balance, track_block_timestamp_lag, track_provider,
             },
+            wallet_pool::WalletPool,
         },
     },
     anyhow,
-    ethers::{
-        signers::{LocalWallet, Signer},
-        types::U256,
-    },
+    ethers::types::{Address, U256},
     keeper_metrics::{AccountLabel, KeeperMetrics},
-    std::{collections::HashSet, str::FromStr, sync::Arc},
+    std::{collections::HashSet, sync::Arc},
     tokio::{
         spawn,
-        sync::{mpsc, RwLock},
+        sync::RwLock,
         time::{self, Duration},
     },
     tracing::{self, Instrument},
 };
 
 pub(crate) mod block;
+pub(crate) mod callback;
 pub(crate) mod commitment;
 pub(crate) mod fee;
 pub(crate) mod keeper_metrics;
 pub(crate) mod process_event;
+pub(crate) mod replica;
+pub(crate) mod stuck_tx;
 pub(crate) mod track;
+pub(crate) mod wallet_pool;
 
 /// Track metrics in this interval
 const TRACK_INTERVAL: Duration = Duration::from_secs(10);
@@ -56,99 +62,297 @@ pub enum RequestState {
     Processed,
 }
 
-/// Run threads to handle events for the last `BACKLOG_RANGE` blocks, watch for new blocks and
-/// handle any events for the new blocks.
-#[allow(clippy::too_many_arguments)] // Top level orchestration function that needs to configure several threads
-#[tracing::instrument(name = "keeper", skip_all, fields(chain_id = chain_state.id))]
+/// The chain specific signers and settings that the keeper threads of a chain run with.
+pub struct KeeperChain {
+    /// Signs the reveal and commitment transactions.
+    pub keeper: Arc<dyn EntropyWriter>,
+    /// Signs the fee management transactions, if a fee manager key is configured.
+    pub fee_manager: Option<Arc<dyn EntropyWriter>>,
+    /// The addresses of the other keepers in the replica set on this chain.
+    pub other_keeper_addresses: Vec<Address>,
+    /// Watches the new blocks of the chain for all the providers served on it.
+    pub block_watcher: Arc<BlockWatcher>,
+    pub backlog_range: u64,
+    pub block_delays: Vec<u64>,
+    pub min_keeper_balance: u128,
+    pub min_profit_pct: i64,
+    pub target_profit_pct: i64,
+    pub max_profit_pct: i64,
+    pub fee: u128,
+    pub max_fee: Option<u128>,
+    /// Decodes the errors of failed callbacks.
+    pub consumer_abis: Arc<ConsumerAbis>,
+    /// Reveal the requests whose callback failed again, if set.
+    pub callback_retry: Option<CallbackRetryConfig>,
+    /// Simulate the reveals instead of sending them, and send no other transaction.
+    pub dry_run: bool,
+    /// Registers the next hash chain of the provider before the current one is exhausted, if
+    /// enabled.
+    pub commitment_rotation: Option<Arc<CommitmentRotation>>,
+}
+
+/// Build the contract that uses the keeper wallets to send transactions on an EVM chain. The
+/// providers served on the chain share it, so that the nonces of each keeper wallet are managed in
+/// one place.
+pub fn keeper_writer(
+    keeper_config: &KeeperConfig,
+    chain_eth_config: &EthereumConfig,
+    chain_id: ChainId,
+    network_id: u64,
+    metrics: Arc<KeeperMetrics>,
+    rpc_metrics: Arc<RpcMetrics>,
+) -> anyhow::Result<Arc<dyn EntropyWriter>> {
+    let keeper_private_key = keeper_config.private_key.load()?.ok_or_else(|| {
+        anyhow::anyhow!("Keeper private key is required but not provided in config")
+    })?;
+    let mut private_keys = vec![keeper_private_key];
+    for secret in &keeper_config.pool_private_keys {
+        private_keys.push(
+            secret
+                .load()?
+                .ok_or_else(|| anyhow::anyhow!("Keeper pool private key is empty in config"))?,
+        );
+    }
+    let wallets = private_keys
+        .iter()
+        .map(|private_key| {
+            Ok(Arc::new(EthereumWriter::from_config(
+                chain_eth_config,
+                private_key,
+                chain_id.clone(),
+                rpc_metrics.clone(),
+                network_id,
+            )?) as Arc<dyn EntropyWriter>)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Arc::new(WalletPool::new(wallets, chain_id, metrics)?))
+}
+
+/// Run the keeper threads of a provider on an EVM chain, sending the transactions with `keeper`
+/// (see [`keeper_writer`]) and managing the fees of the provider with `fee_manager_private_key`.
+/// The hash chain of the provider is rotated with `commitment_rotation`, if set. The new blocks are
+/// received from `block_watcher`, which is shared by the providers served on the chain.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "keeper", skip_all, fields(chain_id = chain_state.id, provider = ?chain_state.provider_address))]
 pub async fn run_keeper_threads(
     keeper_config: KeeperConfig,
+    keeper: Arc<dyn EntropyWriter>,
+    block_watcher: Arc<BlockWatcher>,
+    fee_manager_private_key: Option<String>,
+    commitment_rotation: Option<CommitmentRotation>,
     chain_eth_config: EthereumConfig,
     chain_state: BlockchainState,
     metrics: Arc<KeeperMetrics>,
     history: Arc<History>,
     rpc_metrics: Arc<RpcMetrics>,
 ) -> anyhow::Result<()> {
-    tracing::info!("Starting keeper");
-    let latest_safe_block = get_latest_safe_block(&chain_state).in_current_span().await;
-    tracing::info!("Latest safe block: {}", &latest_safe_block);
+    let fee_manager: Option<Arc<dyn EntropyWriter>> = match fee_manager_private_key {
+        Some(fee_manager_private_key) => Some(Arc::new(EthereumWriter::from_config(
+            &chain_eth_config,
+            &fee_manager_private_key,
+            chain_state.id.clone(),
+            rpc_metrics.clone(),
+            chain_state.network_id,
+        )?)),
+        None => None,
+    };
+
+    run_keeper_threads_for_chain(
+        KeeperChain {
+            keeper,
+            fee_manager,
+            other_keeper_addresses: keeper_config.other_keeper_addresses.clone(),
+            block_watcher,
+            backlog_range: chain_eth_config.backlog_range,
+            block_delays: chain_eth_config.block_delays.clone(),
+            min_keeper_balance: chain_eth_config.min_keeper_balance,
+            min_profit_pct: chain_eth_config.min_profit_pct,
+            target_profit_pct: chain_eth_config.target_profit_pct,
+            max_profit_pct: chain_eth_config.max_profit_pct,
+            fee: chain_eth_config.fee,
+            max_fee: chain_eth_config.max_fee,
+            consumer_abis: Arc::new(ConsumerAbis::load(&chain_eth_config.consumer_abis)?),
+            callback_retry: chain_eth_config.callback_retry.clone(),
+            dry_run: keeper_config.dry_run,
+            commitment_rotation: commitment_rotation.map(Arc::new),
+        },
+        keeper_config.replica_config,
+        chain_state,
+        metrics,
+        history,
+    )
+    .in_current_span()
+    .await
+}
 
+/// Run the keeper threads for a CosmWasm chain.
+#[tracing::instrument(name = "keeper", skip_all, fields(chain_id = chain_state.id))]
+pub async fn run_cosmwasm_keeper_threads(
+    keeper_config: KeeperConfig,
+    chain_config: CosmWasmConfig,
+    chain_state: BlockchainState,
+    metrics: Arc<KeeperMetrics>,
+    history: Arc<History>,
+) -> anyhow::Result<()> {
+    if keeper_config.dry_run {
+        anyhow::bail!("Dry-run mode is not supported on CosmWasm chains");
+    }
     let keeper_private_key = keeper_config.private_key.load()?.ok_or_else(|| {
         anyhow::anyhow!("Keeper private key is required but not provided in config")
     })?;
 
-    // Contract that uses the keeper wallet to send transactions
-    let contract = Arc::new(InstrumentedSignablePythContract::from_config(
-        &chain_eth_config,
-        &keeper_private_key,
-        chain_state.id.clone(),
-        rpc_metrics.clone(),
-        chain_state.network_id,
+    let keeper = Arc::new(CosmWasmEntropy::from_config(
+        &chain_config,
+        Some(&keeper_private_key),
     )?);
-    let keeper_address = contract.wallet().address();
+
+    let fee_manager: Option<Arc<dyn EntropyWriter>> =
+        match load_fee_manager_private_key(&keeper_config)? {
+            Some(fee_manager_private_key) => Some(Arc::new(CosmWasmEntropy::from_config(
+                &chain_config,
+                Some(&fee_manager_private_key),
+            )?)),
+            None => None,
+        };
+
+    run_keeper_threads_for_chain(
+        KeeperChain {
+            keeper,
+            fee_manager,
+            other_keeper_addresses: chain_config.other_keeper_addresses()?,
+            block_watcher: Arc::new(BlockWatcher::new(None)),
+            backlog_range: chain_config.backlog_range,
+            block_delays: chain_config.block_delays.clone(),
+            min_keeper_balance: chain_config.min_keeper_balance,
+            min_profit_pct: chain_config.min_profit_pct,
+            target_profit_pct: chain_config.target_profit_pct,
+            max_profit_pct: chain_config.max_profit_pct,
+            fee: chain_config.fee,
+            max_fee: chain_config.max_fee,
+            consumer_abis: Default::default(),
+            callback_retry: None,
+            dry_run: false,
+            commitment_rotation: None,
+        },
+        keeper_config.replica_config,
+        chain_state,
+        metrics,
+        history,
+    )
+    .in_current_span()
+    .await
+}
+
+fn load_fee_manager_private_key(keeper_config: &KeeperConfig) -> anyhow::Result<Option<String>> {
+    match keeper_config.fee_manager_private_key {
+        Some(ref secret) => secret.load(),
+        None => Ok(None),
+    }
+}
+
+/// Run threads to handle events for the last `BACKLOG_RANGE` blocks and handle any events for the
+/// new blocks received from the block watcher of the chain.
+pub async fn run_keeper_threads_for_chain(
+    keeper_chain: KeeperChain,
+    replica_config: Option<ReplicaConfig>,
+    chain_state: BlockchainState,
+    metrics: Arc<KeeperMetrics>,
+    history: Arc<History>,
+) -> anyhow::Result<()> {
+    tracing::info!("Starting keeper");
+    // Subscribe to the new blocks before fetching the latest safe block, so that the blocks after
+    // the backlog are all received.
+    let rx = keeper_chain
+        .block_watcher
+        .subscribe(&chain_state)
+        .in_current_span()
+        .await;
+    let latest_safe_block = get_latest_safe_block(&chain_state).in_current_span().await;
+    tracing::info!("Latest safe block: {}", &latest_safe_block);
+
+    let contract = keeper_chain.keeper.clone();
+    let keeper_address = contract.address();
+    let keeper_addresses = contract.addresses();
+
+    // In dry-run mode, the keeper simulates all the requests, as the other replicas may not.
+    let replica_config = if keeper_chain.dry_run {
+        tracing::info!("Running in dry-run mode: simulating the reveals without sending them");
+        None
+    } else {
+        replica_config
+    };
 
     let fulfilled_requests_cache = Arc::new(RwLock::new(HashSet::<u64>::new()));
 
+    // If replica leases are configured, spawn a thread that renews the lease of this replica in the
+    // history database. The keeper address identifies the replica, as each replica has its own key.
+    let replica_coordinator = match replica_config.as_ref().and_then(|c| c.lease.clone()) {
+        Some(lease_config) => {
+            let coordinator = Arc::new(ReplicaCoordinator::new(
+                history.clone(),
+                chain_state.id.clone(),
+                format!("{keeper_address:?}"),
+                lease_config,
+            ));
+            // Renew the lease once before processing the backlog, so that it is split among the replicas.
+            if let Err(e) = coordinator.renew().await {
+                tracing::error!("Error renewing replica lease: {:?}", e);
+            }
+            spawn(renew_lease_loop(coordinator.clone(), metrics.clone()).in_current_span());
+            Some(coordinator)
+        }
+        None => None,
+    };
+
     // Spawn a thread to handle the events from last backlog_range blocks.
     let process_params = ProcessParams {
         chain_state: chain_state.clone(),
         contract: contract.clone(),
-        escalation_policy: chain_eth_config.escalation_policy.to_policy(),
-        replica_config: keeper_config.replica_config.clone(),
+        replica_config,
+        replica_coordinator,
         metrics: metrics.clone(),
         fulfilled_requests_cache,
-        history,
+        history: history.clone(),
+        consumer_abis: keeper_chain.consumer_abis.clone(),
+        callback_retry: keeper_chain.callback_retry.clone(),
+        dry_run: keeper_chain.dry_run,
     };
     spawn(
         process_backlog(
             process_params.clone(),
             BlockRange {
-                from: latest_safe_block.saturating_sub(chain_eth_config.backlog_range),
+                from: latest_safe_block.saturating_sub(keeper_chain.backlog_range),
                 to: latest_safe_block,
             },
-            chain_eth_config.block_delays.clone(),
+            keeper_chain.block_delays.clone(),
         )
         .in_current_span(),
     );
 
-    let (tx, rx) = mpsc::channel::<BlockRange>(1000);
-    // Spawn a thread to watch for new blocks and send the range of blocks for which events has not been handled to the `tx` channel.
-    spawn(watch_blocks_wrapper(chain_state.clone(), latest_safe_block, tx).in_current_span());
-
     // Spawn a thread for block processing with configured delays
     spawn(
         process_new_blocks(
             process_params.clone(),
             rx,
-            chain_eth_config.block_delays.clone(),
+            keeper_chain.block_delays.clone(),
         )
         .in_current_span(),
     );
 
     // If fee manager private key is provided, spawn fee withdrawal and adjustment threads
-    let fee_manager_private_key = if let Some(ref secret) = keeper_config.fee_manager_private_key {
-        secret.load()?
-    } else {
-        None
-    };
-
-    if let Some(fee_manager_private_key) = fee_manager_private_key.clone() {
-        let contract_as_fee_manager = Arc::new(InstrumentedSignablePythContract::from_config(
-            &chain_eth_config,
-            &fee_manager_private_key,
-            chain_state.id.clone(),
-            rpc_metrics.clone(),
-            chain_state.network_id,
-        )?);
-
+    if keeper_chain.dry_run {
+        tracing::info!("Fee withdrawal, fee adjustment and commitment update threads will not run in dry-run mode.");
+    } else if let Some(contract_as_fee_manager) = keeper_chain.fee_manager.clone() {
         // Spawn a thread that periodically withdraws fees to the fee manager and keeper.
         spawn(
             withdraw_fees_wrapper(
+                chain_state.contract.clone(),
                 contract_as_fee_manager.clone(),
                 chain_state.provider_address,
                 WITHDRAW_INTERVAL,
-                U256::from(chain_eth_config.min_keeper_balance),
-                keeper_address,
-                keeper_config.other_keeper_addresses.clone(),
+                U256::from(keeper_chain.min_keeper_balance),
+                keeper_addresses.clone(),
+                keeper_chain.other_keeper_addresses.clone(),
             )
             .in_current_span(),
         );
@@ -160,16 +364,15 @@ pub async fn run_keeper_threads(
                 chain_state.clone(),
                 chain_state.provider_address,
                 ADJUST_FEE_INTERVAL,
-                chain_eth_config.legacy_tx,
                 // NOTE: unwrap() here so we panic early if someone configures these values below -100.
-                u64::try_from(100 + chain_eth_config.min_profit_pct)
+                u64::try_from(100 + keeper_chain.min_profit_pct)
                     .expect("min_profit_pct must be >= -100"),
-                u64::try_from(100 + chain_eth_config.target_profit_pct)
+                u64::try_from(100 + keeper_chain.target_profit_pct)
                     .expect("target_profit_pct must be >= -100"),
-                u64::try_from(100 + chain_eth_config.max_profit_pct)
+                u64::try_from(100 + keeper_chain.max_profit_pct)
                     .expect("max_profit_pct must be >= -100"),
-                chain_eth_config.fee,
-                chain_eth_config.max_fee,
+                keeper_chain.fee,
+                keeper_chain.max_fee,
                 metrics.clone(),
             )
             .in_current_span(),
@@ -180,36 +383,35 @@ pub async fn run_keeper_threads(
         );
     }
 
-    spawn(update_commitments_loop(contract.clone(), chain_state.clone()).in_current_span());
+    if !keeper_chain.dry_run {
+        spawn(update_commitments_loop(contract.clone(), chain_state.clone()).in_current_span());
+
+        if let Some(commitment_rotation) = keeper_chain.commitment_rotation.clone() {
+            spawn(
+                rotate_commitment_loop(
+                    commitment_rotation,
+                    chain_state.clone(),
+                    process_params.replica_config.clone(),
+                    process_params.replica_coordinator.clone(),
+                    metrics.clone(),
+                    history.clone(),
+                )
+                .in_current_span(),
+            );
+        }
+    }
 
     // Spawn a thread to track the provider info and the balance of the keeper & fee manager
     spawn(
         async move {
             let chain_id = chain_state.id.clone();
-            let chain_config = chain_eth_config.clone();
             let provider_address = chain_state.provider_address;
             let keeper_metrics = metrics.clone();
-            let fee_manager_address_option = fee_manager_private_key.as_ref().and_then(
-                |private_key| match LocalWallet::from_str(private_key) {
-                    Ok(wallet) => Some(wallet.address()),
-                    Err(e) => {
-                        tracing::error!("Invalid fee manager private key: {:?}", e);
-                        None
-                    }
-                },
-            );
-
-            let contract = match InstrumentedPythContract::from_config(
-                &chain_config,
-                chain_id.clone(),
-                rpc_metrics,
-            ) {
-                Ok(r) => r,
-                Err(e) => {
-                    tracing::error!("Error while connecting to pythnet contract. error: {:?}", e);
-                    return;
-                }
-            };
+            let fee_manager_address_option = keeper_chain
+                .fee_manager
+                .as_ref()
+                .map(|fee_manager| fee_manager.address());
+            let contract = chain_state.contract.clone();
 
             loop {
                 time::sleep(TRACK_INTERVAL).await;
@@ -229,23 +431,30 @@ pub async fn run_keeper_threads(
                     continue;
                 }
 
-                if let Err(e) = track_balance(
-                    chain_id.clone(),
-                    contract.client(),
-                    keeper_address,
-                    keeper_metrics.clone(),
-                )
-                .await
-                {
-                    tracing::error!("Error tracking balance for keeper: {:?}", e);
+                let mut keeper_balances_tracked = true;
+                for &address in &keeper_addresses {
+                    if let Err(e) = track_balance(
+                        chain_id.clone(),
+                        contract.clone(),
+                        address,
+                        keeper_metrics.clone(),
+                    )
+                    .await
+                    {
+                        tracing::error!("Error tracking balance for keeper {:?}: {:?}", address, e);
+                        keeper_balances_tracked = false;
+                        break;
+                    }
+                }
+                if !keeper_balances_tracked {
                     continue;
                 }
 
                 if let Some(fee_manager_address) = fee_manager_address_option {
-                    if fee_manager_address != keeper_address {
+                    if !keeper_addresses.contains(&fee_manager_address) {
                         if let Err(e) = track_balance(
                             chain_id.clone(),
-                            contract.client(),
+                            contract.clone(),
                             fee_manager_address,
                             keeper_metrics.clone(),
                         )
@@ -269,7 +478,7 @@ pub async fn run_keeper_threads(
 
                 if let Err(e) = track_block_timestamp_lag(
                     chain_id.clone(),
-                    contract.client(),
+                    contract.clone(),
                     keeper_metrics.clone(),
                 )
                 .await
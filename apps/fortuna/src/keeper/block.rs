@@ -1,21 +1,30 @@
 use {
     crate::{
         api::BlockchainState,
-        chain::{ethereum::InstrumentedSignablePythContract, reader::BlockNumber},
-        config::ReplicaConfig,
-        eth_utils::utils::EscalationPolicy,
+        chain::{
+            reader::{BlockNumber, BlockStatus},
+            writer::EntropyWriter,
+        },
+        config::{CallbackRetryConfig, ReplicaConfig},
         history::History,
         keeper::{
+            callback::ConsumerAbis,
             keeper_metrics::{ChainIdLabel, KeeperMetrics},
             process_event::process_event_with_backoff,
+            replica::ReplicaCoordinator,
         },
     },
-    anyhow::Result,
+    anyhow::{bail, Result},
+    ethers::providers::{Middleware, Provider, Ws},
+    futures::{Stream, StreamExt},
     std::time::{SystemTime, UNIX_EPOCH},
-    std::{collections::HashSet, sync::Arc},
+    std::{collections::HashSet, pin::pin, sync::Arc},
     tokio::{
         spawn,
-        sync::{mpsc, RwLock},
+        sync::{
+            broadcast::{self, error::RecvError},
+            OnceCell, RwLock,
+        },
         time::{self, Duration},
     },
     tracing::{self, Instrument},
@@ -29,6 +38,13 @@ const BLOCK_BATCH_SIZE: u64 = 100;
 const POLL_INTERVAL: Duration = Duration::from_secs(2);
 /// Retry last N blocks
 const RETRY_PREVIOUS_BLOCKS: u64 = 100;
+/// How much to wait before polling the latest block when new blocks are received over a
+/// subscription. Polling then only fills the gaps of the subscription, so it can be less frequent.
+const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How long to poll for new blocks after the block subscription fails, before subscribing again.
+const SUBSCRIPTION_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+/// How many block ranges a keeper can fall behind the block watcher of its chain.
+const BLOCK_RANGE_CHANNEL_CAPACITY: usize = 1000;
 
 #[derive(Debug, Clone)]
 pub struct BlockRange {
@@ -38,13 +54,21 @@ pub struct BlockRange {
 
 #[derive(Clone)]
 pub struct ProcessParams {
-    pub contract: Arc<InstrumentedSignablePythContract>,
-    pub escalation_policy: EscalationPolicy,
+    /// The keeper's signer for the contract in `chain_state`.
+    pub contract: Arc<dyn EntropyWriter>,
     pub chain_state: BlockchainState,
     pub replica_config: Option<ReplicaConfig>,
+    /// Assigns the requests to the replicas holding a lease, if leases are configured.
+    pub replica_coordinator: Option<Arc<ReplicaCoordinator>>,
     pub metrics: Arc<KeeperMetrics>,
     pub history: Arc<History>,
     pub fulfilled_requests_cache: Arc<RwLock<HashSet<u64>>>,
+    /// Decodes the errors of failed callbacks.
+    pub consumer_abis: Arc<ConsumerAbis>,
+    /// Reveal the requests whose callback failed again, if set.
+    pub callback_retry: Option<CallbackRetryConfig>,
+    /// Simulate the reveals and record them in the history instead of sending them.
+    pub dry_run: bool,
 }
 
 /// Get the latest safe block number for the chain. Retry internally if there is an error.
@@ -184,26 +208,105 @@ pub async fn process_single_block_batch(block_range: BlockRange, process_params:
     }
 }
 
+/// Watches the blocks of a chain once for all the providers served on it, and broadcasts the ranges
+/// of new blocks to their keepers.
+pub struct BlockWatcher {
+    geth_rpc_wss: Option<String>,
+    tx: OnceCell<broadcast::Sender<BlockRange>>,
+}
+
+impl BlockWatcher {
+    /// If `geth_rpc_wss` is provided, new blocks are received over a subscription to that endpoint
+    /// (see [`watch_blocks_wrapper`]).
+    pub fn new(geth_rpc_wss: Option<String>) -> Self {
+        Self {
+            geth_rpc_wss,
+            tx: OnceCell::new(),
+        }
+    }
+
+    /// Subscribe to the ranges of new blocks. The first subscription spawns the thread watching the
+    /// blocks of the chain of `chain_state`, starting from its latest safe block. The ranges sent
+    /// before the first subscription are sent again, as sending fails without receivers.
+    pub async fn subscribe(
+        &self,
+        chain_state: &BlockchainState,
+    ) -> broadcast::Receiver<BlockRange> {
+        self.tx
+            .get_or_init(|| async {
+                let (tx, _) = broadcast::channel(BLOCK_RANGE_CHANNEL_CAPACITY);
+                let latest_safe_block = get_latest_safe_block(chain_state).in_current_span().await;
+                spawn(
+                    watch_blocks_wrapper(
+                        chain_state.clone(),
+                        latest_safe_block,
+                        tx.clone(),
+                        self.geth_rpc_wss.clone(),
+                    )
+                    .instrument(tracing::info_span!("keeper", chain_id = chain_state.id)),
+                );
+                tx
+            })
+            .await
+            .subscribe()
+    }
+}
+
 /// Wrapper for the `watch_blocks` method. If there was an error while watching, it will retry after a delay.
-/// It retries indefinitely.
+/// It retries indefinitely. If `geth_rpc_wss` is provided, new blocks are received over a subscription
+/// to that endpoint instead of polling for them. Whenever the subscription fails, blocks are polled for
+/// `SUBSCRIPTION_RETRY_INTERVAL` before subscribing again, so that block processing does not stop while
+/// the websocket endpoint is down.
 #[tracing::instrument(name = "watch_blocks", skip_all, fields(
     initial_safe_block = latest_safe_block
 ))]
 pub async fn watch_blocks_wrapper(
     chain_state: BlockchainState,
     latest_safe_block: BlockNumber,
-    tx: mpsc::Sender<BlockRange>,
+    tx: broadcast::Sender<BlockRange>,
+    geth_rpc_wss: Option<String>,
 ) {
     let mut last_safe_block_processed = latest_safe_block;
     loop {
-        if let Err(e) = watch_blocks(
-            chain_state.clone(),
-            &mut last_safe_block_processed,
-            tx.clone(),
-        )
-        .in_current_span()
-        .await
-        {
+        let result = match geth_rpc_wss {
+            Some(ref geth_rpc_wss) => {
+                if let Err(e) = watch_blocks_with_subscription(
+                    chain_state.clone(),
+                    geth_rpc_wss,
+                    &mut last_safe_block_processed,
+                    tx.clone(),
+                )
+                .in_current_span()
+                .await
+                {
+                    tracing::error!(
+                        "Block subscription failed, polling for blocks until it is restored. error: {:?}",
+                        e
+                    );
+                }
+                time::timeout(
+                    SUBSCRIPTION_RETRY_INTERVAL,
+                    watch_blocks(
+                        chain_state.clone(),
+                        &mut last_safe_block_processed,
+                        tx.clone(),
+                    )
+                    .in_current_span(),
+                )
+                .await
+                .unwrap_or(Ok(()))
+            }
+            None => {
+                watch_blocks(
+                    chain_state.clone(),
+                    &mut last_safe_block_processed,
+                    tx.clone(),
+                )
+                .in_current_span()
+                .await
+            }
+        };
+        if let Err(e) = result {
             tracing::error!("watching blocks. error: {:?}", e);
             time::sleep(RETRY_INTERVAL).await;
         }
@@ -217,7 +320,7 @@ pub async fn watch_blocks_wrapper(
 pub async fn watch_blocks(
     chain_state: BlockchainState,
     last_safe_block_processed: &mut BlockNumber,
-    tx: mpsc::Sender<BlockRange>,
+    tx: broadcast::Sender<BlockRange>,
 ) -> Result<()> {
     tracing::info!("Watching blocks to handle new events");
 
@@ -226,70 +329,175 @@ pub async fn watch_blocks(
 
         let latest_safe_block = get_latest_safe_block(&chain_state).in_current_span().await;
         if latest_safe_block > *last_safe_block_processed {
-            let mut from = latest_safe_block.saturating_sub(RETRY_PREVIOUS_BLOCKS);
-
-            // In normal situation, the difference between latest and last safe block should not be more than 2-3 (for arbitrum it can be 10)
-            // TODO: add a metric for this in separate PR. We need alerts
-            // But in extreme situation, where we were unable to send the block range multiple times, the difference between latest_safe_block and
-            // last_safe_block_processed can grow. It is fine to not have the retry mechanisms for those earliest blocks as we expect the rpc
-            // to be in consistency after this much time.
-            if from > *last_safe_block_processed {
-                from = *last_safe_block_processed;
-            }
-            match tx
-                .send(BlockRange {
-                    from,
-                    to: latest_safe_block,
-                })
-                .await
-            {
-                Ok(_) => {
-                    tracing::info!(
-                        from_block = from,
-                        to_block = &latest_safe_block,
-                        "Block range sent to handle events",
-                    );
-                    *last_safe_block_processed = latest_safe_block;
+            send_block_range(
+                latest_safe_block,
+                last_safe_block_processed,
+                RETRY_PREVIOUS_BLOCKS,
+                &tx,
+            )
+            .await;
+        }
+    }
+}
+
+/// Subscribe to the new blocks (`eth_subscribe("newHeads")`) of the websocket endpoint `geth_rpc_wss` and
+/// watch them with `watch_block_stream`. As with polling, we subscribe to blocks rather than logs so that
+/// missed blocks are detected. Returns an error if the connection or the subscription fails.
+pub async fn watch_blocks_with_subscription(
+    chain_state: BlockchainState,
+    geth_rpc_wss: &str,
+    last_safe_block_processed: &mut BlockNumber,
+    tx: broadcast::Sender<BlockRange>,
+) -> Result<()> {
+    let provider = Provider::<Ws>::connect(geth_rpc_wss).await?;
+    let heads = provider
+        .subscribe_blocks()
+        .await?
+        .filter_map(|block| async move { block.number.map(|number| number.as_u64()) });
+    tracing::info!("Subscribed to new blocks");
+    watch_block_stream(
+        chain_state,
+        heads,
+        SUBSCRIPTION_POLL_INTERVAL,
+        last_safe_block_processed,
+        tx,
+    )
+    .await
+}
+
+/// Send the blocks up to each new block number of `heads` for which events have not been handled to
+/// the `tx` channel. These ranges don't retry previous blocks, so that events are handled as soon as
+/// possible with a single rpc call. Every `poll_interval`, the latest safe block is also polled and the
+/// last `RETRY_PREVIOUS_BLOCKS` blocks are sent, to handle the blocks that the subscription missed or
+/// that were reorganized. Returns an error when `heads` ends.
+pub async fn watch_block_stream(
+    chain_state: BlockchainState,
+    heads: impl Stream<Item = BlockNumber>,
+    poll_interval: Duration,
+    last_safe_block_processed: &mut BlockNumber,
+    tx: broadcast::Sender<BlockRange>,
+) -> Result<()> {
+    let mut heads = pin!(heads);
+    let mut poll = time::interval(poll_interval);
+    poll.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            head = heads.next() => {
+                let Some(head) = head else {
+                    bail!("Block subscription ended");
+                };
+                // The heads are the latest blocks, other confirmation levels need to be fetched.
+                let latest_safe_block = match chain_state.confirmed_block_status {
+                    BlockStatus::Latest => head.saturating_sub(chain_state.reveal_delay_blocks),
+                    _ => get_latest_safe_block(&chain_state).in_current_span().await,
+                };
+                if latest_safe_block > *last_safe_block_processed {
+                    send_block_range(latest_safe_block, last_safe_block_processed, 0, &tx).await;
                 }
-                Err(e) => {
-                    tracing::error!(
-                        from_block = from,
-                        to_block = &latest_safe_block,
-                        "Error while sending block range to handle events. These will be handled in next call. error: {:?}",
-                        e
-                    );
+            }
+            _ = poll.tick() => {
+                let latest_safe_block = get_latest_safe_block(&chain_state).in_current_span().await;
+                if latest_safe_block >= *last_safe_block_processed {
+                    send_block_range(
+                        latest_safe_block,
+                        last_safe_block_processed,
+                        RETRY_PREVIOUS_BLOCKS,
+                        &tx,
+                    )
+                    .await;
                 }
-            };
+            }
         }
     }
 }
 
+/// Send the range of blocks from `last_safe_block_processed` (or `retry_previous_blocks` blocks before
+/// `latest_safe_block` if that is earlier) to `latest_safe_block` to the `tx` channel, and update
+/// `last_safe_block_processed` if it was sent.
+async fn send_block_range(
+    latest_safe_block: BlockNumber,
+    last_safe_block_processed: &mut BlockNumber,
+    retry_previous_blocks: u64,
+    tx: &broadcast::Sender<BlockRange>,
+) {
+    let mut from = latest_safe_block.saturating_sub(retry_previous_blocks);
+
+    // In normal situation, the difference between latest and last safe block should not be more than 2-3 (for arbitrum it can be 10)
+    // TODO: add a metric for this in separate PR. We need alerts
+    // But in extreme situation, where we were unable to send the block range multiple times, the difference between latest_safe_block and
+    // last_safe_block_processed can grow. It is fine to not have the retry mechanisms for those earliest blocks as we expect the rpc
+    // to be in consistency after this much time.
+    if from > *last_safe_block_processed {
+        from = *last_safe_block_processed;
+    }
+    match tx.send(BlockRange {
+        from,
+        to: latest_safe_block,
+    }) {
+        Ok(_) => {
+            tracing::info!(
+                from_block = from,
+                to_block = &latest_safe_block,
+                "Block range sent to handle events",
+            );
+            *last_safe_block_processed = latest_safe_block;
+        }
+        Err(e) => {
+            tracing::error!(
+                from_block = from,
+                to_block = &latest_safe_block,
+                "Error while sending block range to handle events. These will be handled in next call. error: {:?}",
+                e
+            );
+        }
+    };
+}
+
 /// It waits on rx channel to receive block ranges and then calls process_block_range to process them
-/// for each configured block delay.
+/// for each configured block delay. If the keeper falls behind the block watcher and misses some
+/// ranges, the next range is extended to the last block processed.
 #[tracing::instrument(skip_all)]
 pub async fn process_new_blocks(
     process_params: ProcessParams,
-    mut rx: mpsc::Receiver<BlockRange>,
+    mut rx: broadcast::Receiver<BlockRange>,
     block_delays: Vec<u64>,
 ) {
     tracing::info!("Waiting for new block ranges to process");
+    let mut last_block_processed: Option<BlockNumber> = None;
+    let mut lagged = false;
     loop {
-        if let Some(block_range) = rx.recv().await {
-            // Process blocks immediately first
-            process_block_range(block_range.clone(), process_params.clone())
+        let mut block_range = match rx.recv().await {
+            Ok(block_range) => block_range,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("Missed {} block ranges to process", skipped);
+                lagged = true;
+                continue;
+            }
+            Err(RecvError::Closed) => {
+                tracing::error!("Block watcher stopped, no new blocks will be processed");
+                return;
+            }
+        };
+        if let (true, Some(last_block_processed)) = (lagged, last_block_processed) {
+            block_range.from = block_range.from.min(last_block_processed + 1);
+        }
+        lagged = false;
+        last_block_processed = Some(block_range.to);
+
+        // Process blocks immediately first
+        process_block_range(block_range.clone(), process_params.clone())
+            .in_current_span()
+            .await;
+
+        // Then process with each configured delay
+        for delay in &block_delays {
+            let adjusted_range = BlockRange {
+                from: block_range.from.saturating_sub(*delay),
+                to: block_range.to.saturating_sub(*delay),
+            };
+            process_block_range(adjusted_range, process_params.clone())
                 .in_current_span()
                 .await;
-
-            // Then process with each configured delay
-            for delay in &block_delays {
-                let adjusted_range = BlockRange {
-                    from: block_range.from.saturating_sub(*delay),
-                    to: block_range.to.saturating_sub(*delay),
-                };
-                process_block_range(adjusted_range, process_params.clone())
-                    .in_current_span()
-                    .await;
-            }
         }
     }
 }
@@ -320,3 +528,388 @@ pub async fn process_backlog(
     }
     tracing::info!("Backlog processed");
 }
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            api::StateTag,
+            chain::{
+                cosmwasm::{
+                    decode_account_address,
+                    mock::{chain_config, contract_addr, MockLcd, KEEPER_PRIVATE_KEY},
+                    CosmWasmEntropy,
+                },
+                reader::{mock::MockEntropyReader, BlockStatus, EntropyReader},
+                writer::mock::MockEntropyWriter,
+            },
+            config::CallbackRetryConfig,
+            history::{CallbackFailureKind, RequestEntryState},
+            state::{HashChainState, MonitoredHashChainState, PebbleHashChain},
+        },
+        ethers::types::{Address, U256},
+    };
+
+    fn hash_chain() -> Arc<HashChainState> {
+        Arc::new(HashChainState::from_chain_at_offset(
+            0,
+            PebbleHashChain::new([0u8; 32], 100, 1),
+        ))
+    }
+
+    async fn process_params(
+        reader: Arc<dyn EntropyReader>,
+        writer: Arc<dyn EntropyWriter>,
+        provider_address: Address,
+    ) -> ProcessParams {
+        ProcessParams {
+            contract: writer,
+            chain_state: BlockchainState {
+                id: "test".into(),
+                network_id: 1,
+                state: Arc::new(MonitoredHashChainState::new(
+                    hash_chain(),
+                    Default::default(),
+                    "test".into(),
+                    provider_address,
+                )),
+                contract: reader,
+                provider_address,
+                reveal_delay_blocks: 0,
+                confirmed_block_status: BlockStatus::Latest,
+            },
+            replica_config: None,
+            replica_coordinator: None,
+            metrics: Default::default(),
+            history: Arc::new(History::new_in_memory().await.unwrap()),
+            fulfilled_requests_cache: Default::default(),
+            consumer_abis: Default::default(),
+            callback_retry: None,
+            dry_run: false,
+        }
+    }
+
+    /// Run the keeper loop up to the latest block and wait for `sequence_numbers` to be revealed,
+    /// checking the revealed values against the provider's hash chain.
+    async fn run_keeper_loop(process_params: ProcessParams, sequence_numbers: &[u64]) {
+        let latest_block = get_latest_safe_block(&process_params.chain_state).await;
+        process_block_range(
+            BlockRange {
+                from: 0,
+                to: latest_block,
+            },
+            process_params.clone(),
+        )
+        .await;
+
+        let completed = tokio::time::timeout(Duration::from_secs(20), async {
+            loop {
+                let completed = process_params
+                    .history
+                    .query()
+                    .state(StateTag::Completed)
+                    .execute()
+                    .await
+                    .unwrap();
+                if completed.len() == sequence_numbers.len() {
+                    return completed;
+                }
+                time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("requests were not revealed");
+
+        let chain = hash_chain();
+        for request in completed {
+            assert!(sequence_numbers.contains(&request.sequence));
+            match request.state {
+                RequestEntryState::Completed {
+                    provider_random_number,
+                    callback_failed,
+                    ..
+                } => {
+                    assert_eq!(
+                        provider_random_number,
+                        chain.reveal(request.sequence).unwrap()
+                    );
+                    assert!(!callback_failed);
+                }
+                _ => panic!("request {} is not completed", request.sequence),
+            }
+        }
+    }
+
+    async fn next_range(rx: &mut broadcast::Receiver<BlockRange>) -> (BlockNumber, BlockNumber) {
+        let range = time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("no block range was sent")
+            .unwrap();
+        (range.from, range.to)
+    }
+
+    #[tokio::test]
+    async fn test_watch_block_stream() {
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        let writer = Arc::new(MockEntropyWriter::new(
+            Address::from_low_u64_be(10),
+            reader.clone(),
+        ));
+        let params = process_params(reader, writer, Address::from_low_u64_be(1)).await;
+        let (heads_tx, heads_rx) = futures::channel::mpsc::unbounded();
+        let (tx, mut rx) = broadcast::channel(10);
+        let watcher = spawn(async move {
+            let mut last_safe_block_processed = 10;
+            watch_block_stream(
+                params.chain_state,
+                heads_rx,
+                Duration::from_secs(3600),
+                &mut last_safe_block_processed,
+                tx,
+            )
+            .await
+        });
+        // The first poll retries the previous blocks.
+        assert_eq!(next_range(&mut rx).await, (0, 10));
+
+        // New heads only send the new blocks, including the ones the subscription missed.
+        heads_tx.unbounded_send(11).unwrap();
+        assert_eq!(next_range(&mut rx).await, (10, 11));
+        heads_tx.unbounded_send(14).unwrap();
+        assert_eq!(next_range(&mut rx).await, (11, 14));
+
+        // Heads that were already handled are ignored.
+        heads_tx.unbounded_send(12).unwrap();
+        heads_tx.unbounded_send(15).unwrap();
+        assert_eq!(next_range(&mut rx).await, (14, 15));
+
+        drop(heads_tx);
+        assert!(watcher.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_blocks_falls_back_to_polling() {
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        let writer = Arc::new(MockEntropyWriter::new(
+            Address::from_low_u64_be(10),
+            reader.clone(),
+        ));
+        let params = process_params(reader.clone(), writer, Address::from_low_u64_be(1)).await;
+        let (tx, mut rx) = broadcast::channel(10);
+        // Nothing listens on this endpoint, so the subscription fails right away.
+        let watcher = spawn(watch_blocks_wrapper(
+            params.chain_state,
+            10,
+            tx,
+            Some("ws://127.0.0.1:1".to_string()),
+        ));
+
+        reader.set_block_number(12);
+        assert_eq!(next_range(&mut rx).await, (0, 12));
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn test_block_watcher_is_shared_by_the_providers() {
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        let writer = Arc::new(MockEntropyWriter::new(
+            Address::from_low_u64_be(10),
+            reader.clone(),
+        ));
+        let first =
+            process_params(reader.clone(), writer.clone(), Address::from_low_u64_be(1)).await;
+        let second = process_params(reader.clone(), writer, Address::from_low_u64_be(2)).await;
+        let block_watcher = BlockWatcher::new(None);
+        let mut first_rx = block_watcher.subscribe(&first.chain_state).await;
+        let mut second_rx = block_watcher.subscribe(&second.chain_state).await;
+
+        reader.set_block_number(12);
+        assert_eq!(next_range(&mut first_rx).await, (0, 12));
+        assert_eq!(next_range(&mut second_rx).await, (0, 12));
+    }
+
+    #[tokio::test]
+    async fn test_keeper_loop_with_mock_chain() {
+        let provider = Address::from_low_u64_be(1);
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        reader.insert_with_callback(provider, 1, 2, [1u8; 32]);
+        reader.insert_with_callback(provider, 2, 4, [2u8; 32]);
+        // Requests of other providers are ignored
+        reader.insert_with_callback(Address::from_low_u64_be(2), 3, 4, [3u8; 32]);
+        let writer = Arc::new(MockEntropyWriter::new(
+            Address::from_low_u64_be(10),
+            reader.clone(),
+        ));
+
+        run_keeper_loop(
+            process_params(reader.clone(), writer.clone(), provider).await,
+            &[1, 2],
+        )
+        .await;
+        assert_eq!(writer.reveals.read().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_keeper_loop_retries_failed_callbacks() {
+        let provider = Address::from_low_u64_be(1);
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        reader.insert_with_callback(provider, 1, 2, [1u8; 32]);
+        reader.insert_with_callback(provider, 2, 2, [2u8; 32]);
+        let writer = Arc::new(MockEntropyWriter::new(
+            Address::from_low_u64_be(10),
+            reader.clone(),
+        ));
+        // The callback of 1 succeeds with twice the gas limit of the request, the callback of 2
+        // needs more than the maximum gas limit.
+        writer
+            .set_callback_gas(1, 150_000)
+            .set_callback_gas(2, 10_000_000);
+        let mut params = process_params(reader.clone(), writer.clone(), provider).await;
+        params.callback_retry = Some(CallbackRetryConfig {
+            retry_on: vec![CallbackFailureKind::OutOfGas],
+            max_attempts: 2,
+            delay_seconds: 0,
+            gas_limit_multiplier_pct: 200,
+            max_gas_limit: 5_000_000,
+        });
+        let latest_block = get_latest_safe_block(&params.chain_state).await;
+        process_block_range(
+            BlockRange {
+                from: 0,
+                to: latest_block,
+            },
+            params.clone(),
+        )
+        .await;
+
+        let callback_failures = tokio::time::timeout(Duration::from_secs(20), async {
+            loop {
+                let requests = params.history.query().execute().await.unwrap();
+                let callback_failures: Vec<_> = requests
+                    .into_iter()
+                    .filter_map(|request| match request.state {
+                        RequestEntryState::Completed {
+                            callback_failed,
+                            callback_failure,
+                            ..
+                        } => Some((request.sequence, callback_failed, callback_failure)),
+                        _ => None,
+                    })
+                    .collect();
+                // The callback of 1 only succeeds once the request is revealed again.
+                if callback_failures.len() == 2
+                    && callback_failures
+                        .iter()
+                        .all(|(sequence, callback_failed, _)| *sequence == 2 || !callback_failed)
+                {
+                    return callback_failures;
+                }
+                time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("requests were not revealed");
+
+        for (sequence, callback_failed, callback_failure) in callback_failures {
+            if sequence == 1 {
+                assert_eq!(callback_failure, None);
+            } else {
+                assert!(callback_failed);
+                assert_eq!(
+                    callback_failure.map(|failure| failure.kind),
+                    Some(CallbackFailureKind::OutOfGas)
+                );
+            }
+        }
+        // The request whose callback still fails is left on the contract.
+        assert!(reader.get_request_v2(provider, 2).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_keeper_loop_dry_run() {
+        let provider = Address::from_low_u64_be(1);
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        reader.insert_with_callback(provider, 1, 2, [1u8; 32]);
+        reader.insert_with_callback(provider, 2, 2, [2u8; 32]);
+        let writer = Arc::new(MockEntropyWriter::new(
+            Address::from_low_u64_be(10),
+            reader.clone(),
+        ));
+        writer.set_callback_gas(2, 150_000);
+        let mut params = process_params(reader.clone(), writer.clone(), provider).await;
+        params.dry_run = true;
+        let latest_block = get_latest_safe_block(&params.chain_state).await;
+        process_block_range(
+            BlockRange {
+                from: 0,
+                to: latest_block,
+            },
+            params.clone(),
+        )
+        .await;
+
+        let simulated = tokio::time::timeout(Duration::from_secs(20), async {
+            loop {
+                let simulated = params
+                    .history
+                    .query()
+                    .state(StateTag::Simulated)
+                    .execute()
+                    .await
+                    .unwrap();
+                if simulated.len() == 2 {
+                    return simulated;
+                }
+                time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("requests were not simulated");
+
+        let chain = hash_chain();
+        for request in simulated {
+            match request.state {
+                RequestEntryState::Simulated {
+                    provider_random_number,
+                    gas_estimate,
+                    callback_failure,
+                    ..
+                } => {
+                    assert_eq!(
+                        provider_random_number,
+                        chain.reveal(request.sequence).unwrap()
+                    );
+                    assert!(gas_estimate > U256::zero());
+                    // The callback of 2 needs more than the gas limit of the request.
+                    assert_eq!(
+                        callback_failure.map(|failure| failure.kind),
+                        (request.sequence == 2).then_some(CallbackFailureKind::OutOfGas)
+                    );
+                }
+                _ => panic!("request {} is not simulated", request.sequence),
+            }
+        }
+        // No transaction was sent and the requests are left on the contract.
+        assert!(writer.reveals.read().unwrap().is_empty());
+        assert!(reader.get_request_v2(provider, 1).await.unwrap().is_some());
+        assert!(reader.get_request_v2(provider, 2).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_keeper_loop_with_cosmwasm_chain() {
+        let (lcd, url) = MockLcd::start(&contract_addr()).await;
+        let config = chain_config(&url);
+        let provider = decode_account_address(&config.provider_addr).unwrap();
+        let requester = config.provider_addr.clone();
+        lcd.request(&config.provider_addr, &requester, 1, [1u8; 32]);
+        lcd.request(&config.provider_addr, &requester, 2, [2u8; 32]);
+
+        let reader = Arc::new(CosmWasmEntropy::from_config(&config, None).unwrap());
+        let writer =
+            Arc::new(CosmWasmEntropy::from_config(&config, Some(KEEPER_PRIVATE_KEY)).unwrap());
+
+        run_keeper_loop(process_params(reader, writer, provider).await, &[1, 2]).await;
+        assert_eq!(lcd.executed().len(), 2);
+    }
+}
@@ -0,0 +1,320 @@
+use {
+    crate::{
+        chain::{
+            reader::{RequestCallbackStatus, RequestedV2Event},
+            writer::RevealReceipt,
+        },
+        config::CallbackRetryConfig,
+        history::{CallbackFailure, CallbackFailureKind},
+        keeper::{block::ProcessParams, keeper_metrics::AccountLabel},
+    },
+    anyhow::{anyhow, Result},
+    ethers::{
+        abi::{self, Abi, ParamType, Token},
+        types::{Address, I256},
+    },
+    std::{collections::HashMap, fs},
+    tokio::time::{self, Duration},
+    tracing,
+};
+
+/// The selector of `Error(string)`, raised by `require` and `revert` with a message.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// The selector of `Panic(uint256)`, raised by failed assertions, overflows, etc.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// The gas used by a reveal transaction besides the callback, e.g. to verify the provider's
+/// revelation.
+const REVEAL_GAS_OVERHEAD: u64 = 500_000;
+
+/// The ABIs of the consumer contracts of a chain, used to decode the custom errors of their
+/// callbacks.
+#[derive(Default)]
+pub struct ConsumerAbis {
+    abis: HashMap<Address, Abi>,
+}
+
+impl ConsumerAbis {
+    /// Load the ABIs from JSON files, each containing either the ABI itself or a compiler artifact
+    /// with an `abi` field.
+    pub fn load(paths: &HashMap<Address, String>) -> Result<Self> {
+        let mut abis = HashMap::new();
+        for (consumer, path) in paths {
+            let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)
+                .map_err(|e| anyhow!("Failed to parse the ABI in {}: {:?}", path, e))?;
+            let abi = match json {
+                serde_json::Value::Object(mut artifact) => artifact
+                    .remove("abi")
+                    .ok_or_else(|| anyhow!("No abi field in {}", path))?,
+                abi => abi,
+            };
+            let abi = serde_json::from_value(abi)
+                .map_err(|e| anyhow!("Failed to parse the ABI in {}: {:?}", path, e))?;
+            abis.insert(*consumer, abi);
+        }
+        Ok(Self { abis })
+    }
+
+    pub fn get(&self, consumer: Address) -> Option<&Abi> {
+        self.abis.get(&consumer)
+    }
+}
+
+/// Classify the failure of a callback and decode its error from the return value of the
+/// callback. The errors of the consumer contract are decoded if its `abi` is known.
+pub fn diagnose_callback_failure(
+    gas_limit: u32,
+    callback_gas_used: u32,
+    return_value: &[u8],
+    abi: Option<&Abi>,
+) -> CallbackFailure {
+    let reason = decode_revert_reason(return_value, abi);
+    // Some contracts catch out-of-gas errors and revert with another error, so running out of gas
+    // takes precedence over the error. The contract forwards at most 63/64 of the remaining gas.
+    let kind = if gas_limit > 0 && callback_gas_used as u64 * 64 >= gas_limit as u64 * 63 {
+        CallbackFailureKind::OutOfGas
+    } else if reason.is_some() {
+        CallbackFailureKind::Revert
+    } else {
+        CallbackFailureKind::Other
+    };
+    CallbackFailure { kind, reason }
+}
+
+/// Decode the revert data of a call, e.g. `Error("insufficient balance")`. Returns `None` if
+/// there is no revert data.
+pub fn decode_revert_reason(data: &[u8], abi: Option<&Abi>) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
+    let (selector, args) = data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        // The return value of callbacks is truncated to 256 bytes, which cuts long messages.
+        return Some(match abi::decode(&[ParamType::String], args).as_deref() {
+            Ok([Token::String(message)]) => format!("Error({message:?})"),
+            _ => "Error(<truncated>)".to_string(),
+        });
+    }
+    if selector == PANIC_SELECTOR {
+        return Some(
+            match abi::decode(&[ParamType::Uint(256)], args).as_deref() {
+                Ok([Token::Uint(code)]) => {
+                    format!("Panic({code:#x}: {})", panic_description(code.low_u64()))
+                }
+                _ => "Panic(<truncated>)".to_string(),
+            },
+        );
+    }
+    let custom_error = abi.and_then(|abi| {
+        abi.errors()
+            .find(|error| error.signature()[..4] == *selector)
+            .map(|error| match error.decode(args) {
+                Ok(tokens) => format!(
+                    "{}({})",
+                    error.name,
+                    tokens
+                        .iter()
+                        .map(format_token)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Err(_) => format!("{}(<truncated>)", error.name),
+            })
+    });
+    Some(custom_error.unwrap_or_else(|| format!("Unknown error 0x{}", hex::encode(selector))))
+}
+
+/// The meaning of the panic codes of Solidity.
+fn panic_description(code: u64) -> &'static str {
+    match code {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to an uninitialized function",
+        _ => "unknown panic",
+    }
+}
+
+fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{address:?}"),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => format!("{value:?}"),
+        Token::Array(tokens) | Token::FixedArray(tokens) => format!(
+            "[{}]",
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Token::Tuple(tokens) => format!(
+            "({})",
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Reveal a request whose callback failed again, up to the number of attempts of `policy`, with
+/// more gas for the callback on each attempt. Returns the receipt of the reveal whose callback
+/// succeeded, if any.
+pub async fn retry_failed_callback(
+    process_param: &ProcessParams,
+    policy: &CallbackRetryConfig,
+    event: &RequestedV2Event,
+    provider_revelation: [u8; 32],
+) -> Option<RevealReceipt> {
+    let account_label = AccountLabel {
+        chain_id: process_param.chain_state.id.clone(),
+        address: process_param.chain_state.provider_address.to_string(),
+    };
+    for attempt in 1..=policy.max_attempts {
+        time::sleep(Duration::from_secs(policy.delay_seconds)).await;
+
+        // Anyone can reveal the request again, e.g. the requester, so stop once it is gone.
+        match process_param
+            .chain_state
+            .contract
+            .get_request_v2(event.provider_address, event.sequence_number)
+            .await
+        {
+            Ok(Some(request))
+                if request.callback_status == RequestCallbackStatus::CallbackFailed => {}
+            Ok(_) => {
+                tracing::info!("Request was revealed again by another party, stop retrying");
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, "Error checking request status, retrying the callback anyway");
+            }
+        }
+
+        let callback_gas_limit = policy.callback_gas_limit(event.gas_limit, attempt);
+        process_param
+            .metrics
+            .callback_retries
+            .get_or_create(&account_label)
+            .inc();
+        match process_param
+            .contract
+            .retry_reveal_with_callback(
+                event.provider_address,
+                event.sequence_number,
+                event.user_random_number,
+                provider_revelation,
+                callback_gas_limit + REVEAL_GAS_OVERHEAD,
+            )
+            .await
+        {
+            Ok(receipt) => {
+                tracing::info!(
+                    attempt,
+                    callback_gas_limit,
+                    "Callback succeeded when revealed again"
+                );
+                process_param
+                    .metrics
+                    .callback_retries_success
+                    .get_or_create(&account_label)
+                    .inc();
+                return Some(receipt);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    attempt,
+                    callback_gas_limit,
+                    "Failed to reveal the request again: {}",
+                    e.reason
+                );
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, ethers::types::U256};
+
+    fn encode_with_selector(selector: [u8; 4], tokens: &[Token]) -> Vec<u8> {
+        [selector.to_vec(), abi::encode(tokens)].concat()
+    }
+
+    #[test]
+    fn test_decode_revert_reason() {
+        let error = encode_with_selector(
+            ERROR_SELECTOR,
+            &[Token::String("insufficient balance".to_string())],
+        );
+        assert_eq!(
+            decode_revert_reason(&error, None).unwrap(),
+            "Error(\"insufficient balance\")"
+        );
+
+        let panic = encode_with_selector(PANIC_SELECTOR, &[Token::Uint(U256::from(0x11))]);
+        assert_eq!(
+            decode_revert_reason(&panic, None).unwrap(),
+            "Panic(0x11: arithmetic overflow or underflow)"
+        );
+
+        let abi: Abi = serde_json::from_str(
+            r#"[{"type":"error","name":"TooLow","inputs":[{"name":"value","type":"int256"},{"name":"sender","type":"address"}]}]"#,
+        )
+        .unwrap();
+        let custom_error = abi.errors().next().unwrap();
+        let data = [
+            custom_error.signature()[..4].to_vec(),
+            abi::encode(&[
+                Token::Int(I256::from(-5).into_raw()),
+                Token::Address(Address::from_low_u64_be(1)),
+            ]),
+        ]
+        .concat();
+        assert_eq!(
+            decode_revert_reason(&data, Some(&abi)).unwrap(),
+            "TooLow(-5, 0x0000000000000000000000000000000000000001)"
+        );
+        assert_eq!(
+            decode_revert_reason(&data, None).unwrap(),
+            format!("Unknown error 0x{}", hex::encode(&data[..4]))
+        );
+
+        assert_eq!(decode_revert_reason(&[], None), None);
+        // Messages longer than the return value of the callback are truncated.
+        let long_error = encode_with_selector(ERROR_SELECTOR, &[Token::String("a".repeat(300))]);
+        assert_eq!(
+            decode_revert_reason(&long_error[..256], None).unwrap(),
+            "Error(<truncated>)"
+        );
+    }
+
+    #[test]
+    fn test_diagnose_callback_failure() {
+        let error = encode_with_selector(ERROR_SELECTOR, &[Token::String("nope".to_string())]);
+
+        let failure = diagnose_callback_failure(100_000, 20_000, &error, None);
+        assert_eq!(failure.kind, CallbackFailureKind::Revert);
+        assert_eq!(failure.reason.unwrap(), "Error(\"nope\")");
+
+        // Running out of gas takes precedence over the error.
+        let failure = diagnose_callback_failure(100_000, 100_500, &error, None);
+        assert_eq!(failure.kind, CallbackFailureKind::OutOfGas);
+
+        let failure = diagnose_callback_failure(100_000, 20_000, &[], None);
+        assert_eq!(failure.kind, CallbackFailureKind::Other);
+        assert_eq!(failure.reason, None);
+    }
+}
@@ -1,9 +1,21 @@
 use {
     crate::{
-        api::BlockchainState, chain::ethereum::InstrumentedSignablePythContract,
-        eth_utils::utils::send_and_confirm, keeper::block::get_latest_safe_block,
+        api::{BlockchainState, ChainId},
+        chain::{ethereum::EthereumWriter, reader::ProviderInfo, writer::EntropyWriter},
+        command::CommitmentMetadata,
+        config::{Commitment, EthereumConfig, HashChainStorage, ProviderConfig, ReplicaConfig},
+        eth_utils::traced_client::RpcMetrics,
+        history::History,
+        keeper::{
+            block::get_latest_safe_block,
+            keeper_metrics::{AccountLabel, KeeperMetrics},
+            replica::{is_leader_replica, ReplicaCoordinator},
+        },
+        state::{load_hash_chain, HashChain},
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, ensure, Result},
+    ethers::types::{Address, Bytes},
+    sha3::{Digest, Keccak256},
     std::sync::Arc,
     tokio::time::{self, Duration},
     tracing::{self, Instrument},
@@ -13,10 +25,12 @@ use {
 /// requests and reduce the gas cost of the reveal.
 const UPDATE_COMMITMENTS_INTERVAL: Duration = Duration::from_secs(30);
 const UPDATE_COMMITMENTS_THRESHOLD_FACTOR: f64 = 0.95;
+/// Check whether the hash chain of the provider needs to be rotated at this interval.
+const ROTATE_COMMITMENT_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tracing::instrument(name = "update_commitments", skip_all)]
 pub async fn update_commitments_loop(
-    contract: Arc<InstrumentedSignablePythContract>,
+    contract: Arc<dyn EntropyWriter>,
     chain_state: BlockchainState,
 ) {
     loop {
@@ -31,16 +45,16 @@ pub async fn update_commitments_loop(
 }
 
 pub async fn update_commitments_if_necessary(
-    contract: Arc<InstrumentedSignablePythContract>,
+    contract: Arc<dyn EntropyWriter>,
     chain_state: &BlockchainState,
 ) -> Result<()> {
     //TODO: we can reuse the result from the last call from the watch_blocks thread to reduce RPCs
     let latest_safe_block = get_latest_safe_block(chain_state).in_current_span().await;
     let provider_address = chain_state.provider_address;
-    let provider_info = contract
-        .get_provider_info_v2(provider_address)
-        .block(latest_safe_block) // To ensure we are not revealing sooner than we should
-        .call()
+    let provider_info = chain_state
+        .contract
+        // Read at the latest safe block to ensure we are not revealing sooner than we should
+        .get_provider_info(provider_address, Some(latest_safe_block))
         .await
         .map_err(|e| {
             anyhow!(
@@ -64,9 +78,474 @@ pub async fn update_commitments_if_necessary(
             .state
             .reveal(seq_number)
             .map_err(|e| anyhow!("Error revealing: {:?}", e))?;
-        let contract_call =
-            contract.advance_provider_commitment(provider_address, seq_number, provider_revelation);
-        send_and_confirm(contract_call).await?;
+        contract
+            .advance_provider_commitment(provider_address, seq_number, provider_revelation)
+            .await?;
     }
     Ok(())
 }
+
+/// Registers the next hash chain of a provider before the current one is exhausted, and serves the
+/// hash chains registered on the contract without a restart.
+pub struct CommitmentRotation {
+    /// Signs the registrations with the provider's key.
+    pub provider: Arc<dyn EntropyWriter>,
+    pub secret: String,
+    pub chain_length: u64,
+    pub chain_sample_interval: u64,
+    pub hash_chain_storage: HashChainStorage,
+    pub contract_address: Address,
+    /// The URI registered with the hash chains.
+    pub uri: String,
+    /// Register the next hash chain once fewer than this many sequence numbers remain.
+    pub min_remaining_sequence_numbers: u64,
+}
+
+impl CommitmentRotation {
+    /// The commitment rotation of a provider on an EVM chain, if it is enabled.
+    pub fn from_config(
+        provider_config: &ProviderConfig,
+        chain_config: &EthereumConfig,
+        chain_id: &ChainId,
+        network_id: u64,
+        uri: String,
+        rpc_metrics: Arc<RpcMetrics>,
+    ) -> Result<Option<Self>> {
+        let Some(rotation_config) = &provider_config.commitment_rotation else {
+            return Ok(None);
+        };
+        let private_key = provider_config.private_key.load()?.ok_or(anyhow!(
+            "Please specify a provider private key in the config file to rotate the commitments."
+        ))?;
+        let secret = provider_config.secret.load()?.ok_or(anyhow!(
+            "Please specify a provider secret in the config file."
+        ))?;
+        let provider = EthereumWriter::from_config(
+            chain_config,
+            &private_key,
+            chain_id.clone(),
+            rpc_metrics,
+            network_id,
+        )?;
+        ensure!(
+            provider.address() == provider_config.address,
+            "The provider private key does not match the provider address {:?}",
+            provider_config.address
+        );
+        Ok(Some(Self {
+            provider: Arc::new(provider),
+            secret,
+            chain_length: provider_config.chain_length,
+            chain_sample_interval: provider_config.chain_sample_interval,
+            hash_chain_storage: provider_config.hash_chain_storage.clone(),
+            contract_address: chain_config.contract_addr,
+            uri,
+            min_remaining_sequence_numbers: rotation_config.min_remaining_sequence_numbers,
+        }))
+    }
+
+    async fn load_hash_chain(
+        &self,
+        chain_state: &BlockchainState,
+        seed: &[u8; 32],
+        chain_length: u64,
+    ) -> Result<Box<dyn HashChain>> {
+        load_hash_chain(
+            &self.hash_chain_storage,
+            &self.secret,
+            &chain_state.id,
+            &chain_state.provider_address,
+            self.contract_address.as_bytes(),
+            seed,
+            chain_length,
+            self.chain_sample_interval,
+        )
+        .await
+    }
+
+    /// The seed of the hash chain that takes over from the current one at `next_sequence_start`
+    /// at the latest. It is derived from the secret, so that every replica registers the same
+    /// hash chain and it can be regenerated after a restart.
+    fn next_seed(&self, chain_id: &ChainId, next_sequence_start: u64) -> Result<[u8; 32]> {
+        let mut input = hex::decode(self.secret.trim())?;
+        input.extend_from_slice(chain_id.as_bytes());
+        input.extend_from_slice(&next_sequence_start.to_be_bytes());
+        Ok(Keccak256::digest(input).into())
+    }
+}
+
+/// Serve the hash chains registered on the contract, and register the next one on the leader
+/// replica (see `is_leader_replica`), so that the replicas don't race to register it.
+#[tracing::instrument(name = "rotate_commitment", skip_all)]
+pub async fn rotate_commitment_loop(
+    rotation: Arc<CommitmentRotation>,
+    chain_state: BlockchainState,
+    replica_config: Option<ReplicaConfig>,
+    replica_coordinator: Option<Arc<ReplicaCoordinator>>,
+    metrics: Arc<KeeperMetrics>,
+    history: Arc<History>,
+) {
+    loop {
+        let leader = is_leader_replica(replica_config.as_ref(), replica_coordinator.as_deref());
+        if let Err(e) =
+            rotate_commitment_if_necessary(&rotation, &chain_state, leader, &metrics, &history)
+                .in_current_span()
+                .await
+        {
+            tracing::error!("Rotate commitment. error: {:?}", e);
+        }
+        time::sleep(ROTATE_COMMITMENT_INTERVAL).await;
+    }
+}
+
+/// Serve the hash chain registered on the contract if it is new, then register the next hash
+/// chain if the current one is about to be exhausted and this replica is the `leader`.
+pub async fn rotate_commitment_if_necessary(
+    rotation: &CommitmentRotation,
+    chain_state: &BlockchainState,
+    leader: bool,
+    metrics: &KeeperMetrics,
+    history: &History,
+) -> Result<()> {
+    let provider_address = chain_state.provider_address;
+    let provider_info = chain_state
+        .contract
+        .get_provider_info(provider_address, None)
+        .await
+        .map_err(|e| anyhow!("Error while getting provider info. error: {:?}", e))?;
+    let label = AccountLabel {
+        chain_id: chain_state.id.clone(),
+        address: provider_address.to_string(),
+    };
+
+    if let Err(e) = load_registered_commitment(rotation, chain_state, history, &provider_info)
+        .in_current_span()
+        .await
+    {
+        metrics
+            .commitment_rotation_failures
+            .get_or_create(&label)
+            .inc();
+        return Err(e);
+    }
+
+    let remaining = provider_info
+        .end_sequence_number
+        .saturating_sub(provider_info.sequence_number);
+    if remaining >= rotation.min_remaining_sequence_numbers {
+        return Ok(());
+    }
+    if !leader {
+        tracing::info!(
+            "{} sequence numbers left on the hash chain. Waiting for the leader replica to register the next hash chain.",
+            remaining
+        );
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "{} sequence numbers left on the hash chain. Registering the next hash chain.",
+        remaining
+    );
+    match register_next_commitment(rotation, chain_state, history, &provider_info)
+        .in_current_span()
+        .await
+    {
+        Ok(()) => {
+            metrics.commitment_rotations.get_or_create(&label).inc();
+            Ok(())
+        }
+        Err(e) => {
+            metrics
+                .commitment_rotation_failures
+                .get_or_create(&label)
+                .inc();
+            // NOTE: Alert on this log message or on the commitment_rotation_failures metric. The
+            // provider stops serving requests once the hash chain is exhausted.
+            Err(anyhow!(
+                "Failed to register the next hash chain with {} sequence numbers left. error: {:?}",
+                remaining,
+                e
+            ))
+        }
+    }
+}
+
+/// Serve the hash chain of the commitment registered on the contract, if it is not served yet,
+/// e.g. because another replica registered it.
+async fn load_registered_commitment(
+    rotation: &CommitmentRotation,
+    chain_state: &BlockchainState,
+    history: &History,
+    provider_info: &ProviderInfo,
+) -> Result<()> {
+    let offset = provider_info.original_commitment_sequence_number;
+    if chain_state.state.reveal(offset).ok() == Some(provider_info.original_commitment) {
+        return Ok(());
+    }
+
+    let metadata = bincode::deserialize::<CommitmentMetadata>(&provider_info.commitment_metadata)
+        .map_err(|e| anyhow!("Failed to deserialize commitment metadata: {}", e))?;
+    let hash_chain = rotation
+        .load_hash_chain(chain_state, &metadata.seed, metadata.chain_length)
+        .await?;
+    ensure!(
+        hash_chain.reveal_ith(0)? == provider_info.original_commitment,
+        "The root of the hash chain registered at sequence number {} does not match the commitment",
+        offset
+    );
+    chain_state.state.rotate(offset, hash_chain)?;
+    tracing::info!(
+        "Serving the hash chain registered at sequence number {}",
+        offset
+    );
+
+    history
+        .record_commitment(
+            &chain_state.id,
+            chain_state.network_id,
+            chain_state.provider_address,
+            &Commitment {
+                seed: metadata.seed,
+                chain_length: metadata.chain_length,
+                original_commitment_sequence_number: offset,
+            },
+        )
+        .await
+}
+
+/// Register a new hash chain generated from the provider's secret, and serve it.
+async fn register_next_commitment(
+    rotation: &CommitmentRotation,
+    chain_state: &BlockchainState,
+    history: &History,
+    provider_info: &ProviderInfo,
+) -> Result<()> {
+    // Record the current hash chain, so that its pending requests can still be revealed after a
+    // restart.
+    let current = bincode::deserialize::<CommitmentMetadata>(&provider_info.commitment_metadata)
+        .map_err(|e| anyhow!("Failed to deserialize commitment metadata: {}", e))?;
+    history
+        .record_commitment(
+            &chain_state.id,
+            chain_state.network_id,
+            chain_state.provider_address,
+            &Commitment {
+                seed: current.seed,
+                chain_length: current.chain_length,
+                original_commitment_sequence_number: provider_info
+                    .original_commitment_sequence_number,
+            },
+        )
+        .await?;
+
+    let seed = rotation.next_seed(&chain_state.id, provider_info.end_sequence_number)?;
+    let hash_chain = rotation
+        .load_hash_chain(chain_state, &seed, rotation.chain_length)
+        .await?;
+    let commitment = hash_chain.reveal_ith(0)?;
+    let metadata = CommitmentMetadata {
+        seed,
+        chain_length: rotation.chain_length,
+    };
+    // Keep the fee of the provider, which the fee manager may have adjusted.
+    rotation
+        .provider
+        .register_provider(
+            provider_info.fee_in_wei,
+            commitment,
+            bincode::serialize(&metadata)?.into(),
+            rotation.chain_length,
+            Bytes::from(rotation.uri.clone().into_bytes()),
+        )
+        .await?;
+
+    let provider_info = chain_state
+        .contract
+        .get_provider_info(chain_state.provider_address, None)
+        .await?;
+    ensure!(
+        provider_info.original_commitment == commitment,
+        "The commitment on the contract is not the one registered"
+    );
+    let offset = provider_info.original_commitment_sequence_number;
+    chain_state.state.rotate(offset, hash_chain)?;
+    tracing::info!(
+        "Registered the next hash chain at sequence number {}",
+        offset
+    );
+
+    history
+        .record_commitment(
+            &chain_state.id,
+            chain_state.network_id,
+            chain_state.provider_address,
+            &Commitment {
+                seed,
+                chain_length: rotation.chain_length,
+                original_commitment_sequence_number: offset,
+            },
+        )
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            chain::{
+                reader::{mock::MockEntropyReader, BlockStatus, EntropyReader},
+                writer::mock::MockEntropyWriter,
+            },
+            state::{HashChainState, MonitoredHashChainState, PebbleHashChain},
+        },
+    };
+
+    const SECRET: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+    fn hash_chain(provider: Address, seed: [u8; 32]) -> PebbleHashChain {
+        PebbleHashChain::from_config(
+            SECRET,
+            &"ethereum".to_string(),
+            &provider,
+            Address::zero().as_bytes(),
+            &seed,
+            10,
+            1,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rotate_commitment() {
+        let provider = Address::from_low_u64_be(1);
+        let first_chain = hash_chain(provider, [1u8; 32]);
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        reader.set_provider_info(ProviderInfo {
+            original_commitment: first_chain.reveal_ith(0).unwrap(),
+            original_commitment_sequence_number: 0,
+            commitment_metadata: bincode::serialize(&CommitmentMetadata {
+                seed: [1u8; 32],
+                chain_length: 10,
+            })
+            .unwrap()
+            .into(),
+            end_sequence_number: 10,
+            sequence_number: 6,
+            fee_in_wei: 7,
+            ..Default::default()
+        });
+        let chain_state = BlockchainState {
+            id: "ethereum".into(),
+            network_id: 121,
+            state: Arc::new(MonitoredHashChainState::new(
+                Arc::new(HashChainState::from_chain_at_offset(0, first_chain.clone())),
+                Default::default(),
+                "ethereum".into(),
+                provider,
+            )),
+            contract: reader.clone(),
+            provider_address: provider,
+            reveal_delay_blocks: 0,
+            confirmed_block_status: BlockStatus::Latest,
+        };
+        let writer = Arc::new(MockEntropyWriter::new(provider, reader.clone()));
+        let rotation = CommitmentRotation {
+            provider: writer.clone(),
+            secret: SECRET.to_string(),
+            chain_length: 10,
+            chain_sample_interval: 1,
+            hash_chain_storage: HashChainStorage::Sampled,
+            contract_address: Address::zero(),
+            uri: "https://fortuna.example".to_string(),
+            min_remaining_sequence_numbers: 3,
+        };
+        let metrics = KeeperMetrics::default();
+        let history = History::new_in_memory().await.unwrap();
+        let rotations = || {
+            metrics
+                .commitment_rotations
+                .get_or_create(&AccountLabel {
+                    chain_id: "ethereum".into(),
+                    address: provider.to_string(),
+                })
+                .get()
+        };
+
+        // 4 sequence numbers are left.
+        rotate_commitment_if_necessary(&rotation, &chain_state, true, &metrics, &history)
+            .await
+            .unwrap();
+        assert_eq!(rotations(), 0);
+
+        // With 2 sequence numbers left, the next hash chain is registered at sequence number 8,
+        // and the previous sequence numbers are still served by the first hash chain.
+        reader.set_provider_info(ProviderInfo {
+            sequence_number: 8,
+            ..reader.get_provider_info(provider, None).await.unwrap()
+        });
+        // Only the leader replica registers it.
+        rotate_commitment_if_necessary(&rotation, &chain_state, false, &metrics, &history)
+            .await
+            .unwrap();
+        assert_eq!(rotations(), 0);
+        rotate_commitment_if_necessary(&rotation, &chain_state, true, &metrics, &history)
+            .await
+            .unwrap();
+        assert_eq!(rotations(), 1);
+        let provider_info = reader.get_provider_info(provider, None).await.unwrap();
+        assert_eq!(provider_info.original_commitment_sequence_number, 8);
+        assert_eq!(provider_info.end_sequence_number, 18);
+        assert_eq!(provider_info.fee_in_wei, 7);
+        // The seed of the hash chain is derived from the secret.
+        let metadata: CommitmentMetadata =
+            bincode::deserialize(&provider_info.commitment_metadata).unwrap();
+        assert_eq!(
+            metadata.seed,
+            rotation.next_seed(&"ethereum".to_string(), 10).unwrap()
+        );
+        assert_eq!(
+            chain_state.state.reveal(8).unwrap(),
+            provider_info.original_commitment
+        );
+        assert_eq!(
+            chain_state.state.reveal(7).unwrap(),
+            first_chain.reveal_ith(7).unwrap()
+        );
+        let offsets: Vec<u64> = history
+            .commitments(121, provider)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| c.original_commitment_sequence_number)
+            .collect();
+        assert_eq!(offsets, vec![0, 8]);
+
+        // A hash chain registered by another replica is served too.
+        let other_chain = hash_chain(provider, [2u8; 32]);
+        writer
+            .register_provider(
+                7,
+                other_chain.reveal_ith(0).unwrap(),
+                bincode::serialize(&CommitmentMetadata {
+                    seed: [2u8; 32],
+                    chain_length: 10,
+                })
+                .unwrap()
+                .into(),
+                10,
+                Bytes::default(),
+            )
+            .await
+            .unwrap();
+        rotate_commitment_if_necessary(&rotation, &chain_state, true, &metrics, &history)
+            .await
+            .unwrap();
+        assert_eq!(rotations(), 1);
+        assert_eq!(
+            chain_state.state.reveal(10).unwrap(),
+            other_chain.reveal_ith(1).unwrap()
+        );
+    }
+}
@@ -1,16 +1,11 @@
 use {
     crate::{
         api::BlockchainState,
-        chain::ethereum::InstrumentedSignablePythContract,
-        eth_utils::utils::{estimate_tx_cost, send_and_confirm, submit_transfer_tx},
+        chain::{reader::EntropyReader, writer::EntropyWriter},
         keeper::{AccountLabel, ChainId, KeeperMetrics},
     },
     anyhow::{anyhow, Result},
-    ethers::{
-        middleware::Middleware,
-        signers::Signer,
-        types::{Address, U256},
-    },
+    ethers::types::{Address, U256},
     std::sync::Arc,
     tokio::time::{self, Duration},
     tracing::{self, Instrument},
@@ -23,8 +18,8 @@ use {
 ///
 /// `other_keeper_addresses` is expected to not include the `keeper_address`, and should
 /// include the fee manager so that the fee manager wallet stays funded.
-async fn calculate_fair_fee_withdrawal_amount<M: Middleware + 'static>(
-    provider: Arc<M>,
+async fn calculate_fair_fee_withdrawal_amount(
+    contract: &dyn EntropyReader,
     keeper_address: Address,
     other_keeper_addresses: &[Address],
     available_fees: U256,
@@ -39,8 +34,8 @@ async fn calculate_fair_fee_withdrawal_amount<M: Middleware + 'static>(
         return Ok(available_fees);
     }
 
-    let current_balance = provider
-        .get_balance(keeper_address, None)
+    let current_balance = contract
+        .get_balance(keeper_address)
         .await
         .map_err(|e| anyhow!("Error while getting current keeper balance. error: {:?}", e))?;
 
@@ -55,7 +50,7 @@ async fn calculate_fair_fee_withdrawal_amount<M: Middleware + 'static>(
     let mut total_funds = current_balance + available_fees;
 
     for &address in other_keeper_addresses {
-        let balance = provider.get_balance(address, None).await.map_err(|e| {
+        let balance = contract.get_balance(address).await.map_err(|e| {
             anyhow!(
                 "Error while getting keeper balance for {:?}. error: {:?}",
                 address,
@@ -90,25 +85,32 @@ async fn calculate_fair_fee_withdrawal_amount<M: Middleware + 'static>(
 
 #[tracing::instrument(name = "withdraw_fees", skip_all, fields())]
 pub async fn withdraw_fees_wrapper(
-    contract_as_fee_manager: Arc<InstrumentedSignablePythContract>,
+    contract: Arc<dyn EntropyReader>,
+    contract_as_fee_manager: Arc<dyn EntropyWriter>,
     provider_address: Address,
     poll_interval: Duration,
     min_balance: U256,
-    keeper_address: Address,
+    keeper_addresses: Vec<Address>,
     other_keeper_addresses: Vec<Address>,
 ) {
-    let fee_manager_wallet = contract_as_fee_manager.wallet().address();
+    let fee_manager_wallet = contract_as_fee_manager.address();
 
-    // Add the fee manager to the list of other keepers so that we can fairly distribute the fees
-    // across the fee manager and all the keepers.
+    // Add the fee manager and the wallets of this keeper to the list of other keepers so that we
+    // can fairly distribute the fees across the fee manager and all the keepers.
     let mut other_keepers_and_fee_mgr = other_keeper_addresses.clone();
-    other_keepers_and_fee_mgr.push(contract_as_fee_manager.wallet().address());
+    other_keepers_and_fee_mgr.push(fee_manager_wallet);
+    other_keepers_and_fee_mgr.extend(
+        keeper_addresses
+            .iter()
+            .filter(|&&address| address != fee_manager_wallet),
+    );
 
     loop {
         // Top up the fee manager balance
         // Do this before attempting to top up the keeper balance, since we need a funded
         // fee manager to be able to withdraw & transfer funds to the keeper.
         if let Err(e) = withdraw_fees_if_necessary(
+            contract.clone(),
             contract_as_fee_manager.clone(),
             provider_address,
             fee_manager_wallet,
@@ -121,18 +123,30 @@ pub async fn withdraw_fees_wrapper(
             tracing::error!("Withdrawing fees to fee manager. error: {:?}", e);
         }
 
-        // Top up the keeper balance
-        if let Err(e) = withdraw_fees_if_necessary(
-            contract_as_fee_manager.clone(),
-            provider_address,
-            keeper_address,
-            other_keepers_and_fee_mgr.clone(),
-            min_balance,
-        )
-        .in_current_span()
-        .await
-        {
-            tracing::error!("Withdrawing fees to keeper. error: {:?}", e);
+        // Top up the balance of each keeper wallet
+        for &keeper_address in &keeper_addresses {
+            let other_keepers: Vec<Address> = other_keepers_and_fee_mgr
+                .iter()
+                .copied()
+                .filter(|&address| address != keeper_address)
+                .collect();
+            if let Err(e) = withdraw_fees_if_necessary(
+                contract.clone(),
+                contract_as_fee_manager.clone(),
+                provider_address,
+                keeper_address,
+                other_keepers,
+                min_balance,
+            )
+            .in_current_span()
+            .await
+            {
+                tracing::error!(
+                    "Withdrawing fees to keeper {:?}. error: {:?}",
+                    keeper_address,
+                    e
+                );
+            }
         }
 
         time::sleep(poll_interval).await;
@@ -141,17 +155,15 @@ pub async fn withdraw_fees_wrapper(
 
 /// Withdraws accumulated fees in the contract as needed to maintain the balance of the keeper wallet.
 pub async fn withdraw_fees_if_necessary(
-    contract_as_fee_manager: Arc<InstrumentedSignablePythContract>,
+    contract: Arc<dyn EntropyReader>,
+    contract_as_fee_manager: Arc<dyn EntropyWriter>,
     provider_address: Address,
     keeper_address: Address,
     other_keeper_addresses: Vec<Address>,
     min_balance: U256,
 ) -> Result<()> {
-    let provider = contract_as_fee_manager.provider();
-    let fee_manager_wallet = contract_as_fee_manager.wallet();
-
-    let keeper_balance = provider
-        .get_balance(keeper_address, None)
+    let keeper_balance = contract
+        .get_balance(keeper_address)
         .await
         .map_err(|e| anyhow!("Error while getting balance. error: {:?}", e))?;
 
@@ -160,9 +172,8 @@ pub async fn withdraw_fees_if_necessary(
         return Ok(());
     }
 
-    let provider_info = contract_as_fee_manager
-        .get_provider_info_v2(provider_address)
-        .call()
+    let provider_info = contract
+        .get_provider_info(provider_address, None)
         .await
         .map_err(|e| anyhow!("Error while getting provider info. error: {:?}", e))?;
 
@@ -170,7 +181,7 @@ pub async fn withdraw_fees_if_necessary(
 
     // Determine how much we can fairly withdraw from the contract
     let withdrawal_amount = calculate_fair_fee_withdrawal_amount(
-        Arc::new(provider.clone()),
+        contract.as_ref(),
         keeper_address,
         &other_keeper_addresses,
         available_fees,
@@ -195,24 +206,21 @@ pub async fn withdraw_fees_if_necessary(
     );
 
     // Proceed with withdrawal
-    let contract_call = contract_as_fee_manager
-        .withdraw_as_fee_manager(provider_address, withdrawal_amount.as_u128());
-    send_and_confirm(contract_call).await?;
+    contract_as_fee_manager
+        .withdraw_as_fee_manager(provider_address, withdrawal_amount.as_u128())
+        .await?;
 
     // Transfer the withdrawn funds from fee manager to keeper if fee manager is different from keeper
-    if fee_manager_wallet.address() != keeper_address {
-        submit_transfer_tx(
-            contract_as_fee_manager.clone(),
-            keeper_address,
-            withdrawal_amount,
-        )
-        .await
-        .map_err(|e| {
-            anyhow!(
-                "Failed to transfer fees from fee manager to keeper. error: {:?}",
-                e
-            )
-        })?;
+    if contract_as_fee_manager.address() != keeper_address {
+        contract_as_fee_manager
+            .transfer(keeper_address, withdrawal_amount)
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to transfer fees from fee manager to keeper. error: {:?}",
+                    e
+                )
+            })?;
     }
 
     Ok(())
@@ -221,11 +229,10 @@ pub async fn withdraw_fees_if_necessary(
 #[tracing::instrument(name = "adjust_fee", skip_all)]
 #[allow(clippy::too_many_arguments)]
 pub async fn adjust_fee_wrapper(
-    contract: Arc<InstrumentedSignablePythContract>,
+    contract: Arc<dyn EntropyWriter>,
     chain_state: BlockchainState,
     provider_address: Address,
     poll_interval: Duration,
-    legacy_tx: bool,
     min_profit_pct: u64,
     target_profit_pct: u64,
     max_profit_pct: u64,
@@ -240,9 +247,9 @@ pub async fn adjust_fee_wrapper(
     loop {
         if let Err(e) = adjust_fee_if_necessary(
             contract.clone(),
+            chain_state.contract.clone(),
             chain_state.id.clone(),
             provider_address,
-            legacy_tx,
             min_profit_pct,
             target_profit_pct,
             max_profit_pct,
@@ -277,10 +284,10 @@ pub async fn adjust_fee_wrapper(
 /// update transactions.
 #[allow(clippy::too_many_arguments)]
 pub async fn adjust_fee_if_necessary(
-    contract: Arc<InstrumentedSignablePythContract>,
+    contract: Arc<dyn EntropyWriter>,
+    reader: Arc<dyn EntropyReader>,
     chain_id: ChainId,
     provider_address: Address,
-    legacy_tx: bool,
     min_profit_pct: u64,
     target_profit_pct: u64,
     max_profit_pct: u64,
@@ -290,20 +297,19 @@ pub async fn adjust_fee_if_necessary(
     sequence_number_of_last_fee_update: &mut Option<u64>,
     metrics: Arc<KeeperMetrics>,
 ) -> Result<()> {
-    let provider_info = contract
-        .get_provider_info_v2(provider_address)
-        .call()
+    let provider_info = reader
+        .get_provider_info(provider_address, None)
         .await
         .map_err(|e| anyhow!("Error while getting provider info. error: {:?}", e))?;
 
-    if provider_info.fee_manager != contract.wallet().address() {
-        return Err(anyhow!("Fee manager for provider {:?} is not the keeper wallet. Fee manager: {:?} Keeper: {:?}", contract.provider(), provider_info.fee_manager, contract.wallet().address()));
+    if provider_info.fee_manager != contract.address() {
+        return Err(anyhow!("Fee manager for provider {:?} is not the keeper wallet. Fee manager: {:?} Keeper: {:?}", provider_address, provider_info.fee_manager, contract.address()));
     }
 
     // Calculate target window for the on-chain fee.
-    let middleware = contract.client();
     let gas_limit: u128 = u128::from(provider_info.default_gas_limit);
-    let max_callback_cost: u128 = estimate_tx_cost(middleware, legacy_tx, gas_limit)
+    let max_callback_cost: u128 = contract
+        .estimate_tx_cost(gas_limit)
         .await
         .map_err(|e| anyhow!("Could not estimate transaction cost. error {:?}", e))?;
 
@@ -344,9 +350,8 @@ pub async fn adjust_fee_if_necessary(
     }
 
     // Calculate current P&L to determine if we can reduce fees.
-    let current_keeper_balance = contract
-        .provider()
-        .get_balance(contract.wallet().address(), None)
+    let current_keeper_balance = reader
+        .get_balance(contract.address())
         .await
         .map_err(|e| anyhow!("Error while getting balance. error: {:?}", e))?;
     let current_keeper_fees = U256::from(provider_info.accrued_fees_in_wei);
@@ -378,8 +383,9 @@ pub async fn adjust_fee_if_necessary(
             provider_fee,
             target_fee
         );
-        let contract_call = contract.set_provider_fee_as_fee_manager(provider_address, target_fee);
-        send_and_confirm(contract_call).await?;
+        contract
+            .set_provider_fee_as_fee_manager(provider_address, target_fee)
+            .await?;
 
         *sequence_number_of_last_fee_update = Some(provider_info.sequence_number);
     } else {
@@ -1,4 +1,5 @@
 use {
+    crate::history::CallbackFailureKind,
     ethers::types::Address,
     prometheus_client::{
         encoding::EncodeLabelSet,
@@ -15,6 +16,13 @@ pub struct AccountLabel {
     pub address: String,
 }
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct CallbackFailureLabel {
+    pub chain_id: String,
+    pub address: String,
+    pub kind: String,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
 pub struct ChainIdLabel {
     pub chain_id: String,
@@ -24,6 +32,9 @@ pub struct KeeperMetrics {
     pub current_sequence_number: Family<AccountLabel, Gauge>,
     pub current_commitment_sequence_number: Family<AccountLabel, Gauge>,
     pub end_sequence_number: Family<AccountLabel, Gauge>,
+    pub remaining_sequence_numbers: Family<AccountLabel, Gauge>,
+    pub commitment_rotations: Family<AccountLabel, Counter>,
+    pub commitment_rotation_failures: Family<AccountLabel, Counter>,
     pub balance: Family<AccountLabel, Gauge<f64, AtomicU64>>,
     pub collected_fee: Family<AccountLabel, Gauge<f64, AtomicU64>>,
     pub current_fee: Family<AccountLabel, Gauge<f64, AtomicU64>>,
@@ -48,6 +59,16 @@ pub struct KeeperMetrics {
     pub process_event_timestamp: Family<ChainIdLabel, Gauge>,
     pub latest_block_number: Family<ChainIdLabel, Gauge>,
     pub process_event_block_number: Family<ChainIdLabel, Gauge>,
+    pub live_replicas: Family<ChainIdLabel, Gauge>,
+    pub callback_failures: Family<CallbackFailureLabel, Counter>,
+    pub callback_retries: Family<AccountLabel, Counter>,
+    pub callback_retries_success: Family<AccountLabel, Counter>,
+    pub simulated_reveals: Family<AccountLabel, Counter>,
+    pub simulated_reveal_failures: Family<AccountLabel, Counter>,
+    pub simulated_fee: Family<AccountLabel, Gauge<f64, AtomicU64>>,
+    pub pending_transactions: Family<AccountLabel, Gauge>,
+    pub replaced_transactions: Family<AccountLabel, Counter>,
+    pub transaction_replacement_failures: Family<AccountLabel, Counter>,
 }
 
 impl Default for KeeperMetrics {
@@ -56,6 +77,9 @@ impl Default for KeeperMetrics {
             current_sequence_number: Family::default(),
             current_commitment_sequence_number: Family::default(),
             end_sequence_number: Family::default(),
+            remaining_sequence_numbers: Family::default(),
+            commitment_rotations: Family::default(),
+            commitment_rotation_failures: Family::default(),
             balance: Family::default(),
             collected_fee: Family::default(),
             current_fee: Family::default(),
@@ -89,6 +113,16 @@ impl Default for KeeperMetrics {
             process_event_timestamp: Family::default(),
             latest_block_number: Family::default(),
             process_event_block_number: Family::default(),
+            live_replicas: Family::default(),
+            callback_failures: Family::default(),
+            callback_retries: Family::default(),
+            callback_retries_success: Family::default(),
+            simulated_reveals: Family::default(),
+            simulated_reveal_failures: Family::default(),
+            simulated_fee: Family::default(),
+            pending_transactions: Family::default(),
+            replaced_transactions: Family::default(),
+            transaction_replacement_failures: Family::default(),
         }
     }
 }
@@ -116,6 +150,24 @@ impl KeeperMetrics {
             keeper_metrics.end_sequence_number.clone(),
         );
 
+        writable_registry.register(
+            "remaining_sequence_numbers",
+            "The number of sequence numbers left on the current hash chain of the provider",
+            keeper_metrics.remaining_sequence_numbers.clone(),
+        );
+
+        writable_registry.register(
+            "commitment_rotations",
+            "Number of hash chains registered by the keeper before the current one was exhausted",
+            keeper_metrics.commitment_rotations.clone(),
+        );
+
+        writable_registry.register(
+            "commitment_rotation_failures",
+            "Number of failed attempts to register the next hash chain of the provider",
+            keeper_metrics.commitment_rotation_failures.clone(),
+        );
+
         writable_registry.register(
             "requests",
             "Number of requests received through events",
@@ -260,6 +312,66 @@ impl KeeperMetrics {
             keeper_metrics.process_event_block_number.clone(),
         );
 
+        writable_registry.register(
+            "live_replicas",
+            "Number of keeper replicas holding a lease, if replica leases are configured",
+            keeper_metrics.live_replicas.clone(),
+        );
+
+        writable_registry.register(
+            "callback_failures",
+            "Number of revealed requests whose callback failed, by kind of failure",
+            keeper_metrics.callback_failures.clone(),
+        );
+
+        writable_registry.register(
+            "callback_retries",
+            "Number of attempts to reveal a request again after its callback failed",
+            keeper_metrics.callback_retries.clone(),
+        );
+
+        writable_registry.register(
+            "callback_retries_success",
+            "Number of requests whose callback succeeded when revealed again",
+            keeper_metrics.callback_retries_success.clone(),
+        );
+
+        writable_registry.register(
+            "simulated_reveals",
+            "Number of reveals simulated in dry-run mode",
+            keeper_metrics.simulated_reveals.clone(),
+        );
+
+        writable_registry.register(
+            "simulated_reveal_failures",
+            "Number of reveals simulated in dry-run mode that would have failed",
+            keeper_metrics.simulated_reveal_failures.clone(),
+        );
+
+        writable_registry.register(
+            "simulated_fee",
+            "Total estimated fee of the reveals simulated in dry-run mode",
+            keeper_metrics.simulated_fee.clone(),
+        );
+
+        writable_registry.register(
+            "pending_transactions",
+            "Number of reveals being submitted by each keeper wallet",
+            keeper_metrics.pending_transactions.clone(),
+        );
+
+        writable_registry.register(
+            "replaced_transactions",
+            "Number of stuck transactions of each keeper wallet replaced by a cancellation",
+            keeper_metrics.replaced_transactions.clone(),
+        );
+
+        writable_registry.register(
+            "transaction_replacement_failures",
+            "Number of failed attempts to replace a stuck transaction of each keeper wallet",
+            keeper_metrics.transaction_replacement_failures.clone(),
+        );
+
         // *Important*: When adding a new metric:
         // 1. Register it above using `writable_registry.register(...)`
         // 2. Add a get_or_create call in the add_chain function below to initialize it for each chain/provider pair
@@ -279,6 +391,15 @@ impl KeeperMetrics {
         let _ = self
             .process_event_block_number
             .get_or_create(&chain_id_label);
+        let _ = self.live_replicas.get_or_create(&chain_id_label);
+
+        for kind in CallbackFailureKind::ALL {
+            let _ = self.callback_failures.get_or_create(&CallbackFailureLabel {
+                chain_id: chain_id.clone(),
+                address: provider_address.to_string(),
+                kind: kind.as_str().to_string(),
+            });
+        }
 
         let account_label = AccountLabel {
             chain_id,
@@ -290,6 +411,13 @@ impl KeeperMetrics {
             .current_commitment_sequence_number
             .get_or_create(&account_label);
         let _ = self.end_sequence_number.get_or_create(&account_label);
+        let _ = self
+            .remaining_sequence_numbers
+            .get_or_create(&account_label);
+        let _ = self.commitment_rotations.get_or_create(&account_label);
+        let _ = self
+            .commitment_rotation_failures
+            .get_or_create(&account_label);
         let _ = self.balance.get_or_create(&account_label);
         let _ = self.collected_fee.get_or_create(&account_label);
         let _ = self.current_fee.get_or_create(&account_label);
@@ -313,5 +441,10 @@ impl KeeperMetrics {
         let _ = self.retry_count.get_or_create(&account_label);
         let _ = self.final_fee_multiplier.get_or_create(&account_label);
         let _ = self.gas_price_estimate.get_or_create(&account_label);
+        let _ = self.callback_retries.get_or_create(&account_label);
+        let _ = self.callback_retries_success.get_or_create(&account_label);
+        let _ = self.simulated_reveals.get_or_create(&account_label);
+        let _ = self.simulated_reveal_failures.get_or_create(&account_label);
+        let _ = self.simulated_fee.get_or_create(&account_label);
     }
 }
@@ -1,17 +1,19 @@
 use {
-    super::keeper_metrics::AccountLabel,
+    super::keeper_metrics::{AccountLabel, CallbackFailureLabel},
     crate::{
         chain::{
-            ethereum::PythRandomErrorsErrors,
             reader::{RequestCallbackStatus, RequestedV2Event},
+            writer::RevealReceipt,
+        },
+        history::{CallbackFailure, RequestEntryState, RequestStatus},
+        keeper::{
+            block::ProcessParams,
+            callback::{diagnose_callback_failure, retry_failed_callback},
+            replica::{is_primary_replica, wait_as_backup_replica},
         },
-        eth_utils::utils::{submit_tx_with_backoff, SubmitTxError},
-        history::{RequestEntryState, RequestStatus},
-        keeper::block::ProcessParams,
     },
     anyhow::{anyhow, Result},
-    ethers::{abi::AbiDecode, contract::ContractError},
-    std::time::Duration,
+    ethers::types::Bytes,
     tracing,
 };
 
@@ -26,11 +28,10 @@ pub async fn process_event_with_backoff(
     let ProcessParams {
         chain_state,
         contract,
-        escalation_policy,
         metrics,
         history,
         ..
-    } = process_param;
+    } = &process_param;
 
     // ignore requests that are not for the configured provider
     if chain_state.provider_address != event.provider_address {
@@ -38,24 +39,20 @@ pub async fn process_event_with_backoff(
     }
 
     // If replica config is present, we're running with multiple instances.
-    // The incoming request is assigned by modulo operation on the sequence number
-    // and the total number of replicas. If our replica_id is the primary for this sequence number,
-    // we process the request directly. If our replica_id is a backup, we wait for the delay and
-    // then check if the request is still open. If it is, we process it as a backup replica.
+    // The incoming request is assigned to one of the replicas holding a lease, or by modulo operation
+    // on the sequence number and the total number of replicas if there are no leases. If this replica
+    // is the primary for this sequence number, we process the request directly. If it is a backup, we
+    // wait for the delay (or until the lease of the primary expires) and then check if the request is
+    // still open. If it is, we process it as a backup replica.
     if let Some(replica_config) = &process_param.replica_config {
-        let assigned_replica = event.sequence_number % replica_config.total_replicas;
-        let is_primary_replica = assigned_replica == replica_config.replica_id;
-
-        if is_primary_replica {
+        let coordinator = process_param.replica_coordinator.as_deref();
+        if is_primary_replica(replica_config, coordinator, event.sequence_number) {
             tracing::debug!("Processing request as primary replica");
         } else {
             tracing::debug!("Processing request as backup replica");
 
             tracing::info!("Waiting before processing as backup replica");
-            tokio::time::sleep(tokio::time::Duration::from_secs(
-                replica_config.backup_delay_seconds,
-            ))
-            .await;
+            wait_as_backup_replica(replica_config, coordinator, event.sequence_number).await;
 
             // Check if the request is still open after the delay.
             // If it is, we will process it as a backup replica.
@@ -138,52 +135,26 @@ pub async fn process_event_with_backoff(
             anyhow!("Error revealing: {:?}", e)
         })?;
 
-    let contract_call = contract.reveal_with_callback(
-        event.provider_address,
-        event.sequence_number,
-        event.user_random_number,
-        provider_revelation,
-    );
-    let error_mapper = |num_retries, e| {
-        if let backoff::Error::Transient {
-            err: SubmitTxError::GasUsageEstimateError(tx, ContractError::Revert(revert)),
-            ..
-        } = &e
-        {
-            if let Ok(PythRandomErrorsErrors::NoSuchRequest(_)) =
-                PythRandomErrorsErrors::decode(revert)
-            {
-                let err = SubmitTxError::GasUsageEstimateError(
-                    tx.clone(),
-                    ContractError::Revert(revert.clone()),
-                );
-                // Slow down the retries if the request is not found.
-                // This probably means that the request is already fulfilled via another process.
-                // After 5 retries, we return the error permanently.
-                if num_retries >= 5 {
-                    return backoff::Error::Permanent(err);
-                }
-                let retry_after_seconds = match num_retries {
-                    0 => 5,
-                    1 => 10,
-                    _ => 60,
-                };
-                return backoff::Error::Transient {
-                    err,
-                    retry_after: Some(Duration::from_secs(retry_after_seconds)),
-                };
-            }
-        }
-        e
-    };
+    if process_param.dry_run {
+        simulate_event(
+            &process_param,
+            &event,
+            &mut status,
+            provider_revelation,
+            &account_label,
+        )
+        .await;
+        return Ok(());
+    }
 
-    let success = submit_tx_with_backoff(
-        contract.client(),
-        contract_call,
-        escalation_policy,
-        Some(error_mapper),
-    )
-    .await;
+    let success = contract
+        .reveal_with_callback(
+            event.provider_address,
+            event.sequence_number,
+            event.user_random_number,
+            provider_revelation,
+        )
+        .await;
 
     metrics
         .requests_processed
@@ -193,25 +164,25 @@ pub async fn process_event_with_backoff(
     status.last_updated_at = chrono::Utc::now();
     match success {
         Ok(result) => {
-            status.state = RequestEntryState::Completed {
-                reveal_block_number: result.receipt.block_number.unwrap_or_default().as_u64(),
-                reveal_tx_hash: result.receipt.transaction_hash,
-                provider_random_number: provider_revelation,
-                gas_used: result.receipt.gas_used.unwrap_or_default(),
-                combined_random_number: RequestStatus::generate_combined_random_number(
-                    &event.user_random_number,
-                    &provider_revelation,
-                ),
-                callback_failed: result.revealed_event.callback_failed,
-                callback_return_value: result.revealed_event.callback_return_value,
-                callback_gas_used: result.revealed_event.callback_gas_used,
-            };
+            let callback_failure = diagnose_callback(
+                &process_param,
+                &event,
+                result.callback_failed,
+                result.callback_gas_used,
+                &result.callback_return_value,
+            );
+            status.state = completed_state(
+                &event,
+                provider_revelation,
+                &result,
+                callback_failure.clone(),
+            );
             history.add(&status);
             tracing::info!(
                 "Processed event successfully in {:?} after {} retries. Receipt: {:?}",
                 result.duration,
                 result.num_retries,
-                result.receipt
+                result
             );
 
             metrics
@@ -235,14 +206,14 @@ pub async fn process_event_with_backoff(
                 .get_or_create(&account_label)
                 .observe(result.fee_multiplier as f64);
 
-            if let Some(gas_used) = result.receipt.gas_used {
+            if let Some(gas_used) = result.gas_used {
                 let gas_used_float = gas_used.as_u128() as f64 / 1e18;
                 metrics
                     .total_gas_spent
                     .get_or_create(&account_label)
                     .inc_by(gas_used_float);
 
-                if let Some(gas_price) = result.receipt.effective_gas_price {
+                if let Some(gas_price) = result.effective_gas_price {
                     let gas_fee = (gas_used * gas_price).as_u128() as f64 / 1e18;
                     metrics
                         .total_gas_fee_spent
@@ -251,6 +222,39 @@ pub async fn process_event_with_backoff(
                 }
             }
             metrics.reveals.get_or_create(&account_label).inc();
+
+            if let Some(failure) = callback_failure {
+                tracing::warn!(
+                    kind = failure.kind.as_str(),
+                    reason = ?failure.reason,
+                    "Callback failed"
+                );
+                metrics
+                    .callback_failures
+                    .get_or_create(&CallbackFailureLabel {
+                        chain_id: account_label.chain_id.clone(),
+                        address: account_label.address.clone(),
+                        kind: failure.kind.as_str().to_string(),
+                    })
+                    .inc();
+                if let Some(policy) = &process_param.callback_retry {
+                    if policy.retry_on.contains(&failure.kind) {
+                        if let Some(receipt) = retry_failed_callback(
+                            &process_param,
+                            policy,
+                            &event,
+                            provider_revelation,
+                        )
+                        .await
+                        {
+                            status.last_updated_at = chrono::Utc::now();
+                            status.state =
+                                completed_state(&event, provider_revelation, &receipt, None);
+                            history.add(&status);
+                        }
+                    }
+                }
+            }
         }
         Err(e) => {
             // The reveal failed after exhausting retries. Re-check the on-chain request state before
@@ -269,37 +273,8 @@ pub async fn process_event_with_backoff(
                         .requests_processed_failure
                         .get_or_create(&account_label)
                         .inc();
-                    // Do not display the internal error, it might include RPC details.
-                    let reason = match e {
-                        SubmitTxError::GasUsageEstimateError(_, ContractError::Revert(revert)) => {
-                            format!("Reverted: {revert}")
-                        }
-                        SubmitTxError::GasLimitExceeded { limit, estimate } => {
-                            format!("Gas limit exceeded: limit = {limit}, estimate = {estimate}")
-                        }
-                        SubmitTxError::GasUsageEstimateError(_, _) => {
-                            "Unable to estimate gas usage".to_string()
-                        }
-                        SubmitTxError::GasPriceEstimateError(_) => {
-                            "Unable to estimate gas price".to_string()
-                        }
-                        SubmitTxError::SubmissionError(_, _) => {
-                            "Error submitting the transaction on-chain".to_string()
-                        }
-                        SubmitTxError::ConfirmationTimeout(tx) => format!(
-                            "Transaction was submitted, but never confirmed. Hash: {}",
-                            tx.sighash()
-                        ),
-                        SubmitTxError::ConfirmationError(tx, _) => format!(
-                            "Transaction was submitted, but never confirmed. Hash: {}",
-                            tx.sighash()
-                        ),
-                        SubmitTxError::ReceiptError(tx, _) => {
-                            format!("Reveal transaction failed on-chain. Hash: {}", tx.sighash())
-                        }
-                    };
                     status.state = RequestEntryState::Failed {
-                        reason,
+                        reason: e.reason,
                         provider_random_number: Some(provider_revelation),
                     };
                     history.add(&status);
@@ -331,6 +306,13 @@ pub async fn process_event_with_backoff(
                             gas_used: revealed.gas_used,
                             combined_random_number: revealed.random_number,
                             callback_failed: revealed.callback_failed,
+                            callback_failure: diagnose_callback(
+                                &process_param,
+                                &event,
+                                revealed.callback_failed,
+                                revealed.callback_gas_used,
+                                &revealed.callback_return_value,
+                            ),
                             callback_return_value: revealed.callback_return_value,
                             callback_gas_used: revealed.callback_gas_used,
                         };
@@ -352,3 +334,118 @@ pub async fn process_event_with_backoff(
 
     Ok(())
 }
+
+/// Simulate the reveal of `event` in dry-run mode and record the outcome in the history. The
+/// request stays open on the contract.
+async fn simulate_event(
+    process_param: &ProcessParams,
+    event: &RequestedV2Event,
+    status: &mut RequestStatus,
+    provider_revelation: [u8; 32],
+    account_label: &AccountLabel,
+) {
+    let ProcessParams {
+        contract,
+        metrics,
+        history,
+        ..
+    } = process_param;
+
+    let simulation = contract
+        .simulate_reveal_with_callback(
+            event.provider_address,
+            event.sequence_number,
+            event.user_random_number,
+            provider_revelation,
+        )
+        .await;
+    status.last_updated_at = chrono::Utc::now();
+    match simulation {
+        Ok(simulated) => {
+            let callback_failure = diagnose_callback(
+                process_param,
+                event,
+                simulated.callback_failed,
+                simulated.callback_gas_used,
+                &simulated.callback_return_value,
+            );
+            tracing::info!(
+                gas_estimate = %simulated.gas_estimate,
+                estimated_fee = %simulated.estimated_fee,
+                callback_failure = ?callback_failure,
+                "Simulated reveal"
+            );
+            metrics.simulated_reveals.get_or_create(account_label).inc();
+            metrics
+                .simulated_fee
+                .get_or_create(account_label)
+                .inc_by(simulated.estimated_fee.as_u128() as f64 / 1e18);
+            status.state = RequestEntryState::Simulated {
+                provider_random_number: provider_revelation,
+                combined_random_number: RequestStatus::generate_combined_random_number(
+                    &event.user_random_number,
+                    &provider_revelation,
+                ),
+                gas_estimate: simulated.gas_estimate,
+                estimated_fee: simulated.estimated_fee,
+                callback_failed: simulated.callback_failed,
+                callback_return_value: simulated.callback_return_value,
+                callback_gas_used: simulated.callback_gas_used,
+                callback_failure,
+            };
+        }
+        Err(e) => {
+            tracing::warn!("Simulated reveal failed: {}", e);
+            metrics
+                .simulated_reveal_failures
+                .get_or_create(account_label)
+                .inc();
+            status.state = RequestEntryState::Failed {
+                reason: e.reason,
+                provider_random_number: Some(provider_revelation),
+            };
+        }
+    }
+    history.add(status);
+}
+
+/// Classify and decode the failure of the callback of `event`, if it failed.
+fn diagnose_callback(
+    process_param: &ProcessParams,
+    event: &RequestedV2Event,
+    callback_failed: bool,
+    callback_gas_used: u32,
+    callback_return_value: &Bytes,
+) -> Option<CallbackFailure> {
+    callback_failed.then(|| {
+        diagnose_callback_failure(
+            event.gas_limit,
+            callback_gas_used,
+            callback_return_value,
+            process_param.consumer_abis.get(event.sender),
+        )
+    })
+}
+
+/// The state of `event` after the reveal transaction of `receipt` landed.
+fn completed_state(
+    event: &RequestedV2Event,
+    provider_revelation: [u8; 32],
+    receipt: &RevealReceipt,
+    callback_failure: Option<CallbackFailure>,
+) -> RequestEntryState {
+    RequestEntryState::Completed {
+        reveal_block_number: receipt.block_number,
+        reveal_tx_hash: receipt.transaction_hash,
+        provider_random_number: provider_revelation,
+        gas_used: receipt.gas_used.unwrap_or_default(),
+        combined_random_number: RequestStatus::generate_combined_random_number(
+            &event.user_random_number,
+            &provider_revelation,
+        ),
+        callback_failed: receipt.callback_failed,
+        callback_return_value: receipt.callback_return_value.clone(),
+        callback_gas_used: receipt.callback_gas_used,
+        callback_failure,
+    }
+}
@@ -0,0 +1,368 @@
+use {
+    crate::{
+        api::ChainId,
+        config::{ReplicaConfig, ReplicaLeaseConfig},
+        history::History,
+        keeper::keeper_metrics::{ChainIdLabel, KeeperMetrics},
+    },
+    anyhow::Result,
+    axum::async_trait,
+    chrono::{DateTime, Utc},
+    std::sync::{Arc, RwLock},
+    tokio::time::{self, Duration, Instant},
+    tracing,
+};
+
+/// Where the replicas of a chain publish their leases. All the replicas of a chain need to use the
+/// same registry, e.g. the same history database.
+#[async_trait]
+pub trait LeaseRegistry: Send + Sync {
+    /// Create or extend the lease of `replica` on `chain_id` until `expires_at`.
+    async fn renew_lease(
+        &self,
+        chain_id: &ChainId,
+        replica: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// The replicas on `chain_id` whose lease has not expired at `now`, in ascending order.
+    async fn live_replicas(&self, chain_id: &ChainId, now: DateTime<Utc>) -> Result<Vec<String>>;
+}
+
+#[async_trait]
+impl LeaseRegistry for History {
+    async fn renew_lease(
+        &self,
+        chain_id: &ChainId,
+        replica: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.renew_replica_lease(chain_id, replica, expires_at)
+            .await
+    }
+
+    async fn live_replicas(&self, chain_id: &ChainId, now: DateTime<Utc>) -> Result<Vec<String>> {
+        History::live_replicas(self, chain_id, now).await
+    }
+}
+
+struct LiveReplicas {
+    replicas: Vec<String>,
+    updated_at: Instant,
+}
+
+/// Splits the requests of a chain among the replicas that hold a lease in the registry. Each request
+/// is assigned to a live replica by its sequence number, so the assignment changes as soon as a
+/// lease expires or a new replica renews its lease.
+pub struct ReplicaCoordinator {
+    registry: Arc<dyn LeaseRegistry>,
+    chain_id: ChainId,
+    replica: String,
+    config: ReplicaLeaseConfig,
+    live: RwLock<Option<LiveReplicas>>,
+}
+
+impl ReplicaCoordinator {
+    pub fn new(
+        registry: Arc<dyn LeaseRegistry>,
+        chain_id: ChainId,
+        replica: String,
+        config: ReplicaLeaseConfig,
+    ) -> Self {
+        Self {
+            registry,
+            chain_id,
+            replica,
+            config,
+            live: RwLock::new(None),
+        }
+    }
+
+    pub fn replica(&self) -> &str {
+        &self.replica
+    }
+
+    pub fn renew_interval(&self) -> Duration {
+        Duration::from_secs(self.config.renew_interval_seconds)
+    }
+
+    /// Renew the lease of this replica and refresh the set of live replicas. Returns the number of
+    /// live replicas.
+    pub async fn renew(&self) -> Result<usize> {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::seconds(self.config.duration_seconds as i64);
+        self.registry
+            .renew_lease(&self.chain_id, &self.replica, expires_at)
+            .await?;
+        let replicas = self.registry.live_replicas(&self.chain_id, now).await?;
+        let count = replicas.len();
+        *self.live.write().unwrap() = Some(LiveReplicas {
+            replicas,
+            updated_at: Instant::now(),
+        });
+        Ok(count)
+    }
+
+    /// The live replica that should process `sequence_number` first. Returns `None` if there is no
+    /// live replica, or the set of live replicas is older than a lease, e.g. because the registry
+    /// is unavailable.
+    pub fn assigned_replica(&self, sequence_number: u64) -> Option<String> {
+        let live = self.live.read().unwrap();
+        let live = live.as_ref()?;
+        if live.replicas.is_empty()
+            || live.updated_at.elapsed() > Duration::from_secs(self.config.duration_seconds)
+        {
+            return None;
+        }
+        let index = sequence_number % live.replicas.len() as u64;
+        Some(live.replicas[index as usize].clone())
+    }
+}
+
+/// Renew the lease of the replica every renew interval, and track the number of live replicas.
+#[tracing::instrument(name = "replica_lease", skip_all)]
+pub async fn renew_lease_loop(coordinator: Arc<ReplicaCoordinator>, metrics: Arc<KeeperMetrics>) {
+    let label = ChainIdLabel {
+        chain_id: coordinator.chain_id.clone(),
+    };
+    loop {
+        match coordinator.renew().await {
+            Ok(count) => {
+                metrics
+                    .live_replicas
+                    .get_or_create(&label)
+                    .set(count as i64);
+            }
+            Err(e) => {
+                tracing::error!("Error renewing replica lease: {:?}", e);
+            }
+        }
+        time::sleep(coordinator.renew_interval()).await;
+    }
+}
+
+/// Whether this replica should process `sequence_number` first. The leases of `coordinator` are
+/// used if available, otherwise the request is assigned by `sequence_number % total_replicas`.
+pub fn is_primary_replica(
+    replica_config: &ReplicaConfig,
+    coordinator: Option<&ReplicaCoordinator>,
+    sequence_number: u64,
+) -> bool {
+    if let Some(coordinator) = coordinator {
+        match coordinator.assigned_replica(sequence_number) {
+            Some(assigned_replica) => return assigned_replica == coordinator.replica(),
+            None => tracing::warn!("No live replica leases, assigning the request by modulo"),
+        }
+    }
+    sequence_number % replica_config.total_replicas == replica_config.replica_id
+}
+
+/// Whether this replica runs the tasks that a single replica of the chain should run, e.g.
+/// registering the next commitment of the provider. The leader is the primary replica of sequence
+/// number 0, so the live replicas agree on it. Without replicas, the keeper is the leader.
+pub fn is_leader_replica(
+    replica_config: Option<&ReplicaConfig>,
+    coordinator: Option<&ReplicaCoordinator>,
+) -> bool {
+    replica_config.is_none_or(|replica_config| is_primary_replica(replica_config, coordinator, 0))
+}
+
+/// Wait `backup_delay_seconds` for the primary replica to process `sequence_number`. If a
+/// `coordinator` is provided, stop waiting as soon as the request is assigned to this replica,
+/// i.e. when the lease of the primary replica expired.
+pub async fn wait_as_backup_replica(
+    replica_config: &ReplicaConfig,
+    coordinator: Option<&ReplicaCoordinator>,
+    sequence_number: u64,
+) {
+    let deadline = Instant::now() + Duration::from_secs(replica_config.backup_delay_seconds);
+    let Some(coordinator) = coordinator else {
+        time::sleep_until(deadline).await;
+        return;
+    };
+    while Instant::now() < deadline {
+        time::sleep_until(deadline.min(Instant::now() + coordinator.renew_interval())).await;
+        if coordinator.assigned_replica(sequence_number).as_deref() == Some(coordinator.replica()) {
+            tracing::info!("Primary replica lease expired, taking over the request");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use {
+        super::LeaseRegistry,
+        crate::api::ChainId,
+        anyhow::Result,
+        axum::async_trait,
+        chrono::{DateTime, Utc},
+        std::{collections::HashMap, sync::RwLock},
+    };
+
+    /// A lease registry for replicas running in the same process, e.g. in tests.
+    #[derive(Default)]
+    pub struct InMemoryLeaseRegistry {
+        leases: RwLock<HashMap<(ChainId, String), DateTime<Utc>>>,
+    }
+
+    #[async_trait]
+    impl LeaseRegistry for InMemoryLeaseRegistry {
+        async fn renew_lease(
+            &self,
+            chain_id: &ChainId,
+            replica: &str,
+            expires_at: DateTime<Utc>,
+        ) -> Result<()> {
+            self.leases
+                .write()
+                .unwrap()
+                .insert((chain_id.clone(), replica.to_string()), expires_at);
+            Ok(())
+        }
+
+        async fn live_replicas(
+            &self,
+            chain_id: &ChainId,
+            now: DateTime<Utc>,
+        ) -> Result<Vec<String>> {
+            let mut replicas: Vec<String> = self
+                .leases
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|((c, _), expires_at)| c == chain_id && **expires_at > now)
+                .map(|((_, replica), _)| replica.clone())
+                .collect();
+            replicas.sort();
+            Ok(replicas)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, mock::InMemoryLeaseRegistry};
+
+    const CHAIN_ID: &str = "ethereum";
+
+    fn coordinator(registry: Arc<InMemoryLeaseRegistry>, replica: &str) -> ReplicaCoordinator {
+        ReplicaCoordinator::new(
+            registry,
+            CHAIN_ID.to_string(),
+            replica.to_string(),
+            ReplicaLeaseConfig {
+                renew_interval_seconds: 1,
+                duration_seconds: 3,
+            },
+        )
+    }
+
+    fn replica_config(replica_id: u64) -> ReplicaConfig {
+        ReplicaConfig {
+            replica_id,
+            total_replicas: 2,
+            backup_delay_seconds: 3600,
+            lease: None,
+        }
+    }
+
+    async fn expire(registry: &InMemoryLeaseRegistry, replica: &str) {
+        registry
+            .renew_lease(
+                &CHAIN_ID.to_string(),
+                replica,
+                Utc::now() - chrono::Duration::seconds(1),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_split_among_live_replicas() {
+        let registry = Arc::new(InMemoryLeaseRegistry::default());
+        let a = coordinator(registry.clone(), "a");
+        let b = coordinator(registry.clone(), "b");
+        let c = coordinator(registry.clone(), "c");
+        a.renew().await.unwrap();
+        b.renew().await.unwrap();
+        assert_eq!(c.renew().await.unwrap(), 3);
+        a.renew().await.unwrap();
+
+        assert_eq!(a.assigned_replica(3), Some("a".to_string()));
+        assert_eq!(a.assigned_replica(4), Some("b".to_string()));
+        assert_eq!(a.assigned_replica(5), Some("c".to_string()));
+
+        // The requests of a replica are reassigned as soon as its lease expires.
+        expire(&registry, "c").await;
+        assert_eq!(a.renew().await.unwrap(), 2);
+        assert_eq!(a.assigned_replica(5), Some("b".to_string()));
+
+        // Replicas of other chains are ignored.
+        registry
+            .renew_lease(
+                &"other".to_string(),
+                "d",
+                Utc::now() + chrono::Duration::seconds(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(a.renew().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_to_modulo_assignment() {
+        let registry = Arc::new(InMemoryLeaseRegistry::default());
+        let b = coordinator(registry.clone(), "b");
+
+        // Without leases, the requests are assigned by modulo.
+        assert!(is_primary_replica(&replica_config(1), Some(&b), 3));
+        assert!(!is_primary_replica(&replica_config(1), Some(&b), 4));
+        assert!(!is_primary_replica(&replica_config(1), None, 4));
+
+        // With leases, the modulo assignment is ignored.
+        b.renew().await.unwrap();
+        assert!(is_primary_replica(&replica_config(1), Some(&b), 4));
+    }
+
+    #[tokio::test]
+    async fn test_single_leader_replica() {
+        let registry = Arc::new(InMemoryLeaseRegistry::default());
+        let a = coordinator(registry.clone(), "a");
+        let b = coordinator(registry.clone(), "b");
+        assert!(is_leader_replica(None, None));
+        assert!(is_leader_replica(Some(&replica_config(0)), None));
+        assert!(!is_leader_replica(Some(&replica_config(1)), None));
+
+        // With leases, the first live replica leads, and another takes over when its lease expires.
+        a.renew().await.unwrap();
+        b.renew().await.unwrap();
+        a.renew().await.unwrap();
+        assert!(is_leader_replica(Some(&replica_config(1)), Some(&a)));
+        assert!(!is_leader_replica(Some(&replica_config(0)), Some(&b)));
+        expire(&registry, "a").await;
+        b.renew().await.unwrap();
+        assert!(is_leader_replica(Some(&replica_config(0)), Some(&b)));
+    }
+
+    #[tokio::test]
+    async fn test_backup_takes_over_when_lease_expires() {
+        let registry = Arc::new(InMemoryLeaseRegistry::default());
+        let a = Arc::new(coordinator(registry.clone(), "a"));
+        let b = coordinator(registry.clone(), "b");
+        b.renew().await.unwrap();
+        a.renew().await.unwrap();
+        assert!(!is_primary_replica(&replica_config(0), Some(&a), 1));
+
+        let waiting = tokio::spawn({
+            let a = a.clone();
+            async move { wait_as_backup_replica(&replica_config(0), Some(&a), 1).await }
+        });
+        expire(&registry, "b").await;
+        a.renew().await.unwrap();
+        time::timeout(Duration::from_secs(10), waiting)
+            .await
+            .expect("backup replica did not take over")
+            .unwrap();
+    }
+}
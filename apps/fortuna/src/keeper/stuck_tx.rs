@@ -0,0 +1,233 @@
+use {
+    super::keeper_metrics::{AccountLabel, KeeperMetrics},
+    crate::{
+        api::{ChainId, NetworkId},
+        chain::writer::EntropyWriter,
+        history::{History, TransactionReplacement},
+    },
+    anyhow::Result,
+    chrono::{DateTime, Utc},
+    ethers::types::Address,
+    std::{collections::HashMap, sync::Arc},
+    tokio::time::{self, Duration},
+};
+
+/// How often the nonces of the keeper wallets are checked for stuck transactions.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The pending transaction of a keeper wallet with the lowest nonce, which blocks the others.
+#[derive(Clone, Copy, Debug)]
+struct PendingTransaction {
+    nonce: u64,
+    since: DateTime<Utc>,
+}
+
+/// Cancels the transactions of the keeper wallets of a chain that stay pending for longer than a
+/// threshold. A transaction that never gets mined blocks all the following transactions of its
+/// wallet, as the nonce manager keeps handing out the nonces after it.
+pub struct StuckTransactionMonitor {
+    chain_id: ChainId,
+    network_id: NetworkId,
+    contract: Arc<dyn EntropyWriter>,
+    stuck_after: chrono::Duration,
+    metrics: Arc<KeeperMetrics>,
+    history: Arc<History>,
+    /// The oldest pending transaction of each wallet.
+    pending: HashMap<Address, PendingTransaction>,
+}
+
+impl StuckTransactionMonitor {
+    pub fn new(
+        chain_id: ChainId,
+        network_id: NetworkId,
+        contract: Arc<dyn EntropyWriter>,
+        stuck_after: Duration,
+        metrics: Arc<KeeperMetrics>,
+        history: Arc<History>,
+    ) -> Self {
+        for address in contract.addresses() {
+            let label = AccountLabel {
+                chain_id: chain_id.clone(),
+                address: address.to_string(),
+            };
+            let _ = metrics.replaced_transactions.get_or_create(&label);
+            let _ = metrics
+                .transaction_replacement_failures
+                .get_or_create(&label);
+        }
+        Self {
+            chain_id,
+            network_id,
+            contract,
+            stuck_after: chrono::Duration::from_std(stuck_after).unwrap_or(chrono::Duration::MAX),
+            metrics,
+            history,
+            pending: HashMap::new(),
+        }
+    }
+
+    #[tracing::instrument(name = "stuck_tx", skip_all, fields(chain_id = self.chain_id))]
+    pub async fn run(mut self) {
+        tracing::info!(
+            "Cancelling the keeper transactions pending for more than {}s",
+            self.stuck_after.num_seconds()
+        );
+        loop {
+            self.check(Utc::now()).await;
+            time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Check the nonces of the keeper wallets at `now`, and cancel their transactions that have
+    /// been pending for too long.
+    async fn check(&mut self, now: DateTime<Utc>) {
+        for address in self.contract.addresses() {
+            if let Err(e) = self.check_wallet(address, now).await {
+                tracing::error!(
+                    "Error checking the pending transactions of {:?}: {:?}",
+                    address,
+                    e
+                );
+            }
+        }
+    }
+
+    async fn check_wallet(&mut self, address: Address, now: DateTime<Utc>) -> Result<()> {
+        let nonces = self.contract.account_nonces(address).await?;
+        if nonces.pending <= nonces.mined {
+            self.pending.remove(&address);
+            return Ok(());
+        }
+
+        // The transaction with the next nonce to be mined is pending. It is stuck if it was
+        // already pending at least `stuck_after` ago.
+        let pending = self
+            .pending
+            .entry(address)
+            .and_modify(|pending| {
+                if pending.nonce != nonces.mined {
+                    *pending = PendingTransaction {
+                        nonce: nonces.mined,
+                        since: now,
+                    };
+                }
+            })
+            .or_insert(PendingTransaction {
+                nonce: nonces.mined,
+                since: now,
+            });
+        if now - pending.since < self.stuck_after {
+            return Ok(());
+        }
+        let pending = *pending;
+
+        tracing::warn!(
+            "Transaction of {:?} with nonce {} pending since {}. Cancelling it.",
+            address,
+            pending.nonce,
+            pending.since
+        );
+        let label = AccountLabel {
+            chain_id: self.chain_id.clone(),
+            address: address.to_string(),
+        };
+        let transaction_hash = match self
+            .contract
+            .cancel_transaction(address, pending.nonce)
+            .await
+        {
+            Ok(transaction_hash) => transaction_hash,
+            Err(e) => {
+                self.metrics
+                    .transaction_replacement_failures
+                    .get_or_create(&label)
+                    .inc();
+                return Err(e);
+            }
+        };
+        self.metrics
+            .replaced_transactions
+            .get_or_create(&label)
+            .inc();
+        self.pending.remove(&address);
+        self.history
+            .record_transaction_replacement(&TransactionReplacement {
+                chain_id: self.chain_id.clone(),
+                network_id: self.network_id,
+                address,
+                nonce: pending.nonce,
+                transaction_hash,
+                pending_since: pending.since,
+                replaced_at: Utc::now(),
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::chain::{
+            reader::mock::MockEntropyReader,
+            writer::{mock::MockEntropyWriter, AccountNonces},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_cancel_stuck_transaction() {
+        let address = Address::from_low_u64_be(10);
+        let writer = Arc::new(MockEntropyWriter::new(
+            address,
+            Arc::new(MockEntropyReader::with_requests(10, &[])),
+        ));
+        *writer.nonces.write().unwrap() = AccountNonces {
+            mined: 3,
+            pending: 5,
+        };
+        let metrics = Arc::new(KeeperMetrics::default());
+        let history = Arc::new(History::new_in_memory().await.unwrap());
+        let mut monitor = StuckTransactionMonitor::new(
+            "ethereum".into(),
+            121,
+            writer.clone(),
+            Duration::from_secs(60),
+            metrics.clone(),
+            history.clone(),
+        );
+
+        let start = DateTime::from_timestamp_millis(Utc::now().timestamp_millis()).unwrap();
+        monitor.check(start).await;
+        monitor.check(start + chrono::Duration::seconds(59)).await;
+        assert!(writer.cancelled.read().unwrap().is_empty());
+
+        // The transaction with nonce 3 has been pending for a minute.
+        monitor.check(start + chrono::Duration::seconds(60)).await;
+        assert_eq!(*writer.cancelled.read().unwrap(), vec![3]);
+        let replaced_transactions = metrics
+            .replaced_transactions
+            .get_or_create(&AccountLabel {
+                chain_id: "ethereum".into(),
+                address: address.to_string(),
+            })
+            .get();
+        assert_eq!(replaced_transactions, 1);
+        let replacements = history
+            .transaction_replacements(&"ethereum".to_string())
+            .await
+            .unwrap();
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(replacements[0].nonce, 3);
+        assert_eq!(replacements[0].pending_since, start);
+
+        // The transaction with nonce 4 is pending from now on.
+        monitor.check(start + chrono::Duration::seconds(90)).await;
+        assert_eq!(*writer.cancelled.read().unwrap(), vec![3]);
+        monitor.check(start + chrono::Duration::seconds(150)).await;
+        assert_eq!(*writer.cancelled.read().unwrap(), vec![3, 4]);
+
+        // Nothing is cancelled once no transaction is pending.
+        monitor.check(start + chrono::Duration::seconds(300)).await;
+        assert_eq!(*writer.cancelled.read().unwrap(), vec![3, 4]);
+    }
+}
@@ -1,12 +1,8 @@
 use {
     super::keeper_metrics::{AccountLabel, ChainIdLabel, KeeperMetrics},
-    crate::{
-        api::ChainId, chain::ethereum::InstrumentedPythContract,
-        eth_utils::traced_client::TracedClient,
-    },
-    anyhow::{anyhow, Result},
-    ethers::{middleware::Middleware, prelude::BlockNumber, providers::Provider, types::Address},
-    num_traits::cast::ToPrimitive,
+    crate::{api::ChainId, chain::reader::EntropyReader},
+    anyhow::Result,
+    ethers::types::Address,
     std::{
         sync::Arc,
         time::{SystemTime, UNIX_EPOCH},
@@ -18,11 +14,11 @@ use {
 #[tracing::instrument(skip_all)]
 pub async fn track_balance(
     chain_id: String,
-    provider: Arc<Provider<TracedClient>>,
+    contract: Arc<dyn EntropyReader>,
     address: Address,
     metrics: Arc<KeeperMetrics>,
 ) -> Result<()> {
-    let balance = provider.get_balance(address, None).await?;
+    let balance = contract.get_balance(address).await?;
     // This conversion to u128 is fine as the total balance will never cross the limits
     // of u128 practically.
     let balance = balance.as_u128();
@@ -45,21 +41,15 @@ pub async fn track_balance(
 #[tracing::instrument(skip_all)]
 pub async fn track_block_timestamp_lag(
     chain_id: String,
-    provider: Arc<Provider<TracedClient>>,
+    contract: Arc<dyn EntropyReader>,
     metrics: Arc<KeeperMetrics>,
 ) -> Result<()> {
     let label = ChainIdLabel {
         chain_id: chain_id.clone(),
     };
 
-    let block = provider.get_block(BlockNumber::Latest).await?;
-    let block = block.ok_or(anyhow!("block was none"))?;
-    let block_timestamp = block.timestamp.as_u64();
+    let (block_number, block_timestamp) = contract.get_latest_block_timestamp().await?;
     let block_timestamp = i64::try_from(block_timestamp)?;
-    let block_number = block
-        .number
-        .ok_or(anyhow!("block number was none"))?
-        .as_u64();
 
     metrics
         .latest_block_timestamp
@@ -83,14 +73,11 @@ pub async fn track_block_timestamp_lag(
 #[tracing::instrument(skip_all)]
 pub async fn track_provider(
     chain_id: ChainId,
-    contract: InstrumentedPythContract,
+    contract: Arc<dyn EntropyReader>,
     provider_address: Address,
     metrics: Arc<KeeperMetrics>,
 ) -> Result<()> {
-    let provider_info = contract
-        .get_provider_info_v2(provider_address)
-        .call()
-        .await?;
+    let provider_info = contract.get_provider_info(provider_address, None).await?;
 
     // The f64 conversion is made to be able to serve metrics with the constraints of Prometheus.
     // The fee is in wei, so we divide by 1e18 to convert it to eth.
@@ -144,6 +131,13 @@ pub async fn track_provider(
             address: provider_address.to_string(),
         })
         .set(end_sequence_number as i64);
+    metrics
+        .remaining_sequence_numbers
+        .get_or_create(&AccountLabel {
+            chain_id: chain_id.clone(),
+            address: provider_address.to_string(),
+        })
+        .set(end_sequence_number.saturating_sub(current_sequence_number) as i64);
 
     Ok(())
 }
@@ -152,19 +146,14 @@ pub async fn track_provider(
 #[tracing::instrument(skip_all)]
 pub async fn track_accrued_pyth_fees(
     chain_id: ChainId,
-    contract: InstrumentedPythContract,
+    contract: Arc<dyn EntropyReader>,
     metrics: Arc<KeeperMetrics>,
 ) -> Result<()> {
-    let accrued_pyth_fees = contract.get_accrued_pyth_fees().call().await?;
+    let accrued_pyth_fees = contract.get_accrued_pyth_fees().await?;
 
     // The f64 conversion is made to be able to serve metrics with the constraints of Prometheus.
     // The fee is in wei, so we divide by 1e18 to convert it to eth.
-    let accrued_pyth_fees = accrued_pyth_fees.to_f64().ok_or_else(|| {
-        anyhow!(
-            "Failed to convert accrued_pyth_fees value {:?} to f64",
-            accrued_pyth_fees
-        )
-    })? / 1e18;
+    let accrued_pyth_fees = accrued_pyth_fees as f64 / 1e18;
 
     metrics
         .accrued_pyth_fees
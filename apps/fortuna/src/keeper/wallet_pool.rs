@@ -0,0 +1,297 @@
+use {
+    super::keeper_metrics::{AccountLabel, KeeperMetrics},
+    crate::{
+        api::ChainId,
+        chain::writer::{
+            AccountNonces, EntropyWriter, RevealError, RevealReceipt, SimulatedReveal,
+        },
+    },
+    anyhow::{anyhow, Result},
+    axum::async_trait,
+    ethers::types::{Address, Bytes, H256, U256},
+    std::sync::{Arc, Mutex},
+};
+
+/// Sends the reveals of the keeper from a pool of wallets, each with its own nonce manager, so that
+/// reveals are submitted in parallel and a stuck transaction only blocks the reveals of one wallet.
+/// Each reveal is sent by the wallet with the fewest pending reveals. The other transactions are
+/// sent by the first wallet of the pool.
+pub struct WalletPool {
+    wallets: Vec<Arc<dyn EntropyWriter>>,
+    /// The number of pending reveals of each wallet.
+    pending: Mutex<Vec<usize>>,
+    chain_id: ChainId,
+    metrics: Arc<KeeperMetrics>,
+}
+
+impl WalletPool {
+    pub fn new(
+        wallets: Vec<Arc<dyn EntropyWriter>>,
+        chain_id: ChainId,
+        metrics: Arc<KeeperMetrics>,
+    ) -> Result<Self> {
+        if wallets.is_empty() {
+            return Err(anyhow!(
+                "The keeper wallet pool must have at least one wallet"
+            ));
+        }
+        for wallet in &wallets {
+            let _ = metrics.pending_transactions.get_or_create(&AccountLabel {
+                chain_id: chain_id.clone(),
+                address: wallet.address().to_string(),
+            });
+        }
+        Ok(Self {
+            pending: Mutex::new(vec![0; wallets.len()]),
+            wallets,
+            chain_id,
+            metrics,
+        })
+    }
+
+    /// Reserve the wallet with the fewest pending reveals, preferring the first wallets of the
+    /// pool, until the returned guard is dropped.
+    fn acquire(&self) -> PendingReveal<'_> {
+        let mut pending = self.pending.lock().unwrap();
+        let index = (0..pending.len())
+            .min_by_key(|&index| pending[index])
+            .unwrap_or_default();
+        pending[index] += 1;
+        self.record_pending(index, pending[index]);
+        PendingReveal { pool: self, index }
+    }
+
+    fn release(&self, index: usize) {
+        let mut pending = self.pending.lock().unwrap();
+        pending[index] = pending[index].saturating_sub(1);
+        self.record_pending(index, pending[index]);
+    }
+
+    fn record_pending(&self, index: usize, pending: usize) {
+        self.metrics
+            .pending_transactions
+            .get_or_create(&AccountLabel {
+                chain_id: self.chain_id.clone(),
+                address: self.wallets[index].address().to_string(),
+            })
+            .set(pending as i64);
+    }
+
+    fn primary(&self) -> &Arc<dyn EntropyWriter> {
+        &self.wallets[0]
+    }
+
+    fn wallet(&self, account: Address) -> Result<&Arc<dyn EntropyWriter>> {
+        self.wallets
+            .iter()
+            .find(|wallet| wallet.address() == account)
+            .ok_or_else(|| anyhow!("{:?} is not a wallet of the keeper pool", account))
+    }
+}
+
+/// A reveal pending on a wallet of the pool.
+struct PendingReveal<'a> {
+    pool: &'a WalletPool,
+    index: usize,
+}
+
+impl PendingReveal<'_> {
+    fn wallet(&self) -> &Arc<dyn EntropyWriter> {
+        &self.pool.wallets[self.index]
+    }
+}
+
+impl Drop for PendingReveal<'_> {
+    fn drop(&mut self) {
+        self.pool.release(self.index);
+    }
+}
+
+#[async_trait]
+impl EntropyWriter for WalletPool {
+    fn address(&self) -> Address {
+        self.primary().address()
+    }
+
+    fn addresses(&self) -> Vec<Address> {
+        self.wallets.iter().map(|wallet| wallet.address()).collect()
+    }
+
+    async fn reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+    ) -> Result<RevealReceipt, RevealError> {
+        let pending = self.acquire();
+        pending
+            .wallet()
+            .reveal_with_callback(
+                provider,
+                sequence_number,
+                user_random_number,
+                provider_revelation,
+            )
+            .await
+    }
+
+    async fn retry_reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+        gas_limit: u64,
+    ) -> Result<RevealReceipt, RevealError> {
+        let pending = self.acquire();
+        pending
+            .wallet()
+            .retry_reveal_with_callback(
+                provider,
+                sequence_number,
+                user_random_number,
+                provider_revelation,
+                gas_limit,
+            )
+            .await
+    }
+
+    async fn simulate_reveal_with_callback(
+        &self,
+        provider: Address,
+        sequence_number: u64,
+        user_random_number: [u8; 32],
+        provider_revelation: [u8; 32],
+    ) -> Result<SimulatedReveal, RevealError> {
+        self.primary()
+            .simulate_reveal_with_callback(
+                provider,
+                sequence_number,
+                user_random_number,
+                provider_revelation,
+            )
+            .await
+    }
+
+    async fn advance_provider_commitment(
+        &self,
+        provider: Address,
+        advanced_sequence_number: u64,
+        provider_revelation: [u8; 32],
+    ) -> Result<()> {
+        self.primary()
+            .advance_provider_commitment(provider, advanced_sequence_number, provider_revelation)
+            .await
+    }
+
+    async fn register_provider(
+        &self,
+        fee: u128,
+        commitment: [u8; 32],
+        commitment_metadata: Bytes,
+        chain_length: u64,
+        uri: Bytes,
+    ) -> Result<()> {
+        self.primary()
+            .register_provider(fee, commitment, commitment_metadata, chain_length, uri)
+            .await
+    }
+
+    async fn set_provider_fee_as_fee_manager(&self, provider: Address, fee: u128) -> Result<()> {
+        self.primary()
+            .set_provider_fee_as_fee_manager(provider, fee)
+            .await
+    }
+
+    async fn withdraw_as_fee_manager(&self, provider: Address, amount: u128) -> Result<()> {
+        self.primary()
+            .withdraw_as_fee_manager(provider, amount)
+            .await
+    }
+
+    async fn transfer(&self, destination: Address, amount: U256) -> Result<()> {
+        self.primary().transfer(destination, amount).await
+    }
+
+    async fn estimate_tx_cost(&self, gas: u128) -> Result<u128> {
+        self.primary().estimate_tx_cost(gas).await
+    }
+
+    async fn account_nonces(&self, account: Address) -> Result<AccountNonces> {
+        self.wallet(account)?.account_nonces(account).await
+    }
+
+    async fn cancel_transaction(&self, account: Address, nonce: u64) -> Result<H256> {
+        self.wallet(account)?
+            .cancel_transaction(account, nonce)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::chain::{reader::mock::MockEntropyReader, writer::mock::MockEntropyWriter},
+    };
+
+    #[tokio::test]
+    async fn test_wallet_pool_routes_to_least_loaded_wallet() {
+        let provider = Address::from_low_u64_be(1);
+        let reader = Arc::new(MockEntropyReader::with_requests(10, &[]));
+        reader.insert_with_callback(provider, 1, 2, [1u8; 32]);
+        let wallets: Vec<Arc<MockEntropyWriter>> = (10..13)
+            .map(|address| {
+                Arc::new(MockEntropyWriter::new(
+                    Address::from_low_u64_be(address),
+                    reader.clone(),
+                ))
+            })
+            .collect();
+        let metrics = Arc::new(KeeperMetrics::default());
+        let pool = WalletPool::new(
+            wallets
+                .iter()
+                .map(|wallet| wallet.clone() as Arc<dyn EntropyWriter>)
+                .collect(),
+            "ethereum".into(),
+            metrics.clone(),
+        )
+        .unwrap();
+        assert_eq!(pool.address(), Address::from_low_u64_be(10));
+        assert_eq!(pool.addresses().len(), 3);
+
+        let pending_transactions = |address: u64| {
+            metrics
+                .pending_transactions
+                .get_or_create(&AccountLabel {
+                    chain_id: "ethereum".into(),
+                    address: Address::from_low_u64_be(address).to_string(),
+                })
+                .get()
+        };
+
+        // Each pending reveal goes to another wallet, and released wallets are reused first.
+        let first = pool.acquire();
+        let second = pool.acquire();
+        assert_eq!((first.index, second.index), (0, 1));
+        assert_eq!(pending_transactions(11), 1);
+        drop(first);
+        assert_eq!(pending_transactions(10), 0);
+        let third = pool.acquire();
+        assert_eq!(third.index, 0);
+        let fourth = pool.acquire();
+        assert_eq!(fourth.index, 2);
+
+        // The reveal is sent by the wallet with no pending reveal.
+        drop(fourth);
+        pool.reveal_with_callback(provider, 1, [1u8; 32], [2u8; 32])
+            .await
+            .unwrap();
+        assert!(wallets[0].reveals.read().unwrap().is_empty());
+        assert!(wallets[1].reveals.read().unwrap().is_empty());
+        assert_eq!(wallets[2].reveals.read().unwrap().len(), 1);
+        assert_eq!(pending_transactions(12), 0);
+    }
+}
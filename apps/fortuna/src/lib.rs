@@ -2,8 +2,12 @@ pub mod api;
 pub mod chain;
 pub mod command;
 pub mod config;
+#[cfg(test)]
+mod e2e;
 pub mod eth_utils;
 pub mod history;
 pub mod keeper;
 pub mod serde;
 pub mod state;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
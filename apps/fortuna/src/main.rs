@@ -9,7 +9,6 @@ use {
 
 // Server TODO list:
 // - Tests
-// - Reduce memory requirements for storing hash chains to increase scalability
 // - Name things nicely (API resource names)
 // - README
 // - Choose data formats for binary data
@@ -37,6 +36,7 @@ async fn main() -> Result<()> {
         config::Options::SetupProvider(opts) => command::setup_provider(&opts).await,
         config::Options::RequestRandomness(opts) => command::request_randomness(&opts).await,
         config::Options::Inspect(opts) => command::inspect(&opts).await,
+        config::Options::Audit(opts) => command::audit(&opts).await,
         config::Options::WithdrawFees(opts) => command::withdraw_fees(&opts).await,
     }
 }
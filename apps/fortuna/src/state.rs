@@ -1,15 +1,38 @@
 use {
     crate::{
         api::ChainId,
+        config::HashChainStorage,
         keeper::keeper_metrics::{AccountLabel, KeeperMetrics},
+        state::hierarchical::HierarchicalHashChain,
     },
     anyhow::{ensure, Result},
     ethers::types::Address,
     sha3::{Digest, Keccak256},
-    std::sync::Arc,
+    std::sync::{Arc, RwLock},
     tokio::task::spawn_blocking,
 };
 
+pub mod hierarchical;
+
+/// A hash chain whose values are revealed from the end, i.e., with the property that
+/// hash(chain.reveal_ith(i)) == chain.reveal_ith(i - 1)
+#[allow(clippy::len_without_is_empty)]
+pub trait HashChain: Send + Sync {
+    fn reveal_ith(&self, i: usize) -> Result<[u8; 32]>;
+
+    fn len(&self) -> usize;
+}
+
+impl<C: HashChain + ?Sized> HashChain for Box<C> {
+    fn reveal_ith(&self, i: usize) -> Result<[u8; 32]> {
+        (**self).reveal_ith(i)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
 /// A hash chain of a specific length. The hash chain has the property that
 /// hash(chain.reveal_ith(i)) == chain.reveal_ith(i - 1)
 ///
@@ -51,14 +74,14 @@ impl PebbleHashChain {
         secret: &str,
         chain_id: &ChainId,
         provider_address: &Address,
-        contract_address: &Address,
+        contract_address: &[u8],
         random: &[u8; 32],
     ) -> Result<[u8; 32]> {
         let mut input: Vec<u8> = vec![];
         input.extend_from_slice(&hex::decode(secret.trim())?);
         input.extend_from_slice(chain_id.as_bytes());
         input.extend_from_slice(provider_address.as_bytes());
-        input.extend_from_slice(contract_address.as_bytes());
+        input.extend_from_slice(contract_address);
         input.extend_from_slice(random);
         let secret: [u8; 32] = Keccak256::digest(input).into();
         Ok(secret)
@@ -68,7 +91,7 @@ impl PebbleHashChain {
         secret: &str,
         chain_id: &ChainId,
         provider_address: &Address,
-        contract_address: &Address,
+        contract_address: &[u8],
         random: &[u8; 32],
         chain_length: u64,
         sample_interval: u64,
@@ -87,7 +110,7 @@ impl PebbleHashChain {
         secret: &str,
         chain_id: &ChainId,
         provider_address: &Address,
-        contract_address: &Address,
+        contract_address: &[u8],
         random: &[u8; 32],
         chain_length: u64,
         sample_interval: u64,
@@ -126,17 +149,72 @@ impl PebbleHashChain {
     }
 }
 
+impl HashChain for PebbleHashChain {
+    fn reveal_ith(&self, i: usize) -> Result<[u8; 32]> {
+        PebbleHashChain::reveal_ith(self, i)
+    }
+
+    fn len(&self) -> usize {
+        PebbleHashChain::len(self)
+    }
+}
+
+/// Create the hash chain for the given parameters (see `PebbleHashChain::from_config`), stored as
+/// configured.
+#[allow(clippy::too_many_arguments)]
+pub async fn load_hash_chain(
+    storage: &HashChainStorage,
+    secret: &str,
+    chain_id: &ChainId,
+    provider_address: &Address,
+    contract_address: &[u8],
+    random: &[u8; 32],
+    chain_length: u64,
+    sample_interval: u64,
+) -> Result<Box<dyn HashChain>> {
+    Ok(match storage {
+        HashChainStorage::Sampled => Box::new(
+            PebbleHashChain::from_config_async(
+                secret,
+                chain_id,
+                provider_address,
+                contract_address,
+                random,
+                chain_length,
+                sample_interval,
+            )
+            .await?,
+        ),
+        HashChainStorage::Hierarchical { checkpoint_dir } => Box::new(
+            HierarchicalHashChain::from_config_async(
+                secret,
+                chain_id,
+                provider_address,
+                contract_address,
+                random,
+                chain_length,
+                checkpoint_dir.as_deref().map(std::path::Path::new),
+                sample_interval,
+            )
+            .await?,
+        ),
+    })
+}
+
 /// `HashChainState` tracks the mapping between on-chain sequence numbers to hash chains.
 /// This struct is required to handle the case where the provider rotates their commitment,
 /// which requires tracking multiple hash chains here.
 pub struct HashChainState {
     // The sequence number where the hash chain starts. Must be stored in sorted order.
     offsets: Vec<usize>,
-    hash_chains: Vec<PebbleHashChain>,
+    hash_chains: Vec<Box<dyn HashChain>>,
 }
 
 impl HashChainState {
-    pub fn new(offsets: Vec<usize>, hash_chains: Vec<PebbleHashChain>) -> Result<HashChainState> {
+    pub fn new<C: HashChain + 'static>(
+        offsets: Vec<usize>,
+        hash_chains: Vec<C>,
+    ) -> Result<HashChainState> {
         if offsets.len() != hash_chains.len() {
             return Err(anyhow::anyhow!(
                 "Offsets and hash chains must have the same length."
@@ -144,16 +222,30 @@ impl HashChainState {
         }
         Ok(HashChainState {
             offsets,
-            hash_chains,
+            hash_chains: hash_chains
+                .into_iter()
+                .map(|chain| Box::new(chain) as Box<dyn HashChain>)
+                .collect(),
         })
     }
-    pub fn from_chain_at_offset(offset: usize, chain: PebbleHashChain) -> HashChainState {
+    pub fn from_chain_at_offset<C: HashChain + 'static>(offset: usize, chain: C) -> HashChainState {
         HashChainState {
             offsets: vec![offset],
-            hash_chains: vec![chain],
+            hash_chains: vec![Box::new(chain)],
         }
     }
 
+    /// Add a hash chain starting at `offset`, which must be after the offsets of the other chains.
+    pub fn push(&mut self, offset: usize, chain: Box<dyn HashChain>) -> Result<()> {
+        ensure!(
+            self.offsets.last().is_none_or(|&last| last < offset),
+            "Hash chains must be added in increasing order of offsets."
+        );
+        self.offsets.push(offset);
+        self.hash_chains.push(chain);
+        Ok(())
+    }
+
     pub fn reveal(&self, sequence_number: u64) -> Result<[u8; 32]> {
         let sequence_number: usize = sequence_number.try_into()?;
         let chain_index = self
@@ -169,6 +261,9 @@ impl HashChainState {
 
 pub struct MonitoredHashChainState {
     hash_chain_state: Arc<HashChainState>,
+    /// The hash chains registered while the server runs, which start after those of
+    /// `hash_chain_state`.
+    rotated_hash_chains: RwLock<HashChainState>,
     metrics: Arc<KeeperMetrics>,
     account_label: AccountLabel,
 }
@@ -181,6 +276,10 @@ impl MonitoredHashChainState {
     ) -> Self {
         Self {
             hash_chain_state,
+            rotated_hash_chains: RwLock::new(HashChainState {
+                offsets: vec![],
+                hash_chains: vec![],
+            }),
             metrics,
             account_label: AccountLabel {
                 chain_id,
@@ -189,8 +288,30 @@ impl MonitoredHashChainState {
         }
     }
 
+    /// Serve the sequence numbers from `offset` on with `chain`, once the provider registered it.
+    pub fn rotate(&self, offset: u64, chain: Box<dyn HashChain>) -> Result<()> {
+        let offset: usize = offset.try_into()?;
+        ensure!(
+            self.hash_chain_state
+                .offsets
+                .last()
+                .is_none_or(|&last| last < offset),
+            "The hash chain must start after the current hash chains."
+        );
+        self.rotated_hash_chains
+            .write()
+            .unwrap()
+            .push(offset, chain)
+    }
+
     pub fn reveal(&self, sequence_number: u64) -> Result<[u8; 32]> {
-        let res = self.hash_chain_state.reveal(sequence_number);
+        let rotated_hash_chains = self.rotated_hash_chains.read().unwrap();
+        let res = match rotated_hash_chains.offsets.first() {
+            Some(&offset) if offset as u64 <= sequence_number => {
+                rotated_hash_chains.reveal(sequence_number)
+            }
+            _ => self.hash_chain_state.reveal(sequence_number),
+        };
         if res.is_ok() {
             let metric = self
                 .metrics
@@ -311,7 +432,7 @@ mod test {
 
         let hash_chain_state = HashChainState {
             offsets: vec![5, 20],
-            hash_chains: vec![chain1, chain2],
+            hash_chains: vec![Box::new(chain1), Box::new(chain2)],
         };
 
         let result1 = hash_chain_state.reveal(8)?;
@@ -337,7 +458,7 @@ mod test {
 
         let hash_chain_state = HashChainState {
             offsets: vec![5, 10],
-            hash_chains: vec![chain1, chain2],
+            hash_chains: vec![Box::new(chain1), Box::new(chain2)],
         };
 
         let result1 = hash_chain_state.reveal(8)?;
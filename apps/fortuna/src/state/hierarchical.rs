@@ -0,0 +1,614 @@
+use {
+    super::{HashChain, PebbleHashChain},
+    crate::api::ChainId,
+    anyhow::{ensure, Result},
+    ethers::types::Address,
+    sha3::{Digest, Keccak256},
+    std::{
+        collections::VecDeque,
+        fs::{self, File, OpenOptions},
+        io::{BufWriter, Write},
+        os::unix::fs::{FileExt, OpenOptionsExt},
+        path::Path,
+        sync::Mutex,
+    },
+    tokio::task::spawn_blocking,
+};
+
+/// The number of recently revealed hashes that are kept, so that requests revealed slightly out
+/// of order don't need to be recomputed.
+const RECENT_REVEALS: usize = 256;
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"FTNCHKP2";
+/// The magic, followed by the length of the chain and the checkpoint interval (u64 LE), and the
+/// keccak256 checksum of the checkpoints.
+const CHECKPOINT_HEADER_LEN: u64 = 56;
+/// The number of checkpoints read at once when verifying the checksum.
+const CHECKPOINT_READ_BATCH: usize = 1024;
+
+fn hash(value: [u8; 32]) -> [u8; 32] {
+    Keccak256::digest(value).into()
+}
+
+fn hash_n(mut value: [u8; 32], n: usize) -> [u8; 32] {
+    for _ in 0..n {
+        value = hash(value);
+    }
+    value
+}
+
+/// The hashes of a chain at every `interval`-th position, stored in a file.
+///
+/// The file contains unrevealed values of the chain, so it must be protected like the provider
+/// secret.
+struct Checkpoints {
+    file: File,
+    interval: usize,
+}
+
+impl Checkpoints {
+    /// Open the checkpoint file at `path`, (re)generating it if it doesn't exist or doesn't
+    /// match the chain starting with `first`.
+    fn open_or_create(
+        path: &Path,
+        first: [u8; 32],
+        length: usize,
+        interval: usize,
+    ) -> Result<Self> {
+        if path.exists() {
+            match Self::open(path, first, length, interval) {
+                Ok(checkpoints) => return Ok(checkpoints),
+                Err(e) => tracing::warn!(
+                    "Regenerating hash chain checkpoints at {}: {:?}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        Self::create(path, first, length, interval)?;
+        Self::open(path, first, length, interval)
+    }
+
+    fn open(path: &Path, first: [u8; 32], length: usize, interval: usize) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut header = [0u8; CHECKPOINT_HEADER_LEN as usize];
+        file.read_exact_at(&mut header, 0)?;
+        ensure!(
+            &header[..8] == CHECKPOINT_MAGIC,
+            "Not a hash chain checkpoint file"
+        );
+        ensure!(
+            header[8..16] == (length as u64).to_le_bytes()
+                && header[16..24] == (interval as u64).to_le_bytes(),
+            "Checkpoints were generated with a different chain length or interval"
+        );
+        let count = length.div_ceil(interval) as u64;
+        ensure!(
+            file.metadata()?.len() == CHECKPOINT_HEADER_LEN + 32 * count,
+            "Checkpoint file is truncated"
+        );
+
+        let checkpoints = Self { file, interval };
+        ensure!(
+            checkpoints.read(0)? == first,
+            "Checkpoints were generated for a different chain"
+        );
+        ensure!(
+            header[24..] == checkpoints.checksum(count)?,
+            "Checkpoint file is corrupted"
+        );
+        Ok(checkpoints)
+    }
+
+    fn create(path: &Path, first: [u8; 32], length: usize, interval: usize) -> Result<()> {
+        tracing::info!("Generating hash chain checkpoints at {}", path.display());
+        let tmp_path = path.with_extension("tmp");
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_path)?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&(length as u64).to_le_bytes())?;
+        writer.write_all(&(interval as u64).to_le_bytes())?;
+        // The checksum is written once all the checkpoints are known.
+        writer.write_all(&[0u8; 32])?;
+        let mut checksum = Keccak256::new();
+        let mut value = first;
+        for position in 0..length {
+            if position % interval == 0 {
+                writer.write_all(&value)?;
+                checksum.update(value);
+            }
+            value = hash(value);
+        }
+        let file = writer.into_inner()?;
+        file.write_all_at(&checksum.finalize(), 24)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The checksum of the first `count` checkpoints of the file.
+    fn checksum(&self, count: u64) -> Result<[u8; 32]> {
+        let mut checksum = Keccak256::new();
+        let mut buffer = vec![0u8; 32 * CHECKPOINT_READ_BATCH];
+        let mut index = 0;
+        while index < count {
+            let batch = (count - index).min(CHECKPOINT_READ_BATCH as u64);
+            let bytes = &mut buffer[..32 * batch as usize];
+            self.file
+                .read_exact_at(bytes, CHECKPOINT_HEADER_LEN + 32 * index)?;
+            checksum.update(&*bytes);
+            index += batch;
+        }
+        Ok(checksum.finalize().into())
+    }
+
+    /// The hash at position `index * interval`.
+    fn read(&self, index: usize) -> Result<[u8; 32]> {
+        let mut value = [0u8; 32];
+        self.file
+            .read_exact_at(&mut value, CHECKPOINT_HEADER_LEN + 32 * index as u64)?;
+        Ok(value)
+    }
+
+    /// The closest checkpoint at or before `position`.
+    fn floor(&self, position: usize) -> Result<(usize, [u8; 32])> {
+        let index = position / self.interval;
+        Ok((index * self.interval, self.read(index)?))
+    }
+}
+
+/// The name of the checkpoint file of a chain, from a domain-separated hash of its public
+/// parameters. `random` is the seed of the commitment, so the successive chains of a provider
+/// don't share a file.
+fn checkpoint_file_name(
+    chain_id: &ChainId,
+    provider_address: &Address,
+    contract_address: &[u8],
+    random: &[u8; 32],
+    chain_length: u64,
+) -> String {
+    let mut digest = Keccak256::new();
+    digest.update(b"fortuna-checkpoints");
+    digest.update(chain_id.as_bytes());
+    digest.update(provider_address.as_bytes());
+    digest.update(contract_address);
+    digest.update(random);
+    digest.update(chain_length.to_le_bytes());
+    format!("{}.chain", hex::encode(digest.finalize()))
+}
+
+/// The positions `[start, end)` of the chain that haven't been revealed yet, along with the
+/// hash at `start`.
+struct Segment {
+    start: usize,
+    value: [u8; 32],
+    end: usize,
+}
+
+struct Traversal {
+    /// Contiguous segments sorted by position. The last one contains the next position to be
+    /// revealed, and the positions after it have already been revealed or skipped.
+    segments: Vec<Segment>,
+    recent: VecDeque<(usize, [u8; 32])>,
+}
+
+/// A hash chain that keeps O(log n) hashes in memory.
+///
+/// Positions count from the secret: the hash at position `p` is the secret hashed `p + 1` times,
+/// and `reveal_ith(i)` is the hash at position `length - 1 - i`. Values are revealed from the end
+/// of the chain, so the chain is traversed backwards: the unrevealed positions are kept as a stack
+/// of segments that are recursively halved, keeping the hash at the start of each of them. Revealing
+/// the positions in order takes amortized O(log n) hashes, and positions can be skipped at no cost.
+///
+/// Positions that were already passed are recomputed from the closest known hash before them. With
+/// checkpoints, the hash at every `interval`-th position is also stored on disk, which bounds this
+/// to `interval` hashes and lets the chain start without hashing it entirely.
+pub struct HierarchicalHashChain {
+    length: usize,
+    /// The hash at position 0, from which any other can be computed.
+    first: [u8; 32],
+    checkpoints: Option<Checkpoints>,
+    traversal: Mutex<Traversal>,
+}
+
+impl HierarchicalHashChain {
+    pub fn new(secret: [u8; 32], length: usize) -> Self {
+        assert!(length > 0, "Chain length must be positive");
+        let first = hash(secret);
+        Self {
+            length,
+            first,
+            checkpoints: None,
+            traversal: Mutex::new(Traversal {
+                segments: vec![Segment {
+                    start: 0,
+                    value: first,
+                    end: length,
+                }],
+                recent: VecDeque::with_capacity(RECENT_REVEALS),
+            }),
+        }
+    }
+
+    /// Create the chain backed by the checkpoint file at `path`, generating the file if needed.
+    pub fn with_checkpoints(
+        secret: [u8; 32],
+        length: usize,
+        path: &Path,
+        interval: usize,
+    ) -> Result<Self> {
+        assert!(interval > 0, "Checkpoint interval must be positive");
+        let mut chain = Self::new(secret, length);
+        chain.checkpoints = Some(Checkpoints::open_or_create(
+            path,
+            chain.first,
+            length,
+            interval,
+        )?);
+        Ok(chain)
+    }
+
+    /// Create the chain for the given parameters (see `PebbleHashChain::from_config`). If
+    /// `checkpoint_dir` is provided, the checkpoints are stored in a file of that directory named
+    /// after the public parameters of the chain. The name must not be derived from the secret,
+    /// as the hash of the secret is the last value of the chain to be revealed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_config(
+        secret: &str,
+        chain_id: &ChainId,
+        provider_address: &Address,
+        contract_address: &[u8],
+        random: &[u8; 32],
+        chain_length: u64,
+        checkpoint_dir: Option<&Path>,
+        checkpoint_interval: u64,
+    ) -> Result<Self> {
+        let secret = PebbleHashChain::generate_secret(
+            secret,
+            chain_id,
+            provider_address,
+            contract_address,
+            random,
+        )?;
+        let name = checkpoint_file_name(
+            chain_id,
+            provider_address,
+            contract_address,
+            random,
+            chain_length,
+        );
+        let chain_length: usize = chain_length.try_into()?;
+        let chain = match checkpoint_dir {
+            Some(checkpoint_dir) => {
+                fs::create_dir_all(checkpoint_dir)?;
+                let path = checkpoint_dir.join(name);
+                Self::with_checkpoints(
+                    secret,
+                    chain_length,
+                    &path,
+                    checkpoint_interval.try_into()?,
+                )?
+            }
+            None => Self::new(secret, chain_length),
+        };
+        // Descend to the end of the chain, so that the first reveals don't have to.
+        chain.reveal_ith(0)?;
+        Ok(chain)
+    }
+
+    /// Asynchronous version of `from_config` that runs the computation in a blocking thread.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn from_config_async(
+        secret: &str,
+        chain_id: &ChainId,
+        provider_address: &Address,
+        contract_address: &[u8],
+        random: &[u8; 32],
+        chain_length: u64,
+        checkpoint_dir: Option<&Path>,
+        checkpoint_interval: u64,
+    ) -> Result<Self> {
+        let secret = secret.to_string();
+        let chain_id = chain_id.clone();
+        let provider_address = *provider_address;
+        let contract_address = contract_address.to_vec();
+        let random = *random;
+        let checkpoint_dir = checkpoint_dir.map(Path::to_path_buf);
+        spawn_blocking(move || {
+            Self::from_config(
+                &secret,
+                &chain_id,
+                &provider_address,
+                &contract_address,
+                &random,
+                chain_length,
+                checkpoint_dir.as_deref(),
+                checkpoint_interval,
+            )
+        })
+        .await
+        .expect("Failed to make hash chain")
+    }
+
+    /// Split `segment` in two, returning the upper half. The split is done on a checkpoint if
+    /// possible, so that its hash is read rather than computed.
+    fn split(&self, segment: &Segment) -> Result<Segment> {
+        let len = segment.end - segment.start;
+        let (mid, value) = match &self.checkpoints {
+            Some(checkpoints) if len > checkpoints.interval => {
+                let interval = checkpoints.interval;
+                let mut mid = (segment.start + len / 2) / interval * interval;
+                if mid <= segment.start {
+                    mid += interval;
+                }
+                (mid, checkpoints.read(mid / interval)?)
+            }
+            _ => {
+                let mid = segment.start + len / 2;
+                (mid, hash_n(segment.value, mid - segment.start))
+            }
+        };
+        Ok(Segment {
+            start: mid,
+            value,
+            end: segment.end,
+        })
+    }
+
+    fn reveal_position(&self, position: usize) -> Result<[u8; 32]> {
+        let mut traversal = self.traversal.lock().expect("Poisoned hash chain lock");
+        if let Some((_, value)) = traversal.recent.iter().find(|(p, _)| *p == position) {
+            return Ok(*value);
+        }
+
+        while traversal
+            .segments
+            .last()
+            .is_some_and(|segment| segment.start > position)
+        {
+            traversal.segments.pop();
+        }
+        if let Some(segment) = traversal.segments.last_mut() {
+            if position < segment.end {
+                // Skip the positions after the requested one, then halve the segment until it
+                // only contains the requested position.
+                segment.end = position + 1;
+                while let Some(segment) = traversal.segments.last_mut() {
+                    if segment.end - segment.start == 1 {
+                        break;
+                    }
+                    let upper = self.split(segment)?;
+                    segment.end = upper.start;
+                    traversal.segments.push(upper);
+                }
+                let value = traversal
+                    .segments
+                    .pop()
+                    .expect("the requested position is on the stack")
+                    .value;
+                if traversal.recent.len() == RECENT_REVEALS {
+                    traversal.recent.pop_front();
+                }
+                traversal.recent.push_back((position, value));
+                return Ok(value);
+            }
+        }
+
+        // The position was already passed: recompute it from the closest known hash before it.
+        let mut base = (0, self.first);
+        let known = traversal
+            .segments
+            .last()
+            .map(|segment| (segment.start, segment.value))
+            .into_iter()
+            .chain(traversal.recent.iter().copied());
+        for (p, value) in known {
+            if p <= position && p > base.0 {
+                base = (p, value);
+            }
+        }
+        drop(traversal);
+        if let Some(checkpoints) = &self.checkpoints {
+            let checkpoint = checkpoints.floor(position)?;
+            if checkpoint.0 > base.0 {
+                base = checkpoint;
+            }
+        }
+        Ok(hash_n(base.1, position - base.0))
+    }
+
+    /// The number of hashes currently held in memory.
+    pub fn stored_hashes(&self) -> usize {
+        let traversal = self.traversal.lock().expect("Poisoned hash chain lock");
+        1 + traversal.segments.len() + traversal.recent.len()
+    }
+}
+
+impl HashChain for HierarchicalHashChain {
+    fn reveal_ith(&self, i: usize) -> Result<[u8; 32]> {
+        ensure!(i < self.length, "index not in range");
+        self.reveal_position(self.length - 1 - i)
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        rand::{rngs::StdRng, Rng, SeedableRng},
+    };
+
+    const LENGTH: usize = 1000;
+
+    fn reference() -> PebbleHashChain {
+        PebbleHashChain::new([3u8; 32], LENGTH, 1)
+    }
+
+    #[test]
+    fn test_sequential_reveals() {
+        let expected = reference();
+        let chain = HierarchicalHashChain::new([3u8; 32], LENGTH);
+        for i in 0..LENGTH {
+            assert_eq!(
+                chain.reveal_ith(i).unwrap(),
+                expected.reveal_ith(i).unwrap()
+            );
+        }
+        assert!(chain.reveal_ith(LENGTH).is_err());
+    }
+
+    #[test]
+    fn test_memory_is_logarithmic() {
+        let length: usize = 1 << 16;
+        let chain = HierarchicalHashChain::new([3u8; 32], length);
+        for i in 0..(1 << 12) {
+            chain.reveal_ith(i).unwrap();
+            // The stack of segments, the recent reveals and the first hash.
+            assert!(chain.stored_hashes() <= length.ilog2() as usize + 1 + RECENT_REVEALS + 1);
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_reveals() {
+        let expected = reference();
+        let chain = HierarchicalHashChain::new([3u8; 32], LENGTH);
+        let mut rng = StdRng::seed_from_u64(0);
+        // Mostly increasing, like concurrent requests
+        for i in 0..LENGTH {
+            let i = (i + rng.gen_range(0..10)).min(LENGTH - 1);
+            assert_eq!(
+                chain.reveal_ith(i).unwrap(),
+                expected.reveal_ith(i).unwrap()
+            );
+        }
+        // Fully random
+        for _ in 0..100 {
+            let i = rng.gen_range(0..LENGTH);
+            assert_eq!(
+                chain.reveal_ith(i).unwrap(),
+                expected.reveal_ith(i).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let expected = reference();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain");
+        for interval in [1, 7, 100, LENGTH, 2 * LENGTH] {
+            let chain = HierarchicalHashChain::with_checkpoints([3u8; 32], LENGTH, &path, interval)
+                .unwrap();
+            for i in (0..LENGTH).step_by(3).chain((0..LENGTH).rev().step_by(11)) {
+                assert_eq!(
+                    chain.reveal_ith(i).unwrap(),
+                    expected.reveal_ith(i).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_checkpoints_are_reused_or_regenerated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain");
+        HierarchicalHashChain::with_checkpoints([3u8; 32], LENGTH, &path, 10).unwrap();
+        let created = fs::metadata(&path).unwrap().modified().unwrap();
+
+        // Reused as is
+        HierarchicalHashChain::with_checkpoints([3u8; 32], LENGTH, &path, 10).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), created);
+
+        // Regenerated for a different chain
+        let chain = HierarchicalHashChain::with_checkpoints([4u8; 32], LENGTH, &path, 10).unwrap();
+        assert_eq!(
+            chain.reveal_ith(5).unwrap(),
+            PebbleHashChain::new([4u8; 32], LENGTH, 1)
+                .reveal_ith(5)
+                .unwrap()
+        );
+
+        // Regenerated if truncated
+        let mut content = fs::read(&path).unwrap();
+        content.truncate(content.len() - 1);
+        fs::write(&path, content).unwrap();
+        let chain = HierarchicalHashChain::with_checkpoints([4u8; 32], LENGTH, &path, 10).unwrap();
+        assert_eq!(
+            chain.reveal_ith(0).unwrap(),
+            PebbleHashChain::new([4u8; 32], LENGTH, 1)
+                .reveal_ith(0)
+                .unwrap()
+        );
+
+        // Regenerated if any checkpoint is corrupted
+        let mut content = fs::read(&path).unwrap();
+        let checkpoint = CHECKPOINT_HEADER_LEN as usize + 32 * 50;
+        content[checkpoint] ^= 1;
+        fs::write(&path, content).unwrap();
+        let chain = HierarchicalHashChain::with_checkpoints([4u8; 32], LENGTH, &path, 10).unwrap();
+        // The position 500 is at the corrupted checkpoint.
+        assert_eq!(
+            chain.reveal_ith(LENGTH - 1 - 500).unwrap(),
+            PebbleHashChain::new([4u8; 32], LENGTH, 1)
+                .reveal_ith(LENGTH - 1 - 500)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_config_matches_pebble_hash_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = (
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "ethereum".to_string(),
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            [5u8; 32],
+        );
+        let expected = PebbleHashChain::from_config(
+            args.0,
+            &args.1,
+            &args.2,
+            args.3.as_bytes(),
+            &args.4,
+            100,
+            1,
+        )
+        .unwrap();
+        for checkpoint_dir in [None, Some(dir.path())] {
+            let chain = HierarchicalHashChain::from_config(
+                args.0,
+                &args.1,
+                &args.2,
+                args.3.as_bytes(),
+                &args.4,
+                100,
+                checkpoint_dir,
+                10,
+            )
+            .unwrap();
+            for i in 0..100 {
+                assert_eq!(
+                    chain.reveal_ith(i).unwrap(),
+                    expected.reveal_ith(i).unwrap()
+                );
+            }
+        }
+        let files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(files.len(), 1);
+        // The file name doesn't reveal the hash of the secret, which is on the chain.
+        assert!(!files[0].contains(&hex::encode(expected.reveal_ith(99).unwrap())));
+    }
+}
@@ -0,0 +1,74 @@
+//! Helpers to deploy the contracts of `target_chains/ethereum/contracts` to a local anvil node,
+//! shared by the end-to-end tests of fortuna and argus. The contracts must be built with
+//! `forge build` first.
+
+use {
+    anyhow::{anyhow, Result},
+    ethers::{
+        abi::{Abi, Tokenize},
+        contract::{Contract, ContractFactory},
+        middleware::SignerMiddleware,
+        providers::{Http, Middleware, Provider},
+        signers::{LocalWallet, Signer},
+        types::Bytes,
+        utils::AnvilInstance,
+    },
+    std::{path::Path, sync::Arc},
+};
+
+pub type AnvilClient = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// The private keys of the funded anvil accounts, in hex.
+pub fn anvil_keys(anvil: &AnvilInstance) -> Vec<String> {
+    anvil
+        .keys()
+        .iter()
+        .map(|key| hex::encode(key.to_bytes()))
+        .collect()
+}
+
+/// A client signing with the first funded anvil account, which deploys the contracts.
+pub fn deployer(anvil: &AnvilInstance) -> Result<Arc<AnvilClient>> {
+    let wallet = anvil_keys(anvil)[0]
+        .parse::<LocalWallet>()?
+        .with_chain_id(anvil.chain_id());
+    Ok(Arc::new(SignerMiddleware::new(
+        Provider::<Http>::try_from(anvil.endpoint())?,
+        wallet,
+    )))
+}
+
+/// The ABI and bytecode of a contract compiled by forge.
+pub fn load_artifact(name: &str) -> Result<(Abi, Bytes)> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../target_chains/ethereum/contracts/out")
+        .join(format!("{name}.sol"))
+        .join(format!("{name}.json"));
+    let artifact: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).map_err(|e| {
+            anyhow!(
+                "Failed to read {:?}. Build the contracts with `forge build`: {}",
+                path,
+                e
+            )
+        })?)?;
+    let abi = serde_json::from_value(artifact["abi"].clone())?;
+    let bytecode = artifact["bytecode"]["object"]
+        .as_str()
+        .ok_or(anyhow!("No bytecode in {:?}", path))?
+        .parse()?;
+    Ok((abi, bytecode))
+}
+
+/// Deploy the contract `name` with the constructor `args`.
+pub async fn deploy<M: Middleware + 'static>(
+    client: Arc<M>,
+    name: &str,
+    args: impl Tokenize,
+) -> Result<Contract<M>> {
+    let (abi, bytecode) = load_artifact(name)?;
+    Ok(ContractFactory::new(abi, bytecode, client)
+        .deploy(args)?
+        .send()
+        .await?)
+}
@@ -734,14 +734,13 @@ async fn scrape_pusher_metrics(endpoint: &str) -> Result<PusherMetrics> {
 
         if let Some((name, value)) = parse_metric_line(line) {
             match name.as_str() {
-                "lazer_pusher_bulk_pushes_total" => metrics.bulk_pushes_total += value,
-                "lazer_pusher_bulk_push_results_total" if line.contains("accepted") => {
+                "lazer_pusher_push_results_total" if line.contains("accepted") => {
                     metrics.push_accepted += value
                 }
-                "lazer_pusher_bulk_push_results_total" if line.contains("deduplicated") => {
+                "lazer_pusher_push_results_total" if line.contains("deduplicated") => {
                     metrics.push_deduplicated += value
                 }
-                "lazer_pusher_bulk_push_results_total" if line.contains("error") => {
+                "lazer_pusher_push_results_total" if line.contains("error") => {
                     metrics.push_error += value
                 }
                 "lazer_pusher_updates_received_total" => metrics.lazer_updates += value,
@@ -761,6 +760,11 @@ async fn scrape_pusher_metrics(endpoint: &str) -> Result<PusherMetrics> {
             }
         }
     }
+    // Every push ends with a response or a timeout.
+    metrics.bulk_pushes_total = metrics.push_accepted
+        + metrics.push_deduplicated
+        + metrics.push_error
+        + metrics.push_timeouts_total;
 
     Ok(metrics)
 }
@@ -1,9 +1,11 @@
 //! WebSocket client for Bulk Trade validators.
 
 use crate::config::BulkConfig;
-use crate::metrics;
+use crate::metrics::{self, base_metrics};
+use crate::pusher::BulkTarget;
 use anyhow::Result;
 use bulk_keychain::SignedTransaction;
+use pusher_base::{handle_ack, PushResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -62,13 +64,6 @@ pub struct BulkResponseData {
     pub error: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub enum PushResult {
-    Accepted,
-    Deduplicated,
-    Error(String),
-}
-
 struct PendingRequest {
     sent_at: Instant,
 }
@@ -218,45 +213,40 @@ async fn run_delivery_task(
 }
 
 fn handle_response(msg: &IncomingMessage, pending: &mut HashMap<(String, u64), PendingRequest>) {
-    let response: BulkResponse = match serde_json::from_str(&msg.text) {
-        Ok(r) => r,
-        Err(e) => {
-            trace!(text = %msg.text, ?e, "failed to parse response");
-            return;
-        }
+    let Some(ack) = handle_ack::<BulkTarget>(&msg.text, base_metrics()) else {
+        trace!(text = %msg.text, "failed to parse response");
+        return;
     };
 
     let endpoint_str = msg.endpoint.to_string();
-    let key = (endpoint_str.clone(), response.id);
+    let key = (endpoint_str.clone(), ack.request_id);
 
     if let Some(req) = pending.remove(&key) {
-        let latency = req.sent_at.elapsed();
-        let result = parse_response(&response);
+        metrics::record_push_latency(req.sent_at.elapsed().as_secs_f64());
 
-        match &result {
+        match &ack.result {
             PushResult::Accepted => {
-                debug!(endpoint = %endpoint_str, request_id = response.id, "push accepted");
-                metrics::record_push_success(latency.as_secs_f64());
+                debug!(endpoint = %endpoint_str, request_id = ack.request_id, "push accepted");
             }
             PushResult::Deduplicated => {
-                debug!(endpoint = %endpoint_str, request_id = response.id, "push deduplicated");
-                metrics::record_push_dedup();
+                debug!(endpoint = %endpoint_str, request_id = ack.request_id, "push deduplicated");
             }
             PushResult::Error(e) => {
-                warn!(endpoint = %endpoint_str, request_id = response.id, error = %e, "push error");
-                metrics::record_push_error();
+                warn!(endpoint = %endpoint_str, request_id = ack.request_id, error = %e, "push error");
             }
         }
     } else {
         trace!(
             endpoint = %endpoint_str,
-            request_id = response.id,
+            request_id = ack.request_id,
             "response for unknown request"
         );
     }
 }
 
-fn parse_response(response: &BulkResponse) -> PushResult {
+/// The result of a transaction acked by a validator. Validators reject the
+/// transactions already pushed by another instance because of their nonce.
+pub fn parse_response(response: &BulkResponse) -> PushResult {
     if response.data.ok {
         return PushResult::Accepted;
     }
@@ -290,6 +280,7 @@ fn cleanup_expired(pending: &mut HashMap<(String, u64), PendingRequest>, timeout
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pusher_base::{PushAck, PushTarget};
 
     #[test]
     fn test_bulk_request_serialization() {
@@ -341,6 +332,27 @@ mod tests {
         assert_eq!(response.data.error.as_deref(), Some("duplicate nonce"));
     }
 
+    #[test]
+    fn test_interpret_ack() {
+        assert_eq!(
+            BulkTarget::interpret_ack(r#"{"type":"post","id":7,"data":{"type":"ack","ok":true}}"#),
+            Some(PushAck {
+                request_id: 7,
+                result: PushResult::Accepted,
+            })
+        );
+        assert_eq!(
+            BulkTarget::interpret_ack(
+                r#"{"type":"post","id":8,"data":{"type":"ack","ok":false,"error":"duplicate nonce"}}"#
+            ),
+            Some(PushAck {
+                request_id: 8,
+                result: PushResult::Deduplicated,
+            })
+        );
+        assert_eq!(BulkTarget::interpret_ack(r#"{"type":"pong"}"#), None);
+    }
+
     #[test]
     fn test_parse_response_accepted() {
         let response = BulkResponse {
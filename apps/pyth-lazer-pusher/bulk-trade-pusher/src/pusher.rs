@@ -3,17 +3,15 @@
 //! HA: Multiple uncoordinated pushers share an oracle account but have
 //! different signing keys. Validators deduplicate by (account, nonce).
 
-use crate::bulk_client::BulkClient;
+use crate::bulk_client::{self, BulkClient, BulkResponse};
 use crate::config::{load_signing_key, Config};
 use crate::metrics::{self, base_metrics, ws_metrics};
 use crate::signing::BulkSigner;
 use anyhow::{Context as _, Result};
-use bulk_keychain::PythOraclePrice;
-use pusher_base::AppRuntime;
-use pusher_base::{CachedPrice, LazerReceiver};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::time::interval;
-use tracing::{debug, error, info, warn};
+use bulk_keychain::{PythOraclePrice, SignedTransaction};
+use pusher_base::{run_push_loop, AppRuntime, CachedPrice, LazerReceiver, PushAck, PushTarget};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
 
 pub async fn run(config: Config, runtime: AppRuntime) -> Result<()> {
     info!("initializing bulk-trade-pusher");
@@ -44,88 +42,41 @@ pub async fn run(config: Config, runtime: AppRuntime) -> Result<()> {
     base_metrics().set_feeds_configured(receiver.feed_registry().len());
 
     run_push_loop(
-        config.base.feeds.update_interval,
+        BulkTarget {
+            signer,
+            client: bulk_client,
+        },
         receiver,
-        signer,
-        bulk_client,
+        &config.base.feeds,
+        base_metrics().clone(),
         runtime,
     )
     .await
 }
 
-async fn run_push_loop(
-    update_interval: Duration,
-    receiver: LazerReceiver,
-    mut signer: BulkSigner,
-    bulk_client: BulkClient,
-    runtime: AppRuntime,
-) -> Result<()> {
-    info!(
-        update_interval_ms = update_interval.as_millis(),
-        "starting push loop"
-    );
-
-    let mut interval = interval(update_interval);
-
-    loop {
-        tokio::select! {
-            _ = runtime.cancelled() => {
-                info!("push loop shutdown requested");
-                return Ok(());
-            }
-            _ = interval.tick() => {}
-        }
+/// Pushes signed oracle transactions to the Bulk validators.
+pub struct BulkTarget {
+    signer: BulkSigner,
+    client: BulkClient,
+}
 
-        if !bulk_client.is_running() {
-            error!("Bulk client stopped unexpectedly");
-            anyhow::bail!("Bulk client stopped");
-        }
-        if !receiver.is_running() {
-            error!("Lazer receiver stopped unexpectedly");
-            anyhow::bail!("Lazer receiver stopped");
-        }
+impl PushTarget for BulkTarget {
+    type Entry = PythOraclePrice;
+    type Message = SignedTransaction;
 
-        let prices: Vec<CachedPrice> = {
-            let cache = receiver.price_cache().read().await;
-            receiver
-                .feed_registry()
-                .feed_ids()
-                .filter_map(|id| cache.get(id).cloned())
-                .collect()
-        };
-
-        metrics::set_push_queue_depth(bulk_client.queue_depth());
-
-        if prices.is_empty() {
-            debug!("no prices available, skipping push");
-            metrics::record_prices_skipped();
-            continue;
-        }
+    fn name(&self) -> &str {
+        "bulk-trade"
+    }
 
-        metrics::record_price_ages(&prices);
-
-        let oracles: Vec<PythOraclePrice> = prices
-            .iter()
-            .filter_map(|cached| {
-                let price = cached.data.price.as_ref()?;
-                let exponent = cached.data.exponent?;
-                Some(PythOraclePrice {
-                    timestamp: cached.timestamp_ms,
-                    feed_index: u64::from(cached.feed_id.0),
-                    price: u64::try_from(price.mantissa_i64()).unwrap_or(0),
-                    exponent,
-                })
-            })
-            .collect();
-
-        if oracles.is_empty() {
-            debug!("no valid prices to push");
-            metrics::record_prices_skipped();
-            continue;
-        }
+    fn is_running(&self) -> bool {
+        self.client.is_running()
+    }
 
-        metrics::set_batch_size(oracles.len());
+    fn encode_batch(&self, prices: &[CachedPrice]) -> Vec<PythOraclePrice> {
+        prices.iter().filter_map(to_oracle_price).collect()
+    }
 
+    fn sign(&mut self, oracles: Vec<PythOraclePrice>) -> Result<SignedTransaction> {
         #[allow(
             clippy::cast_possible_truncation,
             reason = "nanoseconds timestamp fits in u64 for many years"
@@ -135,17 +86,35 @@ async fn run_push_loop(
             .map(|d| d.as_nanos() as u64)
             .unwrap_or(0);
 
-        let tx = match signer.sign_transaction(oracles, nonce) {
-            Ok(tx) => tx,
-            Err(e) => {
-                error!(?e, "failed to sign transaction");
-                continue;
-            }
-        };
+        self.signer.sign_transaction(oracles, nonce)
+    }
 
-        if !bulk_client.push(tx) {
-            warn!("failed to queue transaction (queue full)");
+    async fn deliver(&mut self, tx: SignedTransaction) -> Result<()> {
+        let queued = self.client.push(tx);
+        metrics::set_push_queue_depth(self.client.queue_depth());
+        if !queued {
             metrics::record_push_queue_drop();
+            anyhow::bail!("failed to queue transaction (queue full)");
         }
+        Ok(())
     }
+
+    fn interpret_ack(response: &str) -> Option<PushAck> {
+        let response: BulkResponse = serde_json::from_str(response).ok()?;
+        Some(PushAck {
+            request_id: response.id,
+            result: bulk_client::parse_response(&response),
+        })
+    }
+}
+
+fn to_oracle_price(cached: &CachedPrice) -> Option<PythOraclePrice> {
+    let price = cached.data.price.as_ref()?;
+    let exponent = cached.data.exponent?;
+    Some(PythOraclePrice {
+        timestamp: cached.timestamp_ms,
+        feed_index: u64::from(cached.feed_id.0),
+        price: u64::try_from(price.mantissa_i64()).unwrap_or(0),
+        exponent,
+    })
 }
@@ -0,0 +1,54 @@
+use anyhow::Context as _;
+use config::{Environment, File};
+use pusher_base::BaseConfig;
+use serde::Deserialize;
+use std::path::Path;
+use url::Url;
+
+/// Main configuration for the json-pusher service.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Base pusher configuration (lazer, feeds, prometheus)
+    #[serde(flatten)]
+    pub base: BaseConfig,
+
+    /// JSON receiver configuration (destination for price pushes)
+    pub json: JsonConfig,
+}
+
+/// Configuration for connecting to the JSON receivers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsonConfig {
+    /// Receiver WebSocket endpoints. Every batch is sent to all connected endpoints.
+    pub endpoints: Vec<Url>,
+}
+
+/// Load configuration from a TOML file and validate it.
+pub fn load_config<P: AsRef<Path>>(path: P) -> anyhow::Result<Config> {
+    let path = path.as_ref();
+    let config: Config = config::Config::builder()
+        .add_source(File::with_name(
+            path.to_str().context("invalid config path")?,
+        ))
+        .add_source(Environment::with_prefix("JSON_PUSHER").separator("__"))
+        .build()?
+        .try_deserialize()?;
+
+    validate_config(&config)?;
+    Ok(config)
+}
+
+/// Validate configuration values.
+fn validate_config(config: &Config) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !config.json.endpoints.is_empty(),
+        "json.endpoints cannot be empty - at least one receiver endpoint is required"
+    );
+
+    anyhow::ensure!(
+        !config.base.feeds.subscriptions.is_empty(),
+        "feeds.subscriptions cannot be empty - at least one feed subscription is required"
+    );
+
+    Ok(())
+}
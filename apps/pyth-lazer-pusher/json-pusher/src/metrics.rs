@@ -0,0 +1,48 @@
+//! JSON pusher Prometheus metrics.
+
+use anyhow::Result;
+use pusher_base::BaseMetrics;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use websocket_delivery::DeliveryMetrics;
+
+static METRICS: OnceLock<JsonMetrics> = OnceLock::new();
+
+pub fn metrics() -> &'static JsonMetrics {
+    METRICS.get_or_init(JsonMetrics::new)
+}
+
+pub struct JsonMetrics {
+    pub base: BaseMetrics,
+    pub ws: DeliveryMetrics,
+}
+
+impl JsonMetrics {
+    fn new() -> Self {
+        Self {
+            base: BaseMetrics::new("json"),
+            ws: DeliveryMetrics::new("json"),
+        }
+    }
+
+    fn register(&self) -> Result<()> {
+        let registry = prometheus::default_registry();
+        self.base.register(registry)?;
+        self.ws.register(registry)?;
+        Ok(())
+    }
+}
+
+pub fn init_metrics(address: SocketAddr) -> Result<()> {
+    metrics().register()?;
+    pusher_base::init_prometheus_exporter(address)?;
+    Ok(())
+}
+
+pub fn ws_metrics() -> DeliveryMetrics {
+    metrics().ws.clone()
+}
+
+pub fn base_metrics() -> &'static BaseMetrics {
+    &metrics().base
+}
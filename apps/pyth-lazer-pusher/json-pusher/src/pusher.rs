@@ -0,0 +1,215 @@
+//! Push loop: receives prices from Lazer and pushes them as JSON to the receivers.
+//!
+//! Each batch is a [`PriceBatch`] with an incrementing ID. Receivers reply with
+//! an [`Ack`] carrying the same ID.
+
+use crate::config::{Config, JsonConfig};
+use crate::metrics::{base_metrics, ws_metrics};
+use anyhow::{Context as _, Result};
+use pusher_base::{
+    handle_ack, run_push_loop, AppRuntime, CachedPrice, LazerReceiver, PushAck, PushResult,
+    PushTarget,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use websocket_delivery::{IncomingMessage, WebsocketDeliveryClient};
+
+pub async fn run(config: Config, runtime: AppRuntime) -> Result<()> {
+    info!("initializing json-pusher");
+
+    let target = JsonTarget::start(&config.json, runtime.clone()).await?;
+    info!(
+        endpoints = config.json.endpoints.len(),
+        "started JSON client"
+    );
+
+    let receiver = LazerReceiver::start(
+        &config.base.lazer,
+        &config.base.feeds,
+        Some(base_metrics()),
+        runtime.clone(),
+    )
+    .await
+    .context("failed to start Lazer receiver")?;
+
+    base_metrics().set_feeds_configured(receiver.feed_registry().len());
+
+    run_push_loop(
+        target,
+        receiver,
+        &config.base.feeds,
+        base_metrics().clone(),
+        runtime,
+    )
+    .await
+}
+
+/// A single price in a [`PriceBatch`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonPrice {
+    pub feed_id: u32,
+    pub price: i64,
+    pub exponent: i16,
+    /// Time the price was received from Lazer, in milliseconds since the epoch.
+    pub timestamp_ms: u64,
+}
+
+/// The message pushed to the receivers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceBatch {
+    pub id: u64,
+    pub prices: Vec<JsonPrice>,
+}
+
+/// The response of a receiver to a [`PriceBatch`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ack {
+    pub id: u64,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Pushes unsigned JSON batches to WebSocket receivers.
+pub struct JsonTarget {
+    client: WebsocketDeliveryClient,
+    next_id: u64,
+    ack_handle: JoinHandle<()>,
+}
+
+impl JsonTarget {
+    /// Connect to the receivers and start processing their acks.
+    pub async fn start(config: &JsonConfig, runtime: AppRuntime) -> Result<Self> {
+        let mut client = WebsocketDeliveryClient::new(config.endpoints.clone())
+            .with_metrics(ws_metrics())
+            .await;
+        client.start_all(runtime.clone()).await;
+        let incoming_rx = client
+            .take_incoming_receiver()
+            .context("incoming receiver already taken")?;
+        let ack_handle = runtime.spawn(handle_acks(incoming_rx, runtime.clone()));
+
+        Ok(Self {
+            client,
+            next_id: 1,
+            ack_handle,
+        })
+    }
+}
+
+impl PushTarget for JsonTarget {
+    type Entry = JsonPrice;
+    type Message = PriceBatch;
+
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn is_running(&self) -> bool {
+        !self.ack_handle.is_finished()
+    }
+
+    fn encode_batch(&self, prices: &[CachedPrice]) -> Vec<JsonPrice> {
+        prices.iter().filter_map(to_json_price).collect()
+    }
+
+    fn sign(&mut self, prices: Vec<JsonPrice>) -> Result<PriceBatch> {
+        // Receivers are trusted, so batches are only numbered, not signed.
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        Ok(PriceBatch { id, prices })
+    }
+
+    async fn deliver(&mut self, batch: PriceBatch) -> Result<()> {
+        let text = serde_json::to_string(&batch).context("failed to serialize batch")?;
+        let sent = self.client.broadcast(&text).await;
+        anyhow::ensure!(sent > 0, "no receiver connected");
+        debug!(id = batch.id, sent, "sent batch");
+        Ok(())
+    }
+
+    fn interpret_ack(response: &str) -> Option<PushAck> {
+        let ack: Ack = serde_json::from_str(response).ok()?;
+        let result = match (ack.ok, ack.error) {
+            (true, _) => PushResult::Accepted,
+            (false, error) => PushResult::Error(error.unwrap_or_default()),
+        };
+        Some(PushAck {
+            request_id: ack.id,
+            result,
+        })
+    }
+}
+
+async fn handle_acks(mut incoming_rx: mpsc::Receiver<IncomingMessage>, runtime: AppRuntime) {
+    let metrics = base_metrics();
+    loop {
+        let msg = tokio::select! {
+            _ = runtime.cancelled() => break,
+            msg = incoming_rx.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+        };
+
+        let Some(ack) = handle_ack::<JsonTarget>(&msg.text, metrics) else {
+            debug!(endpoint = %msg.endpoint, text = %msg.text, "ignoring non-ack message");
+            continue;
+        };
+        if let PushResult::Error(error) = &ack.result {
+            warn!(endpoint = %msg.endpoint, id = ack.request_id, error = %error, "batch rejected");
+        }
+    }
+}
+
+fn to_json_price(cached: &CachedPrice) -> Option<JsonPrice> {
+    Some(JsonPrice {
+        feed_id: cached.feed_id.0,
+        price: cached.data.price.as_ref()?.mantissa_i64(),
+        exponent: cached.data.exponent?,
+        timestamp_ms: cached.timestamp_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_batch_format() {
+        let batch = PriceBatch {
+            id: 3,
+            prices: vec![JsonPrice {
+                feed_id: 1,
+                price: 10_000_000,
+                exponent: -8,
+                timestamp_ms: 1_700_000_000_000,
+            }],
+        };
+        assert_eq!(
+            serde_json::to_string(&batch).unwrap(),
+            r#"{"id":3,"prices":[{"feed_id":1,"price":10000000,"exponent":-8,"timestamp_ms":1700000000000}]}"#
+        );
+    }
+
+    #[test]
+    fn test_interpret_ack() {
+        assert_eq!(
+            JsonTarget::interpret_ack(r#"{"id":1,"ok":true}"#),
+            Some(PushAck {
+                request_id: 1,
+                result: PushResult::Accepted,
+            })
+        );
+        assert_eq!(
+            JsonTarget::interpret_ack(r#"{"id":2,"ok":false,"error":"empty batch"}"#),
+            Some(PushAck {
+                request_id: 2,
+                result: PushResult::Error("empty batch".to_string()),
+            })
+        );
+        assert_eq!(JsonTarget::interpret_ack("not json"), None);
+    }
+}
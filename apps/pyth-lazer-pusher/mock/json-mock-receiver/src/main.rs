@@ -0,0 +1,171 @@
+//! Mock JSON price receiver for testing json-pusher.
+//!
+//! Accepts WebSocket connections, logs the received price batches and acks them.
+//!
+//! Usage:
+//!   cargo run --bin json-mock-receiver -- --port 8081 --verbose
+
+use anyhow::Result;
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tracing::{debug, error, info, warn};
+
+#[derive(Parser)]
+#[command(name = "json-mock-receiver")]
+#[command(about = "Mock JSON price receiver for testing json-pusher")]
+struct Args {
+    /// Port to listen on for WebSocket connections
+    #[arg(short, long, default_value = "8081")]
+    port: u16,
+
+    /// Log every received price
+    #[arg(long, default_value = "false")]
+    verbose: bool,
+}
+
+/// Price batch sent by json-pusher
+#[derive(Debug, Clone, Deserialize)]
+struct PriceBatch {
+    id: u64,
+    prices: Vec<JsonPrice>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct JsonPrice {
+    feed_id: u32,
+    price: i64,
+    exponent: i16,
+    timestamp_ms: u64,
+}
+
+/// Response to send back
+#[derive(Debug, Clone, Serialize)]
+struct Ack {
+    id: u64,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Validate a batch and build its ack.
+fn build_ack(batch: &PriceBatch) -> Ack {
+    let error = if batch.prices.is_empty() {
+        Some("empty batch".to_string())
+    } else {
+        None
+    };
+    Ack {
+        id: batch.id,
+        ok: error.is_none(),
+        error,
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    #[allow(
+        clippy::expect_used,
+        reason = "static string directive cannot fail to parse"
+    )]
+    let log_directive = "json_mock_receiver=debug"
+        .parse()
+        .expect("valid log directive");
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env().add_directive(log_directive),
+        )
+        .init();
+
+    let args = Args::parse();
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], args.port));
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Mock JSON receiver listening on ws://{}", addr);
+
+    loop {
+        let (stream, peer_addr) = listener.accept().await?;
+        info!("New connection from {}", peer_addr);
+        let verbose = args.verbose;
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, peer_addr, verbose).await {
+                error!("Connection error from {}: {}", peer_addr, e);
+            }
+            info!("Connection closed: {}", peer_addr);
+        });
+    }
+}
+
+async fn handle_connection(stream: TcpStream, peer_addr: SocketAddr, verbose: bool) -> Result<()> {
+    let ws_stream = accept_async(stream).await?;
+    let (mut write, mut read) = ws_stream.split();
+
+    while let Some(msg) = read.next().await {
+        let text = match msg? {
+            Message::Text(text) => text,
+            Message::Ping(data) => {
+                write.send(Message::Pong(data)).await?;
+                continue;
+            }
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let batch: PriceBatch = match serde_json::from_str(&text) {
+            Ok(batch) => batch,
+            Err(e) => {
+                warn!("Invalid message from {}: {}", peer_addr, e);
+                continue;
+            }
+        };
+
+        debug!(
+            "Received batch {} with {} prices from {}",
+            batch.id,
+            batch.prices.len(),
+            peer_addr
+        );
+        if verbose {
+            for price in &batch.prices {
+                info!(
+                    "  feed={} price={} exponent={} timestamp_ms={}",
+                    price.feed_id, price.price, price.exponent, price.timestamp_ms
+                );
+            }
+        }
+
+        let ack = build_ack(&batch);
+        write
+            .send(Message::Text(serde_json::to_string(&ack)?.into()))
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_ack() {
+        let batch: PriceBatch = serde_json::from_str(
+            r#"{"id":5,"prices":[{"feed_id":1,"price":100,"exponent":-2,"timestamp_ms":1}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&build_ack(&batch)).unwrap(),
+            r#"{"id":5,"ok":true}"#
+        );
+
+        let batch: PriceBatch = serde_json::from_str(r#"{"id":6,"prices":[]}"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&build_ack(&batch)).unwrap(),
+            r#"{"id":6,"ok":false,"error":"empty batch"}"#
+        );
+    }
+}
@@ -59,6 +59,11 @@ pub struct FeedsConfig {
     /// Update interval (how often to batch and push)
     #[serde(with = "humantime_serde", default = "default_update_interval")]
     pub update_interval: Duration,
+
+    /// Maximum age of a price to be pushed. Older prices (e.g. of a halted feed)
+    /// are dropped from the batch. If not set, the latest price is always pushed.
+    #[serde(with = "humantime_serde", default)]
+    pub max_price_age: Option<Duration>,
 }
 
 /// A single feed subscription configuration.
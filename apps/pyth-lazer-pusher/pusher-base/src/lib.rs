@@ -3,11 +3,13 @@
 pub mod config;
 pub mod lazer;
 pub mod metrics;
+pub mod push;
 pub mod types;
 
 pub use config::BaseConfig;
 pub use lazer::{FeedRegistry, FeedSubscription, FeedsConfig, LazerConfig, LazerReceiver};
 pub use metrics::{init_prometheus_exporter, BaseMetrics};
+pub use push::{handle_ack, run_push_loop, PushAck, PushResult, PushTarget};
 pub use pusher_utils::AppRuntime;
 pub use pyth_lazer_protocol::PriceFeedId;
 pub use types::{CachedPrice, PriceCache};
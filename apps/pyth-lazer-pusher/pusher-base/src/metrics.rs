@@ -1,7 +1,9 @@
 //! Base Prometheus metrics shared by all pushers.
 
+use crate::push::PushResult;
+use crate::types::CachedPrice;
 use anyhow::Result;
-use prometheus::{CounterVec, Gauge, GaugeVec, Opts, Registry};
+use prometheus::{Counter, CounterVec, Gauge, GaugeVec, Opts, Registry};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 
@@ -12,6 +14,11 @@ pub struct BaseMetrics {
     pub last_push_timestamp: Gauge,
     pub feeds_configured: Gauge,
     pub feed_last_update_timestamp: GaugeVec,
+    pub prices_skipped: Counter,
+    pub stale_prices_dropped: Counter,
+    pub price_age_seconds: GaugeVec,
+    pub push_failures: CounterVec,
+    pub push_results: CounterVec,
 }
 
 impl BaseMetrics {
@@ -65,10 +72,57 @@ impl BaseMetrics {
                     "Per-feed last update timestamp",
                 )
                 .namespace("lazer_pusher")
-                .const_labels(const_labels),
+                .const_labels(const_labels.clone()),
                 &["feed_id"],
             )
             .expect("failed to create metric"),
+
+            prices_skipped: Counter::with_opts(
+                Opts::new(
+                    "prices_skipped_total",
+                    "Push cycles skipped due to no prices or no valid oracles",
+                )
+                .namespace("lazer_pusher")
+                .const_labels(const_labels.clone()),
+            )
+            .expect("failed to create metric"),
+
+            stale_prices_dropped: Counter::with_opts(
+                Opts::new(
+                    "stale_prices_dropped_total",
+                    "Prices not pushed because they exceeded the max price age",
+                )
+                .namespace("lazer_pusher")
+                .const_labels(const_labels.clone()),
+            )
+            .expect("failed to create metric"),
+
+            price_age_seconds: GaugeVec::new(
+                Opts::new("price_age_seconds", "Age of price at push time")
+                    .namespace("lazer_pusher")
+                    .const_labels(const_labels.clone()),
+                &["feed_id"],
+            )
+            .expect("failed to create metric"),
+
+            push_failures: CounterVec::new(
+                Opts::new(
+                    "push_failures_total",
+                    "Batches that could not be signed or delivered",
+                )
+                .namespace("lazer_pusher")
+                .const_labels(const_labels.clone()),
+                &["stage"],
+            )
+            .expect("failed to create metric"),
+
+            push_results: CounterVec::new(
+                Opts::new("push_results_total", "Push acks by status")
+                    .namespace("lazer_pusher")
+                    .const_labels(const_labels),
+                &["status"],
+            )
+            .expect("failed to create metric"),
         }
     }
 
@@ -78,6 +132,11 @@ impl BaseMetrics {
         registry.register(Box::new(self.last_push_timestamp.clone()))?;
         registry.register(Box::new(self.feeds_configured.clone()))?;
         registry.register(Box::new(self.feed_last_update_timestamp.clone()))?;
+        registry.register(Box::new(self.prices_skipped.clone()))?;
+        registry.register(Box::new(self.stale_prices_dropped.clone()))?;
+        registry.register(Box::new(self.price_age_seconds.clone()))?;
+        registry.register(Box::new(self.push_failures.clone()))?;
+        registry.register(Box::new(self.push_results.clone()))?;
         Ok(())
     }
 
@@ -106,6 +165,38 @@ impl BaseMetrics {
         self.batch_size.set(size as f64);
     }
 
+    pub fn record_prices_skipped(&self) {
+        self.prices_skipped.inc();
+    }
+
+    #[allow(clippy::cast_precision_loss, reason = "price count fits in f64")]
+    pub fn record_stale_prices(&self, count: usize) {
+        self.stale_prices_dropped.inc_by(count as f64);
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "price age in milliseconds fits in f64"
+    )]
+    pub fn record_price_ages(&self, prices: &[CachedPrice], now_ms: u64) {
+        for price in prices {
+            let age_secs = now_ms.saturating_sub(price.timestamp_ms) as f64 / 1000.0;
+            self.price_age_seconds
+                .with_label_values(&[&price.feed_id.0.to_string()])
+                .set(age_secs);
+        }
+    }
+
+    pub fn record_push_failure(&self, stage: &str) {
+        self.push_failures.with_label_values(&[stage]).inc();
+    }
+
+    pub fn record_push_result(&self, result: &PushResult) {
+        self.push_results
+            .with_label_values(&[result.status()])
+            .inc();
+    }
+
     pub fn update_last_push_timestamp(&self) {
         self.last_push_timestamp.set(
             std::time::SystemTime::now()
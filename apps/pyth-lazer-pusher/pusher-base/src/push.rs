@@ -0,0 +1,388 @@
+//! Generic push loop for delivering Lazer prices to a destination.
+//!
+//! A destination implements [`PushTarget`]: it encodes the cached prices into
+//! a batch, signs it, and delivers it. [`run_push_loop`] drives any target on
+//! the configured interval and takes care of staleness filtering, metrics and
+//! graceful shutdown.
+
+use crate::lazer::{FeedsConfig, LazerReceiver};
+use crate::metrics::BaseMetrics;
+use crate::types::CachedPrice;
+use anyhow::Result;
+use pusher_utils::AppRuntime;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::interval;
+use tracing::{debug, error, info, warn};
+
+/// Outcome of a push, as reported by the destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushResult {
+    Accepted,
+    /// The destination already had this update (e.g. pushed by another instance).
+    Deduplicated,
+    Error(String),
+}
+
+impl PushResult {
+    /// Label used for the push result metrics.
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Accepted => "accepted",
+            Self::Deduplicated => "deduplicated",
+            Self::Error(_) => "error",
+        }
+    }
+}
+
+/// A response from the destination, matched to a push by its request ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushAck {
+    pub request_id: u64,
+    pub result: PushResult,
+}
+
+/// A destination that Lazer prices are pushed to.
+///
+/// Each tick, the push loop calls `encode_batch`, `sign` and `deliver` in turn.
+/// Acks usually arrive asynchronously (e.g. on a WebSocket), so targets pass
+/// them to [`handle_ack`] wherever they receive them.
+pub trait PushTarget: Send {
+    /// Destination-specific representation of a single price.
+    type Entry: Send;
+    /// The message sent to the destination for a batch of entries.
+    type Message: Send;
+
+    /// Name of the destination, for logs.
+    fn name(&self) -> &str;
+
+    /// Whether the target can still deliver messages. The push loop fails
+    /// when this returns false.
+    fn is_running(&self) -> bool {
+        true
+    }
+
+    /// Encode the prices that can be pushed, skipping the others.
+    fn encode_batch(&self, prices: &[CachedPrice]) -> Vec<Self::Entry>;
+
+    /// Build the message for a non-empty batch, signing it if the destination
+    /// requires it.
+    fn sign(&mut self, entries: Vec<Self::Entry>) -> Result<Self::Message>;
+
+    /// Hand the message over for delivery.
+    fn deliver(&mut self, message: Self::Message) -> impl Future<Output = Result<()>> + Send;
+
+    /// Parse a response from the destination. Returns `None` for messages
+    /// that are not acks.
+    fn interpret_ack(response: &str) -> Option<PushAck>;
+}
+
+/// Parse a response from the destination of `T` with [`PushTarget::interpret_ack`]
+/// and record its result. Returns `None` for messages that are not acks.
+pub fn handle_ack<T: PushTarget>(response: &str, metrics: &BaseMetrics) -> Option<PushAck> {
+    let ack = T::interpret_ack(response)?;
+    if ack.result == PushResult::Accepted {
+        metrics.update_last_push_timestamp();
+    }
+    metrics.record_push_result(&ack.result);
+    Some(ack)
+}
+
+/// Push the latest prices of `receiver` to `target` every
+/// `feeds_config.update_interval` until shutdown.
+///
+/// Prices older than `feeds_config.max_price_age` are dropped, so a stalled
+/// feed is not pushed over and over. Fails if the target or the receiver stops.
+pub async fn run_push_loop<T: PushTarget>(
+    mut target: T,
+    receiver: LazerReceiver,
+    feeds_config: &FeedsConfig,
+    metrics: BaseMetrics,
+    runtime: AppRuntime,
+) -> Result<()> {
+    info!(
+        destination = target.name(),
+        update_interval_ms = feeds_config.update_interval.as_millis(),
+        "starting push loop"
+    );
+
+    let mut interval = interval(feeds_config.update_interval);
+
+    loop {
+        tokio::select! {
+            _ = runtime.cancelled() => {
+                info!("push loop shutdown requested");
+                return Ok(());
+            }
+            _ = interval.tick() => {}
+        }
+
+        if !target.is_running() {
+            error!(
+                destination = target.name(),
+                "push target stopped unexpectedly"
+            );
+            anyhow::bail!("push target {} stopped", target.name());
+        }
+        if !receiver.is_running() {
+            error!("Lazer receiver stopped unexpectedly");
+            anyhow::bail!("Lazer receiver stopped");
+        }
+
+        let prices: Vec<CachedPrice> = {
+            let cache = receiver.price_cache().read().await;
+            receiver
+                .feed_registry()
+                .feed_ids()
+                .filter_map(|id| cache.get(id).cloned())
+                .collect()
+        };
+
+        push_prices(&mut target, prices, feeds_config.max_price_age, &metrics).await;
+    }
+}
+
+/// Push one batch of `prices` to `target`. Returns whether a message was delivered.
+async fn push_prices<T: PushTarget>(
+    target: &mut T,
+    prices: Vec<CachedPrice>,
+    max_price_age: Option<Duration>,
+    metrics: &BaseMetrics,
+) -> bool {
+    let now_ms = now_ms();
+    let (prices, stale_count) = filter_stale(prices, max_price_age, now_ms);
+    if stale_count > 0 {
+        debug!(stale_count, "dropped stale prices");
+        metrics.record_stale_prices(stale_count);
+    }
+
+    if prices.is_empty() {
+        debug!("no prices available, skipping push");
+        metrics.record_prices_skipped();
+        return false;
+    }
+
+    metrics.record_price_ages(&prices, now_ms);
+
+    let entries = target.encode_batch(&prices);
+    if entries.is_empty() {
+        debug!("no valid prices to push");
+        metrics.record_prices_skipped();
+        return false;
+    }
+
+    metrics.set_batch_size(entries.len());
+
+    let message = match target.sign(entries) {
+        Ok(message) => message,
+        Err(e) => {
+            error!(?e, destination = target.name(), "failed to sign batch");
+            metrics.record_push_failure("sign");
+            return false;
+        }
+    };
+
+    if let Err(e) = target.deliver(message).await {
+        warn!(?e, destination = target.name(), "failed to deliver batch");
+        metrics.record_push_failure("deliver");
+        return false;
+    }
+
+    true
+}
+
+/// Split off the prices received more than `max_age` before `now_ms`.
+/// Returns the fresh prices and the number of stale ones.
+fn filter_stale(
+    prices: Vec<CachedPrice>,
+    max_age: Option<Duration>,
+    now_ms: u64,
+) -> (Vec<CachedPrice>, usize) {
+    let Some(max_age) = max_age else {
+        return (prices, 0);
+    };
+    let max_age_ms = u64::try_from(max_age.as_millis()).unwrap_or(u64::MAX);
+    let total = prices.len();
+    let fresh: Vec<CachedPrice> = prices
+        .into_iter()
+        .filter(|price| now_ms.saturating_sub(price.timestamp_ms) <= max_age_ms)
+        .collect();
+    let stale_count = total - fresh.len();
+    (fresh, stale_count)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyth_lazer_protocol::PriceFeedId;
+
+    fn cached_price(feed_id: u32, timestamp_ms: u64) -> CachedPrice {
+        CachedPrice {
+            data: serde_json::from_value(serde_json::json!({ "priceFeedId": feed_id })).unwrap(),
+            timestamp_ms,
+            feed_id: PriceFeedId(feed_id),
+        }
+    }
+
+    /// Records the delivered batches of feed IDs.
+    #[derive(Default)]
+    struct MockTarget {
+        delivered: Vec<Vec<u32>>,
+        fail_sign: bool,
+        fail_deliver: bool,
+    }
+
+    impl PushTarget for MockTarget {
+        type Entry = u32;
+        type Message = Vec<u32>;
+
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn encode_batch(&self, prices: &[CachedPrice]) -> Vec<u32> {
+            // Pretend that odd feeds have no price.
+            prices
+                .iter()
+                .map(|price| price.feed_id.0)
+                .filter(|id| id % 2 == 0)
+                .collect()
+        }
+
+        fn sign(&mut self, entries: Vec<u32>) -> Result<Vec<u32>> {
+            anyhow::ensure!(!self.fail_sign, "signing failed");
+            Ok(entries)
+        }
+
+        async fn deliver(&mut self, message: Vec<u32>) -> Result<()> {
+            anyhow::ensure!(!self.fail_deliver, "delivery failed");
+            self.delivered.push(message);
+            Ok(())
+        }
+
+        fn interpret_ack(response: &str) -> Option<PushAck> {
+            Some(PushAck {
+                request_id: response.parse().ok()?,
+                result: PushResult::Accepted,
+            })
+        }
+    }
+
+    #[test]
+    fn test_filter_stale() {
+        let prices = vec![cached_price(1, 1_000), cached_price(2, 9_000)];
+
+        let (fresh, stale_count) = filter_stale(prices.clone(), None, 10_000);
+        assert_eq!(fresh.len(), 2);
+        assert_eq!(stale_count, 0);
+
+        let (fresh, stale_count) =
+            filter_stale(prices.clone(), Some(Duration::from_secs(1)), 10_000);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].feed_id, PriceFeedId(2));
+        assert_eq!(stale_count, 1);
+
+        // Prices from the future (clock skew) are not stale.
+        let (fresh, stale_count) = filter_stale(prices, Some(Duration::ZERO), 500);
+        assert_eq!(fresh.len(), 2);
+        assert_eq!(stale_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_push_prices() {
+        let metrics = BaseMetrics::new("test");
+        let mut target = MockTarget::default();
+        let now = now_ms();
+
+        assert!(
+            push_prices(
+                &mut target,
+                vec![
+                    cached_price(1, now),
+                    cached_price(2, now),
+                    cached_price(4, now)
+                ],
+                None,
+                &metrics,
+            )
+            .await
+        );
+        assert_eq!(target.delivered, vec![vec![2, 4]]);
+        assert_eq!(metrics.batch_size.get(), 2.0);
+
+        // Nothing to push once the invalid and stale prices are dropped.
+        assert!(
+            !push_prices(
+                &mut target,
+                vec![cached_price(1, now), cached_price(2, 0)],
+                Some(Duration::from_secs(5)),
+                &metrics,
+            )
+            .await
+        );
+        assert_eq!(metrics.prices_skipped.get(), 1.0);
+        assert_eq!(metrics.stale_prices_dropped.get(), 1.0);
+        assert_eq!(target.delivered.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_push_prices_failures() {
+        let metrics = BaseMetrics::new("test");
+        let now = now_ms();
+
+        let mut target = MockTarget {
+            fail_sign: true,
+            ..MockTarget::default()
+        };
+        assert!(!push_prices(&mut target, vec![cached_price(2, now)], None, &metrics).await);
+
+        let mut target = MockTarget {
+            fail_deliver: true,
+            ..MockTarget::default()
+        };
+        assert!(!push_prices(&mut target, vec![cached_price(2, now)], None, &metrics).await);
+
+        assert_eq!(
+            metrics.push_failures.with_label_values(&["sign"]).get(),
+            1.0
+        );
+        assert_eq!(
+            metrics.push_failures.with_label_values(&["deliver"]).get(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_handle_ack() {
+        let metrics = BaseMetrics::new("test");
+
+        assert_eq!(
+            handle_ack::<MockTarget>("7", &metrics),
+            Some(PushAck {
+                request_id: 7,
+                result: PushResult::Accepted,
+            })
+        );
+        assert_eq!(handle_ack::<MockTarget>("not an ack", &metrics), None);
+
+        assert_eq!(
+            metrics.push_results.with_label_values(&["accepted"]).get(),
+            1.0
+        );
+        assert!(metrics.last_push_timestamp.get() > 0.0);
+    }
+
+    #[test]
+    fn test_push_result_status() {
+        assert_eq!(PushResult::Accepted.status(), "accepted");
+        assert_eq!(PushResult::Deduplicated.status(), "deduplicated");
+        assert_eq!(PushResult::Error("boom".to_string()).status(), "error");
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+/// Custom build script to compile and include the wormhole spy protobufs into the source.
+/// The wormhole protobufs are vendored from the Wormhole git repository at https://github.com/wormhole-foundation/wormhole.git
+/// They reference other protobufs from the Google API repository at https://github.com/googleapis/googleapis.git , which are also vendored.
+/// Our copies live in `proto/vendor`.
+fn main() {
+    let proto_dir = PathBuf::from("proto/vendor");
+
+    // Tell cargo to recompile if any .proto files change
+    println!("cargo:rerun-if-changed=proto/");
+
+    // Quorum only serves the spy `SubscribeSignedVAA` stream, so no client is generated.
+    #[allow(clippy::expect_used, reason = "failing at build time is fine")]
+    tonic_build::configure()
+        .build_server(true)
+        .build_client(false)
+        .compile_protos(
+            &[
+                proto_dir.join("spy/v1/spy.proto"),
+                proto_dir.join("gossip/v1/gossip.proto"),
+                proto_dir.join("publicrpc/v1/publicrpc.proto"),
+            ],
+            &[proto_dir],
+        )
+        .expect("failed to compile protobuf definitions");
+}
@@ -1,5 +1,7 @@
 use ::time::OffsetDateTime;
 use axum::{
+    extract::{DefaultBodyLimit, Path},
+    http::StatusCode,
     routing::{get, post},
     Json, Router,
 };
@@ -22,7 +24,9 @@ use wormhole_sdk::{
 };
 
 use crate::{
+    guardian_health::StatusResponse,
     server::State,
+    vaa_store::StoredVaa,
     ws::{ws_route_handler, UpdateEvent},
 };
 
@@ -32,6 +36,14 @@ async fn root() -> String {
     format!("Quorum API {}", crate_version!())
 }
 
+/// The maximum size of an observation request, so that oversized bodies are rejected before being
+/// parsed. The body is a JSON array of numbers, so each of its bytes takes up to 4 characters.
+fn max_observation_request_size(max_observation_body_size: usize) -> usize {
+    max_observation_body_size
+        .saturating_mul(4)
+        .saturating_add(1024)
+}
+
 pub async fn run(listen_address: SocketAddr, state: State) -> anyhow::Result<()> {
     tracing::info!("Starting server...");
 
@@ -45,7 +57,15 @@ pub async fn run(listen_address: SocketAddr, state: State) -> anyhow::Result<()>
     let routes = Router::new()
         .route("/", get(root))
         .route("/live", get(|| async { "OK" }))
-        .route("/observation", post(post_observation))
+        .route(
+            "/observation",
+            post(post_observation).layer(DefaultBodyLimit::max(max_observation_request_size(
+                state.max_observation_body_size,
+            ))),
+        )
+        .route("/status", get(get_status))
+        .route("/vaa/{sequence}", get(get_vaa_by_sequence))
+        .route("/vaa/slot/{slot}", get(get_vaa_by_slot))
         .route("/ws", get(ws_route_handler))
         .layer(prometheus_layer)
         .with_state(state);
@@ -116,6 +136,19 @@ fn verify_observation(
 }
 
 async fn run_expiration_loop(state: axum::extract::State<State>, observation: Observation) {
+    let body = match observation.get_body() {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to deserialize observation body");
+            return;
+        }
+    };
+    // The deadline is tracked on the tokio clock rather than the wall clock, so that it follows
+    // the clock of the runtime.
+    let deadline = i64::from(body.timestamp) + i64::from(state.observation_lifetime);
+    let time_to_expiry = (deadline + 1 - OffsetDateTime::now_utc().unix_timestamp()).max(0);
+    let expires_at = tokio::time::Instant::now() + Duration::from_secs(time_to_expiry as u64);
+
     loop {
         tokio::time::sleep(Duration::from_secs(state.observation_lifetime as u64)).await;
 
@@ -125,25 +158,48 @@ async fn run_expiration_loop(state: axum::extract::State<State>, observation: Ob
         }
         drop(verification); // Explicitly drop the read lock before acquiring a write lock
 
-        let body = match observation.get_body() {
-            Ok(body) => body,
-            Err(e) => {
-                tracing::warn!(error = ?e, "Failed to deserialize observation body");
-                break;
-            }
-        };
-
-        if is_body_expired(&body, state.observation_lifetime) {
+        if tokio::time::Instant::now() >= expires_at {
             state.verification.write().await.remove(&observation.body);
             break;
         }
     }
 }
 
+async fn record_guardian_observation(
+    state: &axum::extract::State<State>,
+    body_bytes: &[u8],
+    body: &Body<Payload<'_>>,
+    guardian_index: u8,
+) {
+    let arrival_delay = state.guardian_health.write().await.record_observation(
+        body_bytes,
+        body.sequence,
+        body.timestamp,
+        guardian_index,
+    );
+    metrics::histogram!(
+        "observation_arrival_delay_seconds",
+        &[("guardian_index", guardian_index.to_string())]
+    )
+    .record(arrival_delay.as_secs_f64());
+}
+
 async fn handle_observation(
     state: axum::extract::State<State>,
     params: Observation,
 ) -> Result<(), anyhow::Error> {
+    if params.body.len() > state.max_observation_body_size {
+        metrics::counter!(
+            "rejected_observations_total",
+            &[("reason", "body_too_large")]
+        )
+        .increment(1);
+        return Err(anyhow::anyhow!(
+            "Observation body exceeds the maximum size of {} bytes",
+            state.max_observation_body_size
+        ));
+    }
+
     let verifier_index = verify_observation(
         &params,
         state.guardian_set.clone(),
@@ -154,49 +210,66 @@ async fn handle_observation(
         &[("gaurdian_index", verifier_index.to_string())]
     )
     .increment(1);
+    let body = params
+        .get_body()
+        .map_err(|e| anyhow::anyhow!("Failed to deserialize observation body: {}", e))?;
     let new_signature = Signature {
         signature: params.signature,
         index: verifier_index.try_into()?,
     };
 
     let mut verification_writer = state.verification.write().await;
-    let signatures = verification_writer
-        .entry(params.body.clone())
-        .and_modify(|sigs| {
-            if sigs.iter().all(|sig| sig.index != new_signature.index) {
-                sigs.push(new_signature);
-                sigs.sort_by(|a, b| a.index.cmp(&b.index));
-            }
-        })
-        .or_insert_with(|| vec![new_signature])
-        .clone();
+    if verification_writer.is_completed(&params.body) {
+        // Late signatures still count towards the guardian participation.
+        record_guardian_observation(&state, &params.body, &body, new_signature.index).await;
+        metrics::counter!("rejected_observations_total", &[("reason", "completed")]).increment(1);
+        return Err(anyhow::anyhow!(
+            "Observation is for an already completed VAA"
+        ));
+    }
+    let added = verification_writer.add_signature(&params.body, body.timestamp, new_signature)?;
+    record_guardian_observation(&state, &params.body, &body, new_signature.index).await;
 
-    let body = params
-        .get_body()
-        .map_err(|e| anyhow::anyhow!("Failed to deserialize observation body: {}", e))?;
-    if signatures.len() > (state.guardian_set.addresses.len() * 2) / 3 {
+    // A single expiration task per body takes care of forgetting it, whether it completes or not.
+    if added.new_body {
+        tokio::spawn(run_expiration_loop(state.clone(), params.clone()));
+    }
+
+    if added.signatures.len() > (state.guardian_set.addresses.len() * 2) / 3 {
         let vaa: Vaa<Payload> = (
             Header {
                 version: 1,
                 guardian_set_index: state.guardian_set_index,
-                signatures,
+                signatures: added.signatures,
             },
             body,
         )
             .into();
         metrics::counter!("new_vaa_total").increment(1);
+        if let Some(elapsed) = state
+            .guardian_health
+            .write()
+            .await
+            .record_quorum(&params.body)
+        {
+            metrics::histogram!("quorum_formation_seconds").record(elapsed.as_secs_f64());
+        }
+        let vaa_bytes = serde_wormhole::to_vec(&vaa)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize VAA: {}", e))?;
+
+        // The store lock is held while broadcasting so that subscribers resuming from a sequence
+        // see every VAA exactly once, either in the backfill or on the broadcast channel.
+        let mut vaa_store = state.vaa_store.write().await;
+        vaa_store.insert(vaa.sequence, vaa.payload.as_ref(), vaa_bytes.clone());
         if let Err(e) = state
             .ws
             .broadcast_sender
-            .send(UpdateEvent::NewVaa(serde_wormhole::to_vec(&vaa).map_err(
-                |e| anyhow::anyhow!("Failed to serialize VAA: {}", e),
-            )?))
+            .send(UpdateEvent::NewVaa(vaa_bytes))
         {
             tracing::error!(error = ?e, "Failed to broadcast new VAA");
         }
-        verification_writer.remove(&params.body);
-    } else {
-        tokio::spawn(run_expiration_loop(state.clone(), params));
+        drop(vaa_store);
+        verification_writer.complete(&params.body);
     }
 
     Ok(())
@@ -222,20 +295,56 @@ async fn post_observation(
     Json(())
 }
 
+async fn get_status(state: axum::extract::State<State>) -> Json<StatusResponse> {
+    Json(
+        state
+            .guardian_health
+            .read()
+            .await
+            .status(&state.guardian_set, state.guardian_set_index),
+    )
+}
+
+async fn get_vaa_by_sequence(
+    state: axum::extract::State<State>,
+    Path(sequence): Path<u64>,
+) -> Result<Json<StoredVaa>, StatusCode> {
+    state
+        .vaa_store
+        .read()
+        .await
+        .get_by_sequence(sequence)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_vaa_by_slot(
+    state: axum::extract::State<State>,
+    Path(slot): Path<u64>,
+) -> Result<Json<StoredVaa>, StatusCode> {
+    state
+        .vaa_store
+        .read()
+        .await
+        .get_by_slot(slot)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
 #[cfg(test)]
 mod test {
-    use std::{collections::HashMap, sync::Arc};
-
-    use crate::server::tests::get_state;
+    use crate::{
+        observation_table::ObservationLimits,
+        server::tests::{get_state, get_state_with_limits},
+    };
     use secp256k1::{
         rand::{self, seq::SliceRandom},
         Secp256k1,
     };
     use serde::Serialize;
-    use tokio::{
-        sync::RwLock,
-        time::{sleep, timeout},
-    };
+    use tokio::time::{sleep, timeout};
 
     use super::*;
 
@@ -391,21 +500,23 @@ mod test {
             body: serde_wormhole::to_vec(&body).unwrap(),
         };
         let body = serde_wormhole::to_vec(&body).unwrap();
-        let state = get_state(
-            Arc::new(RwLock::new(HashMap::new())),
-            guardian_set,
-            OBSERVERATION_LIFETIME,
-        );
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
         let result = timeout(
             Duration::from_secs((OBSERVERATION_LIFETIME * 3) as u64),
             async {
-                state.verification.write().await.insert(
-                    body.clone(),
-                    vec![Signature {
-                        signature: observation.signature,
-                        index: 0,
-                    }],
-                );
+                state
+                    .verification
+                    .write()
+                    .await
+                    .add_signature(
+                        &body,
+                        observation.get_body().unwrap().timestamp,
+                        Signature {
+                            signature: observation.signature,
+                            index: 0,
+                        },
+                    )
+                    .unwrap();
                 assert_eq!(
                     state
                         .verification
@@ -438,21 +549,23 @@ mod test {
             body: serde_wormhole::to_vec(&body).unwrap(),
         };
         let body = serde_wormhole::to_vec(&body).unwrap();
-        let state = get_state(
-            Arc::new(RwLock::new(HashMap::new())),
-            guardian_set,
-            OBSERVERATION_LIFETIME,
-        );
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
         let result = timeout(
             Duration::from_secs((OBSERVERATION_LIFETIME + 1) as u64),
             async {
-                state.verification.write().await.insert(
-                    body.clone(),
-                    vec![Signature {
-                        signature: observation.signature,
-                        index: 0,
-                    }],
-                );
+                state
+                    .verification
+                    .write()
+                    .await
+                    .add_signature(
+                        &body,
+                        observation.get_body().unwrap().timestamp,
+                        Signature {
+                            signature: observation.signature,
+                            index: 0,
+                        },
+                    )
+                    .unwrap();
                 assert_eq!(
                     state
                         .verification
@@ -494,19 +607,21 @@ mod test {
             body: serde_wormhole::to_vec(&body).unwrap(),
         };
         let body = serde_wormhole::to_vec(&body).unwrap();
-        let state = get_state(
-            Arc::new(RwLock::new(HashMap::new())),
-            guardian_set,
-            OBSERVERATION_LIFETIME,
-        );
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
         let result = timeout(Duration::from_secs(timeout_duration), async {
-            state.verification.write().await.insert(
-                body.clone(),
-                vec![Signature {
-                    signature: observation.signature,
-                    index: 0,
-                }],
-            );
+            state
+                .verification
+                .write()
+                .await
+                .add_signature(
+                    &body,
+                    observation.get_body().unwrap().timestamp,
+                    Signature {
+                        signature: observation.signature,
+                        index: 0,
+                    },
+                )
+                .unwrap();
             assert_eq!(
                 state
                     .verification
@@ -561,16 +676,14 @@ mod test {
             })
             .collect();
         let body = serde_wormhole::to_vec(&sample_body).unwrap();
-        let state = get_state(
-            Arc::new(RwLock::new(HashMap::new())),
-            guardian_set,
-            OBSERVERATION_LIFETIME,
-        );
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
 
         let mut subscriber = state.ws.broadcast_sender.subscribe();
         for (i, observation) in observations.iter().enumerate() {
-            if i > quorum {
-                // It should be once removed from the verification map
+            if i >= quorum {
+                // It should be removed from the verification map once completed
+                assert!(state.verification.read().await.get(&body.clone()).is_none());
+            } else if i > 0 {
                 assert_eq!(
                     state
                         .verification
@@ -579,25 +692,20 @@ mod test {
                         .get(&body.clone())
                         .unwrap()
                         .len(),
-                    i - quorum
+                    i
                 );
-            } else if i < quorum && i > 0 {
+            }
+            let result =
+                handle_observation(axum::extract::State(state.clone()), observation.clone()).await;
+            if i < quorum {
+                assert!(result.is_ok());
+            } else {
+                // Late observations for the completed VAA are rejected
                 assert_eq!(
-                    state
-                        .verification
-                        .read()
-                        .await
-                        .get(&body.clone())
-                        .unwrap()
-                        .len(),
-                    i
+                    result.unwrap_err().to_string(),
+                    "Observation is for an already completed VAA"
                 );
             }
-            assert!(
-                handle_observation(axum::extract::State(state.clone()), observation.clone())
-                    .await
-                    .is_ok()
-            );
 
             if i == quorum - 1 {
                 // Ensure we have reached the quorum
@@ -635,6 +743,16 @@ mod test {
                     vaa, expected_vaa,
                     "VAA should match the expected VAA with the correct signatures"
                 );
+
+                let vaa_store = state.vaa_store.read().await;
+                let stored = vaa_store
+                    .get_by_sequence(sample_body.sequence)
+                    .expect("VAA should be retained in the store");
+                assert_eq!(
+                    stored.vaa,
+                    serde_wormhole::to_vec(&expected_vaa).unwrap(),
+                    "Stored VAA should match the broadcasted VAA"
+                );
             }
         }
 
@@ -667,11 +785,7 @@ mod test {
             .collect();
 
         let body = serde_wormhole::to_vec(&body).unwrap();
-        let state = get_state(
-            Arc::new(RwLock::new(HashMap::new())),
-            guardian_set,
-            OBSERVERATION_LIFETIME,
-        );
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
 
         assert_eq!(state.verification.read().await.len(), 0);
         for (i, observation) in observations.iter().enumerate() {
@@ -702,4 +816,193 @@ mod test {
         assert_eq!(state.verification.read().await.len(), 0,
             "Verification map should not be empty after handling all observations, as there is no quorum yet");
     }
+
+    #[tokio::test]
+    async fn test_handle_observation_body_too_large() {
+        let mut body = get_sample_body(-(OBSERVERATION_LIFETIME as i64 - 1));
+        let large_payload = [4; 2048];
+        body.payload = RawMessage::new(&large_payload);
+        let (guardian_set, keys) = get_guardian_sets(19);
+        let observation = Observation {
+            signature: sign(&body, &keys[0]),
+            body: serde_wormhole::to_vec(&body).unwrap(),
+        };
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
+
+        let result = handle_observation(axum::extract::State(state.clone()), observation).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "Observation body exceeds the maximum size of {} bytes",
+                state.max_observation_body_size
+            )
+        );
+        assert_eq!(state.verification.read().await.len(), 0);
+    }
+
+    #[test]
+    fn test_max_observation_request_size() {
+        let max_body_size = 1024;
+        let request = serde_json::json!({
+            "signature": hex::encode([255u8; 65]),
+            "body": vec![255u8; max_body_size],
+        })
+        .to_string();
+        assert!(request.len() <= max_observation_request_size(max_body_size));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_handle_observation_replayed_signature_after_quorum() {
+        let body = get_sample_body(-(OBSERVERATION_LIFETIME as i64 - 1));
+        let (guardian_set, keys) = get_guardian_sets(4);
+        let observations: Vec<Observation> = keys
+            .iter()
+            .map(|key| Observation {
+                signature: sign(&body, key),
+                body: serde_wormhole::to_vec(&body).unwrap(),
+            })
+            .collect();
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
+        let mut subscriber = state.ws.broadcast_sender.subscribe();
+
+        for observation in &observations[0..3] {
+            handle_observation(axum::extract::State(state.clone()), observation.clone())
+                .await
+                .unwrap();
+        }
+        assert!(matches!(subscriber.try_recv(), Ok(UpdateEvent::NewVaa(_))));
+
+        // Replaying a signature that was part of the quorum must not start a new pending entry
+        for observation in &observations[0..3] {
+            let result =
+                handle_observation(axum::extract::State(state.clone()), observation.clone()).await;
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "Observation is for an already completed VAA"
+            );
+        }
+        assert_eq!(state.verification.read().await.len(), 0);
+        assert!(subscriber.try_recv().is_err(), "No new VAA should be sent");
+
+        // Once the body expires it is forgotten entirely
+        sleep(Duration::from_secs((OBSERVERATION_LIFETIME * 2 + 1) as u64)).await;
+        assert!(!state
+            .verification
+            .read()
+            .await
+            .contains_key(&serde_wormhole::to_vec(&body).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_handle_observation_guardian_in_flight_limit() {
+        let (guardian_set, keys) = get_guardian_sets(19);
+        let state = get_state_with_limits(
+            guardian_set,
+            OBSERVERATION_LIFETIME,
+            ObservationLimits {
+                max_pending_bodies: 100,
+                max_in_flight_per_guardian: 3,
+            },
+        );
+
+        let mut bodies = vec![];
+        for sequence in 0..4 {
+            let mut body = get_sample_body(-(OBSERVERATION_LIFETIME as i64 - 1));
+            body.sequence = sequence;
+            bodies.push(body);
+        }
+
+        for body in &bodies[0..3] {
+            let observation = Observation {
+                signature: sign(body, &keys[0]),
+                body: serde_wormhole::to_vec(body).unwrap(),
+            };
+            handle_observation(axum::extract::State(state.clone()), observation)
+                .await
+                .unwrap();
+        }
+
+        let spam = Observation {
+            signature: sign(&bodies[3], &keys[0]),
+            body: serde_wormhole::to_vec(&bodies[3]).unwrap(),
+        };
+        let result = handle_observation(axum::extract::State(state.clone()), spam).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Guardian 0 has too many bodies in flight"
+        );
+        assert_eq!(state.verification.read().await.len(), 3);
+
+        // Another guardian can still sign the same body
+        let observation = Observation {
+            signature: sign(&bodies[3], &keys[1]),
+            body: serde_wormhole::to_vec(&bodies[3]).unwrap(),
+        };
+        handle_observation(axum::extract::State(state.clone()), observation)
+            .await
+            .unwrap();
+        assert_eq!(state.verification.read().await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_handle_observation_pending_table_evicts_oldest() {
+        let (guardian_set, keys) = get_guardian_sets(19);
+        let state = get_state_with_limits(
+            guardian_set,
+            OBSERVERATION_LIFETIME,
+            ObservationLimits {
+                max_pending_bodies: 2,
+                max_in_flight_per_guardian: 100,
+            },
+        );
+
+        let mut bodies = vec![];
+        for offset in 0..3 {
+            let mut body = get_sample_body(-(OBSERVERATION_LIFETIME as i64 - 1) + offset);
+            body.sequence = offset as u64;
+            bodies.push(body);
+        }
+
+        for (i, body) in bodies.iter().enumerate() {
+            let observation = Observation {
+                signature: sign(body, &keys[i]),
+                body: serde_wormhole::to_vec(body).unwrap(),
+            };
+            handle_observation(axum::extract::State(state.clone()), observation)
+                .await
+                .unwrap();
+        }
+
+        let verification = state.verification.read().await;
+        assert_eq!(verification.len(), 2);
+        assert!(verification
+            .get(&serde_wormhole::to_vec(&bodies[0]).unwrap())
+            .is_none());
+        assert!(verification
+            .get(&serde_wormhole::to_vec(&bodies[2]).unwrap())
+            .is_some());
+        assert_eq!(verification.in_flight(0), 0);
+    }
+
+    #[tokio::test]
+    async fn test_handle_observation_duplicate_signature() {
+        let body = get_sample_body(-(OBSERVERATION_LIFETIME as i64 - 1));
+        let (guardian_set, keys) = get_guardian_sets(19);
+        let observation = Observation {
+            signature: sign(&body, &keys[0]),
+            body: serde_wormhole::to_vec(&body).unwrap(),
+        };
+        let state = get_state(guardian_set, OBSERVERATION_LIFETIME);
+
+        for _ in 0..10 {
+            handle_observation(axum::extract::State(state.clone()), observation.clone())
+                .await
+                .unwrap();
+        }
+
+        let verification = state.verification.read().await;
+        assert_eq!(verification.len(), 1);
+        assert_eq!(verification.get(&observation.body).unwrap().len(), 1);
+        assert_eq!(verification.in_flight(0), 1);
+    }
 }
@@ -0,0 +1,306 @@
+use std::{net::SocketAddr, pin::Pin};
+
+use futures::{Stream, StreamExt};
+use serde_wormhole::RawMessage;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tonic::{transport::Server, Request, Response, Status};
+use wormhole_sdk::{Address, Vaa};
+
+use crate::{
+    server::{wait_for_exit, State},
+    ws::UpdateEvent,
+};
+
+use self::proto::spy::v1::{
+    filter_entry::Filter,
+    spy_rpc_service_server::{SpyRpcService, SpyRpcServiceServer},
+    SubscribeSignedVaaRequest, SubscribeSignedVaaResponse,
+};
+
+/// The following module structure must match the protobuf definitions, so that the generated code
+/// can correctly reference modules from each other.
+#[allow(
+    clippy::enum_variant_names,
+    clippy::allow_attributes_without_reason,
+    dead_code,
+    reason = "generated code"
+)]
+pub mod proto {
+    pub mod gossip {
+        pub mod v1 {
+            tonic::include_proto!("gossip.v1");
+        }
+    }
+
+    pub mod spy {
+        pub mod v1 {
+            tonic::include_proto!("spy.v1");
+        }
+    }
+
+    pub mod publicrpc {
+        pub mod v1 {
+            tonic::include_proto!("publicrpc.v1");
+        }
+    }
+}
+
+/// An emitter a subscriber is interested in, parsed from the spy `EmitterFilter`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct EmitterFilter {
+    chain_id: u16,
+    emitter_address: Address,
+}
+
+#[allow(
+    clippy::result_large_err,
+    reason = "the status is returned as is to the gRPC client"
+)]
+fn parse_filters(request: &SubscribeSignedVaaRequest) -> Result<Vec<EmitterFilter>, Status> {
+    request
+        .filters
+        .iter()
+        .map(|entry| match &entry.filter {
+            Some(Filter::EmitterFilter(filter)) => {
+                let chain_id = u16::try_from(filter.chain_id).map_err(|_| {
+                    Status::invalid_argument(format!("Invalid chain id {}", filter.chain_id))
+                })?;
+                let emitter_address: [u8; 32] = hex::decode(&filter.emitter_address)
+                    .ok()
+                    .and_then(|address| address.try_into().ok())
+                    .ok_or_else(|| {
+                        Status::invalid_argument(format!(
+                            "Invalid emitter address {}",
+                            filter.emitter_address
+                        ))
+                    })?;
+                Ok(EmitterFilter {
+                    chain_id,
+                    emitter_address: Address(emitter_address),
+                })
+            }
+            Some(Filter::BatchFilter(_)) | Some(Filter::BatchTransactionFilter(_)) => Err(
+                Status::invalid_argument("Only emitter filters are supported"),
+            ),
+            None => Err(Status::invalid_argument("Empty filter entry")),
+        })
+        .collect()
+}
+
+/// Returns whether the VAA matches any of the filters. An empty filter list matches every VAA,
+/// like the spy does.
+fn matches_filters(vaa: &[u8], filters: &[EmitterFilter]) -> bool {
+    if filters.is_empty() {
+        return true;
+    }
+    match serde_wormhole::from_slice::<Vaa<&RawMessage>>(vaa) {
+        Ok(vaa) => filters.iter().any(|filter| {
+            filter.chain_id == u16::from(vaa.emitter_chain)
+                && filter.emitter_address == vaa.emitter_address
+        }),
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to deserialize VAA for filtering");
+            false
+        }
+    }
+}
+
+/// Serves the Wormhole spy `SubscribeSignedVAA` stream from the VAAs completed by Quorum, so
+/// existing spy consumers can subscribe to Quorum directly.
+pub struct SpyService {
+    state: State,
+}
+
+impl SpyService {
+    pub fn new(state: State) -> Self {
+        Self { state }
+    }
+}
+
+type SubscribeSignedVaaStream =
+    Pin<Box<dyn Stream<Item = Result<SubscribeSignedVaaResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl SpyRpcService for SpyService {
+    type SubscribeSignedVAAStream = SubscribeSignedVaaStream;
+
+    async fn subscribe_signed_vaa(
+        &self,
+        request: Request<SubscribeSignedVaaRequest>,
+    ) -> Result<Response<Self::SubscribeSignedVAAStream>, Status> {
+        let filters = parse_filters(request.get_ref())?;
+        metrics::counter!("grpc_subscriptions_total").increment(1);
+
+        let receiver = self.state.ws.broadcast_receiver.resubscribe();
+        let stream = BroadcastStream::new(receiver).filter_map(move |event| {
+            let response = match event {
+                Ok(UpdateEvent::NewVaa(vaa)) if matches_filters(&vaa, &filters) => {
+                    Some(Ok(SubscribeSignedVaaResponse { vaa_bytes: vaa }))
+                }
+                Ok(_) => None,
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "gRPC subscriber lagged behind, skipping VAAs");
+                    metrics::counter!("grpc_lagged_vaas_total").increment(skipped);
+                    None
+                }
+            };
+            async move { response }
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+pub async fn run(listen_address: SocketAddr, state: State) -> anyhow::Result<()> {
+    tracing::info!("Starting gRPC server...");
+
+    Server::builder()
+        .add_service(SpyRpcServiceServer::new(SpyService::new(state)))
+        .serve_with_shutdown(listen_address, async {
+            wait_for_exit().await;
+            tracing::info!("Shutting down gRPC server...");
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_wormhole::RawMessage;
+    use wormhole_sdk::{
+        vaa::{Body, Header},
+        Chain, GuardianSetInfo,
+    };
+
+    use super::{
+        proto::spy::v1::{EmitterFilter as ProtoEmitterFilter, FilterEntry},
+        *,
+    };
+    use crate::server::tests::get_state;
+
+    fn get_vaa(emitter_chain: Chain, emitter_address: [u8; 32], sequence: u64) -> Vec<u8> {
+        let vaa: Vaa<&RawMessage> = (
+            Header {
+                version: 1,
+                guardian_set_index: 0,
+                signatures: vec![],
+            },
+            Body {
+                timestamp: 0,
+                nonce: 0,
+                emitter_chain,
+                emitter_address: Address(emitter_address),
+                sequence,
+                consistency_level: 0,
+                payload: RawMessage::new(&[1, 2, 3]),
+            },
+        )
+            .into();
+        serde_wormhole::to_vec(&vaa).unwrap()
+    }
+
+    fn emitter_filter(chain_id: i32, emitter_address: &str) -> FilterEntry {
+        FilterEntry {
+            filter: Some(Filter::EmitterFilter(ProtoEmitterFilter {
+                chain_id,
+                emitter_address: emitter_address.to_string(),
+            })),
+        }
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        let request = SubscribeSignedVaaRequest {
+            filters: vec![emitter_filter(26, &hex::encode([3; 32]))],
+        };
+        assert_eq!(
+            parse_filters(&request).unwrap(),
+            vec![EmitterFilter {
+                chain_id: 26,
+                emitter_address: Address([3; 32]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_filters_invalid() {
+        let invalid_address = SubscribeSignedVaaRequest {
+            filters: vec![emitter_filter(26, "1234")],
+        };
+        assert_eq!(
+            parse_filters(&invalid_address).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+
+        let invalid_chain = SubscribeSignedVaaRequest {
+            filters: vec![emitter_filter(-1, &hex::encode([3; 32]))],
+        };
+        assert_eq!(
+            parse_filters(&invalid_chain).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_matches_filters() {
+        let vaa = get_vaa(Chain::Pythnet, [3; 32], 1);
+        let pythnet = EmitterFilter {
+            chain_id: Chain::Pythnet.into(),
+            emitter_address: Address([3; 32]),
+        };
+        let other_emitter = EmitterFilter {
+            chain_id: Chain::Pythnet.into(),
+            emitter_address: Address([4; 32]),
+        };
+        let other_chain = EmitterFilter {
+            chain_id: Chain::Ethereum.into(),
+            emitter_address: Address([3; 32]),
+        };
+
+        assert!(matches_filters(&vaa, &[]));
+        assert!(matches_filters(&vaa, std::slice::from_ref(&pythnet)));
+        assert!(matches_filters(&vaa, &[other_emitter.clone(), pythnet]));
+        assert!(!matches_filters(&vaa, &[other_emitter, other_chain]));
+        assert!(!matches_filters(
+            &[1, 2, 3],
+            &[EmitterFilter {
+                chain_id: 0,
+                emitter_address: Address([0; 32]),
+            }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_signed_vaa() {
+        let state = get_state(GuardianSetInfo { addresses: vec![] }, 10);
+        let service = SpyService::new(state.clone());
+        let mut stream = service
+            .subscribe_signed_vaa(Request::new(SubscribeSignedVaaRequest {
+                filters: vec![emitter_filter(
+                    u16::from(Chain::Pythnet).into(),
+                    &hex::encode([3; 32]),
+                )],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let matching = get_vaa(Chain::Pythnet, [3; 32], 2);
+        let sender = &state.ws.broadcast_sender;
+        sender
+            .send(UpdateEvent::NewVaa(get_vaa(Chain::Pythnet, [4; 32], 1)))
+            .unwrap();
+        sender.send(UpdateEvent::Ping).unwrap();
+        sender.send(UpdateEvent::NewVaa(matching.clone())).unwrap();
+
+        let response = tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.vaa_bytes, matching);
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use wormhole_sdk::GuardianSetInfo;
+
+/// The number of most recent finalized bodies included in the status response.
+const RECENT_BODIES_IN_STATUS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyOutcome {
+    Quorum,
+    Expired,
+}
+
+impl BodyOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BodyOutcome::Quorum => "quorum",
+            BodyOutcome::Expired => "expired",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SignerReport {
+    pub guardian_index: u8,
+    /// Time between the first observation of the body and this guardian's observation.
+    pub delay_seconds: f64,
+}
+
+/// The participation record of a single VAA body once it has expired.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BodyReport {
+    pub sequence: u64,
+    pub outcome: BodyOutcome,
+    /// Time between the first observation of the body and the quorum being reached.
+    pub quorum_seconds: Option<f64>,
+    pub signers: Vec<SignerReport>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct GuardianStatus {
+    pub index: u8,
+    #[serde(with = "hex::serde")]
+    pub address: [u8; 20],
+    /// Fraction of the recent bodies this guardian has signed.
+    pub participation_rate: f64,
+    pub signed_bodies: usize,
+    pub mean_delay_seconds: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StatusResponse {
+    pub guardian_set_index: u32,
+    /// Number of finalized bodies the rates are computed over.
+    pub window_size: usize,
+    /// Number of bodies currently waiting for observations.
+    pub pending_bodies: usize,
+    /// Fraction of the recent bodies that reached quorum.
+    pub quorum_rate: f64,
+    pub guardians: Vec<GuardianStatus>,
+    pub recent_bodies: Vec<BodyReport>,
+}
+
+struct PendingBody {
+    sequence: u64,
+    timestamp: u32,
+    first_seen: Instant,
+    arrivals: BTreeMap<u8, Duration>,
+    quorum_after: Option<Duration>,
+}
+
+/// Tracks which guardians sign each VAA body and how late their observations arrive.
+///
+/// A body is tracked from its first observation until it expires, so guardians signing after
+/// the quorum is reached still count towards their participation.
+pub struct GuardianHealth {
+    window_size: usize,
+    pending: HashMap<Vec<u8>, PendingBody>,
+    recent: VecDeque<BodyReport>,
+}
+
+impl GuardianHealth {
+    pub fn new(window_size: usize) -> Self {
+        Self {
+            window_size,
+            pending: HashMap::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Records a verified observation and returns its delay relative to the first observation
+    /// of the same body.
+    pub fn record_observation(
+        &mut self,
+        body: &[u8],
+        sequence: u64,
+        timestamp: u32,
+        guardian_index: u8,
+    ) -> Duration {
+        let now = Instant::now();
+        let pending = self
+            .pending
+            .entry(body.to_vec())
+            .or_insert_with(|| PendingBody {
+                sequence,
+                timestamp,
+                first_seen: now,
+                arrivals: BTreeMap::new(),
+                quorum_after: None,
+            });
+        let delay = now.duration_since(pending.first_seen);
+        *pending.arrivals.entry(guardian_index).or_insert(delay)
+    }
+
+    /// Marks the body as having reached quorum and returns the time it took since its first
+    /// observation. Returns `None` if the body is unknown or already reached quorum.
+    pub fn record_quorum(&mut self, body: &[u8]) -> Option<Duration> {
+        let pending = self.pending.get_mut(body)?;
+        if pending.quorum_after.is_some() {
+            return None;
+        }
+        let elapsed = pending.first_seen.elapsed();
+        pending.quorum_after = Some(elapsed);
+        Some(elapsed)
+    }
+
+    /// Moves the bodies whose deadline is before `now` into the recent window and returns
+    /// their reports.
+    pub fn finalize_expired(&mut self, observation_lifetime: u32, now: i64) -> Vec<BodyReport> {
+        let expired: Vec<Vec<u8>> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| {
+                i64::from(pending.timestamp) + i64::from(observation_lifetime) < now
+            })
+            .map(|(body, _)| body.clone())
+            .collect();
+
+        let mut reports: Vec<BodyReport> = expired
+            .into_iter()
+            .filter_map(|body| self.pending.remove(&body))
+            .map(|pending| BodyReport {
+                sequence: pending.sequence,
+                outcome: match pending.quorum_after {
+                    Some(_) => BodyOutcome::Quorum,
+                    None => BodyOutcome::Expired,
+                },
+                quorum_seconds: pending.quorum_after.map(|d| d.as_secs_f64()),
+                signers: pending
+                    .arrivals
+                    .into_iter()
+                    .map(|(guardian_index, delay)| SignerReport {
+                        guardian_index,
+                        delay_seconds: delay.as_secs_f64(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        reports.sort_by_key(|report| report.sequence);
+
+        for report in &reports {
+            metrics::counter!(
+                "finalized_bodies_total",
+                &[("outcome", report.outcome.as_str())]
+            )
+            .increment(1);
+            self.recent.push_back(report.clone());
+        }
+        while self.recent.len() > self.window_size {
+            self.recent.pop_front();
+        }
+        reports
+    }
+
+    pub fn status(
+        &self,
+        guardian_set: &GuardianSetInfo,
+        guardian_set_index: u32,
+    ) -> StatusResponse {
+        let window_size = self.recent.len();
+        let rate = |count: usize| {
+            if window_size == 0 {
+                0.0
+            } else {
+                count as f64 / window_size as f64
+            }
+        };
+
+        let guardians = guardian_set
+            .addresses
+            .iter()
+            .enumerate()
+            .map(|(index, address)| {
+                let delays: Vec<f64> = self
+                    .recent
+                    .iter()
+                    .flat_map(|report| report.signers.iter())
+                    .filter(|signer| usize::from(signer.guardian_index) == index)
+                    .map(|signer| signer.delay_seconds)
+                    .collect();
+                GuardianStatus {
+                    index: index as u8,
+                    address: address.0,
+                    participation_rate: rate(delays.len()),
+                    signed_bodies: delays.len(),
+                    mean_delay_seconds: if delays.is_empty() {
+                        None
+                    } else {
+                        Some(delays.iter().sum::<f64>() / delays.len() as f64)
+                    },
+                }
+            })
+            .collect();
+
+        StatusResponse {
+            guardian_set_index,
+            window_size,
+            pending_bodies: self.pending.len(),
+            quorum_rate: rate(
+                self.recent
+                    .iter()
+                    .filter(|report| report.outcome == BodyOutcome::Quorum)
+                    .count(),
+            ),
+            guardians,
+            recent_bodies: self
+                .recent
+                .iter()
+                .rev()
+                .take(RECENT_BODIES_IN_STATUS)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Publishes the per-guardian participation rate over the recent window.
+    pub fn update_metrics(&self, guardian_set: &GuardianSetInfo, guardian_set_index: u32) {
+        let status = self.status(guardian_set, guardian_set_index);
+        for guardian in status.guardians {
+            metrics::gauge!(
+                "guardian_participation_rate",
+                &[("guardian_index", guardian.index.to_string())]
+            )
+            .set(guardian.participation_rate);
+        }
+        metrics::gauge!("quorum_rate").set(status.quorum_rate);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wormhole_sdk::GuardianAddress;
+
+    use super::*;
+
+    const LIFETIME: u32 = 10;
+
+    fn guardian_set(n: u8) -> GuardianSetInfo {
+        GuardianSetInfo {
+            addresses: (0..n).map(|i| GuardianAddress([i; 20])).collect(),
+        }
+    }
+
+    #[test]
+    fn test_record_observation_delay() {
+        let mut health = GuardianHealth::new(10);
+        let first = health.record_observation(b"body", 1, 100, 0);
+        assert_eq!(first, Duration::ZERO);
+
+        std::thread::sleep(Duration::from_millis(10));
+        let second = health.record_observation(b"body", 1, 100, 1);
+        assert!(second >= Duration::from_millis(10));
+
+        // A duplicate observation keeps the original arrival time
+        let duplicate = health.record_observation(b"body", 1, 100, 0);
+        assert_eq!(duplicate, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_record_quorum_once() {
+        let mut health = GuardianHealth::new(10);
+        assert!(health.record_quorum(b"body").is_none());
+        health.record_observation(b"body", 1, 100, 0);
+        assert!(health.record_quorum(b"body").is_some());
+        assert!(health.record_quorum(b"body").is_none());
+    }
+
+    #[test]
+    fn test_finalize_expired() {
+        let mut health = GuardianHealth::new(10);
+        health.record_observation(b"quorum", 1, 100, 0);
+        health.record_observation(b"quorum", 1, 100, 2);
+        health.record_quorum(b"quorum");
+        health.record_observation(b"expired", 2, 100, 1);
+        health.record_observation(b"pending", 3, 200, 1);
+
+        assert!(health
+            .finalize_expired(LIFETIME, 100 + LIFETIME as i64)
+            .is_empty());
+
+        let reports = health.finalize_expired(LIFETIME, 101 + LIFETIME as i64);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].sequence, 1);
+        assert_eq!(reports[0].outcome, BodyOutcome::Quorum);
+        assert!(reports[0].quorum_seconds.is_some());
+        assert_eq!(
+            reports[0]
+                .signers
+                .iter()
+                .map(|s| s.guardian_index)
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert_eq!(reports[1].sequence, 2);
+        assert_eq!(reports[1].outcome, BodyOutcome::Expired);
+        assert!(reports[1].quorum_seconds.is_none());
+
+        let status = health.status(&guardian_set(3), 4);
+        assert_eq!(status.guardian_set_index, 4);
+        assert_eq!(status.window_size, 2);
+        assert_eq!(status.pending_bodies, 1);
+        assert_eq!(status.quorum_rate, 0.5);
+        assert_eq!(status.guardians[0].participation_rate, 0.5);
+        assert_eq!(status.guardians[1].participation_rate, 0.5);
+        assert_eq!(status.guardians[2].participation_rate, 0.5);
+        assert_eq!(status.guardians[1].address, [1; 20]);
+        assert_eq!(status.recent_bodies[0].sequence, 2);
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let mut health = GuardianHealth::new(2);
+        for sequence in 0..5u64 {
+            let body = sequence.to_be_bytes();
+            health.record_observation(&body, sequence, 100, 0);
+        }
+        health.finalize_expired(LIFETIME, 200);
+
+        let status = health.status(&guardian_set(2), 0);
+        assert_eq!(status.window_size, 2);
+        assert_eq!(status.guardians[0].participation_rate, 1.0);
+        assert_eq!(status.guardians[0].signed_bodies, 2);
+        assert_eq!(status.guardians[1].participation_rate, 0.0);
+        assert_eq!(status.guardians[1].mean_delay_seconds, None);
+        assert_eq!(
+            status
+                .recent_bodies
+                .iter()
+                .map(|b| b.sequence)
+                .collect::<Vec<_>>(),
+            vec![4, 3]
+        );
+    }
+
+    #[test]
+    fn test_status_empty() {
+        let health = GuardianHealth::new(10);
+        let status = health.status(&guardian_set(3), 0);
+        assert_eq!(status.window_size, 0);
+        assert_eq!(status.quorum_rate, 0.0);
+        assert!(status
+            .guardians
+            .iter()
+            .all(|guardian| guardian.participation_rate == 0.0));
+    }
+}
@@ -4,9 +4,13 @@ use std::io::IsTerminal;
 use crate::server::RunOptions;
 
 mod api;
+mod grpc;
+mod guardian_health;
 mod metrics_server;
+mod observation_table;
 mod pythnet;
 mod server;
+mod vaa_store;
 mod ws;
 
 #[tokio::main]
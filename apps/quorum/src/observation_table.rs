@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use wormhole_sdk::vaa::Signature;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ObservationLimits {
+    /// The maximum number of bodies waiting for a quorum. Once reached, the body closest to
+    /// expiration is evicted to make room for a new one.
+    pub max_pending_bodies: usize,
+    /// The maximum number of pending bodies a single guardian can have signed.
+    pub max_in_flight_per_guardian: usize,
+}
+
+struct PendingBody {
+    timestamp: u32,
+    signatures: Vec<Signature>,
+}
+
+/// The result of adding a signature to the table.
+#[derive(Debug)]
+pub struct AddedSignature {
+    /// All the signatures collected for the body so far, sorted by guardian index.
+    pub signatures: Vec<Signature>,
+    /// Whether this is the first signature seen for the body.
+    pub new_body: bool,
+}
+
+/// Pending observations waiting for a quorum, keyed by the serialized VAA body.
+///
+/// Bodies that reached quorum are remembered until they are expired so that late or replayed
+/// observations for them are rejected instead of starting a new pending entry.
+pub struct ObservationTable {
+    limits: ObservationLimits,
+    pending: HashMap<Vec<u8>, PendingBody>,
+    in_flight: HashMap<u8, usize>,
+    completed: HashMap<Vec<u8>, u32>,
+}
+
+impl ObservationTable {
+    pub fn new(limits: ObservationLimits) -> Self {
+        Self {
+            limits,
+            pending: HashMap::new(),
+            in_flight: HashMap::new(),
+            completed: HashMap::new(),
+        }
+    }
+
+    /// The number of pending bodies.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// The total number of signatures across the pending bodies.
+    pub fn signature_count(&self) -> usize {
+        self.pending
+            .values()
+            .map(|pending| pending.signatures.len())
+            .sum()
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, body: &[u8]) -> Option<&Vec<Signature>> {
+        self.pending.get(body).map(|pending| &pending.signatures)
+    }
+
+    /// Whether the body is either pending or completed.
+    pub fn contains_key(&self, body: &[u8]) -> bool {
+        self.pending.contains_key(body) || self.completed.contains_key(body)
+    }
+
+    pub fn is_completed(&self, body: &[u8]) -> bool {
+        self.completed.contains_key(body)
+    }
+
+    pub fn in_flight(&self, guardian_index: u8) -> usize {
+        self.in_flight.get(&guardian_index).copied().unwrap_or(0)
+    }
+
+    pub fn add_signature(
+        &mut self,
+        body: &[u8],
+        timestamp: u32,
+        signature: Signature,
+    ) -> anyhow::Result<AddedSignature> {
+        if self.is_completed(body) {
+            return Err(anyhow::anyhow!(
+                "Observation is for an already completed VAA"
+            ));
+        }
+
+        if let Some(pending) = self.pending.get(body) {
+            if pending
+                .signatures
+                .iter()
+                .any(|sig| sig.index == signature.index)
+            {
+                return Ok(AddedSignature {
+                    signatures: pending.signatures.clone(),
+                    new_body: false,
+                });
+            }
+        }
+
+        if self.in_flight(signature.index) >= self.limits.max_in_flight_per_guardian {
+            metrics::counter!(
+                "rejected_observations_total",
+                &[("reason", "guardian_in_flight_limit")]
+            )
+            .increment(1);
+            return Err(anyhow::anyhow!(
+                "Guardian {} has too many bodies in flight",
+                signature.index
+            ));
+        }
+
+        let new_body = !self.pending.contains_key(body);
+        if new_body && self.pending.len() >= self.limits.max_pending_bodies {
+            self.evict_oldest();
+        }
+
+        *self.in_flight.entry(signature.index).or_insert(0) += 1;
+        let pending = self
+            .pending
+            .entry(body.to_vec())
+            .or_insert_with(|| PendingBody {
+                timestamp,
+                signatures: vec![],
+            });
+        pending.signatures.push(signature);
+        pending.signatures.sort_by(|a, b| a.index.cmp(&b.index));
+
+        Ok(AddedSignature {
+            signatures: pending.signatures.clone(),
+            new_body,
+        })
+    }
+
+    /// Marks the body as completed, releasing its pending signatures.
+    pub fn complete(&mut self, body: &[u8]) {
+        if let Some(pending) = self.remove_pending(body) {
+            self.completed.insert(body.to_vec(), pending.timestamp);
+        }
+    }
+
+    /// Forgets the body entirely, whether it is pending or completed.
+    pub fn remove(&mut self, body: &[u8]) {
+        self.remove_pending(body);
+        self.completed.remove(body);
+    }
+
+    fn remove_pending(&mut self, body: &[u8]) -> Option<PendingBody> {
+        let pending = self.pending.remove(body)?;
+        for signature in &pending.signatures {
+            if let Some(count) = self.in_flight.get_mut(&signature.index) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    self.in_flight.remove(&signature.index);
+                }
+            }
+        }
+        Some(pending)
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .pending
+            .iter()
+            .min_by_key(|(_, pending)| pending.timestamp)
+            .map(|(body, _)| body.clone());
+        if let Some(body) = oldest {
+            self.remove_pending(&body);
+            metrics::counter!("evicted_pending_bodies_total").increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signature(index: u8) -> Signature {
+        Signature {
+            index,
+            signature: [index; 65],
+        }
+    }
+
+    fn table(max_pending_bodies: usize, max_in_flight_per_guardian: usize) -> ObservationTable {
+        ObservationTable::new(ObservationLimits {
+            max_pending_bodies,
+            max_in_flight_per_guardian,
+        })
+    }
+
+    #[test]
+    fn test_add_signature_sorted_and_deduplicated() {
+        let mut table = table(10, 10);
+        assert!(
+            table
+                .add_signature(b"body", 0, signature(2))
+                .unwrap()
+                .new_body
+        );
+        assert!(
+            !table
+                .add_signature(b"body", 0, signature(0))
+                .unwrap()
+                .new_body
+        );
+        let added = table.add_signature(b"body", 0, signature(2)).unwrap();
+
+        assert!(!added.new_body);
+        assert_eq!(added.signatures, vec![signature(0), signature(2)]);
+        assert_eq!(table.in_flight(2), 1);
+        assert_eq!(table.signature_count(), 2);
+    }
+
+    #[test]
+    fn test_evicts_oldest_body_when_full() {
+        let mut table = table(2, 10);
+        table.add_signature(b"old", 1, signature(0)).unwrap();
+        table.add_signature(b"new", 3, signature(0)).unwrap();
+        table.add_signature(b"newer", 2, signature(1)).unwrap();
+
+        assert_eq!(table.len(), 2);
+        assert!(table.get(b"old").is_none());
+        assert!(table.get(b"new").is_some());
+        assert!(table.get(b"newer").is_some());
+        assert_eq!(table.in_flight(0), 1);
+    }
+
+    #[test]
+    fn test_guardian_in_flight_limit() {
+        let mut table = table(10, 2);
+        table.add_signature(b"a", 0, signature(0)).unwrap();
+        table.add_signature(b"b", 0, signature(0)).unwrap();
+        assert_eq!(
+            table
+                .add_signature(b"c", 0, signature(0))
+                .unwrap_err()
+                .to_string(),
+            "Guardian 0 has too many bodies in flight"
+        );
+        // Other guardians are not affected
+        table.add_signature(b"c", 0, signature(1)).unwrap();
+
+        // Completing a body releases the slot
+        table.complete(b"a");
+        table.add_signature(b"d", 0, signature(0)).unwrap();
+        assert_eq!(table.in_flight(0), 2);
+    }
+
+    #[test]
+    fn test_completed_body_rejected_until_removed() {
+        let mut table = table(10, 10);
+        table.add_signature(b"body", 0, signature(0)).unwrap();
+        table.complete(b"body");
+
+        assert_eq!(table.len(), 0);
+        assert!(table.contains_key(b"body"));
+        assert_eq!(
+            table
+                .add_signature(b"body", 0, signature(1))
+                .unwrap_err()
+                .to_string(),
+            "Observation is for an already completed VAA"
+        );
+
+        table.remove(b"body");
+        assert!(!table.contains_key(b"body"));
+        assert!(table.add_signature(b"body", 0, signature(1)).is_ok());
+    }
+}
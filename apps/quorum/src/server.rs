@@ -3,19 +3,22 @@ use clap::{crate_authors, crate_description, crate_name, crate_version, Args, Pa
 use lazy_static::lazy_static;
 use solana_client::client_error::reqwest::Url;
 use solana_sdk::pubkey::Pubkey;
-use std::{
-    collections::HashMap, future::Future, net::SocketAddr, ops::Deref, sync::Arc, time::Duration,
-};
+use std::{future::Future, net::SocketAddr, ops::Deref, sync::Arc, time::Duration};
+use time::OffsetDateTime;
 use tokio::{
     sync::{watch, RwLock},
     time::sleep,
 };
-use wormhole_sdk::{vaa::Signature, GuardianSetInfo};
+use wormhole_sdk::GuardianSetInfo;
 
 use crate::{
     api::{self},
+    grpc,
+    guardian_health::GuardianHealth,
     metrics_server::{self, metric_collector, setup_metrics_recorder},
+    observation_table::{ObservationLimits, ObservationTable},
     pythnet::fetch_guardian_set,
+    vaa_store::VaaStore,
     ws::WsState,
 };
 
@@ -36,6 +39,11 @@ pub struct ServerOptions {
     #[arg(default_value = DEFAULT_METRICS_ADDR)]
     #[arg(env = "METRICS_ADDR")]
     pub metrics_addr: SocketAddr,
+    /// Address and port the Wormhole spy compatible gRPC server will bind to. The gRPC server
+    /// is disabled if not provided.
+    #[arg(long = "grpc-addr")]
+    #[arg(env = "GRPC_ADDR")]
+    pub grpc_addr: Option<SocketAddr>,
 }
 
 // `Options` is a structup definition to provide clean command-line args for Hermes.
@@ -67,6 +75,31 @@ pub struct RunOptions {
     #[arg(env = "OBSERVATION_LIFETIME")]
     #[arg(default_value_t = DEFAULT_OBSERVATION_LIFETIME)]
     pub observation_lifetime: u32,
+    /// The maximum number of completed VAAs retained for backfilling subscribers.
+    #[arg(long = "vaa-store-capacity")]
+    #[arg(env = "VAA_STORE_CAPACITY")]
+    #[arg(default_value_t = DEFAULT_VAA_STORE_CAPACITY)]
+    pub vaa_store_capacity: usize,
+    /// The number of most recent VAA bodies used to compute guardian participation rates.
+    #[arg(long = "status-window-size")]
+    #[arg(env = "STATUS_WINDOW_SIZE")]
+    #[arg(default_value_t = DEFAULT_STATUS_WINDOW_SIZE)]
+    pub status_window_size: usize,
+    /// The maximum size in bytes of an observation body.
+    #[arg(long = "max-observation-body-size")]
+    #[arg(env = "MAX_OBSERVATION_BODY_SIZE")]
+    #[arg(default_value_t = DEFAULT_MAX_OBSERVATION_BODY_SIZE)]
+    pub max_observation_body_size: usize,
+    /// The maximum number of VAA bodies waiting for a quorum.
+    #[arg(long = "max-pending-bodies")]
+    #[arg(env = "MAX_PENDING_BODIES")]
+    #[arg(default_value_t = DEFAULT_MAX_PENDING_BODIES)]
+    pub max_pending_bodies: usize,
+    /// The maximum number of pending VAA bodies a single guardian can have signed.
+    #[arg(long = "max-in-flight-per-guardian")]
+    #[arg(env = "MAX_IN_FLIGHT_PER_GUARDIAN")]
+    #[arg(default_value_t = DEFAULT_MAX_IN_FLIGHT_PER_GUARDIAN)]
+    pub max_in_flight_per_guardian: usize,
 }
 
 lazy_static! {
@@ -96,12 +129,17 @@ pub async fn wait_for_exit() {
 pub struct State(Arc<StateInner>);
 
 pub struct StateInner {
-    pub verification: Arc<RwLock<HashMap<Vec<u8>, Vec<Signature>>>>,
+    pub verification: Arc<RwLock<ObservationTable>>,
 
     pub guardian_set: GuardianSetInfo,
     pub guardian_set_index: u32,
 
     pub observation_lifetime: u32,
+    pub max_observation_body_size: usize,
+
+    pub vaa_store: RwLock<VaaStore>,
+
+    pub guardian_health: RwLock<GuardianHealth>,
 
     pub ws: WsState,
 
@@ -116,6 +154,11 @@ impl Deref for State {
 }
 
 const DEFAULT_OBSERVATION_LIFETIME: u32 = 10; // In seconds
+const DEFAULT_VAA_STORE_CAPACITY: usize = 10_000;
+const DEFAULT_STATUS_WINDOW_SIZE: usize = 1000;
+const DEFAULT_MAX_OBSERVATION_BODY_SIZE: usize = 1024; // In bytes
+const DEFAULT_MAX_PENDING_BODIES: usize = 10_000;
+const DEFAULT_MAX_IN_FLIGHT_PER_GUARDIAN: usize = 1000;
 const WEBSOCKET_NOTIFICATION_CHANNEL_SIZE: usize = 1000;
 
 async fn fault_tolerant_handler<F, Fut>(name: String, f: F)
@@ -160,12 +203,20 @@ pub async fn run(run_options: RunOptions) -> anyhow::Result<()> {
     .await?;
 
     let state = State(Arc::new(StateInner {
-        verification: Arc::new(RwLock::new(HashMap::new())),
+        verification: Arc::new(RwLock::new(ObservationTable::new(ObservationLimits {
+            max_pending_bodies: run_options.max_pending_bodies,
+            max_in_flight_per_guardian: run_options.max_in_flight_per_guardian,
+        }))),
 
         guardian_set,
         guardian_set_index: run_options.guardian_set_index,
 
         observation_lifetime: run_options.observation_lifetime,
+        max_observation_body_size: run_options.max_observation_body_size,
+
+        vaa_store: RwLock::new(VaaStore::new(run_options.vaa_store_capacity)),
+
+        guardian_health: RwLock::new(GuardianHealth::new(run_options.status_window_size)),
 
         ws: WsState::new(WEBSOCKET_NOTIFICATION_CHANNEL_SIZE),
 
@@ -181,13 +232,37 @@ pub async fn run(run_options: RunOptions) -> anyhow::Result<()> {
             run_options.clone(),
             state.clone()
         )),
+        async {
+            match run_options.server.grpc_addr {
+                Some(grpc_addr) => {
+                    fault_tolerant_handler("gRPC server".to_string(), || {
+                        grpc::run(grpc_addr, state.clone())
+                    })
+                    .await
+                }
+                None => tracing::info!("gRPC address not provided, skipping gRPC server."),
+            }
+        },
         metric_collector("state".to_string(), || {
             let state = state.clone();
             async move {
                 let verification = state.verification.read().await;
                 metrics::gauge!("pending_vaas").set(verification.len() as f64);
                 metrics::gauge!("pending_verified_observations")
-                    .set(verification.values().flatten().count() as f64);
+                    .set(verification.signature_count() as f64);
+                drop(verification);
+                metrics::gauge!("stored_vaas").set(state.vaa_store.read().await.len() as f64);
+            }
+        }),
+        metric_collector("guardian health".to_string(), || {
+            let state = state.clone();
+            async move {
+                let mut guardian_health = state.guardian_health.write().await;
+                guardian_health.finalize_expired(
+                    state.observation_lifetime,
+                    OffsetDateTime::now_utc().unix_timestamp(),
+                );
+                guardian_health.update_metrics(&state.guardian_set, state.guardian_set_index);
             }
         }),
     );
@@ -201,18 +276,34 @@ pub mod tests {
 
     use super::*;
 
-    pub fn get_state(
-        verification: Arc<RwLock<HashMap<Vec<u8>, Vec<Signature>>>>,
+    pub fn get_state(guardian_set: GuardianSetInfo, observation_lifetime: u32) -> State {
+        get_state_with_limits(
+            guardian_set,
+            observation_lifetime,
+            ObservationLimits {
+                max_pending_bodies: DEFAULT_MAX_PENDING_BODIES,
+                max_in_flight_per_guardian: DEFAULT_MAX_IN_FLIGHT_PER_GUARDIAN,
+            },
+        )
+    }
+
+    pub fn get_state_with_limits(
         guardian_set: GuardianSetInfo,
         observation_lifetime: u32,
+        limits: ObservationLimits,
     ) -> State {
         State(Arc::new(StateInner {
-            verification,
+            verification: Arc::new(RwLock::new(ObservationTable::new(limits))),
             guardian_set,
             observation_lifetime,
+            max_observation_body_size: DEFAULT_MAX_OBSERVATION_BODY_SIZE,
 
             guardian_set_index: 0,
 
+            vaa_store: RwLock::new(VaaStore::new(DEFAULT_VAA_STORE_CAPACITY)),
+
+            guardian_health: RwLock::new(GuardianHealth::new(DEFAULT_STATUS_WINDOW_SIZE)),
+
             ws: WsState::new(1),
 
             metrics_recorder: PrometheusBuilder::new().build_recorder().handle(),
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Magic prefix of the Pythnet accumulator update payload carried in the VAA body.
+const ACCUMULATOR_UPDATE_WORMHOLE_VERIFICATION_MAGIC: &[u8; 4] = b"AUWV";
+
+/// A completed VAA retained for backfilling subscribers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StoredVaa {
+    pub sequence: u64,
+    /// The Pythnet slot of the accumulator update, if the payload is one.
+    pub slot: Option<u64>,
+    #[serde(with = "hex::serde")]
+    pub vaa: Vec<u8>,
+}
+
+/// A bounded store of completed VAAs indexed by emitter sequence and by slot.
+///
+/// Once the store is full, the VAAs with the lowest sequence are evicted first.
+pub struct VaaStore {
+    capacity: usize,
+    by_sequence: BTreeMap<u64, StoredVaa>,
+    by_slot: BTreeMap<u64, u64>,
+}
+
+impl VaaStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            by_sequence: BTreeMap::new(),
+            by_slot: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_sequence.len()
+    }
+
+    pub fn insert(&mut self, sequence: u64, payload: &[u8], vaa: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let slot = accumulator_slot(payload);
+        if let Some(previous) = self.by_sequence.insert(
+            sequence,
+            StoredVaa {
+                sequence,
+                slot,
+                vaa,
+            },
+        ) {
+            if let Some(previous_slot) = previous.slot {
+                self.by_slot.remove(&previous_slot);
+            }
+        }
+        if let Some(slot) = slot {
+            self.by_slot.insert(slot, sequence);
+        }
+
+        while self.by_sequence.len() > self.capacity {
+            if let Some((_, evicted)) = self.by_sequence.pop_first() {
+                if let Some(slot) = evicted.slot {
+                    self.by_slot.remove(&slot);
+                }
+            }
+        }
+    }
+
+    pub fn get_by_sequence(&self, sequence: u64) -> Option<&StoredVaa> {
+        self.by_sequence.get(&sequence)
+    }
+
+    pub fn get_by_slot(&self, slot: u64) -> Option<&StoredVaa> {
+        self.by_slot
+            .get(&slot)
+            .and_then(|sequence| self.by_sequence.get(sequence))
+    }
+
+    /// Returns all retained VAAs with a sequence greater than or equal to `from_sequence`,
+    /// ordered by sequence.
+    pub fn range_from(&self, from_sequence: u64) -> impl Iterator<Item = &StoredVaa> {
+        self.by_sequence.range(from_sequence..).map(|(_, vaa)| vaa)
+    }
+}
+
+/// Extracts the slot from a Pythnet accumulator update payload.
+///
+/// The payload is the magic, a single byte enum discriminant for the merkle root variant and
+/// then the big-endian slot.
+fn accumulator_slot(payload: &[u8]) -> Option<u64> {
+    if !payload.starts_with(ACCUMULATOR_UPDATE_WORMHOLE_VERIFICATION_MAGIC) {
+        return None;
+    }
+    let offset = ACCUMULATOR_UPDATE_WORMHOLE_VERIFICATION_MAGIC.len();
+    if *payload.get(offset)? != 0 {
+        return None;
+    }
+    let slot: [u8; 8] = payload.get(offset + 1..offset + 9)?.try_into().ok()?;
+    Some(u64::from_be_bytes(slot))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn accumulator_payload(slot: u64) -> Vec<u8> {
+        let mut payload = ACCUMULATOR_UPDATE_WORMHOLE_VERIFICATION_MAGIC.to_vec();
+        payload.push(0);
+        payload.extend_from_slice(&slot.to_be_bytes());
+        payload.extend_from_slice(&10u32.to_be_bytes());
+        payload.extend_from_slice(&[7; 20]);
+        payload
+    }
+
+    #[test]
+    fn test_accumulator_slot() {
+        assert_eq!(accumulator_slot(&accumulator_payload(42)), Some(42));
+        assert_eq!(accumulator_slot(&[4; 5]), None);
+        assert_eq!(
+            accumulator_slot(&ACCUMULATOR_UPDATE_WORMHOLE_VERIFICATION_MAGIC[..]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut store = VaaStore::new(10);
+        store.insert(1, &accumulator_payload(100), vec![1]);
+        store.insert(2, &[4; 5], vec![2]);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get_by_sequence(1).unwrap().vaa, vec![1]);
+        assert_eq!(store.get_by_sequence(1).unwrap().slot, Some(100));
+        assert_eq!(store.get_by_sequence(2).unwrap().slot, None);
+        assert_eq!(store.get_by_slot(100).unwrap().sequence, 1);
+        assert!(store.get_by_sequence(3).is_none());
+        assert!(store.get_by_slot(101).is_none());
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut store = VaaStore::new(3);
+        for sequence in 0..5 {
+            store.insert(sequence, &accumulator_payload(sequence + 100), vec![]);
+        }
+
+        assert_eq!(store.len(), 3);
+        assert!(store.get_by_sequence(0).is_none());
+        assert!(store.get_by_sequence(1).is_none());
+        assert!(store.get_by_slot(100).is_none());
+        assert!(store.get_by_slot(101).is_none());
+        assert_eq!(store.get_by_slot(104).unwrap().sequence, 4);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut store = VaaStore::new(0);
+        store.insert(1, &accumulator_payload(100), vec![1]);
+        assert_eq!(store.len(), 0);
+    }
+
+    #[test]
+    fn test_range_from() {
+        let mut store = VaaStore::new(10);
+        for sequence in [5, 1, 3, 2, 4] {
+            store.insert(sequence, &[], vec![sequence as u8]);
+        }
+
+        let sequences: Vec<u64> = store.range_from(3).map(|vaa| vaa.sequence).collect();
+        assert_eq!(sequences, vec![3, 4, 5]);
+        assert_eq!(store.range_from(6).count(), 0);
+    }
+}
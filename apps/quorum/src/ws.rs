@@ -4,7 +4,7 @@ use {
     axum::{
         extract::{
             ws::{Message, WebSocket},
-            WebSocketUpgrade,
+            Query, WebSocketUpgrade,
         },
         response::IntoResponse,
     },
@@ -12,6 +12,7 @@ use {
         stream::{SplitSink, SplitStream},
         SinkExt, StreamExt,
     },
+    serde::Deserialize,
     std::{
         sync::atomic::{AtomicUsize, Ordering},
         time::Duration,
@@ -36,18 +37,46 @@ impl WsState {
     }
 }
 
+#[derive(Deserialize, Debug, Default)]
+pub struct WsQueryParams {
+    /// Resume the stream from this emitter sequence, replaying the retained VAAs first.
+    pub from_sequence: Option<u64>,
+}
+
 pub async fn ws_route_handler(
     ws: WebSocketUpgrade,
     state: axum::extract::State<State>,
+    Query(params): Query<WsQueryParams>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| websocket_handler(state, socket))
+    ws.on_upgrade(move |socket| websocket_handler(state, socket, params))
 }
 
-async fn websocket_handler(state: axum::extract::State<State>, stream: WebSocket) {
+async fn websocket_handler(
+    state: axum::extract::State<State>,
+    stream: WebSocket,
+    params: WsQueryParams,
+) {
     let subscriber_id = state.ws.subscriber_counter.fetch_add(1, Ordering::SeqCst);
     let (sender, receiver) = stream.split();
+
+    // Subscribe while holding the store lock so that no VAA is missed or sent twice between
+    // the backfill and the live stream.
+    let vaa_store = state.vaa_store.read().await;
     let new_receiver = state.ws.broadcast_receiver.resubscribe();
+    let backfill = match params.from_sequence {
+        Some(from_sequence) => vaa_store
+            .range_from(from_sequence)
+            .map(|stored| stored.vaa.clone())
+            .collect(),
+        None => vec![],
+    };
+    drop(vaa_store);
+
     let mut subscriber = Subscriber::new(subscriber_id, new_receiver, receiver, sender);
+    if let Err(e) = subscriber.backfill(backfill).await {
+        tracing::warn!(subscriber = subscriber_id, error = ?e, "Failed to backfill subscriber.");
+        return;
+    }
     subscriber.run().await;
 }
 
@@ -100,6 +129,14 @@ impl Subscriber {
         }
     }
 
+    async fn backfill(&mut self, vaas: Vec<Vec<u8>>) -> Result<()> {
+        metrics::counter!("ws_server_backfill_vaas_total").increment(vaas.len() as u64);
+        for vaa in vaas {
+            self.handle_new_vaa(vaa).await?;
+        }
+        Ok(())
+    }
+
     async fn handle_next(&mut self) -> Result<()> {
         tokio::select! {
             maybe_update_event = self.notify_receiver.recv() => {